@@ -0,0 +1,655 @@
+//! An opt-in cache for [`Directory`](../file_system/struct.Directory.html) snapshots
+//! and [`Diff`](../diff/struct.Diff.html)s, for services that browse many repositories
+//! at once and need to bound the memory used.
+//!
+//! A [`Cache`](struct.Cache.html) is given a [`Budget`](struct.Budget.html) of bytes
+//! to hold in memory. When the budget is exceeded the least recently used entries are
+//! evicted, or spilled to temporary files if a spill directory is configured and the
+//! entry supports it. The [`Metrics`](struct.Metrics.html) of a `Cache` report its
+//! hits, misses, evictions and spills.
+//!
+//! # Examples
+//!
+//! ```
+//! use radicle_surf::cache::{Budget, Cache};
+//! use radicle_surf::file_system::{Directory, File};
+//! use radicle_surf::file_system::unsound;
+//!
+//! let mut cache: Cache<&str, Directory> = Cache::new(Budget::new(1024));
+//!
+//! let snapshot = || -> Result<Directory, ()> {
+//!     let mut directory = Directory::root();
+//!     directory.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+//!     Ok(directory)
+//! };
+//!
+//! let first = cache.get_or_insert_with("master", snapshot).unwrap();
+//! let second = cache.get_or_insert_with("master", snapshot).unwrap();
+//!
+//! assert_eq!(first, second);
+//! assert_eq!(cache.metrics().hits, 1);
+//! assert_eq!(cache.metrics().misses, 1);
+//! ```
+
+use crate::diff::{CreateFile, DeleteFile, Diff, ModifiedFile, MoveFile};
+use crate::file_system::{
+    DefaultLabelRules, Directory, DirectoryContents, File, FileMode, Label, Path,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::Hash;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Used to give each spill file a unique name within the process.
+static SPILL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The memory budget of a [`Cache`](struct.Cache.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Budget {
+    /// The maximum number of bytes, as given by [`Cacheable::weight`](trait.Cacheable.html#tymethod.weight),
+    /// to hold in memory.
+    pub max_bytes: usize,
+    /// The directory to spill evicted entries to. If it is `None`, or an entry
+    /// does not support spilling, evicted entries are dropped.
+    pub spill_dir: Option<PathBuf>,
+}
+
+impl Budget {
+    /// Create a `Budget` of `max_bytes` that drops evicted entries.
+    pub fn new(max_bytes: usize) -> Self {
+        Budget {
+            max_bytes,
+            spill_dir: None,
+        }
+    }
+
+    /// Create a `Budget` of `max_bytes` that spills evicted entries to `spill_dir`.
+    pub fn with_spill_dir(max_bytes: usize, spill_dir: PathBuf) -> Self {
+        Budget {
+            max_bytes,
+            spill_dir: Some(spill_dir),
+        }
+    }
+}
+
+/// Counters describing how effective a [`Cache`](struct.Cache.html) is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// The number of lookups that found an entry, in memory or spilled.
+    pub hits: u64,
+    /// The number of lookups that did not find an entry.
+    pub misses: u64,
+    /// The number of entries that were dropped to stay within the budget.
+    pub evictions: u64,
+    /// The number of entries that were spilled to disk to stay within the budget.
+    pub spills: u64,
+}
+
+impl Metrics {
+    /// The ratio of hits to lookups, or `0.0` if there were no lookups.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// A value that can be held in a [`Cache`](struct.Cache.html).
+pub trait Cacheable: Sized {
+    /// The approximate number of bytes the value holds in memory.
+    fn weight(&self) -> usize;
+
+    /// Write the value to `writer` so that it can be evicted from memory,
+    /// returning `false` if spilling is not supported.
+    ///
+    /// The default implementation does not support spilling.
+    fn spill(&self, _writer: &mut dyn Write) -> io::Result<bool> {
+        Ok(false)
+    }
+
+    /// Read back a value that was written by [`Cacheable::spill`](trait.Cacheable.html#method.spill).
+    fn restore(_reader: &mut dyn Read) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "restoring is not supported",
+        ))
+    }
+}
+
+enum Entry<V> {
+    Memory(V),
+    Spilled(PathBuf),
+}
+
+/// A least recently used cache that holds at most [`Budget::max_bytes`](struct.Budget.html#structfield.max_bytes)
+/// in memory.
+///
+/// Any files that entries were spilled to are removed when the `Cache` is dropped.
+pub struct Cache<K, V> {
+    budget: Budget,
+    entries: HashMap<K, (u64, Entry<V>)>,
+    /// The in-memory entries, ordered from least to most recently used.
+    recency: BTreeMap<u64, K>,
+    clock: u64,
+    used: usize,
+    metrics: Metrics,
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Cacheable + Clone,
+{
+    /// Create an empty `Cache` with the given `Budget`.
+    pub fn new(budget: Budget) -> Self {
+        Cache {
+            budget,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            used: 0,
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// Get the `Metrics` of the `Cache` so far.
+    pub fn metrics(&self) -> Metrics {
+        self.metrics
+    }
+
+    /// The number of bytes held in memory.
+    pub fn used_bytes(&self) -> usize {
+        self.used
+    }
+
+    /// The number of entries, in memory or spilled.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether the `Cache` has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the value for `key`, restoring it into memory if it was spilled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::cache::{Budget, Cache};
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("main.rs"), File::new(b"fn main() {}"));
+    ///
+    /// // Only enough room for one snapshot, so the least recently used is dropped.
+    /// let mut cache = Cache::new(Budget::new(directory.size()));
+    /// cache.insert("v0.1.0", directory.clone());
+    /// cache.insert("v0.2.0", directory.clone());
+    ///
+    /// assert_eq!(cache.get(&"v0.1.0"), None);
+    /// assert_eq!(cache.get(&"v0.2.0"), Some(directory));
+    /// assert_eq!(cache.metrics().evictions, 1);
+    /// ```
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        let restored = match self.entries.get(key) {
+            None => {
+                self.metrics.misses += 1;
+                return None;
+            }
+            Some((_, Entry::Memory(_))) => None,
+            Some((_, Entry::Spilled(path))) => {
+                let restored =
+                    fs::File::open(path).and_then(|file| V::restore(&mut BufReader::new(file)));
+                let _ = fs::remove_file(path);
+                Some(restored)
+            }
+        };
+
+        match restored {
+            // The spill file is gone or corrupt, so forget about the entry.
+            Some(Err(_)) => {
+                self.entries.remove(key);
+                self.metrics.misses += 1;
+                return None;
+            }
+            Some(Ok(value)) => {
+                self.used += value.weight();
+                if let Some(entry) = self.entries.get_mut(key) {
+                    entry.1 = Entry::Memory(value);
+                }
+            }
+            None => {}
+        }
+
+        self.metrics.hits += 1;
+        self.touch(key);
+        let value = match self.entries.get(key) {
+            Some((_, Entry::Memory(value))) => Some(value.clone()),
+            _ => None,
+        };
+        self.evict();
+        value
+    }
+
+    /// Insert the `value` for `key`, replacing any previous value and evicting
+    /// the least recently used entries if the budget is exceeded.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.remove(&key);
+        self.used += value.weight();
+        self.entries.insert(key.clone(), (0, Entry::Memory(value)));
+        self.touch(&key);
+        self.evict();
+    }
+
+    /// Remove the value for `key`, returning whether there was one.
+    pub fn remove(&mut self, key: &K) -> bool {
+        match self.entries.remove(key) {
+            None => false,
+            Some((tick, Entry::Memory(value))) => {
+                self.recency.remove(&tick);
+                self.used -= value.weight();
+                true
+            }
+            Some((_, Entry::Spilled(path))) => {
+                let _ = fs::remove_file(path);
+                true
+            }
+        }
+    }
+
+    /// Get the value for `key`, or compute it with `f` and insert it if it is
+    /// missing.
+    pub fn get_or_insert_with<F, E>(&mut self, key: K, f: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+
+        let value = f()?;
+        self.insert(key, value.clone());
+        Ok(value)
+    }
+
+    /// Mark the entry for `key` as the most recently used.
+    fn touch(&mut self, key: &K) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.0);
+            self.clock += 1;
+            entry.0 = self.clock;
+            self.recency.insert(self.clock, key.clone());
+        }
+    }
+
+    /// Evict the least recently used in-memory entries until we are within budget.
+    fn evict(&mut self) {
+        while self.used > self.budget.max_bytes {
+            let tick = match self.recency.keys().next() {
+                Some(tick) => *tick,
+                None => break,
+            };
+            let key = match self.recency.remove(&tick) {
+                Some(key) => key,
+                None => break,
+            };
+            let value = match self.entries.remove(&key) {
+                Some((_, Entry::Memory(value))) => value,
+                Some(spilled) => {
+                    self.entries.insert(key, spilled);
+                    continue;
+                }
+                None => continue,
+            };
+
+            self.used -= value.weight();
+            match self.spill(&value) {
+                Some(path) => {
+                    self.entries.insert(key, (tick, Entry::Spilled(path)));
+                    self.metrics.spills += 1;
+                }
+                None => self.metrics.evictions += 1,
+            }
+        }
+    }
+
+    /// Spill the `value` to a new file in the spill directory, if there is one.
+    fn spill(&self, value: &V) -> Option<PathBuf> {
+        let spill_dir = self.budget.spill_dir.as_ref()?;
+        let path = spill_dir.join(format!(
+            "radicle-surf-{}-{}.spill",
+            process::id(),
+            SPILL_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+
+        let spilled = fs::File::create(&path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            let spilled = value.spill(&mut writer)?;
+            writer.flush()?;
+            Ok(spilled)
+        });
+
+        match spilled {
+            Ok(true) => Some(path),
+            _ => {
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+}
+
+impl<K, V> Drop for Cache<K, V> {
+    fn drop(&mut self) {
+        for (_, entry) in self.entries.values() {
+            if let Entry::Spilled(path) = entry {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// A `Directory` weighs the size of its files, and is spilled by writing
+/// out the contents and mode of each file, the target of each symbolic
+/// link, and the commit of each submodule, along with its `Path`. A
+/// `Directory` with lazy files, or with entries that carry
+/// [`Metadata`](../file_system/struct.Metadata.html), e.g. the `Sha1`s the git
+/// backend attaches, is not spilled.
+impl Cacheable for Directory {
+    fn weight(&self) -> usize {
+        self.size()
+    }
+
+    fn spill(&self, writer: &mut dyn Write) -> io::Result<bool> {
+        // Lazy files would have to be read to be spilled, which is left to their owner,
+        // and metadata can't be written out, so restoring it would lose it.
+        if self.files().any(|(_, file)| !file.is_loaded()) || self.has_metadata() {
+            return Ok(false);
+        }
+
+        let current = self.current();
+        if current.is_root() {
+            write_u64(writer, 0)?;
+        } else {
+            write_u64(writer, 1)?;
+            write_bytes(writer, current.as_bytes())?;
+        }
+
+        let files = self
+            .walk()
+            .filter_map(|(path, contents)| match contents {
+                DirectoryContents::File { file, .. } => Some((path, file)),
+                DirectoryContents::Symlink { target, .. } => Some((
+                    path,
+                    File::new(target.as_bytes()).with_entry_mode(FileMode::Symlink),
+                )),
+                DirectoryContents::Submodule { commit, .. } => Some((
+                    path,
+                    File::new(commit.as_bytes()).with_entry_mode(FileMode::Submodule),
+                )),
+                DirectoryContents::Directory(directory) if directory.is_empty() => Some((
+                    path,
+                    File::new(&[]).with_entry_mode(FileMode::EmptyDirectory),
+                )),
+                DirectoryContents::Directory(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        write_u64(writer, files.len() as u64)?;
+        for (path, file) in files {
+            write_u64(writer, path.0.len() as u64)?;
+            for label in path.iter() {
+                write_bytes(writer, label.as_bytes())?;
+            }
+            let contents = file
+                .contents()
+                .map_err(|err| io::Error::other(format!("{:?}", err)))?;
+            write_bytes(writer, contents)?;
+            write_u64(
+                writer,
+                match file.mode() {
+                    FileMode::Regular => 0,
+                    FileMode::Executable => 1,
+                    FileMode::Symlink => 2,
+                    FileMode::Submodule => 3,
+                    FileMode::EmptyDirectory => 4,
+                },
+            )?;
+        }
+
+        Ok(true)
+    }
+
+    fn restore(reader: &mut dyn Read) -> io::Result<Self> {
+        let mut directory = match read_u64(reader)? {
+            0 => Directory::root(),
+            _ => Directory::new(read_label(reader)?),
+        };
+
+        for _ in 0..read_u64(reader)? {
+            let mut labels = vec![];
+            for _ in 0..read_u64(reader)? {
+                labels.push(read_label(reader)?);
+            }
+            let contents = read_bytes(reader)?;
+            let mode = match read_u64(reader)? {
+                0 => FileMode::Regular,
+                1 => FileMode::Executable,
+                2 => FileMode::Symlink,
+                3 => FileMode::Submodule,
+                4 => FileMode::EmptyDirectory,
+                _ => return Err(invalid_data("spilled file has an unknown mode")),
+            };
+
+            let (first, rest) = labels
+                .split_first()
+                .ok_or_else(|| invalid_data("spilled file has an empty path"))?;
+            directory.insert_file(
+                &Path::from_labels(first.clone(), rest),
+                File::from_vec(contents).with_entry_mode(mode),
+            );
+        }
+
+        Ok(directory)
+    }
+}
+
+/// A `Diff` weighs the entries it holds along with the `Label`s of the `Path`s they
+/// mention, and is not spilled.
+impl Cacheable for Diff {
+    fn weight(&self) -> usize {
+        let path_weight = |path: &Path| {
+            path.iter()
+                .map(|label| size_of::<Label>() + label.as_bytes().len())
+                .sum::<usize>()
+        };
+
+        size_of::<Diff>()
+            + self
+                .created
+                .iter()
+                .map(|created| size_of::<CreateFile>() + path_weight(&created.0))
+                .sum::<usize>()
+            + self
+                .deleted
+                .iter()
+                .map(|deleted| size_of::<DeleteFile>() + path_weight(&deleted.0))
+                .sum::<usize>()
+            + self
+                .moved
+                .iter()
+                .map(|moved| {
+                    size_of::<MoveFile>()
+                        + path_weight(&moved.old_path)
+                        + path_weight(&moved.new_path)
+                })
+                .sum::<usize>()
+            + self
+                .modified
+                .iter()
+                .map(|modified| size_of::<ModifiedFile>() + path_weight(&modified.path))
+                .sum::<usize>()
+    }
+}
+
+fn invalid_data(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+fn write_u64(writer: &mut dyn Write, n: u64) -> io::Result<()> {
+    writer.write_all(&n.to_be_bytes())
+}
+
+fn write_bytes(writer: &mut dyn Write, bytes: &[u8]) -> io::Result<()> {
+    write_u64(writer, bytes.len() as u64)?;
+    writer.write_all(bytes)
+}
+
+fn read_u64(reader: &mut dyn Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+fn read_bytes(reader: &mut dyn Read) -> io::Result<Vec<u8>> {
+    let len = read_u64(reader)?;
+    let mut bytes = vec![];
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(invalid_data("spilled bytes are truncated"));
+    }
+    Ok(bytes)
+}
+
+fn read_label(reader: &mut dyn Read) -> io::Result<Label> {
+    let bytes = read_bytes(reader)?;
+    Label::try_from_bytes(&bytes, &DefaultLabelRules)
+        .map_err(|_| invalid_data("spilled label is invalid"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::unsound;
+
+    fn directory(files: &[(&str, &[u8])]) -> Directory {
+        let mut directory = Directory::root();
+        for (path, contents) in files {
+            directory.insert_file(&unsound::path::new(path), File::new(contents));
+        }
+        directory
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let a = directory(&[("a.txt", b"aaaa")]);
+        let b = directory(&[("b.txt", b"bbbb")]);
+        let c = directory(&[("c.txt", b"cccc")]);
+        let mut cache = Cache::new(Budget::new(8));
+
+        cache.insert("a", a.clone());
+        cache.insert("b", b);
+        assert_eq!(cache.get(&"a"), Some(a.clone()));
+
+        cache.insert("c", c.clone());
+
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(a));
+        assert_eq!(cache.get(&"c"), Some(c));
+        assert_eq!(cache.used_bytes(), 8);
+        assert_eq!(
+            cache.metrics(),
+            Metrics {
+                hits: 3,
+                misses: 1,
+                evictions: 1,
+                spills: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_spill_and_restore() {
+        let spill_dir = tempfile::tempdir().unwrap();
+
+        let mut nested = directory(&[
+            ("README.md", b"# Surf"),
+            ("src/lib.rs", b"pub mod cache;"),
+            ("src/cache/mod.rs", b""),
+        ]);
+        nested.insert_file(
+            &unsound::path::new("bin/surf"),
+            File::new(b"#!/bin/sh").with_mode(FileMode::Executable),
+        );
+        nested.insert_symlink(&unsound::path::new("bin/cache"), "surf");
+        nested.insert_submodule(
+            &unsound::path::new("vendor/surf"),
+            "3873745c8f6ffb45c990eb23b491d4b4b6182f95",
+        );
+        let other = directory(&[("other.rs", b"fn other() {}")]);
+
+        {
+            let mut cache = Cache::new(Budget::with_spill_dir(
+                nested.size(),
+                spill_dir.path().to_path_buf(),
+            ));
+            cache.insert("nested", nested.clone());
+            cache.insert("other", other.clone());
+
+            assert_eq!(cache.metrics().spills, 1);
+            assert_eq!(cache.len(), 2);
+            assert_eq!(cache.get(&"nested"), Some(nested));
+            assert_eq!(cache.metrics().spills, 2);
+            assert_eq!(cache.get(&"other"), Some(other));
+        }
+
+        // Dropping the cache cleans up after itself.
+        assert_eq!(fs::read_dir(spill_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_metadata_is_not_spilled() {
+        let spill_dir = tempfile::tempdir().unwrap();
+
+        let mut tagged = Directory::root();
+        tagged.insert_file(
+            &unsound::path::new("README.md"),
+            File::new(b"# Surf").with_metadata(42u32),
+        );
+        let other = directory(&[("other.rs", b"fn other() {}")]);
+
+        let mut cache = Cache::new(Budget::with_spill_dir(
+            tagged.size(),
+            spill_dir.path().to_path_buf(),
+        ));
+        cache.insert("tagged", tagged);
+        cache.insert("other", other.clone());
+
+        // Restoring the entry would lose its metadata, so it is dropped instead,
+        // while the other one is spilled as it is too large for the budget.
+        assert_eq!(cache.metrics().evictions, 1);
+        assert_eq!(cache.metrics().spills, 1);
+        assert_eq!(cache.get(&"tagged"), None);
+        assert_eq!(cache.get(&"other"), Some(other));
+    }
+
+    #[test]
+    fn test_diff_weight() {
+        let path = unsound::path::new("~/src/lib.rs");
+        let mut diff = Diff::diff(Directory::root(), Directory::root()).unwrap();
+        let empty = diff.weight();
+        diff.created.push(CreateFile(path.clone()));
+
+        // The entry and each of its labels weigh more than the bytes of the labels.
+        assert!(diff.weight() - empty > "~srclib.rs".len() + 3 * size_of::<Label>());
+    }
+}
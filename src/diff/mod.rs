@@ -1,12 +1,22 @@
 #![allow(dead_code, unused_variables, missing_docs)]
 
-use crate::file_system::{Directory, DirectoryContents, Label, Path};
+use crate::file_system::digest::Digest;
+use crate::file_system::error::Error;
+use crate::file_system::{Directory, DirectoryContents, File, Label, Path};
+use nonempty::NonEmpty;
 use std::cell::RefCell;
-use std::cmp::Ordering;
-use std::ops::Deref;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter::Peekable;
 use std::rc::Rc;
+use std::vec;
 
-#[derive(Debug)]
+/// The most edits [`Diff::line_changes`] looks for, which bounds its memory, in
+/// `O(MAX_EDITS ^ 2)`, and time, for files that have little in common.
+const MAX_EDITS: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DiffError {
     reason: String,
 }
@@ -17,7 +27,15 @@ impl From<String> for DiffError {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl fmt::Display for DiffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+impl std::error::Error for DiffError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Diff {
     pub created: Vec<CreateFile>,
     pub deleted: Vec<DeleteFile>,
@@ -25,25 +43,25 @@ pub struct Diff {
     pub modified: Vec<ModifiedFile>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CreateFile(pub Path);
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DeleteFile(pub Path);
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MoveFile {
     pub old_path: Path,
     pub new_path: Path,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ModifiedFile {
     pub path: Path,
     pub diff: FileDiff,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FileDiff {
     // TODO
 }
@@ -74,113 +92,494 @@ impl Diff {
         Ok(diff)
     }
 
+    /// Summarise the `Diff` in a short sentence, e.g. for notifications, listing at most
+    /// `max_items` changes.
+    ///
+    /// Changes to files in the same top-level directory are grouped together, and
+    /// the changes are listed in the order: updated, added, deleted and moved, with the
+    /// largest groups first. The summary for the same `Diff` is always the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::diff::Diff;
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut old = Directory::root();
+    /// old.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// old.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod diff;"));
+    /// old.insert_file(&unsound::path::new("src/diff.rs"), File::new(b"pub struct Diff;"));
+    /// old.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    /// old.insert_file(&unsound::path::new("docs/a.md"), File::new(b"a"));
+    /// old.insert_file(&unsound::path::new("docs/b.md"), File::new(b"b"));
+    ///
+    /// let mut new = Directory::root();
+    /// new.insert_file(&unsound::path::new("README.md"), File::new(b"# Radicle Surf"));
+    /// new.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod diff;\n"));
+    /// new.insert_file(&unsound::path::new("src/diff.rs"), File::new(b"pub enum Diff {}"));
+    /// new.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod git;"));
+    ///
+    /// let diff = Diff::diff(old, new).unwrap();
+    ///
+    /// assert_eq!(
+    ///     diff.human_summary(3),
+    ///     "3 files changed in src/, README.md updated, 2 files deleted in docs/"
+    /// );
+    /// assert_eq!(
+    ///     diff.human_summary(1),
+    ///     "3 files changed in src/, and 3 more files"
+    /// );
+    /// ```
+    pub fn human_summary(&self, max_items: usize) -> String {
+        let mut items = vec![];
+        items.extend(Self::summary_items(
+            self.modified.iter().map(|modified| &modified.path),
+            "updated",
+            "changed",
+        ));
+        items.extend(Self::summary_items(
+            self.created.iter().map(|created| &created.0),
+            "added",
+            "added",
+        ));
+        items.extend(Self::summary_items(
+            self.deleted.iter().map(|deleted| &deleted.0),
+            "deleted",
+            "deleted",
+        ));
+        items.extend(Self::summary_items(
+            self.moved.iter().map(|moved| &moved.new_path),
+            "moved",
+            "moved",
+        ));
+
+        let files = |count: usize| {
+            if count == 1 {
+                "1 file".to_string()
+            } else {
+                format!("{} files", count)
+            }
+        };
+
+        if items.is_empty() {
+            return "no changes".to_string();
+        }
+        if max_items == 0 {
+            let total = items.iter().map(|(count, _)| count).sum();
+            return format!("{} changed", files(total));
+        }
+
+        let rest = items.split_off(max_items.min(items.len()));
+        let mut summary = items.into_iter().map(|(_, item)| item).collect::<Vec<_>>();
+        if !rest.is_empty() {
+            let more: usize = rest.iter().map(|(count, _)| count).sum();
+            summary.push(if more == 1 {
+                "and 1 more file".to_string()
+            } else {
+                format!("and {} more files", more)
+            });
+        }
+        summary.join(", ")
+    }
+
+    /// Compute a patch id for the `Diff` between the `old` and `new` directories it
+    /// was made from, using the [`Digest`](../file_system/digest/trait.Digest.html)
+    /// algorithm `D`, like `git patch-id`.
+    ///
+    /// The patch id only depends on the lines that were removed and added in each
+    /// changed file, ignoring whitespace, line numbers, and unchanged lines. So the
+    /// same change made on top of two different versions of a file, e.g. a commit
+    /// and its cherry-pick, has the same patch id. It fails if the contents of a
+    /// lazy file can't be read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "blake3")] {
+    /// use radicle_surf::diff::Diff;
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::digest::Blake3;
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let directory = |contents: &[u8]| {
+    ///     let mut root = Directory::root();
+    ///     root.insert_file(&unsound::path::new("src/lib.rs"), File::new(contents));
+    ///     root
+    /// };
+    /// let patch_id = |old: &[u8], new: &[u8]| {
+    ///     let (old, new) = (directory(old), directory(new));
+    ///     let diff = Diff::diff(old.clone(), new.clone()).unwrap();
+    ///     diff.patch_id_with::<Blake3>(&old, &new).unwrap()
+    /// };
+    ///
+    /// let on_main = patch_id(b"mod a;\nmod b;\n", b"mod a;\nmod b;\nmod c;\n");
+    /// let on_branch = patch_id(b"mod z;\nmod b;\n", b"mod z;\nmod b;\n  mod c;\n");
+    /// let other = patch_id(b"mod a;\nmod b;\n", b"mod a;\nmod b;\nmod d;\n");
+    ///
+    /// assert_eq!(on_main, on_branch);
+    /// assert_ne!(on_main, other);
+    /// # }
+    /// ```
+    pub fn patch_id_with<D>(&self, old: &Directory, new: &Directory) -> Result<D::Output, Error>
+    where
+        D: Digest,
+    {
+        let mut changes = vec![];
+        changes.extend(self.created.iter().map(|created| (&created.0, &created.0)));
+        changes.extend(self.deleted.iter().map(|deleted| (&deleted.0, &deleted.0)));
+        changes.extend(
+            self.modified
+                .iter()
+                .map(|modified| (&modified.path, &modified.path)),
+        );
+        changes.extend(
+            self.moved
+                .iter()
+                .map(|moved| (&moved.old_path, &moved.new_path)),
+        );
+        changes.sort_by(|left, right| left.1.cmp(right.1).then_with(|| left.0.cmp(right.0)));
+
+        let mut patch = vec![];
+        for (old_path, new_path) in changes {
+            let old_contents = Self::contents(old, old_path).unwrap_or_else(|| File::new(&[]));
+            let new_contents = Self::contents(new, new_path).unwrap_or_else(|| File::new(&[]));
+
+            // Binary files are not diffed line by line, their contents are used as is.
+            if old_contents.is_binary()? || new_contents.is_binary()? {
+                Self::patch_header(&mut patch, b"binary", old_path, new_path);
+                for contents in &[&old_contents, &new_contents] {
+                    patch.extend_from_slice(&contents.size().to_le_bytes());
+                    patch.extend_from_slice(contents.contents()?);
+                }
+                continue;
+            }
+            let old_lines = old_contents
+                .contents()?
+                .split(|byte| *byte == b'\n')
+                .collect::<Vec<_>>();
+            let new_lines = new_contents
+                .contents()?
+                .split(|byte| *byte == b'\n')
+                .collect::<Vec<_>>();
+
+            Self::patch_header(&mut patch, b"diff", old_path, new_path);
+            for (sign, line) in Self::line_changes(&old_lines, &new_lines) {
+                patch.push(sign);
+                patch.extend(line.iter().filter(|byte| !byte.is_ascii_whitespace()));
+                patch.push(b'\n');
+            }
+        }
+
+        Ok(D::digest(&patch))
+    }
+
+    /// Write the header of a file's changes to `patch`, with the bytes of the paths,
+    /// so that paths that are not valid UTF-8 are kept apart.
+    fn patch_header(patch: &mut Vec<u8>, kind: &[u8], old_path: &Path, new_path: &Path) {
+        patch.extend_from_slice(kind);
+        for path in &[old_path, new_path] {
+            patch.push(b' ');
+            for (index, label) in path.iter().enumerate() {
+                if index > 0 {
+                    patch.push(b'/');
+                }
+                patch.extend_from_slice(label.as_bytes());
+            }
+        }
+        patch.push(b'\n');
+    }
+
+    /// Compute a patch id for the `Diff` between the `old` and `new` directories, using
+    /// the [`Blake3`](../file_system/digest/struct.Blake3.html) digest algorithm.
+    ///
+    /// See [`patch_id_with`](#method.patch_id_with).
+    #[cfg(feature = "blake3")]
+    pub fn patch_id(&self, old: &Directory, new: &Directory) -> Result<[u8; 32], Error> {
+        self.patch_id_with::<crate::file_system::digest::Blake3>(old, new)
+    }
+
+    /// The file, or the target of the symbolic link, or the commit of the submodule,
+    /// at `path`, which may start with the root `Label`.
+    fn contents(directory: &Directory, path: &Path) -> Option<File> {
+        let labels = path
+            .iter()
+            .skip_while(|label| label.is_root())
+            .cloned()
+            .collect::<Vec<_>>();
+        let path = Path(NonEmpty::from_slice(&labels)?);
+        directory
+            .find_file(&path)
+            .or_else(|| {
+                directory
+                    .find_symlink(&path)
+                    .map(|target| File::from_vec(target.into_bytes()))
+            })
+            .or_else(|| {
+                directory
+                    .find_submodule(&path)
+                    .map(|commit| File::from_vec(commit.into_bytes()))
+            })
+    }
+
+    /// The lines removed from `old`, marked with a `-`, and added in `new`, marked
+    /// with a `+`, in the order they appear, given by the shortest edit script of the
+    /// lines between their common prefix and suffix.
+    ///
+    /// The edit script is found with Myers' algorithm, in `O((n + m) * d)` time for
+    /// `n` and `m` lines and `d` edits. Beyond [`MAX_EDITS`] edits, all of the lines
+    /// of `old` are removed, and all of the lines of `new` are added, instead.
+    fn line_changes<'a>(old: &[&'a [u8]], new: &[&'a [u8]]) -> Vec<(u8, &'a [u8])> {
+        let prefix = old
+            .iter()
+            .zip(new.iter())
+            .take_while(|(old, new)| old == new)
+            .count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(old, new)| old == new)
+            .count();
+        let old = &old[prefix..old.len() - suffix];
+        let new = &new[prefix..new.len() - suffix];
+
+        let trace = match Self::shortest_edit(old, new) {
+            Some(trace) => trace,
+            None => {
+                let removed = old.iter().map(|line| (b'-', *line));
+                return removed
+                    .chain(new.iter().map(|line| (b'+', *line)))
+                    .collect();
+            }
+        };
+
+        // Walk back from the end through the furthest reaching paths, where a step
+        // down the diagonal `k + 1` is an added line, and a step right from the
+        // diagonal `k - 1` is a removed line.
+        let mut changes = vec![];
+        let (mut x, mut y) = (old.len(), new.len());
+        for d in (1..trace.len()).rev() {
+            let previous = &trace[d - 1];
+            let k = x as isize - y as isize;
+            let at = |k: isize| previous[(k + d as isize - 1) as usize];
+            let down = k == -(d as isize) || (k != d as isize && at(k - 1) < at(k + 1));
+            let previous_k = if down { k + 1 } else { k - 1 };
+            let previous_x = at(previous_k);
+            let previous_y = (previous_x as isize - previous_k) as usize;
+            if down {
+                changes.push((b'+', new[previous_y]));
+            } else {
+                changes.push((b'-', old[previous_x]));
+            }
+            x = previous_x;
+            y = previous_y;
+        }
+        changes.reverse();
+        changes
+    }
+
+    /// Find the furthest reaching path on each diagonal `k`, for each number of edits
+    /// `d` up to the shortest edit script from `old` to `new`, as `trace[d][k + d]`,
+    /// the number of lines of `old` the path covers, or `None` if the script has more
+    /// than [`MAX_EDITS`] edits.
+    fn shortest_edit(old: &[&[u8]], new: &[&[u8]]) -> Option<Vec<Vec<usize>>> {
+        let (n, m) = (old.len() as isize, new.len() as isize);
+        let mut trace: Vec<Vec<usize>> = vec![];
+        for d in 0..=(old.len() + new.len()).min(MAX_EDITS) {
+            let d = d as isize;
+            let mut furthest = vec![0; 2 * d as usize + 1];
+            for k in (-d..=d).step_by(2) {
+                let mut x = match trace.last() {
+                    None => 0,
+                    Some(previous) => {
+                        let at = |k: isize| previous[(k + d - 1) as usize] as isize;
+                        if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                            at(k + 1)
+                        } else {
+                            at(k - 1) + 1
+                        }
+                    }
+                };
+                let mut y = x - k;
+                while x < n && y < m && old[x as usize] == new[y as usize] {
+                    x += 1;
+                    y += 1;
+                }
+                furthest[(k + d) as usize] = x as usize;
+                if x >= n && y >= m {
+                    trace.push(furthest);
+                    return Some(trace);
+                }
+            }
+            trace.push(furthest);
+        }
+        None
+    }
+
+    /// Describe the change `verb` of `paths`, grouping the files by their top-level
+    /// directory and using `group_verb` for groups of more than one file. The items
+    /// are returned along with the number of files in them, largest first.
+    fn summary_items<'a>(
+        paths: impl Iterator<Item = &'a Path>,
+        verb: &str,
+        group_verb: &str,
+    ) -> Vec<(usize, String)> {
+        let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for path in paths {
+            let labels = path
+                .iter()
+                .filter(|label| !label.is_root())
+                .map(|label| label.to_string())
+                .collect::<Vec<_>>();
+            let file = labels.join("/");
+            let group = match labels.split_first() {
+                Some((directory, rest)) if !rest.is_empty() => format!("{}/", directory),
+                _ => file.clone(),
+            };
+            groups.entry(group).or_default().push(file);
+        }
+
+        let mut items = groups
+            .into_iter()
+            .map(|(group, files)| match files.as_slice() {
+                [file] => (1, format!("{} {}", file, verb)),
+                _ => (
+                    files.len(),
+                    format!("{} files {} in {}", files.len(), group_verb, group),
+                ),
+            })
+            .collect::<Vec<_>>();
+        items.sort_by_key(|(count, _)| Reverse(*count));
+        items
+    }
+
     fn collect_diff(
         old: &Directory,
         new: &Directory,
         parent_path: &Rc<RefCell<Path>>,
         diff: &mut Diff,
     ) -> Result<(), String> {
-        let mut old_iter = old.iter();
-        let mut new_iter = new.iter();
-        let mut old_entry_opt = old_iter.next();
-        let mut new_entry_opt = new_iter.next();
-
-        while old_entry_opt.is_some() || new_entry_opt.is_some() {
-            match (&old_entry_opt, &new_entry_opt) {
-                (Some(ref old_entry), Some(ref new_entry)) => {
-                    match new_entry.label().cmp(&old_entry.label()) {
-                        Ordering::Greater => {
-                            diff.add_deleted_files(old_entry, parent_path)?;
-                            old_entry_opt = old_iter.next();
-                        }
-                        Ordering::Less => {
-                            diff.add_created_files(new_entry, parent_path)?;
-                            new_entry_opt = new_iter.next();
-                        }
-                        Ordering::Equal => match (new_entry, old_entry) {
-                            (
-                                DirectoryContents::File {
-                                    name: new_file_name,
-                                    file: new_file,
-                                },
-                                DirectoryContents::File {
-                                    name: old_file_name,
-                                    file: old_file,
-                                },
-                            ) => {
-                                if old_file.size != new_file.size
-                                    || old_file.checksum() != new_file.checksum()
-                                {
-                                    diff.add_modified_file(
-                                        &new_file_name,
-                                        &RefCell::borrow(parent_path),
-                                    );
-                                }
-                                old_entry_opt = old_iter.next();
-                                new_entry_opt = new_iter.next();
-                            }
-                            (
-                                DirectoryContents::File {
-                                    name: new_file_name,
-                                    file: new_file,
-                                },
-                                DirectoryContents::Directory(old_dir),
-                            ) => {
-                                diff.add_created_file(
-                                    &new_file_name,
-                                    &RefCell::borrow(parent_path),
-                                );
-                                diff.add_deleted_files(old_entry, parent_path)?;
-                                old_entry_opt = old_iter.next();
-                                new_entry_opt = new_iter.next();
-                            }
-                            (
-                                DirectoryContents::Directory(new_dir),
-                                DirectoryContents::File {
-                                    name: old_file_name,
-                                    file: old_file,
-                                },
-                            ) => {
-                                diff.add_created_files(new_entry, parent_path)?;
-                                diff.add_deleted_file(
-                                    &old_file_name,
-                                    &RefCell::borrow(parent_path),
-                                );
-                                old_entry_opt = old_iter.next();
-                                new_entry_opt = new_iter.next();
-                            }
-                            (
-                                DirectoryContents::Directory(new_dir),
-                                DirectoryContents::Directory(old_dir),
-                            ) => {
-                                parent_path.borrow_mut().push(new_dir.current().clone());
-                                Diff::collect_diff(
-                                    old_dir.deref(),
-                                    new_dir.deref(),
-                                    parent_path,
-                                    diff,
-                                )?;
-                                parent_path.borrow_mut().pop();
-                                old_entry_opt = old_iter.next();
-                                new_entry_opt = new_iter.next();
-                            }
-                        },
+        // The entries left to compare in each directory being compared, the
+        // directories below the root having their labels pushed to `parent_path`.
+        let mut stack = vec![(Diff::entries(old), Diff::entries(new))];
+        while let Some((old_entries, new_entries)) = stack.last_mut() {
+            let order = match (old_entries.peek(), new_entries.peek()) {
+                (Some(old_entry), Some(new_entry)) => new_entry.label().cmp(&old_entry.label()),
+                (Some(_), None) => Ordering::Greater,
+                (None, Some(_)) => Ordering::Less,
+                (None, None) => {
+                    stack.pop();
+                    if !stack.is_empty() {
+                        parent_path.borrow_mut().pop();
+                    }
+                    continue;
+                }
+            };
+            let (old_entry, new_entry) = match order {
+                Ordering::Greater => {
+                    if let Some(old_entry) = old_entries.next() {
+                        diff.add_deleted_files(&old_entry, parent_path)?;
+                    }
+                    continue;
+                }
+                Ordering::Less => {
+                    if let Some(new_entry) = new_entries.next() {
+                        diff.add_created_files(&new_entry, parent_path)?;
+                    }
+                    continue;
+                }
+                Ordering::Equal => match (old_entries.next(), new_entries.next()) {
+                    (Some(old_entry), Some(new_entry)) => (old_entry, new_entry),
+                    _ => continue,
+                },
+            };
+
+            match (&new_entry, &old_entry) {
+                (
+                    DirectoryContents::File {
+                        name: new_file_name,
+                        file: new_file,
+                    },
+                    DirectoryContents::File { file: old_file, .. },
+                ) => {
+                    let same = old_file.size == new_file.size
+                        && old_file
+                            .same_contents(new_file)
+                            .map_err(|err| format!("{:?}", err))?;
+                    if !same {
+                        diff.add_modified_file(new_file_name, &RefCell::borrow(parent_path));
+                    }
+                }
+                (
+                    DirectoryContents::File {
+                        name: new_file_name,
+                        ..
+                    },
+                    DirectoryContents::Directory(_),
+                ) => {
+                    diff.add_created_file(new_file_name, &RefCell::borrow(parent_path));
+                    diff.add_deleted_files(&old_entry, parent_path)?;
+                }
+                (
+                    DirectoryContents::Directory(_),
+                    DirectoryContents::File {
+                        name: old_file_name,
+                        ..
+                    },
+                ) => {
+                    diff.add_created_files(&new_entry, parent_path)?;
+                    diff.add_deleted_file(old_file_name, &RefCell::borrow(parent_path));
+                }
+                (DirectoryContents::Directory(new_dir), DirectoryContents::Directory(old_dir)) => {
+                    parent_path.borrow_mut().push(new_dir.current());
+                    stack.push((Diff::entries(old_dir), Diff::entries(new_dir)));
+                }
+                (
+                    DirectoryContents::Symlink {
+                        name: new_link_name,
+                        target: new_target,
+                    },
+                    DirectoryContents::Symlink {
+                        target: old_target, ..
+                    },
+                ) => {
+                    if old_target != new_target {
+                        diff.add_modified_file(new_link_name, &RefCell::borrow(parent_path));
+                    }
+                }
+                (
+                    DirectoryContents::Submodule {
+                        name: new_submodule_name,
+                        commit: new_commit,
+                    },
+                    DirectoryContents::Submodule {
+                        commit: old_commit, ..
+                    },
+                ) => {
+                    if old_commit != new_commit {
+                        diff.add_modified_file(new_submodule_name, &RefCell::borrow(parent_path));
                     }
                 }
-                (Some(ref old_entry), None) => {
-                    diff.add_deleted_files(old_entry, parent_path)?;
-                    old_entry_opt = old_iter.next();
+                (DirectoryContents::Directory(_), _) | (_, DirectoryContents::Directory(_)) => {
+                    diff.add_created_files(&new_entry, parent_path)?;
+                    diff.add_deleted_files(&old_entry, parent_path)?;
                 }
-                (None, Some(ref new_entry)) => {
-                    diff.add_created_files(new_entry, parent_path)?;
-                    new_entry_opt = new_iter.next();
+                // A file, symbolic link or submodule replaced by another one of these.
+                _ => {
+                    diff.add_modified_file(&new_entry.label(), &RefCell::borrow(parent_path));
                 }
-                (None, None) => break,
             }
         }
         Ok(())
     }
 
+    /// The entries of `dir`, to be compared by [`collect_diff`](#method.collect_diff)
+    /// in the order of their labels.
+    fn entries(dir: &Directory) -> Peekable<vec::IntoIter<DirectoryContents>> {
+        dir.iter().collect::<Vec<_>>().into_iter().peekable()
+    }
+
     // if entry is a file, then return this file,
     // or a list of files in the directory tree otherwise
     fn collect_files_from_entry<F, T>(
@@ -193,7 +592,9 @@ impl Diff {
     {
         match entry {
             DirectoryContents::Directory(dir) => Diff::collect_files(dir, parent_path, mapper),
-            DirectoryContents::File { name, .. } => {
+            DirectoryContents::File { name, .. }
+            | DirectoryContents::Symlink { name, .. }
+            | DirectoryContents::Submodule { name, .. } => {
                 let mapped = mapper(name, &RefCell::borrow(parent_path));
                 Ok(vec![mapped])
             }
@@ -213,8 +614,8 @@ impl Diff {
         Ok(files)
     }
 
-    fn collect_files_inner<'a, F, T>(
-        dir: &'a Directory,
+    fn collect_files_inner<F, T>(
+        dir: &Directory,
         parent_path: &Rc<RefCell<Path>>,
         mapper: F,
         files: &mut Vec<T>,
@@ -222,18 +623,26 @@ impl Diff {
     where
         F: Fn(&Label, &Path) -> T + Copy,
     {
+        // The entries left in each directory, whose labels are pushed to `parent_path`.
         parent_path.borrow_mut().push(dir.current());
-        for entry in dir.iter() {
-            match entry {
-                DirectoryContents::Directory(subdir) => {
-                    Diff::collect_files_inner(&subdir, parent_path, mapper, files)?;
+        let mut stack = vec![dir.iter().collect::<Vec<_>>().into_iter()];
+        while let Some(entries) = stack.last_mut() {
+            match entries.next() {
+                Some(DirectoryContents::Directory(subdir)) => {
+                    parent_path.borrow_mut().push(subdir.current());
+                    stack.push(subdir.iter().collect::<Vec<_>>().into_iter());
                 }
-                DirectoryContents::File { name, .. } => {
+                Some(DirectoryContents::File { name, .. })
+                | Some(DirectoryContents::Symlink { name, .. })
+                | Some(DirectoryContents::Submodule { name, .. }) => {
                     files.push(mapper(&name, &RefCell::borrow(parent_path)));
                 }
+                None => {
+                    stack.pop();
+                    parent_path.borrow_mut().pop();
+                }
             }
         }
-        parent_path.borrow_mut().pop();
         Ok(())
     }
 
@@ -381,6 +790,37 @@ mod tests {
         assert_eq!(diff, expected_diff)
     }
 
+    #[test]
+    fn test_modify_symlink() {
+        let mut directory = Directory::root();
+        directory.insert_symlink(&unsound::path::new("README"), "README.md");
+        directory.insert_symlink(&unsound::path::new("LICENSE"), "COPYING");
+
+        let mut new_directory = Directory::root();
+        new_directory.insert_symlink(&unsound::path::new("README"), "docs/README.md");
+        new_directory.insert_file(&unsound::path::new("LICENSE"), File::new(b"MIT"));
+
+        let diff = Diff::diff(directory, new_directory).expect("diff failed");
+
+        let expected_diff = Diff {
+            created: vec![],
+            deleted: vec![],
+            moved: vec![],
+            modified: vec![
+                ModifiedFile {
+                    path: Path::with_root(&[unsound::label::new("LICENSE")]),
+                    diff: FileDiff {},
+                },
+                ModifiedFile {
+                    path: Path::with_root(&[unsound::label::new("README")]),
+                    diff: FileDiff {},
+                },
+            ],
+        };
+
+        assert_eq!(diff, expected_diff)
+    }
+
     #[test]
     fn test_create_directory() {
         let directory = Directory::root();
@@ -499,4 +939,64 @@ mod tests {
         // assert_eq!(diff, expected_diff)
         assert!(true)
     }
+
+    #[test]
+    fn test_line_changes() {
+        let lines = |text: &'static str| text.split(' ').map(str::as_bytes).collect::<Vec<_>>();
+        let changes = |old: &[&'static [u8]], new: &[&'static [u8]]| {
+            Diff::line_changes(old, new)
+                .into_iter()
+                .map(|(sign, line)| format!("{}{}", sign as char, String::from_utf8_lossy(line)))
+                .collect::<Vec<_>>()
+        };
+
+        // The example of Myers' paper, which has 5 edits.
+        assert_eq!(
+            changes(&lines("a b c a b b a"), &lines("c b a b a c")),
+            vec!["-a", "-b", "+b", "-b", "+c"]
+        );
+
+        let old = (0..100_000).map(|i| i.to_string()).collect::<Vec<_>>();
+        let mut new = old.clone();
+        new[50_000] = "changed".to_string();
+        let as_lines = |lines: &[String]| {
+            lines
+                .iter()
+                .map(|line| line.as_bytes().to_vec())
+                .collect::<Vec<_>>()
+        };
+        let (old, new) = (as_lines(&old), as_lines(&new));
+        let old = old.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let new = new.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        assert_eq!(
+            Diff::line_changes(&old, &new),
+            vec![(b'-', &b"50000"[..]), (b'+', &b"changed"[..])]
+        );
+
+        // Files with nothing in common are replaced as a whole.
+        let reversed = old.iter().rev().copied().collect::<Vec<_>>();
+        let replaced = Diff::line_changes(&old[..2_000], &reversed[..2_000]);
+        assert_eq!(replaced.len(), 4_000);
+        assert!(replaced[..2_000].iter().all(|(sign, _)| *sign == b'-'));
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_patch_id_of_binary_files() {
+        let patch_id = |old: &[u8], new: &[u8]| {
+            let mut old_directory = Directory::root();
+            old_directory.insert_file(&unsound::path::new("logo.png"), File::new(old));
+            let mut new_directory = Directory::root();
+            new_directory.insert_file(&unsound::path::new("logo.png"), File::new(new));
+            let diff = Diff::diff(old_directory.clone(), new_directory.clone()).unwrap();
+            diff.patch_id(&old_directory, &new_directory)
+        };
+
+        // Whitespace is ignored in text, but is significant in binary contents.
+        assert_eq!(patch_id(b"a\nb", b"a\nb c"), patch_id(b"a\nb", b"a\nbc"));
+        assert_ne!(
+            patch_id(b"\0a\nb", b"\0a\nb c"),
+            patch_id(b"\0a\nb", b"\0a\nbc")
+        );
+    }
 }
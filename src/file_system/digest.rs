@@ -0,0 +1,149 @@
+//! Pluggable digest algorithms for computing checksums of file contents.
+//!
+//! The [`Digest`](trait.Digest.html) trait abstracts over the algorithm so
+//! that consumers can choose the stability and compatibility guarantees they
+//! need, e.g. for cache keys, via [`File::checksum_with`](../struct.File.html#method.checksum_with)
+//! and [`Directory::checksum_with`](../struct.Directory.html#method.checksum_with).
+//! Digests can be encoded as hex with [`to_hex`](fn.to_hex.html).
+//!
+//! The built-in algorithms are behind features:
+//!   * [`Blake3`](struct.Blake3.html) with the `blake3` feature, enabled by default.
+//!   * [`Sha1`](struct.Sha1.html) with the `sha1` feature.
+//!   * [`Sha256`](struct.Sha256.html) with the `sha256` feature.
+
+/// Encode a digest as lowercase hex, e.g. for a cache key or an `ETag`.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::digest::to_hex;
+///
+/// assert_eq!(to_hex(&[0x00, 0xab, 0x3f]), "00ab3f");
+/// ```
+pub fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// A digest algorithm that can be used to compute a checksum over some bytes.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::File;
+/// use radicle_surf::file_system::digest::Digest;
+///
+/// /// The sum of all bytes, which is a terrible digest.
+/// struct Sum;
+///
+/// impl Digest for Sum {
+///     const NAME: &'static str = "sum";
+///     type Output = [u8; 8];
+///
+///     fn digest(bytes: &[u8]) -> Self::Output {
+///         bytes.iter().map(|b| *b as u64).sum::<u64>().to_be_bytes()
+///     }
+/// }
+///
+/// let file = File::new(&[1, 2, 3]);
+/// assert_eq!(file.checksum_with::<Sum>(), Ok([0, 0, 0, 0, 0, 0, 0, 6]));
+/// ```
+pub trait Digest {
+    /// The name of the algorithm, e.g. `blake3`, which is written in the header of
+    /// a [`Manifest`](../struct.Manifest.html).
+    const NAME: &'static str;
+
+    /// The output of the digest, e.g. `[u8; 32]` for a 256-bit digest.
+    type Output: AsRef<[u8]>;
+
+    /// Compute the digest of `bytes`.
+    fn digest(bytes: &[u8]) -> Self::Output;
+}
+
+/// The [BLAKE3](https://github.com/BLAKE3-team/BLAKE3) digest algorithm.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::File;
+/// use radicle_surf::file_system::digest::Blake3;
+///
+/// let file = File::new(b"");
+///
+/// assert_eq!(
+///     file.checksum_with::<Blake3>().unwrap()[..4],
+///     [0xaf, 0x13, 0x49, 0xb9],
+/// );
+/// ```
+#[cfg(feature = "blake3")]
+#[derive(Debug, Clone, Copy)]
+pub struct Blake3;
+
+#[cfg(feature = "blake3")]
+impl Digest for Blake3 {
+    const NAME: &'static str = "blake3";
+    type Output = [u8; 32];
+
+    fn digest(bytes: &[u8]) -> Self::Output {
+        blake3::hash(bytes).into()
+    }
+}
+
+/// The SHA-1 digest algorithm.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::File;
+/// use radicle_surf::file_system::digest::Sha1;
+///
+/// let file = File::new(b"");
+///
+/// assert_eq!(
+///     file.checksum_with::<Sha1>().unwrap()[..4],
+///     [0xda, 0x39, 0xa3, 0xee],
+/// );
+/// ```
+#[cfg(feature = "sha1")]
+#[derive(Debug, Clone, Copy)]
+pub struct Sha1;
+
+#[cfg(feature = "sha1")]
+impl Digest for Sha1 {
+    const NAME: &'static str = "sha1";
+    type Output = [u8; 20];
+
+    fn digest(bytes: &[u8]) -> Self::Output {
+        use sha1::Digest as _;
+        sha1::Sha1::digest(bytes).into()
+    }
+}
+
+/// The SHA-256 digest algorithm.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::File;
+/// use radicle_surf::file_system::digest::Sha256;
+///
+/// let file = File::new(b"");
+///
+/// assert_eq!(
+///     file.checksum_with::<Sha256>().unwrap()[..4],
+///     [0xe3, 0xb0, 0xc4, 0x42],
+/// );
+/// ```
+#[cfg(feature = "sha256")]
+#[derive(Debug, Clone, Copy)]
+pub struct Sha256;
+
+#[cfg(feature = "sha256")]
+impl Digest for Sha256 {
+    const NAME: &'static str = "sha256";
+    type Output = [u8; 32];
+
+    fn digest(bytes: &[u8]) -> Self::Output {
+        use sha2::Digest as _;
+        sha2::Sha256::digest(bytes).into()
+    }
+}
@@ -8,12 +8,29 @@
 //! identifier of what type of `DirectoryContents` one is viewing when
 //! [listing](struct.Directory.html#method.list_directory) a directory.
 
+use crate::file_system::digest::Digest;
+#[cfg(feature = "encoding")]
+use crate::file_system::encoding::Encoding;
+use crate::file_system::error;
+use crate::file_system::ignore::Ignore;
+#[cfg(feature = "spdx")]
+use crate::file_system::license;
+use crate::file_system::lines::Lines;
+use crate::file_system::manifest::{git_mode, Manifest, ManifestEntry};
+use crate::file_system::metadata::Metadata;
+#[cfg(feature = "mime")]
+use crate::file_system::mime;
 use crate::file_system::path::*;
+use crate::file_system::sparse::SparsePatterns;
 use crate::tree::*;
 use nonempty::NonEmpty;
+use std::cmp::Ordering;
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::hash::{Hash, Hasher};
+use std::ops::Bound;
+use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
 
 /// `SystemType` is an enumeration over what can be
 /// found in a [`Directory`](struct.Directory.html)
@@ -28,6 +45,10 @@ pub enum SystemType {
     File,
     /// The `Directory` type in a directory system.
     Directory,
+    /// The `Symlink` type in a directory system.
+    Symlink,
+    /// The `Submodule` type in a directory system.
+    Submodule,
 }
 
 impl SystemType {
@@ -40,23 +61,178 @@ impl SystemType {
     pub fn directory(label: Label) -> (Label, Self) {
         (label, SystemType::Directory)
     }
+
+    /// A symbolic link name and [`SystemType::Symlink`](enum.SystemType.html#variant.Symlink).
+    pub fn symlink(label: Label) -> (Label, Self) {
+        (label, SystemType::Symlink)
+    }
+
+    /// A submodule name and [`SystemType::Submodule`](enum.SystemType.html#variant.Submodule).
+    pub fn submodule(label: Label) -> (Label, Self) {
+        (label, SystemType::Submodule)
+    }
+}
+
+/// The mode of a [`File`](struct.File.html), i.e. whether it is executable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FileMode {
+    /// A regular file, `100644` in git.
+    #[default]
+    Regular,
+    /// An executable file, `100755` in git.
+    Executable,
+    /// A symbolic link, `120000` in git, whose contents are the path it points to.
+    /// It is listed as a [`DirectoryContents::Symlink`](enum.DirectoryContents.html#variant.Symlink)
+    /// rather than a `File`.
+    Symlink,
+    /// A submodule, `160000` in git, whose contents are the id of the commit it is pinned to.
+    /// It is listed as a [`DirectoryContents::Submodule`](enum.DirectoryContents.html#variant.Submodule)
+    /// rather than a `File`.
+    Submodule,
+    /// An empty directory, `040000` in git, which has no contents. It is listed as
+    /// a [`DirectoryContents::Directory`](enum.DirectoryContents.html#variant.Directory)
+    /// with no entries, rather than a `File`, see
+    /// [`Directory::insert_empty_directory`](struct.Directory.html#method.insert_empty_directory).
+    EmptyDirectory,
+}
+
+impl FileMode {
+    /// Check whether the `File` is executable.
+    pub fn is_executable(self) -> bool {
+        self == FileMode::Executable
+    }
+
+    /// Check whether the mode is one of a file, i.e. `Regular` or `Executable`,
+    /// rather than one of another kind of entry.
+    pub fn is_file(self) -> bool {
+        match self {
+            FileMode::Regular | FileMode::Executable => true,
+            FileMode::Symlink | FileMode::Submodule | FileMode::EmptyDirectory => false,
+        }
+    }
+
+    /// Get the Unix permissions for the mode, e.g. for restoring it when
+    /// writing the `File` to disk.
+    ///
+    /// Submodules and empty directories are directories on disk rather than
+    /// files, so they have no file permissions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::FileMode;
+    ///
+    /// assert_eq!(FileMode::Regular.permissions(), Some(0o644));
+    /// assert_eq!(FileMode::Executable.permissions(), Some(0o755));
+    /// assert_eq!(FileMode::Submodule.permissions(), None);
+    /// ```
+    pub fn permissions(self) -> Option<u32> {
+        match self {
+            FileMode::Regular => Some(0o644),
+            FileMode::Executable => Some(0o755),
+            FileMode::Symlink => Some(0o777),
+            FileMode::Submodule | FileMode::EmptyDirectory => None,
+        }
+    }
+}
+
+/// The contents of a [`File`](struct.File.html) kept by a backend, e.g. a git blob,
+/// which are only read when the [`contents`](struct.File.html#method.contents) of
+/// the `File` are needed.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::{Blob, File};
+/// use radicle_surf::file_system::error::Error;
+/// use std::sync::Arc;
+///
+/// struct Greeting;
+///
+/// impl Blob for Greeting {
+///     fn id(&self) -> String {
+///         "greeting".to_string()
+///     }
+///
+///     fn read(&self) -> Result<Vec<u8>, Error> {
+///         Ok(b"Hello, world!".to_vec())
+///     }
+/// }
+///
+/// let file = File::lazy(Arc::new(Greeting), 13);
+/// assert!(!file.is_loaded());
+/// assert_eq!(file.size(), 13);
+///
+/// assert_eq!(file.contents(), Ok(&b"Hello, world!"[..]));
+/// assert!(file.is_loaded());
+/// assert_eq!(file, File::new(b"Hello, world!"));
+/// ```
+pub trait Blob: Send + Sync {
+    /// An id of the contents, which two `Blob`s share if and only if their contents
+    /// are the same, e.g. the id of a git blob, so that lazy `File`s can be compared
+    /// without reading them.
+    fn id(&self) -> String;
+
+    /// Read the contents from the backend.
+    fn read(&self) -> Result<Vec<u8>, error::Error>;
 }
 
-/// A `File` consists of its file contents (a `Vec` of bytes).
+/// The number of bytes at the start of a [`File`](struct.File.html) that are looked
+/// at by [`File::is_binary`](struct.File.html#method.is_binary), the same as git.
+pub const BINARY_CHECK_LEN: usize = 8000;
+
+/// A `File` consists of its file contents (a slice of bytes), and its [`FileMode`](enum.FileMode.html).
+///
+/// A `File` can also be lazy, holding a [`Blob`](trait.Blob.html) that its contents
+/// are read from the first time they are needed. Every method that looks at the
+/// contents gets them from [`contents`](struct.File.html#method.contents), and so
+/// fails if they can't be read.
+///
+/// The contents of a `File` are shared, so cloning a `File`, or a
+/// [`Directory`](struct.Directory.html) of them, does not copy its contents, and
+/// the contents of a lazy `File` are read once for all of its clones.
+/// [`to_vec`](struct.File.html#method.to_vec) gets a copy that is owned.
 ///
 /// The `Debug` instance of `File` will show the first few bytes of
 /// the file and its [`size`](struct.File.html#method.size).
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone)]
 pub struct File {
-    /// The contents of a `File` as a vector of bytes.
-    pub contents: Vec<u8>,
+    contents: Arc<OnceLock<Vec<u8>>>,
     pub(crate) size: usize,
+    pub(crate) mode: FileMode,
+    pub(crate) blob: Option<Arc<dyn Blob>>,
+    pub(crate) metadata: Metadata,
+}
+
+/// `File`s are equal if they have the same mode and contents, where lazy `File`s
+/// are compared by the ids of their `Blob`s, without reading them. A lazy `File`
+/// whose contents can't be read is not equal to a `File` without a `Blob`.
+impl PartialEq for File {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size
+            && self.mode == other.mode
+            && self.same_contents(other).unwrap_or(false)
+    }
+}
+
+impl Eq for File {}
+
+impl From<Vec<u8>> for File {
+    fn from(contents: Vec<u8>) -> Self {
+        File::from_vec(contents)
+    }
 }
 
 impl std::fmt::Debug for File {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut contents = self.contents.clone();
-        contents.truncate(10);
+        let contents = match (self.contents.get(), &self.blob) {
+            (Some(contents), _) => contents,
+            (None, Some(blob)) => {
+                return write!(f, "File {{ blob: {}, size: {} }}", blob.id(), self.size)
+            }
+            (None, None) => &[][..],
+        };
+        let contents = &contents[..contents.len().min(10)];
         write!(
             f,
             "File {{ contents: {:?}, size: {} }}",
@@ -65,469 +241,4376 @@ impl std::fmt::Debug for File {
     }
 }
 
+/// A [`File`](struct.File.html) is serialized as a struct with the fields `mode`
+/// and `contents`, reading the contents of a lazy `File`, which fails if they
+/// can't be read. Its [`Metadata`](struct.Metadata.html) is not serialized, and a
+/// deserialized `File` is never lazy.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::{File, FileMode};
+/// use serde::de::value::{Error, StrDeserializer};
+/// use serde::de::IntoDeserializer;
+/// use serde::Deserialize;
+///
+/// let executable: StrDeserializer<Error> = "Executable".into_deserializer();
+/// assert_eq!(FileMode::deserialize(executable), Ok(FileMode::Executable));
+///
+/// let unknown: StrDeserializer<Error> = "Socket".into_deserializer();
+/// assert!(FileMode::deserialize(unknown).is_err());
+/// ```
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{File, FileMode};
+    use serde::de::{
+        self, Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+    };
+    use serde::ser::{self, Serialize, SerializeStruct, Serializer};
+    use std::fmt;
+
+    const MODES: &[&str] = &[
+        "Regular",
+        "Executable",
+        "Symlink",
+        "Submodule",
+        "EmptyDirectory",
+    ];
+
+    const FIELDS: &[&str] = &["mode", "contents"];
+
+    impl FileMode {
+        fn index(self) -> u32 {
+            match self {
+                FileMode::Regular => 0,
+                FileMode::Executable => 1,
+                FileMode::Symlink => 2,
+                FileMode::Submodule => 3,
+                FileMode::EmptyDirectory => 4,
+            }
+        }
+
+        fn from_index(index: u64) -> Option<Self> {
+            [
+                FileMode::Regular,
+                FileMode::Executable,
+                FileMode::Symlink,
+                FileMode::Submodule,
+                FileMode::EmptyDirectory,
+            ]
+            .get(index as usize)
+            .copied()
+        }
+    }
+
+    impl Serialize for FileMode {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let index = self.index();
+            serializer.serialize_unit_variant("FileMode", index, MODES[index as usize])
+        }
+    }
+
+    impl<'de> Deserialize<'de> for FileMode {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_enum("FileMode", MODES, ModeVisitor)
+        }
+    }
+
+    /// The name or index of a `FileMode` variant.
+    struct Mode(FileMode);
+
+    impl<'de> Deserialize<'de> for Mode {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_identifier(ModeVisitor).map(Mode)
+        }
+    }
+
+    struct ModeVisitor;
+
+    impl<'de> Visitor<'de> for ModeVisitor {
+        type Value = FileMode;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a file mode")
+        }
+
+        fn visit_u64<E: de::Error>(self, index: u64) -> Result<FileMode, E> {
+            FileMode::from_index(index)
+                .ok_or_else(|| E::invalid_value(de::Unexpected::Unsigned(index), &self))
+        }
+
+        fn visit_str<E: de::Error>(self, name: &str) -> Result<FileMode, E> {
+            MODES
+                .iter()
+                .position(|mode| *mode == name)
+                .and_then(|index| FileMode::from_index(index as u64))
+                .ok_or_else(|| E::unknown_variant(name, MODES))
+        }
+
+        fn visit_enum<A>(self, data: A) -> Result<FileMode, A::Error>
+        where
+            A: EnumAccess<'de>,
+        {
+            let (Mode(mode), variant) = data.variant()?;
+            variant.unit_variant()?;
+            Ok(mode)
+        }
+    }
+
+    /// Serializes bytes as bytes rather than a sequence of numbers, for the formats
+    /// that tell them apart.
+    struct Bytes<'a>(&'a [u8]);
+
+    impl<'a> Serialize for Bytes<'a> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    /// Deserializes bytes from bytes, or from a sequence of numbers.
+    struct ByteBuf(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for ByteBuf {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_byte_buf(ByteBufVisitor)
+        }
+    }
+
+    struct ByteBufVisitor;
+
+    impl<'de> Visitor<'de> for ByteBufVisitor {
+        type Value = ByteBuf;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("the contents of a file")
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<ByteBuf, E> {
+            Ok(ByteBuf(bytes.to_vec()))
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<ByteBuf, E> {
+            Ok(ByteBuf(bytes))
+        }
+
+        fn visit_seq<S>(self, mut seq: S) -> Result<ByteBuf, S::Error>
+        where
+            S: SeqAccess<'de>,
+        {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(ByteBuf(bytes))
+        }
+    }
+
+    /// Build a `File` with any mode, rejecting an empty directory that has contents.
+    fn entry<E: de::Error>(contents: Vec<u8>, mode: FileMode) -> Result<File, E> {
+        if mode == FileMode::EmptyDirectory && !contents.is_empty() {
+            return Err(de::Error::custom("an empty directory has contents"));
+        }
+        Ok(File::from_vec(contents).with_entry_mode(mode))
+    }
+
+    impl Serialize for File {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let contents = self.contents().map_err(|err| {
+                ser::Error::custom(format!("could not read the contents of a file: {:?}", err))
+            })?;
+            let mut file = serializer.serialize_struct("File", 2)?;
+            file.serialize_field("mode", &self.mode)?;
+            file.serialize_field("contents", &Bytes(contents))?;
+            file.end()
+        }
+    }
+
+    impl<'de> Deserialize<'de> for File {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_struct("File", FIELDS, FileVisitor)
+        }
+    }
+
+    struct FileVisitor;
+
+    impl<'de> Visitor<'de> for FileVisitor {
+        type Value = File;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a file with the fields `mode` and `contents`")
+        }
+
+        fn visit_seq<S>(self, mut seq: S) -> Result<File, S::Error>
+        where
+            S: SeqAccess<'de>,
+        {
+            let mode = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            let ByteBuf(contents) = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+            entry(contents, mode)
+        }
+
+        fn visit_map<M>(self, mut map: M) -> Result<File, M::Error>
+        where
+            M: MapAccess<'de>,
+        {
+            let mut mode = None;
+            let mut contents = None;
+            while let Some(field) = map.next_key::<String>()? {
+                match field.as_str() {
+                    "mode" if mode.is_some() => return Err(de::Error::duplicate_field("mode")),
+                    "mode" => mode = Some(map.next_value()?),
+                    "contents" if contents.is_some() => {
+                        return Err(de::Error::duplicate_field("contents"))
+                    }
+                    "contents" => contents = Some(map.next_value::<ByteBuf>()?.0),
+                    _ => {
+                        map.next_value::<de::IgnoredAny>()?;
+                    }
+                }
+            }
+            let mode = mode.ok_or_else(|| de::Error::missing_field("mode"))?;
+            let contents = contents.ok_or_else(|| de::Error::missing_field("contents"))?;
+            entry(contents, mode)
+        }
+    }
+}
+
 impl File {
-    /// Create a new `File` with the contents provided.
+    /// Create a new, regular `File` with the contents provided.
     pub fn new(contents: &[u8]) -> Self {
         let size = contents.len();
         File {
-            contents: contents.to_vec(),
+            contents: Arc::new(OnceLock::from(contents.to_vec())),
             size,
+            mode: FileMode::Regular,
+            blob: None,
+            metadata: Metadata::new(),
         }
     }
 
-    /// Get the size of the `File` corresponding to the number of bytes in the file contents.
+    /// Create a new, regular `File` that takes ownership of `contents`, rather than
+    /// copying them as [`new`](struct.File.html#method.new) does. The `Vec` is kept
+    /// as it is, so its spare capacity is kept too.
     ///
     /// # Examples
     ///
     /// ```
     /// use radicle_surf::file_system::File;
     ///
-    /// let file = File::new(
-    ///     b"pub mod diff;\npub mod file_system;\npub mod vcs;\npub use crate::vcs::git;\n",
-    /// );
+    /// let contents = b"# Surf".to_vec();
+    /// let bytes = contents.as_ptr();
+    /// let file = File::from_vec(contents);
     ///
-    /// assert_eq!(file.size(), 73);
+    /// assert_eq!(file.contents().unwrap().as_ptr(), bytes);
+    /// assert_eq!(file, File::new(b"# Surf"));
+    /// assert_eq!(File::from(b"# Surf".to_vec()), file);
     /// ```
-    pub fn size(&self) -> usize {
-        self.size
+    pub fn from_vec(contents: Vec<u8>) -> Self {
+        let size = contents.len();
+        File {
+            contents: Arc::new(OnceLock::from(contents)),
+            size,
+            mode: FileMode::Regular,
+            blob: None,
+            metadata: Metadata::new(),
+        }
     }
 
-    /// Get the hash of the `File` corresponding to the contents of the file.
+    /// Create a new, regular `File` whose contents, of `size` bytes, are read from
+    /// `blob` the first time they are needed, see [`contents`](struct.File.html#method.contents).
+    pub fn lazy(blob: Arc<dyn Blob>, size: usize) -> Self {
+        File {
+            contents: Arc::new(OnceLock::new()),
+            size,
+            mode: FileMode::Regular,
+            blob: Some(blob),
+            metadata: Metadata::new(),
+        }
+    }
+
+    /// Check whether the contents of the `File` are in memory, i.e. it was not
+    /// created with [`lazy`](struct.File.html#method.lazy), or its contents have
+    /// been read since.
+    pub fn is_loaded(&self) -> bool {
+        self.contents.get().is_some()
+    }
+
+    /// Get the contents of the `File`, reading them from its
+    /// [`Blob`](trait.Blob.html) first if it is lazy and they have not been read yet.
+    pub fn contents(&self) -> Result<&[u8], error::Error> {
+        if let Some(contents) = self.contents.get() {
+            return Ok(contents);
+        }
+        // Only lazy `File`s are created without their contents.
+        let read = match &self.blob {
+            Some(blob) => blob.read()?,
+            None => vec![],
+        };
+        Ok(self.contents.get_or_init(|| read))
+    }
+
+    /// Check whether two `File`s have the same contents, comparing the ids of their
+    /// `Blob`s if both are lazy, rather than reading them.
+    pub(crate) fn same_contents(&self, other: &Self) -> Result<bool, error::Error> {
+        match (&self.blob, &other.blob) {
+            (Some(blob), Some(other_blob)) => Ok(blob.id() == other_blob.id()),
+            _ => Ok(self.contents()? == other.contents()?),
+        }
+    }
+
+    /// Get the contents of a symbolic link or submodule, i.e. the path it points to
+    /// or the commit it is pinned to, which are empty if they can't be read.
+    ///
+    /// The backends always read them up front, since they are needed to tell what a
+    /// `Directory` holds.
+    pub(crate) fn target(&self) -> String {
+        String::from_utf8_lossy(self.contents().unwrap_or_default()).into_owned()
+    }
+
+    /// Get a copy of the contents of the `File` that is owned, e.g. to modify it.
     ///
     /// # Examples
     ///
     /// ```
     /// use radicle_surf::file_system::File;
     ///
-    /// let file = File::new(
-    ///     b"pub mod diff;\npub mod file_system;\npub mod vcs;\npub use crate::vcs::git;\n",
-    /// );
+    /// let file = File::new(b"# Surf");
+    /// let shared = file.clone();
     ///
-    /// assert_eq!(file.checksum(), 8457766712413557403);
+    /// let mut contents = file.to_vec().unwrap();
+    /// contents.extend_from_slice(b"ing");
+    ///
+    /// assert_eq!(contents, b"# Surfing".to_vec());
+    /// assert_eq!(shared.contents(), Ok(&b"# Surf"[..]));
     /// ```
-    pub fn checksum(&self) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        self.contents.hash(&mut hasher);
-        hasher.finish()
+    pub fn to_vec(&self) -> Result<Vec<u8>, error::Error> {
+        Ok(self.contents()?.to_vec())
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum Location {
-    Root,
-    SubDirectory(Label),
-}
+    /// Set the [`FileMode`](enum.FileMode.html) of the `File`.
+    ///
+    /// Symbolic links, submodules and empty directories are inserted with
+    /// [`Directory::insert_symlink`](struct.Directory.html#method.insert_symlink),
+    /// [`Directory::insert_submodule`](struct.Directory.html#method.insert_submodule) and
+    /// [`Directory::insert_empty_directory`](struct.Directory.html#method.insert_empty_directory)
+    /// instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{File, FileMode};
+    ///
+    /// let file = File::new(b"#!/bin/sh").with_mode(FileMode::Executable);
+    ///
+    /// assert!(file.mode().is_executable());
+    /// assert!(!File::new(b"# Surf").mode().is_executable());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mode` is not a [file mode](enum.FileMode.html#method.is_file).
+    pub fn with_mode(self, mode: FileMode) -> Self {
+        assert!(mode.is_file(), "{:?} is not the mode of a file", mode);
+        self.with_entry_mode(mode)
+    }
 
-/// A `Directory` can be thought of as a non-empty set of entries of sub-directories
-/// and files. The reason for the non-empty property is that a VCS directory would have
-/// at least one artifact as a sub-directory which tracks the VCS work, e.g. git using the
-/// `.git` folder.
-///
-/// On top of that, some VCSes, such as git, will not track an empty directory, and so when
-/// creating a new directory to track it will have to contain at least one file.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Directory {
-    current: Location,
-    sub_directories: Forest<Label, File>,
-}
+    /// Set the [`FileMode`](enum.FileMode.html) of the `File`, which may be the mode
+    /// of a symbolic link, submodule or empty directory.
+    pub(crate) fn with_entry_mode(mut self, mode: FileMode) -> Self {
+        self.mode = mode;
+        self
+    }
 
-/// `DirectoryContents` is an enumeration of what a [`Directory`](struct.Directory.html) can contain
-/// and is used for when we are [`iter`](struct.Directory.html#method.iter)ating through a `Directory`.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum DirectoryContents {
-    /// The `File` variant contains the file's name and the [`File`](struct.File.html) itself.
-    File {
-        /// The name of the file.
-        name: Label,
-        /// The file data.
-        file: File,
-    },
-    /// The `Directory` variant contains a sub-directory to the current one.
-    Directory(Directory),
-}
+    /// Attach `value` to the [`Metadata`](struct.Metadata.html) of the `File`,
+    /// replacing any value of the same type.
+    pub fn with_metadata<T>(mut self, value: T) -> Self
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        self.metadata.insert(value);
+        self
+    }
 
-impl DirectoryContents {
-    /// Get a label for the `DirectoryContents`, either the name of the `File`
-    /// or the name of the `Directory`.
-    pub fn label(&self) -> Label {
-        match self {
-            DirectoryContents::File { name, .. } => name.clone(),
-            DirectoryContents::Directory(directory) => directory.current(),
+    /// Get the [`Metadata`](struct.Metadata.html) that is attached to the `File`.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Get the [`Metadata`](struct.Metadata.html) that is attached to the `File`,
+    /// to change it.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Get the [`FileMode`](enum.FileMode.html) of the `File`.
+    pub fn mode(&self) -> FileMode {
+        self.mode
+    }
+
+    /// Check whether this is a regular or executable file, rather than a symbolic
+    /// link or submodule stored as a `File`.
+    pub(crate) fn is_file(&self) -> bool {
+        match self.mode {
+            FileMode::Regular | FileMode::Executable => true,
+            FileMode::Symlink | FileMode::Submodule | FileMode::EmptyDirectory => false,
         }
     }
-}
 
-impl From<SubTree<Label, File>> for DirectoryContents {
-    fn from(sub_tree: SubTree<Label, File>) -> Self {
-        match sub_tree {
-            SubTree::Node { key, value } => DirectoryContents::File {
-                name: key,
-                file: value,
-            },
-            SubTree::Branch { key, forest } => DirectoryContents::Directory(Directory {
-                current: Location::SubDirectory(key),
-                sub_directories: (*forest).into(),
-            }),
+    /// Check whether the `File` looks like a binary file, rather than text, going by
+    /// the first [`BINARY_CHECK_LEN`](constant.BINARY_CHECK_LEN.html) bytes of its
+    /// contents, which are binary if they contain a NUL byte or are not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::File;
+    ///
+    /// assert_eq!(File::new(b"# Surf\n").is_binary(), Ok(false));
+    /// assert_eq!(File::new("café surf".as_bytes()).is_binary(), Ok(false));
+    /// assert_eq!(File::new(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").is_binary(), Ok(true));
+    /// assert_eq!(File::new(b"caf\xe9").is_binary(), Ok(true));
+    /// ```
+    pub fn is_binary(&self) -> Result<bool, error::Error> {
+        let contents = self.contents()?;
+        let prefix = &contents[..contents.len().min(BINARY_CHECK_LEN)];
+        if prefix.contains(&0) {
+            return Ok(true);
         }
+        Ok(match std::str::from_utf8(prefix) {
+            Ok(_) => false,
+            // A character that is cut off by the end of the prefix is still text.
+            Err(err) => err.error_len().is_some() || prefix.len() == contents.len(),
+        })
+    }
+
+    /// Get the contents of the `File` as a `str`, if they are valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::File;
+    ///
+    /// assert_eq!(File::new(b"# Surf").as_str(), Ok(Some("# Surf")));
+    /// assert_eq!(File::new(b"caf\xe9").as_str(), Ok(None));
+    /// ```
+    pub fn as_str(&self) -> Result<Option<&str>, error::Error> {
+        Ok(std::str::from_utf8(self.contents()?).ok())
+    }
+
+    /// Decode the contents of the `File` as text, detecting whether they are UTF-8,
+    /// UTF-16 with a byte order mark, or otherwise falling back to Latin-1, along
+    /// with the [`Encoding`](enum.Encoding.html) that was detected.
+    ///
+    /// A byte order mark is not part of the decoded text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Encoding, File};
+    ///
+    /// let decode = |contents: &[u8]| {
+    ///     let file = File::new(contents);
+    ///     let (text, encoding) = file.decode().unwrap();
+    ///     (text.into_owned(), encoding)
+    /// };
+    ///
+    /// assert_eq!(decode("café".as_bytes()), ("café".to_string(), Encoding::Utf8));
+    /// assert_eq!(
+    ///     decode(b"\xff\xfec\0a\0f\0\xe9\0"),
+    ///     ("café".to_string(), Encoding::Utf16Le)
+    /// );
+    /// assert_eq!(decode(b"caf\xe9"), ("café".to_string(), Encoding::Latin1));
+    /// ```
+    #[cfg(feature = "encoding")]
+    pub fn decode(&self) -> Result<(std::borrow::Cow<'_, str>, Encoding), error::Error> {
+        Ok(Encoding::decode(self.contents()?))
+    }
+
+    /// Guess the media type of the `File`, e.g. to set a `Content-Type` header, from
+    /// the magic number at the start of its contents, then from the extension of its
+    /// `name`.
+    ///
+    /// If neither is known, this is `text/plain` for text, and
+    /// `application/octet-stream` for [binary](struct.File.html#method.is_binary)
+    /// contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{unsound, File};
+    ///
+    /// let readme = File::new(b"# Surf\n");
+    /// assert_eq!(readme.mime_type(&unsound::label::new("README.md")), Ok("text/markdown"));
+    /// assert_eq!(readme.mime_type(&unsound::label::new("README")), Ok("text/plain"));
+    ///
+    /// let logo = File::new(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR");
+    /// assert_eq!(logo.mime_type(&unsound::label::new("logo")), Ok("image/png"));
+    ///
+    /// let data = File::new(b"\0\x01\x02");
+    /// assert_eq!(data.mime_type(&unsound::label::new("data")), Ok("application/octet-stream"));
+    /// ```
+    #[cfg(feature = "mime")]
+    pub fn mime_type(&self, name: &Label) -> Result<&'static str, error::Error> {
+        match mime::mime_type(name, self.contents()?) {
+            Some(mime_type) => Ok(mime_type),
+            None if self.is_binary()? => Ok(mime::OCTET_STREAM),
+            None => Ok(mime::TEXT_PLAIN),
+        }
+    }
+
+    /// Guess the programming language of the `File`, e.g. to pick a syntax
+    /// highlighting grammar, from its `name`, such as `Makefile` or `main.rs`, then
+    /// from a `#!` line at the start of its contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{unsound, File};
+    ///
+    /// let file = File::new(b"fn main() {}\n");
+    /// assert_eq!(file.language(&unsound::label::new("main.rs")), Ok(Some("Rust")));
+    /// assert_eq!(
+    ///     File::new(b"all:\n").language(&unsound::label::new("Makefile")),
+    ///     Ok(Some("Makefile"))
+    /// );
+    ///
+    /// let script = File::new(b"#!/usr/bin/env python3\nprint('surf')\n");
+    /// assert_eq!(script.language(&unsound::label::new("surf")), Ok(Some("Python")));
+    ///
+    /// assert_eq!(File::new(b"surf").language(&unsound::label::new("notes.txt")), Ok(None));
+    /// ```
+    #[cfg(feature = "mime")]
+    pub fn language(&self, name: &Label) -> Result<Option<&'static str>, error::Error> {
+        Ok(mime::language(name, self.contents()?))
+    }
+
+    /// Iterate over the lines of the `File`, without their line endings, where
+    /// [`Lines::line_ending`](struct.Lines.html#method.line_ending) tells which line
+    /// endings the `File` uses.
+    ///
+    /// Lines end with either `\n` or `\r\n`, and a line ending at the end of the
+    /// `File` does not start another line, like [`str::lines`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{File, LineEnding};
+    ///
+    /// let file = File::new(b"# Surf\r\n\r\nLet's surf!\r\n");
+    /// let lines = file.lines().unwrap();
+    ///
+    /// assert_eq!(lines.line_ending(), Some(LineEnding::CrLf));
+    /// assert_eq!(
+    ///     lines.collect::<Vec<_>>(),
+    ///     vec![&b"# Surf"[..], b"", b"Let's surf!"]
+    /// );
+    ///
+    /// // Jump to the third line.
+    /// assert_eq!(file.lines().unwrap().nth(2), Some(&b"Let's surf!"[..]));
+    ///
+    /// // A `\r` on its own does not end a line, even at the end of the `File`.
+    /// let file = File::new(b"# Surf\rLet's surf!\r");
+    /// assert_eq!(
+    ///     file.lines().unwrap().collect::<Vec<_>>(),
+    ///     vec![&b"# Surf\rLet's surf!\r"[..]]
+    /// );
+    /// ```
+    pub fn lines(&self) -> Result<Lines<'_>, error::Error> {
+        Ok(Lines::new(self.contents()?))
+    }
+
+    /// Count the [`lines`](struct.File.html#method.lines) of the `File`, or `None`
+    /// if it is [binary](struct.File.html#method.is_binary).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::File;
+    ///
+    /// assert_eq!(File::new(b"# Surf\n\nLet's surf!\n").line_count(), Ok(Some(3)));
+    /// assert_eq!(File::new(b"").line_count(), Ok(Some(0)));
+    /// assert_eq!(File::new(b"\0\x01\n\x02").line_count(), Ok(None));
+    /// ```
+    pub fn line_count(&self) -> Result<Option<usize>, error::Error> {
+        if self.is_binary()? {
+            Ok(None)
+        } else {
+            Ok(Some(self.lines()?.count()))
+        }
+    }
+
+    /// Get the size of the `File` corresponding to the number of bytes in the file contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::File;
+    ///
+    /// let file = File::new(
+    ///     b"pub mod diff;\npub mod file_system;\npub mod vcs;\npub use crate::vcs::git;\n",
+    /// );
+    ///
+    /// assert_eq!(file.size(), 73);
+    /// ```
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get the hash of the `File` corresponding to the contents of the file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::File;
+    ///
+    /// let file = File::new(
+    ///     b"pub mod diff;\npub mod file_system;\npub mod vcs;\npub use crate::vcs::git;\n",
+    /// );
+    ///
+    /// assert_eq!(file.checksum(), Ok(8457766712413557403));
+    /// ```
+    pub fn checksum(&self) -> Result<u64, error::Error> {
+        let mut hasher = DefaultHasher::new();
+        self.contents()?.hash(&mut hasher);
+        Ok(hasher.finish())
+    }
+
+    /// Get the checksum of the `File`'s contents using the [`Digest`](digest/trait.Digest.html)
+    /// algorithm `D`.
+    ///
+    /// Unlike [`checksum`](struct.File.html#method.checksum), the result is stable
+    /// across processes and Rust versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::File;
+    /// use radicle_surf::file_system::digest::Blake3;
+    ///
+    /// let file = File::new(b"pub mod diff;");
+    /// let same_file = File::new(b"pub mod diff;");
+    ///
+    /// assert_eq!(file.checksum_with::<Blake3>(), same_file.checksum_with::<Blake3>());
+    /// ```
+    pub fn checksum_with<D>(&self) -> Result<D::Output, error::Error>
+    where
+        D: Digest,
+    {
+        Ok(D::digest(self.contents()?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Location {
+    Root,
+    SubDirectory(Label),
+}
+
+/// A `Directory` can be thought of as a non-empty set of entries of sub-directories
+/// and files. The reason for the non-empty property is that a VCS directory would have
+/// at least one artifact as a sub-directory which tracks the VCS work, e.g. git using the
+/// `.git` folder.
+///
+/// On top of that, some VCSes, such as git, will not track an empty directory, and so when
+/// creating a new directory to track it will have to contain at least one file.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Directory {
+    current: Location,
+    sub_directories: Forest<Label, File>,
+    stats: Memo<Stats>,
+}
+
+impl std::fmt::Debug for Directory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Directory")
+            .field("current", &self.current)
+            .field("sub_directories", &self.sub_directories)
+            .finish()
+    }
+}
+
+impl std::fmt::Display for Directory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render_tree(false))
+    }
+}
+
+/// A value computed from a `Directory` the first time it is asked for, and
+/// forgotten whenever the `Directory` is changed.
+///
+/// It is derived from the rest of the `Directory`, so it is ignored when
+/// comparing `Directory`s.
+#[derive(Clone, Default)]
+struct Memo<T>(OnceLock<T>);
+
+impl<T> Memo<T> {
+    fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        self.0.get_or_init(f)
+    }
+
+    fn reset(&mut self) {
+        self.0 = OnceLock::new();
+    }
+}
+
+impl<T> PartialEq for Memo<T> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Eq for Memo<T> {}
+
+/// Figures about the whole of a `Directory`, gathered in a single walk.
+#[derive(Debug, Clone, Copy, Default)]
+struct Stats {
+    size: usize,
+    files: usize,
+    directories: usize,
+}
+
+impl Stats {
+    fn of(forest: &Forest<Label, File>) -> Self {
+        let mut stats = Stats::default();
+        forest.visit(&mut stats);
+        stats
+    }
+}
+
+impl TreeVisitor<Label, File> for Stats {
+    fn enter_branch(&mut self, _keys: &[Label], _key: &Label) -> Visit {
+        self.directories += 1;
+        Visit::Continue
+    }
+
+    fn visit_node(&mut self, _keys: &[Label], _key: &Label, file: &File) -> Visit {
+        if file.is_file() {
+            self.size += file.size();
+            self.files += 1;
+        } else if file.mode == FileMode::EmptyDirectory {
+            self.directories += 1;
+        }
+        Visit::Continue
+    }
+}
+
+/// The extensions of READMEs in the order that
+/// [`Directory::find_readme`](struct.Directory.html#method.find_readme) prefers them.
+pub const README_EXTENSIONS: &[&str] = &["md", "markdown", "rst", "adoc", "org", "txt"];
+
+/// Computes [`Directory::tree_hash_with`](struct.Directory.html#method.tree_hash_with)
+/// by keeping the serialized entries of every directory being visited, where
+/// each entry is its mode, name, and digest, like in a git tree object, until the
+/// contents of a file can't be read.
+struct TreeHasher<D> {
+    entries: Vec<Vec<u8>>,
+    error: Option<error::Error>,
+    digest: std::marker::PhantomData<D>,
+}
+
+impl<D: Digest> TreeHasher<D> {
+    fn push_entry(&mut self, mode: FileMode, name: &Label, digest: &[u8]) {
+        if let Some(entries) = self.entries.last_mut() {
+            entries.extend_from_slice(git_mode(mode).as_bytes());
+            entries.push(b' ');
+            entries.extend_from_slice(name.as_bytes());
+            entries.push(0);
+            entries.extend_from_slice(digest);
+        }
+    }
+}
+
+impl<D: Digest> TreeVisitor<Label, File> for TreeHasher<D> {
+    fn enter_branch(&mut self, _keys: &[Label], _key: &Label) -> Visit {
+        self.entries.push(vec![]);
+        Visit::Continue
+    }
+
+    fn leave_branch(&mut self, _keys: &[Label], key: &Label) -> Visit {
+        let entries = self.entries.pop().unwrap_or_default();
+        // Every directory has git's tree mode, which is also the mode of empty ones.
+        self.push_entry(FileMode::EmptyDirectory, key, D::digest(&entries).as_ref());
+        Visit::Continue
+    }
+
+    fn visit_node(&mut self, _keys: &[Label], key: &Label, file: &File) -> Visit {
+        match file.checksum_with::<D>() {
+            Ok(digest) => {
+                self.push_entry(file.mode, key, digest.as_ref());
+                Visit::Continue
+            }
+            Err(err) => {
+                self.error = Some(err);
+                Visit::Stop
+            }
+        }
+    }
+}
+
+/// Collects the `Path`s of the files with any of the `extensions`, see
+/// [`Directory::find_files_with_extensions`](struct.Directory.html#method.find_files_with_extensions).
+struct ExtensionFinder<'a> {
+    extensions: &'a [&'a str],
+    paths: Vec<Path>,
+}
+
+impl<'a> TreeVisitor<Label, File> for ExtensionFinder<'a> {
+    fn visit_node(&mut self, keys: &[Label], key: &Label, file: &File) -> Visit {
+        let matches = key
+            .extension()
+            .is_some_and(|extension| self.extensions.contains(&extension));
+        if matches && file.is_file() {
+            self.paths.push(node_path(keys, key));
+        }
+        Visit::Continue
+    }
+}
+
+/// Finds the first file that matches `predicate`, see
+/// [`Directory::find_first`](struct.Directory.html#method.find_first).
+struct FirstFinder<F> {
+    predicate: F,
+    found: Option<(Path, File)>,
+}
+
+impl<F> TreeVisitor<Label, File> for FirstFinder<F>
+where
+    F: FnMut(&Path, &File) -> bool,
+{
+    fn visit_node(&mut self, keys: &[Label], key: &Label, file: &File) -> Visit {
+        if !file.is_file() {
+            return Visit::Continue;
+        }
+        let path = node_path(keys, key);
+        if (self.predicate)(&path, file) {
+            self.found = Some((path, file.clone()));
+            Visit::Stop
+        } else {
+            Visit::Continue
+        }
+    }
+}
+
+/// The `Path` of the node `key` in the branches `keys`, as given to a `TreeVisitor`.
+fn node_path(keys: &[Label], key: &Label) -> Path {
+    match keys.split_first() {
+        Some((first, rest)) => Path::from_labels(first.clone(), rest).join(key.clone()),
+        None => Path::new(key.clone()),
+    }
+}
+
+/// How [`Directory::list_directory_with`](struct.Directory.html#method.list_directory_with)
+/// orders the entries of a `Directory`.
+///
+/// By default, entries are ordered by their `Label`s, comparing them character by
+/// character. Entries whose `Label`s compare equal under the chosen options are
+/// ordered by their `Label`s, and then their `SystemType`s, so that the order never
+/// depends on how the `Directory` was built.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListOptions {
+    /// List directories before files, symbolic links and submodules.
+    pub directories_first: bool,
+    /// Compare `Label`s ignoring their case.
+    pub case_insensitive: bool,
+    /// Compare runs of digits in `Label`s by their numeric value, see
+    /// [`Label::natural_cmp`](struct.Label.html#method.natural_cmp).
+    pub natural: bool,
+}
+
+impl ListOptions {
+    fn cmp(&self, left: &(Label, SystemType), right: &(Label, SystemType)) -> Ordering {
+        let (left_label, left_type) = left;
+        let (right_label, right_type) = right;
+
+        let directories_first = if self.directories_first {
+            let is_file = |system_type: &SystemType| *system_type != SystemType::Directory;
+            is_file(left_type).cmp(&is_file(right_type))
+        } else {
+            Ordering::Equal
+        };
+
+        let labels = match (self.case_insensitive, self.natural) {
+            (false, false) => Ordering::Equal,
+            (false, true) => left_label.natural_cmp(right_label),
+            (true, natural) => {
+                let left = left_label.to_string().to_lowercase();
+                let right = right_label.to_string().to_lowercase();
+                if natural {
+                    natural_cmp(&left, &right)
+                } else {
+                    left.cmp(&right)
+                }
+            }
+        };
+
+        directories_first.then(labels).then_with(|| left.cmp(right))
+    }
+}
+
+/// A page of the entries of a `Directory`, see
+/// [`Directory::list_directory_page`](struct.Directory.html#method.list_directory_page)
+/// and [`Directory::list_directory_after`](struct.Directory.html#method.list_directory_after).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryPage {
+    /// The entries on the page.
+    pub entries: Vec<(Label, SystemType)>,
+    /// The number of entries in the whole `Directory`.
+    pub total: usize,
+    /// The cursor of the next page, which is the `Label` of the last entry on this
+    /// page, or `None` if this is the last page.
+    pub next: Option<Label>,
+}
+
+/// How [`Directory::from_disk_with`](struct.Directory.html#method.from_disk_with)
+/// reads a `Directory` from disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskOptions {
+    /// Keep directories that are empty, or only have entries that are skipped, as
+    /// empty directories, see
+    /// [`Directory::insert_empty_directory`](struct.Directory.html#method.insert_empty_directory).
+    pub empty_directories: bool,
+}
+
+/// An iterator over the entries of a [`Directory`](struct.Directory.html) in
+/// depth-first pre-order, along with their `Path`s, which is what a `Directory`
+/// turns into as an `IntoIterator`, see [`Directory::walk`](struct.Directory.html#method.walk).
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::{Directory, DirectoryContents, File};
+/// use radicle_surf::file_system::unsound;
+///
+/// let directory = vec![
+///     (unsound::path::new("README.md"), File::new(b"# Surf")),
+///     (unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;")),
+/// ]
+/// .into_iter()
+/// .collect::<Directory>();
+///
+/// let files = directory
+///     .into_iter()
+///     .filter_map(|(path, contents)| match contents {
+///         DirectoryContents::File { .. } => Some(path),
+///         _ => None,
+///     })
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(
+///     files,
+///     vec![unsound::path::new("README.md"), unsound::path::new("src/lib.rs")]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct IntoIter {
+    stack: Vec<(Option<Path>, SubTree<Label, File>)>,
+}
+
+impl IntoIter {
+    /// Walk the whole of `directory`, consuming it.
+    fn new(directory: Directory) -> Self {
+        let stack = match directory.sub_directories.0 {
+            None => vec![],
+            Some(trees) => trees
+                .into_sub_trees()
+                .into_values()
+                .rev()
+                .map(|sub_tree| (None, sub_tree))
+                .collect(),
+        };
+        IntoIter { stack }
+    }
+}
+
+impl Iterator for IntoIter {
+    type Item = (Path, DirectoryContents);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (parent, sub_tree) = self.stack.pop()?;
+        let path = match parent {
+            Some(parent) => parent.join(sub_tree.key().clone()),
+            None => Path::new(sub_tree.key().clone()),
+        };
+
+        let contents = match sub_tree {
+            SubTree::Branch { key, forest } => {
+                let contents = DirectoryContents::from(SubTree::Branch {
+                    key,
+                    forest: forest.clone(),
+                });
+                self.stack.extend(
+                    forest
+                        .into_sub_trees()
+                        .into_values()
+                        .rev()
+                        .map(|child| (Some(path.clone()), child)),
+                );
+                contents
+            }
+            node => DirectoryContents::from(node),
+        };
+
+        Some((path, contents))
+    }
+}
+
+/// Walks a `Directory` by reference down to `depth` levels, only cloning the
+/// entries it yields, see [`Directory::walk_to_depth`](struct.Directory.html#method.walk_to_depth).
+struct Walk<'a> {
+    stack: Vec<(Option<Path>, &'a SubTree<Label, File>)>,
+    depth: usize,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (Path, DirectoryContents);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (parent, sub_tree) = self.stack.pop()?;
+        let path = match parent {
+            Some(parent) => parent.join(sub_tree.key().clone()),
+            None => Path::new(sub_tree.key().clone()),
+        };
+
+        if let SubTree::Branch { forest, .. } = sub_tree {
+            if path.0.len() < self.depth {
+                self.stack.extend(
+                    forest
+                        .0
+                        .values()
+                        .rev()
+                        .map(|child| (Some(path.clone()), child)),
+                );
+            }
+        }
+
+        Some((path, DirectoryContents::from(sub_tree.clone())))
+    }
+}
+
+impl IntoIterator for Directory {
+    type Item = (Path, DirectoryContents);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self)
+    }
+}
+
+/// Collect `File`s along with their `Path`s into a `Directory`, see
+/// [`Directory::from_files`](struct.Directory.html#method.from_files).
+impl std::iter::FromIterator<(Path, File)> for Directory {
+    fn from_iter<I>(files: I) -> Self
+    where
+        I: IntoIterator<Item = (Path, File)>,
+    {
+        Directory::from_files(files)
+    }
+}
+
+/// Insert `File`s along with their `Path`s into a `Directory`, see
+/// [`Directory::insert_file`](struct.Directory.html#method.insert_file).
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::{Directory, File};
+/// use radicle_surf::file_system::unsound;
+///
+/// let mut directory = Directory::root();
+/// directory.extend(vec![
+///     (unsound::path::new("README.md"), File::new(b"# Surf")),
+///     (unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;")),
+/// ]);
+///
+/// assert_eq!(directory.file_count(), 2);
+/// ```
+impl Extend<(Path, File)> for Directory {
+    fn extend<I>(&mut self, files: I)
+    where
+        I: IntoIterator<Item = (Path, File)>,
+    {
+        for (path, file) in files {
+            self.insert_file(&path, file);
+        }
+    }
+}
+
+/// A cursor into a [`Directory`](struct.Directory.html), focused on one of its
+/// sub-directories, see [`Directory::cursor`](struct.Directory.html#method.cursor).
+///
+/// Moving the cursor [`down`](#method.down) and [`up`](#method.up) a level, and
+/// editing the directory in focus, neither searches from the root nor clones the
+/// `Directory`, which suits browsing back and forth through a snapshot.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::{Directory, File, SystemType};
+/// use radicle_surf::file_system::unsound;
+///
+/// let mut root = Directory::root();
+/// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+/// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+/// root.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub struct Browser;"));
+///
+/// let mut cursor = root.cursor();
+/// assert!(cursor.down(&unsound::label::new("src")));
+/// assert_eq!(cursor.path(), Some(unsound::path::new("src")));
+/// assert_eq!(cursor.siblings(), vec![SystemType::file(unsound::label::new("README.md"))]);
+/// assert_eq!(
+///     cursor.list_directory(),
+///     vec![
+///         SystemType::file(unsound::label::new("lib.rs")),
+///         SystemType::directory(unsound::label::new("vcs")),
+///     ]
+/// );
+///
+/// cursor.insert_file(&unsound::path::new("diff.rs"), File::new(b"pub struct Diff;"));
+/// assert!(cursor.up());
+/// assert!(!cursor.up());
+///
+/// let root = cursor.into_directory();
+/// assert!(root.find_file(&unsound::path::new("src/diff.rs")).is_some());
+/// ```
+#[derive(Debug, Clone)]
+pub struct DirectoryCursor {
+    current: Location,
+    cursor: Cursor<Label, File>,
+    /// The empty directory markers of the directories the cursor went down into,
+    /// which are put back if they are still empty when it goes back up.
+    empty: Vec<Option<File>>,
+}
+
+impl DirectoryCursor {
+    /// Get the `Path` of the directory in focus, or `None` if it is the root.
+    pub fn path(&self) -> Option<Path> {
+        let labels = self.cursor.keys().into_iter().cloned().collect::<Vec<_>>();
+        NonEmpty::from_slice(&labels).map(Path)
+    }
+
+    /// List the entries of the directory in focus, see
+    /// [`Directory::list_directory`](struct.Directory.html#method.list_directory).
+    pub fn list_directory(&self) -> Vec<(Label, SystemType)> {
+        self.cursor
+            .focus()
+            .tree()
+            .map(|tree| tree.iter_subtrees().map(Directory::system_type).collect())
+            .unwrap_or_default()
+    }
+
+    /// List the entries next to the directory in focus, which are none when the
+    /// focus is the root.
+    pub fn siblings(&self) -> Vec<(Label, SystemType)> {
+        self.cursor.siblings().map(Directory::system_type).collect()
+    }
+
+    /// Find the file, symbolic link or submodule called `label` in the directory in
+    /// focus.
+    pub fn find_file(&self, label: &Label) -> Option<&File> {
+        self.cursor
+            .focus()
+            .find_node(&NonEmpty::new(label.clone()))
+            .filter(|file| file.mode != FileMode::EmptyDirectory)
+    }
+
+    /// Move the focus down into the sub-directory `label`, returning whether there
+    /// is such a sub-directory. The cursor stays where it is otherwise.
+    pub fn down(&mut self, label: &Label) -> bool {
+        if self.cursor.down(label) {
+            self.empty.push(None);
+            return true;
+        }
+
+        match self.cursor.focus().find_node(&NonEmpty::new(label.clone())) {
+            Some(file) if file.mode == FileMode::EmptyDirectory => {
+                let marker = self.cursor.down_node(label);
+                self.empty.push(marker);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Move the focus up to the parent directory, returning whether there is one,
+    /// i.e. whether the focus was not the root.
+    ///
+    /// A directory that is left empty is removed, unless it was an empty directory
+    /// to begin with, see
+    /// [`Directory::insert_empty_directory`](struct.Directory.html#method.insert_empty_directory).
+    pub fn up(&mut self) -> bool {
+        let label = match self.cursor.keys().last() {
+            Some(label) => (*label).clone(),
+            None => return false,
+        };
+        let marker = self.empty.pop().flatten();
+        let is_empty = self.cursor.focus().is_empty();
+
+        self.cursor.up();
+        if let (Some(marker), true) = (marker, is_empty) {
+            self.cursor
+                .focus_mut()
+                .insert_with(&NonEmpty::new(label), marker, |_| {});
+        }
+        true
+    }
+
+    /// Insert `file` at `path`, relative to the directory in focus, see
+    /// [`Directory::insert_file`](struct.Directory.html#method.insert_file).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the directory in focus and `path` together have more than
+    /// [`MAX_DEPTH`](constant.MAX_DEPTH.html) labels, see
+    /// [`try_insert_file`](#method.try_insert_file).
+    pub fn insert_file(&mut self, path: &Path, file: File) {
+        assert_depth(self.try_insert_file(path, file), path)
+    }
+
+    /// Insert `file` at `path`, relative to the directory in focus, unless the
+    /// directory in focus and `path` together have more than
+    /// [`MAX_DEPTH`](constant.MAX_DEPTH.html) labels, see
+    /// [`Directory::try_insert_file`](struct.Directory.html#method.try_insert_file).
+    pub fn try_insert_file(&mut self, path: &Path, file: File) -> Result<(), error::Error> {
+        check_depth(self.cursor.keys().len() + path.0.len())?;
+        self.cursor.focus_mut().insert(&path.0, file);
+        Ok(())
+    }
+
+    /// Remove the file, symbolic link or submodule called `label` from the directory
+    /// in focus, returning it, see
+    /// [`Directory::remove_file`](struct.Directory.html#method.remove_file).
+    pub fn remove_file(&mut self, label: &Label) -> Option<File> {
+        self.find_file(label)?;
+        match self
+            .cursor
+            .focus_mut()
+            .remove(&NonEmpty::new(label.clone()))?
+        {
+            SubTree::Node { value, .. } => Some(value),
+            SubTree::Branch { .. } => None,
+        }
+    }
+
+    /// Move the focus up to the root, and return the `Directory`.
+    pub fn into_directory(mut self) -> Directory {
+        while self.up() {}
+        Directory {
+            current: self.current,
+            sub_directories: self.cursor.into_forest(),
+            stats: Memo::default(),
+        }
+    }
+}
+
+/// `DirectoryContents` is an enumeration of what a [`Directory`](struct.Directory.html) can contain
+/// and is used for when we are [`iter`](struct.Directory.html#method.iter)ating through a `Directory`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirectoryContents {
+    /// The `File` variant contains the file's name and the [`File`](struct.File.html) itself.
+    File {
+        /// The name of the file.
+        name: Label,
+        /// The file data.
+        file: File,
+    },
+    /// The `Directory` variant contains a sub-directory to the current one.
+    Directory(Directory),
+    /// The `Symlink` variant contains the symbolic link's name and the path it points to.
+    Symlink {
+        /// The name of the symbolic link.
+        name: Label,
+        /// The path the symbolic link points to, which may be relative to the
+        /// directory containing it, and may not exist.
+        target: String,
+    },
+    /// The `Submodule` variant contains the submodule's name and the id of the commit it is pinned to.
+    Submodule {
+        /// The name of the submodule.
+        name: Label,
+        /// The id of the commit the submodule is pinned to, e.g. a git commit SHA.
+        commit: String,
+    },
+}
+
+impl DirectoryContents {
+    /// Get a label for the `DirectoryContents`, either the name of the `File`,
+    /// the name of the `Directory`, the name of the symbolic link, or the name
+    /// of the submodule.
+    pub fn label(&self) -> Label {
+        match self {
+            DirectoryContents::File { name, .. }
+            | DirectoryContents::Symlink { name, .. }
+            | DirectoryContents::Submodule { name, .. } => name.clone(),
+            DirectoryContents::Directory(directory) => directory.current(),
+        }
+    }
+}
+
+/// The paths that differ between two [`Directory`](struct.Directory.html) snapshots,
+/// see [`diff_structure`](struct.Directory.html#method.diff_structure).
+///
+/// Each list of paths is sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StructureDiff {
+    /// The files and directories that only exist in the newer `Directory`.
+    pub added: Vec<Path>,
+    /// The files and directories that only exist in the older `Directory`.
+    pub removed: Vec<Path>,
+    /// The files that exist in both, but whose checksums differ.
+    pub modified: Vec<Path>,
+}
+
+impl StructureDiff {
+    /// Check whether the two `Directory` snapshots have the same structure and contents.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl From<SubTree<Label, File>> for DirectoryContents {
+    fn from(sub_tree: SubTree<Label, File>) -> Self {
+        match sub_tree {
+            SubTree::Node { key, value } if value.mode == FileMode::Symlink => {
+                DirectoryContents::Symlink {
+                    name: key,
+                    target: value.target(),
+                }
+            }
+            SubTree::Node { key, value } if value.mode == FileMode::Submodule => {
+                DirectoryContents::Submodule {
+                    name: key,
+                    commit: value.target(),
+                }
+            }
+            SubTree::Node { key, value } if value.mode == FileMode::EmptyDirectory => {
+                DirectoryContents::Directory(Directory::new(key))
+            }
+            SubTree::Node { key, value } => DirectoryContents::File {
+                name: key,
+                file: value,
+            },
+            SubTree::Branch { key, forest } => DirectoryContents::Directory(Directory {
+                current: Location::SubDirectory(key),
+                sub_directories: (*forest).into(),
+                stats: Memo::default(),
+            }),
+        }
+    }
+}
+
+impl Directory {
+    /// Create a root directory.
+    ///
+    /// This function is usually used for testing and demonstation purposes.
+    pub fn root() -> Self {
+        Directory {
+            current: Location::Root,
+            sub_directories: Forest::root(),
+            stats: Memo::default(),
+        }
+    }
+
+    /// Create a directory, similar to `root`, except with a given name.
+    ///
+    /// This function is usually used for testing and demonstation purposes.
+    pub fn new(label: Label) -> Self {
+        Directory {
+            current: Location::SubDirectory(label),
+            sub_directories: Forest::root(),
+            stats: Memo::default(),
+        }
+    }
+
+    /// List the current `Directory`'s files and sub-directories.
+    ///
+    /// ```
+    /// use nonempty::NonEmpty;
+    /// use radicle_surf::file_system::{Directory, File, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(
+    ///     &unsound::path::new("foo.hs"),
+    ///     File::new(b"module BananaFoo ..."),
+    /// );
+    /// directory.insert_file(
+    ///     &unsound::path::new("bar.hs"),
+    ///     File::new(b"module BananaBar ..."),
+    /// );
+    /// directory.insert_file(
+    ///     &unsound::path::new("baz.hs"),
+    ///     File::new(b"module BananaBaz ..."),
+    /// );
+    ///
+    /// assert_eq!(
+    ///     directory.list_directory(),
+    ///     vec![
+    ///         SystemType::file(unsound::label::new("bar.hs")),
+    ///         SystemType::file(unsound::label::new("baz.hs")),
+    ///         SystemType::file(unsound::label::new("foo.hs")),
+    ///     ]
+    /// );
+    /// ```
+    ///
+    /// ```
+    /// use nonempty::NonEmpty;
+    /// use radicle_surf::file_system::{Directory, File, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    ///
+    /// // Root files set up
+    /// let root_files = NonEmpty::from((
+    ///     (unsound::label::new("foo.rs"), File::new(b"use crate::bar")),
+    ///     vec![(
+    ///         unsound::label::new("bar.rs"),
+    ///         File::new(b"fn hello_world()"),
+    ///     )],
+    /// ));
+    /// directory.insert_files(&[], root_files);
+    ///
+    /// // Haskell files set up
+    /// let haskell_files = NonEmpty::from((
+    ///     (
+    ///         unsound::label::new("foo.hs"),
+    ///         File::new(b"module Foo where"),
+    ///     ),
+    ///     vec![(
+    ///         unsound::label::new("bar.hs"),
+    ///         File::new(b"module Bar where"),
+    ///     )],
+    /// ));
+    ///
+    /// directory.insert_files(&[unsound::label::new("haskell")], haskell_files);
+    ///
+    /// let mut directory_contents = directory.list_directory();
+    /// directory_contents.sort();
+    ///
+    /// assert_eq!(
+    ///     directory_contents,
+    ///     vec![
+    ///         SystemType::file(unsound::label::new("bar.rs")),
+    ///         SystemType::file(unsound::label::new("foo.rs")),
+    ///         SystemType::directory(unsound::label::new("haskell")),
+    ///     ]
+    /// );
+    /// ```
+    pub fn list_directory(&self) -> Vec<(Label, SystemType)> {
+        let forest = &self.sub_directories;
+        match &forest.0 {
+            None => vec![],
+            Some(trees) => trees.iter_subtrees().map(Self::system_type).collect(),
+        }
+    }
+
+    /// List a page of at most `limit` entries of the current `Directory`, skipping
+    /// the first `offset` entries, in the order of
+    /// [`list_directory`](#method.list_directory).
+    ///
+    /// Only the entries on the page are listed, so paging through a `Directory`
+    /// with many entries does not list all of them for every page.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// for name in &["a.rs", "b.rs", "c.rs", "d.rs", "e.rs"] {
+    ///     directory.insert_file(&unsound::path::new(name), File::new(b""));
+    /// }
+    ///
+    /// let page = directory.list_directory_page(2, 2);
+    /// assert_eq!(
+    ///     page.entries,
+    ///     vec![
+    ///         SystemType::file(unsound::label::new("c.rs")),
+    ///         SystemType::file(unsound::label::new("d.rs")),
+    ///     ]
+    /// );
+    /// assert_eq!(page.total, 5);
+    /// assert_eq!(page.next, Some(unsound::label::new("d.rs")));
+    ///
+    /// let last = directory.list_directory_page(4, 2);
+    /// assert_eq!(last.entries, vec![SystemType::file(unsound::label::new("e.rs"))]);
+    /// assert_eq!(last.next, None);
+    /// ```
+    pub fn list_directory_page(&self, offset: usize, limit: usize) -> DirectoryPage {
+        self.page(|trees| Box::new(trees.iter_subtrees().skip(offset)), limit)
+    }
+
+    /// List a page of at most `limit` entries of the current `Directory`, starting
+    /// after the entry named `after`, in the order of
+    /// [`list_directory`](#method.list_directory). If `after` is `None`, the page
+    /// starts with the first entry.
+    ///
+    /// The [`next`](struct.DirectoryPage.html#structfield.next) cursor of a page is
+    /// the `after` of the following page. Unlike an offset, a cursor finds its
+    /// position without counting the entries before it, and stays put when entries
+    /// are added or removed before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// for name in &["a.rs", "b.rs", "c.rs", "d.rs", "e.rs"] {
+    ///     directory.insert_file(&unsound::path::new(name), File::new(b""));
+    /// }
+    ///
+    /// let mut pages = vec![];
+    /// let mut after = None;
+    /// loop {
+    ///     let page = directory.list_directory_after(after.as_ref(), 2);
+    ///     pages.push(page.entries.len());
+    ///     match page.next {
+    ///         Some(next) => after = Some(next),
+    ///         None => break,
+    ///     }
+    /// }
+    /// assert_eq!(pages, vec![2, 2, 1]);
+    /// ```
+    pub fn list_directory_after(&self, after: Option<&Label>, limit: usize) -> DirectoryPage {
+        self.page(
+            |trees| match after {
+                None => Box::new(trees.iter_subtrees()),
+                Some(after) => Box::new(
+                    trees
+                        .0
+                        .range::<Label, _>((Bound::Excluded(after), Bound::Unbounded))
+                        .map(|(_, sub_tree)| sub_tree),
+                ),
+            },
+            limit,
+        )
+    }
+
+    fn page<'a, F>(&'a self, start: F, limit: usize) -> DirectoryPage
+    where
+        F: FnOnce(&'a Tree<Label, File>) -> Box<dyn Iterator<Item = &'a SubTree<Label, File>> + 'a>,
+    {
+        let trees = match &self.sub_directories.0 {
+            None => {
+                return DirectoryPage {
+                    entries: vec![],
+                    total: 0,
+                    next: None,
+                }
+            }
+            Some(trees) => trees,
+        };
+
+        let total = trees.len();
+        let mut sub_trees = start(trees);
+        let entries = sub_trees
+            .by_ref()
+            .take(limit)
+            .map(Self::system_type)
+            .collect::<Vec<_>>();
+        let next = match sub_trees.next() {
+            Some(_) => entries.last().map(|(label, _)| label.clone()),
+            None => None,
+        };
+
+        DirectoryPage {
+            entries,
+            total,
+            next,
+        }
+    }
+
+    fn system_type(sub_tree: &SubTree<Label, File>) -> (Label, SystemType) {
+        match sub_tree {
+            SubTree::Node { key: name, value } if value.mode == FileMode::Symlink => {
+                SystemType::symlink(name.clone())
+            }
+            SubTree::Node { key: name, value } if value.mode == FileMode::Submodule => {
+                SystemType::submodule(name.clone())
+            }
+            SubTree::Node { key: name, value } if value.mode == FileMode::EmptyDirectory => {
+                SystemType::directory(name.clone())
+            }
+            SubTree::Node { key: name, .. } => SystemType::file(name.clone()),
+            SubTree::Branch { key: name, .. } => SystemType::directory(name.clone()),
+        }
+    }
+
+    /// List the contents of the current `Directory`, like
+    /// [`list_directory`](#method.list_directory), ordered according to `options`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File, ListOptions, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("chapter10.md"), File::new(b"10"));
+    /// directory.insert_file(&unsound::path::new("chapter2.md"), File::new(b"2"));
+    /// directory.insert_file(&unsound::path::new("Changelog.md"), File::new(b""));
+    /// directory.insert_file(&unsound::path::new("src/lib.rs"), File::new(b""));
+    ///
+    /// assert_eq!(
+    ///     directory.list_directory_with(&ListOptions::default()),
+    ///     vec![
+    ///         SystemType::file(unsound::label::new("Changelog.md")),
+    ///         SystemType::file(unsound::label::new("chapter10.md")),
+    ///         SystemType::file(unsound::label::new("chapter2.md")),
+    ///         SystemType::directory(unsound::label::new("src")),
+    ///     ]
+    /// );
+    ///
+    /// let options = ListOptions {
+    ///     directories_first: true,
+    ///     case_insensitive: true,
+    ///     natural: true,
+    /// };
+    /// assert_eq!(
+    ///     directory.list_directory_with(&options),
+    ///     vec![
+    ///         SystemType::directory(unsound::label::new("src")),
+    ///         SystemType::file(unsound::label::new("Changelog.md")),
+    ///         SystemType::file(unsound::label::new("chapter2.md")),
+    ///         SystemType::file(unsound::label::new("chapter10.md")),
+    ///     ]
+    /// );
+    /// ```
+    pub fn list_directory_with(&self, options: &ListOptions) -> Vec<(Label, SystemType)> {
+        let mut contents = self.list_directory();
+        contents.sort_by(|left, right| options.cmp(left, right));
+        contents
+    }
+
+    /// Get the `Label` of the current directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, DirectoryContents, File, Label};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    ///
+    /// let main = File::new(b"println!(\"Hello, world!\")");
+    /// root.insert_file(&unsound::path::new("main.rs"), main.clone());
+    ///
+    /// let lib = File::new(b"struct Hello(String)");
+    /// root.insert_file(&unsound::path::new("lib.rs"), lib.clone());
+    ///
+    /// let test_mod = File::new(b"assert_eq!(1 + 1, 2);");
+    /// root.insert_file(&unsound::path::new("test/mod.rs"), test_mod.clone());
+    ///
+    /// let mut root_iter = root.iter();
+    ///
+    /// assert_eq!(root_iter.next(), Some(DirectoryContents::File {
+    ///     name: unsound::label::new("lib.rs"),
+    ///     file: lib
+    /// }));
+    ///
+    /// assert_eq!(root_iter.next(), Some(DirectoryContents::File {
+    ///     name: unsound::label::new("main.rs"),
+    ///     file: main
+    /// }));
+    ///
+    /// let mut test_dir = Directory::new(unsound::label::new("test"));
+    /// test_dir.insert_file(&unsound::path::new("mod.rs"), test_mod);
+    ///
+    /// assert_eq!(root_iter.next(), Some(DirectoryContents::Directory(test_dir)));
+    /// ```
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = DirectoryContents> + 'a {
+        let mut empty_iter = None;
+        let mut trees_iter = None;
+        match &self.sub_directories.0 {
+            None => empty_iter = Some(std::iter::empty()),
+            Some(trees) => {
+                trees_iter = Some(
+                    trees
+                        .iter_subtrees()
+                        .cloned()
+                        .map(|sub_tree| sub_tree.into()),
+                )
+            }
+        }
+
+        empty_iter
+            .into_iter()
+            .flatten()
+            .chain(trees_iter.into_iter().flatten())
+    }
+
+    /// Walk the whole `Directory` in depth-first pre-order, yielding the
+    /// [`DirectoryContents`](enum.DirectoryContents.html) along with its `Path`
+    /// relative to this `Directory`.
+    ///
+    /// The contents of each `Directory` are visited in the same order as
+    /// [`iter`](struct.Directory.html#method.iter).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// directory.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    /// directory.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    ///
+    /// let paths = directory
+    ///     .walk()
+    ///     .map(|(path, _)| path)
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     paths,
+    ///     vec![
+    ///         unsound::path::new("README.md"),
+    ///         unsound::path::new("src"),
+    ///         unsound::path::new("src/lib.rs"),
+    ///         unsound::path::new("src/vcs"),
+    ///         unsound::path::new("src/vcs/git.rs"),
+    ///     ]
+    /// );
+    /// ```
+    pub fn walk(&self) -> impl Iterator<Item = (Path, DirectoryContents)> + '_ {
+        self.walk_to_depth(usize::MAX)
+    }
+
+    /// Walk the `Directory` like [`walk`](struct.Directory.html#method.walk), but
+    /// only down to `depth` levels, where the entries of this `Directory` are at
+    /// depth `1`, so a `depth` of `0` yields nothing.
+    ///
+    /// Directories below `depth` are not visited at all, which makes this cheap
+    /// for rendering the first few levels of a large tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// directory.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    /// directory.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    ///
+    /// let paths = directory
+    ///     .walk_to_depth(2)
+    ///     .map(|(path, _)| path)
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     paths,
+    ///     vec![
+    ///         unsound::path::new("README.md"),
+    ///         unsound::path::new("src"),
+    ///         unsound::path::new("src/lib.rs"),
+    ///         unsound::path::new("src/vcs"),
+    ///     ]
+    /// );
+    ///
+    /// assert_eq!(directory.walk_to_depth(0).count(), 0);
+    /// ```
+    pub fn walk_to_depth(
+        &self,
+        depth: usize,
+    ) -> impl Iterator<Item = (Path, DirectoryContents)> + '_ {
+        let stack = match &self.sub_directories.0 {
+            Some(trees) if depth > 0 => trees
+                .0
+                .values()
+                .rev()
+                .map(|sub_tree| (None, sub_tree))
+                .collect(),
+            _ => vec![],
+        };
+        Walk { stack, depth }
+    }
+
+    /// List the contents of the `Directory` down to `depth` levels, along with their
+    /// `Path`s relative to this `Directory`, in the order of
+    /// [`walk_to_depth`](struct.Directory.html#method.walk_to_depth).
+    ///
+    /// A `depth` of `1` lists the same entries as
+    /// [`list_directory`](struct.Directory.html#method.list_directory).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// directory.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    /// directory.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    ///
+    /// assert_eq!(
+    ///     directory.list_to_depth(2),
+    ///     vec![
+    ///         (unsound::path::new("README.md"), SystemType::File),
+    ///         (unsound::path::new("src"), SystemType::Directory),
+    ///         (unsound::path::new("src/lib.rs"), SystemType::File),
+    ///         (unsound::path::new("src/vcs"), SystemType::Directory),
+    ///     ]
+    /// );
+    /// ```
+    pub fn list_to_depth(&self, depth: usize) -> Vec<(Path, SystemType)> {
+        self.walk_to_depth(depth)
+            .map(|(path, contents)| {
+                let system_type = match contents {
+                    DirectoryContents::File { .. } => SystemType::File,
+                    DirectoryContents::Directory(_) => SystemType::Directory,
+                    DirectoryContents::Symlink { .. } => SystemType::Symlink,
+                    DirectoryContents::Submodule { .. } => SystemType::Submodule,
+                };
+                (path, system_type)
+            })
+            .collect()
+    }
+
+    /// Walk the whole `Directory` in breadth-first order, yielding the
+    /// [`DirectoryContents`](enum.DirectoryContents.html) along with its `Path`
+    /// relative to this `Directory`.
+    ///
+    /// All the entries at one depth are visited before any entry at the next depth,
+    /// and the contents of each `Directory` are visited in the same order as
+    /// [`iter`](struct.Directory.html#method.iter).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// directory.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    /// directory.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// directory.insert_file(&unsound::path::new("tests/vcs.rs"), File::new(b"mod git;"));
+    ///
+    /// let paths = directory
+    ///     .walk_breadth_first()
+    ///     .map(|(path, _)| path)
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     paths,
+    ///     vec![
+    ///         unsound::path::new("README.md"),
+    ///         unsound::path::new("src"),
+    ///         unsound::path::new("tests"),
+    ///         unsound::path::new("src/lib.rs"),
+    ///         unsound::path::new("src/vcs"),
+    ///         unsound::path::new("tests/vcs.rs"),
+    ///         unsound::path::new("src/vcs/git.rs"),
+    ///     ]
+    /// );
+    /// ```
+    pub fn walk_breadth_first(&self) -> impl Iterator<Item = (Path, DirectoryContents)> {
+        let mut queue = self
+            .iter()
+            .map(|contents| (None, contents))
+            .collect::<VecDeque<(Option<Path>, _)>>();
+
+        std::iter::from_fn(move || {
+            let (parent, contents) = queue.pop_front()?;
+            let path = match parent {
+                Some(parent) => parent.join(contents.label()),
+                None => Path::new(contents.label()),
+            };
+
+            if let DirectoryContents::Directory(directory) = &contents {
+                queue.extend(directory.iter().map(|child| (Some(path.clone()), child)));
+            }
+
+            Some((path, contents))
+        })
+    }
+
+    /// Walk the `Directory` like [`walk`](struct.Directory.html#method.walk), but
+    /// skip the entries that are ignored by the `.gitignore` files in it, as if it
+    /// were a working tree.
+    ///
+    /// Each `.gitignore` matches paths relative to its own directory, and an entry
+    /// is ignored if any of the `.gitignore` files in the directories leading to it
+    /// match it. Ignored directories are not walked into, and every `.gitignore` is
+    /// only parsed once. The walk ends with an error if the contents of a
+    /// `.gitignore` can't be read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new(".gitignore"), File::new(b"/target/\n"));
+    /// directory.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// directory.insert_file(&unsound::path::new("src/.gitignore"), File::new(b"*.bak\n"));
+    /// directory.insert_file(&unsound::path::new("src/lib.rs.bak"), File::new(b""));
+    /// directory.insert_file(&unsound::path::new("target/debug/surf"), File::new(b""));
+    ///
+    /// let paths = directory
+    ///     .walk_respecting_ignores()
+    ///     .map(|entry| entry.map(|(path, _)| path))
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     paths,
+    ///     vec![
+    ///         unsound::path::new(".gitignore"),
+    ///         unsound::path::new("src"),
+    ///         unsound::path::new("src/.gitignore"),
+    ///         unsound::path::new("src/lib.rs"),
+    ///     ]
+    /// );
+    /// ```
+    #[cfg(feature = "gitignore")]
+    pub fn walk_respecting_ignores(
+        &self,
+    ) -> impl Iterator<Item = Result<(Path, DirectoryContents), error::Error>> {
+        fn push_ignore(
+            ignores: &Ignores,
+            depth: usize,
+            directory: &Directory,
+        ) -> Result<Ignores, error::Error> {
+            match directory.find_file(&Path::new(gitignore_label())) {
+                None => Ok(ignores.clone()),
+                Some(file) => {
+                    let mut ignores = ignores.to_vec();
+                    ignores.push((
+                        depth,
+                        Ignore::parse(&String::from_utf8_lossy(file.contents()?)),
+                    ));
+                    Ok(Rc::new(ignores))
+                }
+            }
+        }
+
+        let mut stack = vec![];
+        let mut error = None;
+        match push_ignore(&Rc::new(vec![]), 0, self) {
+            Ok(root) => stack.extend(self.iter().map(|contents| (None, root.clone(), contents))),
+            Err(err) => error = Some(err),
+        }
+        let mut stack: Vec<(Option<Path>, Ignores, _)> = stack;
+        stack.reverse();
+
+        std::iter::from_fn(move || loop {
+            if let Some(err) = error.take() {
+                stack.clear();
+                return Some(Err(err));
+            }
+            let (parent, ignores, contents) = stack.pop()?;
+            let path = match parent {
+                Some(parent) => parent.join(contents.label()),
+                None => Path::new(contents.label()),
+            };
+
+            let is_directory = matches!(contents, DirectoryContents::Directory(_));
+            if ignores_match(&ignores, &path, is_directory) {
+                continue;
+            }
+
+            if let DirectoryContents::Directory(directory) = &contents {
+                match push_ignore(&ignores, path.0.len(), directory) {
+                    Ok(ignores) => {
+                        let children = directory.iter().collect::<Vec<_>>();
+                        stack.extend(
+                            children
+                                .into_iter()
+                                .rev()
+                                .map(|child| (Some(path.clone()), ignores.clone(), child)),
+                        );
+                    }
+                    Err(err) => error = Some(err),
+                }
+            }
+
+            return Some(Ok((path, contents)));
+        })
+    }
+
+    /// Get every `File` in the whole `Directory`, along with its `Path` relative
+    /// to this `Directory`, in the order of [`walk`](struct.Directory.html#method.walk).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// directory.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    /// directory.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    ///
+    /// let files = directory.files().collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     files,
+    ///     vec![
+    ///         (unsound::path::new("README.md"), File::new(b"# Surf")),
+    ///         (unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;")),
+    ///         (unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;")),
+    ///     ]
+    /// );
+    /// ```
+    pub fn files(&self) -> impl Iterator<Item = (Path, File)> {
+        self.sub_directories
+            .iter_paths()
+            .filter(|(_, file)| file.is_file())
+            .map(|(labels, file)| (Path(labels), file.clone()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Fold the files of the whole `Directory` in parallel, along with their `Path`s,
+    /// see [`Forest::par_fold`](../tree/struct.Forest.html#method.par_fold), e.g.
+    /// to hash or count the lines of every file of a large snapshot.
+    ///
+    /// Symbolic links, submodules and empty directories are not given to `fold`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf\n\nLet's surf!\n"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;\n"));
+    /// root.insert_symlink(&unsound::path::new("src/main.rs"), "lib.rs");
+    ///
+    /// let loc = root.par_fold_files(
+    ///     || 0,
+    ///     |loc, _path, file| loc + file.line_count().unwrap().unwrap_or(0),
+    ///     |left, right| left + right,
+    /// );
+    /// assert_eq!(Ok(loc), root.loc());
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_fold_files<B, I, F, R>(&self, identity: I, fold: F, reduce: R) -> B
+    where
+        B: Send,
+        I: Fn() -> B + Send + Sync,
+        F: Fn(B, &Path, &File) -> B + Send + Sync,
+        R: Fn(B, B) -> B + Send + Sync,
+    {
+        self.sub_directories.par_fold(
+            identity,
+            |acc, labels, file| {
+                if file.is_file() {
+                    fold(acc, &Path(labels.clone()), file)
+                } else {
+                    acc
+                }
+            },
+            reduce,
+        )
+    }
+
+    /// Transform the files of the `Directory` into a [`Forest`](../tree/struct.Forest.html)
+    /// keyed by the same labels, in one pass, keeping the files for which `f` gives a
+    /// value. Directories that are left empty are left out.
+    ///
+    /// Symbolic links, submodules and empty directories are not given to `f`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File, Label};
+    /// use radicle_surf::file_system::unsound;
+    /// use radicle_surf::tree::Forest;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    ///
+    /// let sizes: Forest<Label, usize> = root.map_files(|path, file| {
+    ///     if path.split_last().1.extension() == Some("rs") { Some(file.size()) } else { None }
+    /// });
+    ///
+    /// assert_eq!(sizes.find_node(&unsound::path::new("src/lib.rs").0), Some(&12));
+    /// assert_eq!(sizes.find_node(&unsound::path::new("README.md").0), None);
+    /// ```
+    pub fn map_files<F, B>(&self, mut f: F) -> Forest<Label, B>
+    where
+        F: FnMut(&Path, &File) -> Option<B>,
+    {
+        self.sub_directories.filter_map(|keys, key, file| {
+            if file.is_file() {
+                f(&node_path(keys, key), file)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Compare this `Directory` to a `newer` one, finding the paths that were added,
+    /// removed, or lead to files whose [`checksum`](struct.File.html#method.checksum)s differ.
+    ///
+    /// Only the checksums of the files are compared, so this is much cheaper than a
+    /// [`Diff`](../diff/struct.Diff.html). A path that leads to a file in one `Directory`
+    /// and to a directory in the other is both removed and added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut old = Directory::root();
+    /// old.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// old.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// old.insert_file(&unsound::path::new("src/vcs.rs"), File::new(b"pub mod git;"));
+    ///
+    /// let mut new = Directory::root();
+    /// new.insert_file(&unsound::path::new("README.md"), File::new(b"# Radicle Surf"));
+    /// new.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// new.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    ///
+    /// let diff = old.diff_structure(&new).unwrap();
+    ///
+    /// assert_eq!(
+    ///     diff.added,
+    ///     vec![unsound::path::new("src/vcs"), unsound::path::new("src/vcs/git.rs")]
+    /// );
+    /// assert_eq!(diff.removed, vec![unsound::path::new("src/vcs.rs")]);
+    /// assert_eq!(diff.modified, vec![unsound::path::new("README.md")]);
+    ///
+    /// assert!(new.diff_structure(&new).unwrap().is_empty());
+    /// ```
+    pub fn diff_structure(&self, newer: &Self) -> Result<StructureDiff, error::Error> {
+        // Symbolic links and submodules differ from files, and from each other, even
+        // with the same contents, but a file's mode does not count.
+        let kind = |file: &File| {
+            if file.is_file() {
+                None
+            } else {
+                Some(file.mode)
+            }
+        };
+        // The first file whose contents can't be read.
+        let error = std::cell::RefCell::new(None);
+        let ForestDiff {
+            added,
+            removed,
+            changed,
+        } = self
+            .sub_directories
+            .diff(&newer.sub_directories, |old, new| {
+                kind(old) == kind(new)
+                    && old.same_contents(new).unwrap_or_else(|err| {
+                        error.borrow_mut().get_or_insert(err);
+                        false
+                    })
+            });
+        if let Some(err) = error.into_inner() {
+            return Err(err);
+        }
+
+        // Empty directories are nodes in the tree, so they have to be told apart from
+        // files, and matched with directories that have entries.
+        let is_directory = |labels: &NonEmpty<Label>| {
+            let is_directory = |directory: &Directory| match directory.sub_directories.find(labels)
+            {
+                Some(SubTree::Branch { .. }) => true,
+                Some(SubTree::Node { value, .. }) => value.mode == FileMode::EmptyDirectory,
+                None => false,
+            };
+            (is_directory(self), is_directory(newer))
+        };
+        let mut diff = StructureDiff::default();
+        for labels in changed {
+            let (old, new) = is_directory(&labels);
+            if old == new {
+                diff.modified.push(Path(labels));
+            } else {
+                diff.removed.push(Path(labels.clone()));
+                diff.added.push(Path(labels));
+            }
+        }
+
+        // Both lists are sorted, so they can be searched.
+        let differs = |labels: &&NonEmpty<Label>, other: &[NonEmpty<Label>]| {
+            other.binary_search(labels).is_err() || is_directory(labels) != (true, true)
+        };
+        diff.removed.extend(
+            removed
+                .iter()
+                .filter(|labels| differs(labels, &added))
+                .map(|labels| Path(labels.clone())),
+        );
+        diff.added.extend(
+            added
+                .iter()
+                .filter(|labels| differs(labels, &removed))
+                .map(|labels| Path(labels.clone())),
+        );
+
+        diff.added.sort();
+        diff.removed.sort();
+        Ok(diff)
+    }
+
+    /// Get the [`Manifest`](struct.Manifest.html) of this `Directory`, using the
+    /// [`Digest`](digest/trait.Digest.html) algorithm `D`.
+    ///
+    /// The `Manifest` lists every file, symbolic link, and submodule, so that the
+    /// `Directory` can later be checked against it with
+    /// [`verify_with`](#method.verify_with).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "blake3")] {
+    /// use radicle_surf::file_system::{Directory, File, FileMode};
+    /// use radicle_surf::file_system::digest::Blake3;
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// root.insert_symlink(&unsound::path::new("README"), "README.md");
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    ///
+    /// let manifest = root.manifest_with::<Blake3>().unwrap();
+    /// let entries = manifest
+    ///     .entries()
+    ///     .iter()
+    ///     .map(|entry| (entry.path.to_string(), entry.mode))
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     entries,
+    ///     vec![
+    ///         ("README".to_string(), FileMode::Symlink),
+    ///         ("README.md".to_string(), FileMode::Regular),
+    ///         ("src/lib.rs".to_string(), FileMode::Regular),
+    ///     ]
+    /// );
+    /// # }
+    /// ```
+    pub fn manifest_with<D>(&self) -> Result<Manifest, error::Error>
+    where
+        D: Digest,
+    {
+        let entries = self
+            .walk()
+            .filter_map(|(path, contents)| {
+                let (mode, digest) = match &contents {
+                    DirectoryContents::File { file, .. } => match file.checksum_with::<D>() {
+                        Ok(digest) => (file.mode, digest),
+                        Err(err) => return Some(Err(err)),
+                    },
+                    DirectoryContents::Symlink { target, .. } => {
+                        (FileMode::Symlink, D::digest(target.as_bytes()))
+                    }
+                    DirectoryContents::Submodule { commit, .. } => {
+                        (FileMode::Submodule, D::digest(commit.as_bytes()))
+                    }
+                    DirectoryContents::Directory(directory) if directory.is_empty() => {
+                        (FileMode::EmptyDirectory, D::digest(&[]))
+                    }
+                    DirectoryContents::Directory(_) => return None,
+                };
+                Some(Ok(ManifestEntry {
+                    path,
+                    mode,
+                    digest: digest.as_ref().to_vec(),
+                }))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Manifest::new(D::NAME, entries))
+    }
+
+    /// Get the [`Manifest`](struct.Manifest.html) of this `Directory`, using the
+    /// [`Blake3`](digest/struct.Blake3.html) digest algorithm.
+    ///
+    /// See [`manifest_with`](#method.manifest_with).
+    #[cfg(feature = "blake3")]
+    pub fn manifest(&self) -> Result<Manifest, error::Error> {
+        self.manifest_with::<crate::file_system::digest::Blake3>()
+    }
+
+    /// Get the checksum of the whole `Directory` using the [`Digest`](digest/trait.Digest.html)
+    /// algorithm `D`, which is the digest of its [`Manifest`](struct.Manifest.html)
+    /// as it is written out, see [`manifest_with`](#method.manifest_with).
+    ///
+    /// So the checksum depends on the paths, modes, and contents of every file,
+    /// symbolic link, and submodule, and is stable across processes and Rust
+    /// versions, e.g. for an `ETag`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "blake3")] {
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::digest::{to_hex, Blake3};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// let etag = to_hex(&root.checksum_with::<Blake3>().unwrap());
+    /// assert_eq!(etag.len(), 64);
+    ///
+    /// let mut moved = Directory::root();
+    /// moved.insert_file(&unsound::path::new("lib.rs"), File::new(b"pub mod vcs;"));
+    /// assert_ne!(to_hex(&moved.checksum_with::<Blake3>().unwrap()), etag);
+    /// # }
+    /// ```
+    pub fn checksum_with<D>(&self) -> Result<D::Output, error::Error>
+    where
+        D: Digest,
+    {
+        Ok(D::digest(self.manifest_with::<D>()?.to_string().as_bytes()))
+    }
+
+    /// Get the hash of the `Directory` as a Merkle tree using the
+    /// [`Digest`](digest/trait.Digest.html) algorithm `D`, like a git tree id.
+    ///
+    /// Each directory is hashed bottom-up from the names, modes, and digests of its
+    /// entries, where a file is digested by its
+    /// [`checksum_with`](struct.File.html#method.checksum_with), and a directory
+    /// by its own tree hash. So two `Directory`s have the same tree hash if they
+    /// have the same contents, regardless of how they were built, and the tree
+    /// hashes of a sub-directory in two snapshots tell whether anything in it
+    /// changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "blake3")] {
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::digest::Blake3;
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut old = Directory::root();
+    /// old.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// old.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// old.insert_file(&unsound::path::new("docs/guide.md"), File::new(b"# Guide"));
+    ///
+    /// let mut new = old.clone();
+    /// new.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod file_system;"));
+    /// assert_ne!(old.tree_hash_with::<Blake3>(), new.tree_hash_with::<Blake3>());
+    ///
+    /// let subtree_hash = |directory: &Directory, path: &str| {
+    ///     directory
+    ///         .find_directory(&unsound::path::new(path))
+    ///         .map(|directory| directory.tree_hash_with::<Blake3>().unwrap())
+    /// };
+    /// assert_eq!(subtree_hash(&old, "docs"), subtree_hash(&new, "docs"));
+    /// assert_ne!(subtree_hash(&old, "src"), subtree_hash(&new, "src"));
+    /// # }
+    /// ```
+    pub fn tree_hash_with<D>(&self) -> Result<D::Output, error::Error>
+    where
+        D: Digest,
+    {
+        let mut hasher = TreeHasher::<D> {
+            entries: vec![vec![]],
+            error: None,
+            digest: std::marker::PhantomData,
+        };
+        self.sub_directories.visit(&mut hasher);
+        match hasher.error {
+            Some(err) => Err(err),
+            None => Ok(D::digest(&hasher.entries.pop().unwrap_or_default())),
+        }
+    }
+
+    /// Get the hash of the `Directory` as a Merkle tree, using the
+    /// [`Blake3`](digest/struct.Blake3.html) digest algorithm.
+    ///
+    /// See [`tree_hash_with`](#method.tree_hash_with).
+    #[cfg(feature = "blake3")]
+    pub fn tree_hash(&self) -> Result<[u8; 32], error::Error> {
+        self.tree_hash_with::<crate::file_system::digest::Blake3>()
+    }
+
+    /// Check this `Directory` against a `manifest` made with the [`Digest`](digest/trait.Digest.html)
+    /// algorithm `D`, e.g. to detect that a cached snapshot has been tampered with.
+    ///
+    /// The paths that are only in this `Directory` are `added`, those only in the
+    /// `manifest` are `removed`, and those whose mode or digest differ are `modified`.
+    /// Directories are not part of a `Manifest`, unless they are empty, so other
+    /// directories are never reported. If the `manifest` was made with another
+    /// algorithm, its digests can't be checked, so every path in both is `modified`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "blake3")] {
+    /// use radicle_surf::file_system::{Directory, File, FileMode};
+    /// use radicle_surf::file_system::digest::Blake3;
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    ///
+    /// let manifest = root.manifest_with::<Blake3>().unwrap();
+    /// assert!(root.verify_with::<Blake3>(&manifest).unwrap().is_empty());
+    ///
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod evil;"));
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf").with_mode(FileMode::Executable));
+    /// root.insert_file(&unsound::path::new("build.rs"), File::new(b"fn main() {}"));
+    ///
+    /// let report = root.verify_with::<Blake3>(&manifest).unwrap();
+    /// assert_eq!(report.added, vec![unsound::path::new("build.rs")]);
+    /// assert_eq!(
+    ///     report.modified,
+    ///     vec![unsound::path::new("README.md"), unsound::path::new("src/lib.rs")]
+    /// );
+    /// assert!(report.removed.is_empty());
+    /// # }
+    /// ```
+    pub fn verify_with<D>(&self, manifest: &Manifest) -> Result<StructureDiff, error::Error>
+    where
+        D: Digest,
+    {
+        let actual = self.manifest_with::<D>()?;
+        let expected = manifest
+            .entries()
+            .iter()
+            .map(|entry| (&entry.path, entry))
+            .collect::<BTreeMap<_, _>>();
+        let mut diff = StructureDiff::default();
+
+        for entry in actual.entries() {
+            match expected.get(&entry.path) {
+                None => diff.added.push(entry.path.clone()),
+                Some(expected) if *expected != entry || manifest.algorithm() != D::NAME => {
+                    diff.modified.push(entry.path.clone())
+                }
+                Some(_) => {}
+            }
+        }
+
+        let actual = actual
+            .entries()
+            .iter()
+            .map(|entry| &entry.path)
+            .collect::<HashSet<_>>();
+        diff.removed = expected
+            .keys()
+            .filter(|path| !actual.contains(*path))
+            .map(|path| (*path).clone())
+            .collect();
+
+        Ok(diff)
+    }
+
+    /// Check this `Directory` against a `manifest` made with the [`Blake3`](digest/struct.Blake3.html)
+    /// digest algorithm.
+    ///
+    /// See [`verify_with`](#method.verify_with).
+    #[cfg(feature = "blake3")]
+    pub fn verify(&self, manifest: &Manifest) -> Result<StructureDiff, error::Error> {
+        self.verify_with::<crate::file_system::digest::Blake3>(manifest)
+    }
+
+    /// Find a `File` in the directory given the `Path` to the `File`.
+    ///
+    /// # Failures
+    /// This operation fails if the path does not lead to a `File`. If
+    /// the search is for a `Directory` then use `find_directory`.
+    ///
+    /// # Examples
+    ///
+    /// Search for a file in the path: `foo.hs`.
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let file = File::new(b"module Banana ...");
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("foo.hs"), file.clone());
+    ///
+    /// assert_eq!(
+    ///     directory.find_file(&unsound::path::new("foo.hs")),
+    ///     Some(file)
+    /// )
+    /// ```
+    ///
+    /// Search for a file in the path: `foo/bar/baz.hs`.
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let file_path = unsound::path::new("foo/bar/baz.rs");
+    ///
+    /// let file = File::new(b"module Banana ...");
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("foo/bar/baz.rs"), file.clone());
+    ///
+    /// assert_eq!(directory.find_file(&file_path), Some(file))
+    /// ```
+    ///
+    /// Search for a file in the path: `bar.hs`, which does not exist.
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let file_path = unsound::path::new("bar.hs");
+    ///
+    /// let file = File::new(b"module Banana ...");
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("foo.hs"), file);
+    ///
+    /// assert_eq!(directory.find_file(&file_path), None)
+    /// ```
+    pub fn find_file(&self, path: &Path) -> Option<File> {
+        self.sub_directories
+            .find_node(&path.0)
+            .filter(|file| file.is_file())
+            .cloned()
+    }
+
+    /// Find the target of a symbolic link in the directory given the `Path`
+    /// to the symbolic link.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("docs/README.md"), File::new(b"# Surf"));
+    /// directory.insert_symlink(&unsound::path::new("README.md"), "docs/README.md");
+    ///
+    /// assert_eq!(
+    ///     directory.find_symlink(&unsound::path::new("README.md")),
+    ///     Some("docs/README.md".to_string())
+    /// );
+    ///
+    /// // Symbolic links are not files, and the other way around.
+    /// assert_eq!(directory.find_file(&unsound::path::new("README.md")), None);
+    /// assert_eq!(directory.find_symlink(&unsound::path::new("docs/README.md")), None);
+    /// ```
+    pub fn find_symlink(&self, path: &Path) -> Option<String> {
+        self.find_node_with_mode(path, FileMode::Symlink)
+    }
+
+    /// Find the id of the commit a submodule is pinned to in the directory,
+    /// given the `Path` to the submodule.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let commit = "3873745c8f6ffb45c990eb23b491d4b4b6182f95";
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// directory.insert_submodule(&unsound::path::new("vendor/git-platinum"), commit);
+    ///
+    /// assert_eq!(
+    ///     directory.find_submodule(&unsound::path::new("vendor/git-platinum")),
+    ///     Some(commit.to_string())
+    /// );
+    /// assert_eq!(directory.find_file(&unsound::path::new("vendor/git-platinum")), None);
+    ///
+    /// let vendor = directory.find_directory(&unsound::path::new("vendor")).unwrap();
+    /// assert_eq!(
+    ///     vendor.list_directory(),
+    ///     vec![SystemType::submodule(unsound::label::new("git-platinum"))]
+    /// );
+    /// ```
+    pub fn find_submodule(&self, path: &Path) -> Option<String> {
+        self.find_node_with_mode(path, FileMode::Submodule)
+    }
+
+    fn find_node_with_mode(&self, path: &Path, mode: FileMode) -> Option<String> {
+        self.sub_directories
+            .find_node(&path.0)
+            .filter(|file| file.mode == mode)
+            .map(|file| file.target())
+    }
+
+    /// Find a `Directory` in the directory given the `Path` to
+    /// the `Directory`.
+    ///
+    /// This operation fails if the path does not lead to
+    /// the `Directory`.
+    pub fn find_directory(&self, path: &Path) -> Option<Self> {
+        let (_, current) = path.split_last();
+        match self.sub_directories.find(&path.0)? {
+            SubTree::Branch { forest, .. } => Some(Directory {
+                current: Location::SubDirectory(current),
+                sub_directories: (**forest).clone().into(),
+                stats: Memo::default(),
+            }),
+            SubTree::Node { value, .. } if value.mode == FileMode::EmptyDirectory => {
+                Some(Directory::new(current))
+            }
+            SubTree::Node { .. } => None,
+        }
+    }
+
+    /// Find a `File` in the directory given the `Path` to the `File`,
+    /// ignoring the case of each [`Label`](struct.Label.html) in the `Path`.
+    ///
+    /// The `Path` that is returned is the one stored in the `Directory`, rather
+    /// than the one that was searched for. If there is an exact match for a `Label`
+    /// it is preferred.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let file = File::new(b"# Banana");
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("docs/README.md"), file.clone());
+    ///
+    /// assert_eq!(
+    ///     directory.find_file_ci(&unsound::path::new("DOCS/readme.md")),
+    ///     Some((unsound::path::new("docs/README.md"), file))
+    /// );
+    ///
+    /// assert_eq!(directory.find_file_ci(&unsound::path::new("docs")), None);
+    /// ```
+    pub fn find_file_ci(&self, path: &Path) -> Option<(Path, File)> {
+        self.find_file_by(path, Label::eq_ignore_case)
+    }
+
+    /// Find a `File` in the directory given the `Path` to the `File`,
+    /// comparing each [`Label`](struct.Label.html) in the `Path` after
+    /// Unicode NFC normalization. See [`Label::eq_nfc`](struct.Label.html#method.eq_nfc).
+    ///
+    /// The `Path` that is returned is the one stored in the `Directory`, rather
+    /// than the one that was searched for. If there is an exact match for a `Label`
+    /// it is preferred.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let file = File::new(b"# Cafe");
+    /// let mut directory = Directory::root();
+    ///
+    /// // Checked in on macOS using NFD, i.e. 'e' followed by a combining acute accent.
+    /// directory.insert_file(&unsound::path::new("docs/cafe\u{301}.md"), file.clone());
+    ///
+    /// assert_eq!(
+    ///     directory.find_file_nfc(&unsound::path::new("docs/caf\u{e9}.md")),
+    ///     Some((unsound::path::new("docs/cafe\u{301}.md"), file))
+    /// );
+    /// ```
+    #[cfg(feature = "unicode")]
+    pub fn find_file_nfc(&self, path: &Path) -> Option<(Path, File)> {
+        self.find_file_by(path, Label::eq_nfc)
+    }
+
+    fn find_file_by<F>(&self, path: &Path, eq: F) -> Option<(Path, File)>
+    where
+        F: Fn(&Label, &Label) -> bool,
+    {
+        self.sub_directories
+            .find_by(&path.0, eq, |tree| match tree {
+                SubTree::Node { value, .. } => value.is_file(),
+                SubTree::Branch { .. } => false,
+            })
+            .and_then(|(labels, tree)| match tree {
+                SubTree::Node { value, .. } if value.is_file() => {
+                    Some((Path(labels), value.clone()))
+                }
+                SubTree::Node { .. } | SubTree::Branch { .. } => None,
+            })
+    }
+
+    /// Find a `Directory` in the directory given the `Path` to
+    /// the `Directory`, ignoring the case of each [`Label`](struct.Label.html)
+    /// in the `Path`.
+    ///
+    /// The `Path` that is returned is the one stored in the `Directory`, rather
+    /// than the one that was searched for. If there is an exact match for a `Label`
+    /// it is preferred.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("Src/Main.hs"), File::new(b"module Main"));
+    ///
+    /// let (path, src) = directory
+    ///     .find_directory_ci(&unsound::path::new("src"))
+    ///     .expect("Missing src directory");
+    ///
+    /// assert_eq!(path, unsound::path::new("Src"));
+    /// assert_eq!(src.current(), unsound::label::new("Src"));
+    /// ```
+    pub fn find_directory_ci(&self, path: &Path) -> Option<(Path, Self)> {
+        self.find_directory_by(path, Label::eq_ignore_case)
+    }
+
+    /// Find a `Directory` in the directory given the `Path` to
+    /// the `Directory`, comparing each [`Label`](struct.Label.html) in the
+    /// `Path` after Unicode NFC normalization. See
+    /// [`Label::eq_nfc`](struct.Label.html#method.eq_nfc).
+    ///
+    /// The `Path` that is returned is the one stored in the `Directory`, rather
+    /// than the one that was searched for. If there is an exact match for a `Label`
+    /// it is preferred.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("re\u{301}sume\u{301}/cv.md"), File::new(b"CV"));
+    ///
+    /// let (path, _) = directory
+    ///     .find_directory_nfc(&unsound::path::new("r\u{e9}sum\u{e9}"))
+    ///     .expect("Missing résumé directory");
+    ///
+    /// assert_eq!(path, unsound::path::new("re\u{301}sume\u{301}"));
+    /// ```
+    #[cfg(feature = "unicode")]
+    pub fn find_directory_nfc(&self, path: &Path) -> Option<(Path, Self)> {
+        self.find_directory_by(path, Label::eq_nfc)
+    }
+
+    fn find_directory_by<F>(&self, path: &Path, eq: F) -> Option<(Path, Self)>
+    where
+        F: Fn(&Label, &Label) -> bool,
+    {
+        self.sub_directories
+            .find_by(&path.0, eq, |tree| match tree {
+                SubTree::Node { value, .. } => value.mode == FileMode::EmptyDirectory,
+                SubTree::Branch { .. } => true,
+            })
+            .and_then(|(labels, tree)| match tree {
+                SubTree::Node { key, value } if value.mode == FileMode::EmptyDirectory => {
+                    Some((Path(labels), Directory::new(key.clone())))
+                }
+                SubTree::Node { .. } => None,
+                SubTree::Branch { key, forest } => Some((
+                    Path(labels),
+                    Directory {
+                        current: Location::SubDirectory(key.clone()),
+                        sub_directories: (**forest).clone().into(),
+                        stats: Memo::default(),
+                    },
+                )),
+            })
+    }
+
+    /// Get the `Label` of the current directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File, Label};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("main.rs"), File::new(b"println!(\"Hello, world!\")"));
+    /// root.insert_file(&unsound::path::new("lib.rs"), File::new(b"struct Hello(String)"));
+    /// root.insert_file(&unsound::path::new("test/mod.rs"), File::new(b"assert_eq!(1 + 1, 2);"));
+    ///
+    /// assert_eq!(root.current(), Label::root());
+    ///
+    /// let test = root.find_directory(
+    ///     &unsound::path::new("test")
+    /// ).expect("Missing test directory");
+    /// assert_eq!(test.current(), unsound::label::new("test"));
+    /// ```
+    pub fn current(&self) -> Label {
+        match &self.current {
+            Location::Root => Label::root(),
+            Location::SubDirectory(label) => label.clone(),
+        }
+    }
+
+    /// Find the README of the project in this `Directory`, along with its `Path`.
+    ///
+    /// A README is a file named `README`, ignoring case, with or without an
+    /// extension, e.g. `README.md` or `readme.rst`. It is looked for in this
+    /// `Directory` first, then in `docs`, and then in `.github`, and within one
+    /// directory, READMEs are preferred by their extension in the order of
+    /// [`README_EXTENSIONS`](constant.README_EXTENSIONS.html), then without an
+    /// extension, and then with any other extension, by name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("docs/README.md"), File::new(b"# Docs"));
+    /// root.insert_file(&unsound::path::new("src/README.md"), File::new(b"# Source"));
+    /// assert_eq!(
+    ///     root.find_readme(),
+    ///     Some((unsound::path::new("docs/README.md"), File::new(b"# Docs")))
+    /// );
+    ///
+    /// root.insert_file(&unsound::path::new("README"), File::new(b"Surf"));
+    /// root.insert_file(&unsound::path::new("readme.rst"), File::new(b"Surf\n===="));
+    /// assert_eq!(
+    ///     root.find_readme(),
+    ///     Some((unsound::path::new("readme.rst"), File::new(b"Surf\n====")))
+    /// );
+    /// ```
+    pub fn find_readme(&self) -> Option<(Path, File)> {
+        self.find_by_stem("README", false, README_EXTENSIONS, &["docs", ".github"])
+    }
+
+    /// Find the license of the project in this `Directory`, along with its `Path`.
+    ///
+    /// A license is a file named, ignoring case, `LICENSE`, `LICENCE`, `COPYING`,
+    /// or `UNLICENSE`, in that order of precedence, with or without an extension,
+    /// where `.md` and then `.txt` are preferred over other extensions. The name
+    /// may also be followed by a `-`, `_` or `.` and a suffix, e.g. `LICENSE-MIT`
+    /// or `COPYING.LESSER`, though the name without a suffix is preferred, and of
+    /// several suffixes the first by name is found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("COPYING"), File::new(b"GNU GPL"));
+    /// root.insert_file(&unsound::path::new("license.txt"), File::new(b"MIT"));
+    ///
+    /// assert_eq!(
+    ///     root.find_license(),
+    ///     Some((unsound::path::new("license.txt"), File::new(b"MIT")))
+    /// );
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("LICENSE-MIT"), File::new(b"MIT"));
+    /// root.insert_file(&unsound::path::new("LICENSE-APACHE"), File::new(b"Apache-2.0"));
+    ///
+    /// assert_eq!(
+    ///     root.find_license(),
+    ///     Some((unsound::path::new("LICENSE-APACHE"), File::new(b"Apache-2.0")))
+    /// );
+    /// ```
+    pub fn find_license(&self) -> Option<(Path, File)> {
+        ["LICENSE", "LICENCE", "COPYING", "UNLICENSE"]
+            .iter()
+            .find_map(|stem| self.find_by_stem(stem, true, &["md", "txt"], &[]))
+    }
+
+    /// Identify the license of the project in this `Directory`, found by
+    /// [`find_license`](#method.find_license), by matching its text against the
+    /// texts of common licenses, returning its [SPDX](https://spdx.org/licenses/)
+    /// identifier, e.g. `MIT` or `Apache-2.0`.
+    ///
+    /// The licenses that are identified are the AGPL-3.0, Apache-2.0, BSD-2-Clause,
+    /// BSD-3-Clause, BSD-4-Clause, CC0-1.0, GPL-2.0, GPL-3.0, ISC, LGPL-2.1,
+    /// LGPL-3.0, MIT, MPL-2.0, and the Unlicense. The GNU licenses are identified
+    /// as `-only`, e.g. `GPL-3.0-only`, unless the text starts with a notice that
+    /// any later version may be used, e.g. `GPL-3.0-or-later`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(
+    ///     &unsound::path::new("LICENSE"),
+    ///     File::new(b"\
+    ///                                  Apache License
+    ///                            Version 2.0, January 2004
+    ///                         http://www.apache.org/licenses/"),
+    /// );
+    /// assert_eq!(root.license(), Ok(Some("Apache-2.0")));
+    ///
+    /// root.insert_file(&unsound::path::new("LICENSE"), File::new(b"All rights reserved."));
+    /// assert_eq!(root.license(), Ok(None));
+    /// ```
+    #[cfg(feature = "spdx")]
+    pub fn license(&self) -> Result<Option<&'static str>, error::Error> {
+        let file = match self.find_license() {
+            Some((_, file)) => file,
+            None => return Ok(None),
+        };
+        Ok(license::identify(&String::from_utf8_lossy(
+            file.contents()?,
+        )))
+    }
+
+    /// Find the file with the given `stem` in this `Directory`, or in the
+    /// `sub_directories` in order, preferring the `extensions` in order, then
+    /// no extension, then any other extension, ignoring case.
+    ///
+    /// If `suffixed`, the stem may also be followed by a `-`, `_` or `.` and a
+    /// suffix, though files without one are preferred.
+    fn find_by_stem(
+        &self,
+        stem: &str,
+        suffixed: bool,
+        extensions: &[&str],
+        sub_directories: &[&str],
+    ) -> Option<(Path, File)> {
+        let rank = |label: &Label| -> Option<(bool, usize)> {
+            let file_stem = label.file_stem();
+            let has_suffix = if file_stem.eq_ignore_ascii_case(stem) {
+                false
+            } else if suffixed
+                && file_stem
+                    .get(..stem.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(stem))
+                && file_stem[stem.len()..].starts_with(&['-', '_', '.'][..])
+            {
+                true
+            } else {
+                return None;
+            };
+            let extension = match label.extension() {
+                Some(extension) => extensions
+                    .iter()
+                    .position(|known| known.eq_ignore_ascii_case(extension))
+                    .unwrap_or(extensions.len() + 1),
+                None => extensions.len(),
+            };
+            Some((has_suffix, extension))
+        };
+
+        let find_in = |directory: &Directory, parent: Option<Path>| {
+            let (_, name) = directory
+                .list_directory()
+                .into_iter()
+                .filter(|(_, system_type)| *system_type == SystemType::File)
+                .filter_map(|(name, _)| Some((rank(&name)?, name)))
+                .min()?;
+            let path = match parent {
+                Some(parent) => parent.join(name.clone()),
+                None => Path::new(name.clone()),
+            };
+            let file = directory.find_file(&Path::new(name))?;
+            Some((path, file))
+        };
+
+        find_in(self, None).or_else(|| {
+            sub_directories.iter().find_map(|sub_directory| {
+                let path = sub_directory.parse::<Path>().ok()?;
+                let directory = self.find_directory(&path)?;
+                find_in(&directory, Some(path))
+            })
+        })
+    }
+
+    /// Find the files whose paths contain the characters of `query` in order, though
+    /// not necessarily next to each other, returning at most `limit` paths along with
+    /// their scores, best matches first.
+    ///
+    /// Matching ignores case, unless `query` contains an uppercase character.
+    /// Characters matching at the start of a word, e.g. after a `/`, `_` or `.`,
+    /// next to each other, or in the file name score higher, and skipping characters
+    /// between two matched characters scores lower. Paths with the same
+    /// score are ranked shallower and shorter first, and then by the paths
+    /// themselves, so the results for a given `Directory` never vary between runs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("src/file_system/directory.rs"), File::new(b"pub struct Directory;"));
+    /// root.insert_file(&unsound::path::new("src/diff/mod.rs"), File::new(b"pub struct Diff;"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod diff;"));
+    ///
+    /// let paths = root
+    ///     .fuzzy_find("dir", 10)
+    ///     .into_iter()
+    ///     .map(|(path, _)| path)
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     paths,
+    ///     vec![
+    ///         unsound::path::new("src/file_system/directory.rs"),
+    ///         unsound::path::new("src/diff/mod.rs"),
+    ///     ]
+    /// );
+    ///
+    /// assert!(root.fuzzy_find("xyz", 10).is_empty());
+    /// ```
+    pub fn fuzzy_find(&self, query: &str, limit: usize) -> Vec<(Path, u32)> {
+        let case_sensitive = query.chars().any(char::is_uppercase);
+        let query = query
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<Vec<_>>();
+
+        let mut matches = self
+            .files()
+            .filter_map(|(path, _)| {
+                let score = fuzzy_score(&query, &path.to_string(), case_sensitive)?;
+                Some((path, score))
+            })
+            .collect::<Vec<_>>();
+
+        matches.sort_by_cached_key(|(path, score)| {
+            let path_str = path.to_string();
+            (
+                std::cmp::Reverse(*score),
+                path.0.len(),
+                path_str.len(),
+                path_str,
+            )
+        });
+        matches.truncate(limit);
+        matches
+    }
+
+    /// Complete a partially typed path, returning at most `limit` paths,
+    /// of both files and directories, whose string form starts with `prefix`.
+    ///
+    /// Matching ignores case, but paths matching the case of the `prefix`
+    /// are ranked first. After that, shallower and shorter paths are ranked
+    /// before deeper and longer ones.
+    ///
+    /// If fewer than `limit` paths start with `prefix`, the rest are filled with the
+    /// files that fuzzily match it, ranked as by
+    /// [`fuzzy_find`](#method.fuzzy_find).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod diff;"));
+    /// root.insert_file(&unsound::path::new("src/diff/mod.rs"), File::new(b"pub struct Diff;"));
+    /// root.insert_file(&unsound::path::new("Cargo.toml"), File::new(b"[package]"));
+    ///
+    /// assert_eq!(
+    ///     root.complete("src/", 10),
+    ///     vec![
+    ///         unsound::path::new("src/diff"),
+    ///         unsound::path::new("src/lib.rs"),
+    ///         unsound::path::new("src/diff/mod.rs"),
+    ///     ]
+    /// );
+    ///
+    /// assert_eq!(root.complete("c", 1), vec![unsound::path::new("Cargo.toml")]);
+    /// ```
+    pub fn complete(&self, prefix: &str, limit: usize) -> Vec<Path> {
+        let mut matches = vec![];
+        if let Some(tree) = &self.sub_directories.0 {
+            Self::complete_paths(tree, &prefix.to_lowercase(), &mut matches);
+        }
+
+        matches.sort_by_cached_key(|path| {
+            let path_str = path.to_string();
+            (
+                !path_str.starts_with(prefix),
+                path.0.len(),
+                path_str.len(),
+                path_str,
+            )
+        });
+        matches.truncate(limit);
+
+        if matches.len() < limit {
+            let fuzzy = self.fuzzy_find(prefix, limit + matches.len());
+            let seen = matches.iter().cloned().collect::<HashSet<_>>();
+            let rest = limit - matches.len();
+            matches.extend(
+                fuzzy
+                    .into_iter()
+                    .map(|(path, _)| path)
+                    .filter(|path| !seen.contains(path))
+                    .take(rest),
+            );
+        }
+        matches
+    }
+
+    fn complete_paths(tree: &Tree<Label, File>, prefix: &str, matches: &mut Vec<Path>) {
+        // Walk with an explicit stack, so that deep trees cannot overflow the call stack.
+        let mut stack = vec![(tree, None::<Path>)];
+        while let Some((tree, parent)) = stack.pop() {
+            for sub_tree in tree.iter_subtrees() {
+                let path = match sub_tree {
+                    SubTree::Node { key, .. } | SubTree::Branch { key, .. } => match &parent {
+                        None => Path::new(key.clone()),
+                        Some(parent) => {
+                            let mut path = parent.clone();
+                            path.push(key.clone());
+                            path
+                        }
+                    },
+                };
+                let path_str = path.to_string().to_lowercase();
+
+                if let SubTree::Branch { forest, .. } = sub_tree {
+                    // Only descend if everything below matches, or the prefix
+                    // is pointing further into this directory.
+                    if path_str.starts_with(prefix) || prefix.starts_with(&format!("{}/", path_str))
+                    {
+                        stack.push((forest, Some(path.clone())));
+                    }
+                }
+
+                if path_str.starts_with(prefix) {
+                    matches.push(path);
+                }
+            }
+        }
+    }
+
+    /// Get the total size, in bytes, of a `Directory`. The size is
+    /// the sum of all files that can be reached from this `Directory`.
+    ///
+    /// The size is computed the first time it, or any of the other figures such as
+    /// [`file_count`](#method.file_count), is asked for and remembered until the
+    /// `Directory` is changed, e.g. by [`insert_file`](#method.insert_file).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nonempty::NonEmpty;
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_files(
+    ///     &[],
+    ///     NonEmpty::from((
+    ///         (
+    ///             unsound::label::new("main.rs"),
+    ///             File::new(b"println!(\"Hello, world!\")"),
+    ///         ),
+    ///         vec![
+    ///             (
+    ///                 unsound::label::new("lib.rs"),
+    ///                 File::new(b"struct Hello(String)"),
+    ///             ),
+    ///         ],
+    ///     )),
+    /// );
+    ///
+    /// assert_eq!(root.size(), 45);
+    /// ```
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("main.rs"), File::new(b"println!(\"Hello, world!\")"));
+    /// root.insert_file(&unsound::path::new("lib.rs"), File::new(b"struct Hello(String)"));
+    /// root.insert_file(&unsound::path::new("test/mod.rs"), File::new(b"assert_eq!(1 + 1, 2);"));
+    ///
+    /// assert_eq!(root.size(), 66);
+    ///
+    /// root.insert_file(&unsound::path::new("test/mod.rs"), File::new(b""));
+    /// assert_eq!(root.size(), 45);
+    /// ```
+    pub fn size(&self) -> usize {
+        self.stats().size
+    }
+
+    /// Get the number of files that can be reached from this `Directory`.
+    ///
+    /// Symbolic links and submodules are not counted as files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// root.insert_symlink(&unsound::path::new("src/main.rs"), "lib.rs");
+    ///
+    /// assert_eq!(root.file_count(), 2);
+    /// ```
+    pub fn file_count(&self) -> usize {
+        self.stats().files
+    }
+
+    /// Get the number of directories that can be reached from this `Directory`,
+    /// not including this `Directory` itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    /// root.insert_file(&unsound::path::new("tests/vcs.rs"), File::new(b"mod git;"));
+    ///
+    /// assert_eq!(root.directory_count(), 3);
+    /// ```
+    pub fn directory_count(&self) -> usize {
+        self.stats().directories
+    }
+
+    /// Get the number of [`Label`](struct.Label.html)s in the longest `Path`
+    /// that can be reached from this `Directory`, or `0` if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// assert_eq!(root.depth(), 0);
+    ///
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// assert_eq!(root.depth(), 1);
+    ///
+    /// root.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    /// assert_eq!(root.depth(), 3);
+    /// ```
+    pub fn depth(&self) -> usize {
+        self.sub_directories.depth()
+    }
+
+    /// Count the lines of every text `File` in the whole `Directory`, skipping
+    /// binary files, see [`File::line_count`](struct.File.html#method.line_count).
+    ///
+    /// Lazy files have to be [`load`](#method.load)ed first, otherwise they have
+    /// no lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf\n\nLet's surf!\n"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;\n"));
+    /// root.insert_file(&unsound::path::new("logo.png"), File::new(b"\x89PNG\r\n\x1a\n\0"));
+    ///
+    /// assert_eq!(root.loc(), Ok(4));
+    /// ```
+    pub fn loc(&self) -> Result<usize, error::Error> {
+        self.loc_per_file()
+            .map(|entry| entry.map(|(_, lines)| lines))
+            .sum()
+    }
+
+    /// Count the lines of each text `File` in the whole `Directory`, along with its
+    /// `Path`, in the order of [`files`](#method.files), skipping binary files, or
+    /// the error of reading the contents of a lazy file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf\n\nLet's surf!\n"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;\n"));
+    /// root.insert_file(&unsound::path::new("logo.png"), File::new(b"\x89PNG\r\n\x1a\n\0"));
+    ///
+    /// assert_eq!(
+    ///     root.loc_per_file().collect::<Vec<_>>(),
+    ///     vec![
+    ///         Ok((unsound::path::new("README.md"), 3)),
+    ///         Ok((unsound::path::new("src/lib.rs"), 1)),
+    ///     ]
+    /// );
+    /// ```
+    pub fn loc_per_file(&self) -> impl Iterator<Item = Result<(Path, usize), error::Error>> {
+        self.files().filter_map(|(path, file)| {
+            file.line_count()
+                .transpose()
+                .map(|lines| lines.map(|lines| (path, lines)))
+        })
+    }
+
+    /// Get the `n` largest files in the whole `Directory`, along with their `Path`s,
+    /// largest first, where files of the same size are ordered by `Path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// root.insert_file(&unsound::path::new("src/vcs.rs"), File::new(b"pub mod git;"));
+    ///
+    /// let largest = root
+    ///     .largest_files(2)
+    ///     .into_iter()
+    ///     .map(|(path, file)| (path, file.size()))
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     largest,
+    ///     vec![
+    ///         (unsound::path::new("src/lib.rs"), 12),
+    ///         (unsound::path::new("src/vcs.rs"), 12),
+    ///     ]
+    /// );
+    /// ```
+    pub fn largest_files(&self, n: usize) -> Vec<(Path, File)> {
+        let mut files = self.files().collect::<Vec<_>>();
+        files.sort_by(|(left_path, left), (right_path, right)| {
+            right
+                .size()
+                .cmp(&left.size())
+                .then_with(|| left_path.cmp(right_path))
+        });
+        files.truncate(n);
+        files
+    }
+
+    /// Group the files in the whole `Directory` that have the same contents, by
+    /// their checksums, leaving out files whose contents are unique.
+    ///
+    /// The `Path`s in each group are in the order of [`files`](#method.files), and
+    /// the groups are in the order of their first `Path`. Lazy files are grouped by
+    /// the ids of their [`Blob`](trait.Blob.html)s, so they are only grouped with
+    /// other lazy files, see [`File::checksum`](struct.File.html#method.checksum).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("LICENSE"), File::new(b"GPL-3.0"));
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("vendor/surf/LICENSE"), File::new(b"GPL-3.0"));
+    ///
+    /// assert_eq!(
+    ///     root.duplicate_files(),
+    ///     Ok(vec![vec![
+    ///         unsound::path::new("LICENSE"),
+    ///         unsound::path::new("vendor/surf/LICENSE"),
+    ///     ]])
+    /// );
+    /// ```
+    pub fn duplicate_files(&self) -> Result<Vec<Vec<Path>>, error::Error> {
+        let mut by_checksum: HashMap<u64, Vec<(File, Vec<Path>)>> = HashMap::new();
+        for (path, file) in self.files() {
+            let groups = by_checksum.entry(file.checksum()?).or_default();
+            // Files with the same checksum are compared as well, since checksums collide.
+            let mut same = None;
+            for (index, (other, _)) in groups.iter().enumerate() {
+                if file.same_contents(other)? {
+                    same = Some(index);
+                    break;
+                }
+            }
+            match same {
+                Some(index) => groups[index].1.push(path),
+                None => groups.push((file, vec![path])),
+            }
+        }
+
+        let mut duplicates = by_checksum
+            .into_values()
+            .flatten()
+            .map(|(_, paths)| paths)
+            .filter(|paths| paths.len() > 1)
+            .collect::<Vec<_>>();
+        duplicates.sort();
+        Ok(duplicates)
+    }
+
+    /// Find the files in the whole `Directory` with the given `extension`, see
+    /// [`Label::extension`](struct.Label.html#method.extension), in the order of
+    /// [`files`](#method.files).
+    ///
+    /// Extensions are compared exactly, so `rs` does not find `lib.RS`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("Cargo.toml"), File::new(b"[package]"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// root.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    ///
+    /// assert_eq!(
+    ///     root.find_files_with_extension("rs"),
+    ///     vec![unsound::path::new("src/lib.rs"), unsound::path::new("src/vcs/git.rs")]
+    /// );
+    /// ```
+    pub fn find_files_with_extension(&self, extension: &str) -> Vec<Path> {
+        self.find_files_with_extensions(&[extension])
+    }
+
+    /// Find the files in the whole `Directory` with any of the given `extensions`,
+    /// like [`find_files_with_extension`](#method.find_files_with_extension).
+    ///
+    /// The `Directory` is visited in place, without building the sub-directories
+    /// that [`walk`](#method.walk) yields.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("Cargo.toml"), File::new(b"[package]"));
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    ///
+    /// assert_eq!(
+    ///     root.find_files_with_extensions(&["rs", "toml"]),
+    ///     vec![unsound::path::new("Cargo.toml"), unsound::path::new("src/lib.rs")]
+    /// );
+    /// ```
+    pub fn find_files_with_extensions(&self, extensions: &[&str]) -> Vec<Path> {
+        let mut finder = ExtensionFinder {
+            extensions,
+            paths: vec![],
+        };
+        self.sub_directories.visit(&mut finder);
+        finder.paths
+    }
+
+    /// Find the first file, in the order of [`walk`](#method.walk), for which
+    /// `predicate` holds, along with its `Path`.
+    ///
+    /// The search stops at the first match, rather than going through the whole
+    /// `Directory`. Symbolic links, submodules and empty directories are not given
+    /// to `predicate`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// root.insert_file(&unsound::path::new("src/vcs.rs"), File::new(b"pub mod git;"));
+    ///
+    /// let (path, file) = root
+    ///     .find_first(|path, file| {
+    ///         path.iter().count() > 1 && file.contents().unwrap().starts_with(b"pub")
+    ///     })
+    ///     .expect("there is a matching file");
+    /// assert_eq!(path, unsound::path::new("src/lib.rs"));
+    /// assert_eq!(file, File::new(b"pub mod vcs;"));
+    ///
+    /// assert_eq!(root.find_first(|_, file| file.size() > 100), None);
+    /// ```
+    pub fn find_first<F>(&self, predicate: F) -> Option<(Path, File)>
+    where
+        F: FnMut(&Path, &File) -> bool,
+    {
+        let mut finder = FirstFinder {
+            predicate,
+            found: None,
+        };
+        self.sub_directories.visit(&mut finder);
+        finder.found
+    }
+
+    fn stats(&self) -> Stats {
+        *self.stats.get_or_init(|| Stats::of(&self.sub_directories))
+    }
+
+    /// Read the contents of every lazy `File` in the `Directory` up front, see
+    /// [`File::contents`](struct.File.html#method.contents).
+    pub fn load(&self) -> Result<(), error::Error> {
+        for (_, file) in self.files() {
+            file.contents()?;
+        }
+        Ok(())
+    }
+
+    /// Insert a file into a directory, given the full path to file (file name inclusive) and
+    /// the `File` itself.
+    ///
+    /// This function is usually used for testing and demonstation purposes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` has more than [`MAX_DEPTH`](constant.MAX_DEPTH.html) labels,
+    /// see [`try_insert_file`](#method.try_insert_file).
+    pub fn insert_file(&mut self, path: &Path, file: File) {
+        assert_depth(self.try_insert_file(path, file), path)
+    }
+
+    /// Insert a file into a directory, given the full path to file (file name inclusive) and
+    /// the `File` itself, unless `path` has more than [`MAX_DEPTH`](constant.MAX_DEPTH.html)
+    /// labels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{error, Directory, File, MAX_DEPTH};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// let mut deep = unsound::path::new(&vec!["a"; MAX_DEPTH].join("/"));
+    /// assert!(directory.try_insert_file(&deep, File::new(b"deep")).is_ok());
+    ///
+    /// deep.push(unsound::label::new("b"));
+    /// assert_eq!(
+    ///     directory.try_insert_file(&deep, File::new(b"deeper")),
+    ///     Err(error::Error::Path(error::Path::TooDeep))
+    /// );
+    /// assert_eq!(directory.file_count(), 1);
+    /// ```
+    pub fn try_insert_file(&mut self, path: &Path, file: File) -> Result<(), error::Error> {
+        check_depth(path.0.len())?;
+        self.stats.reset();
+        self.sub_directories.insert(&path.0, file);
+        Ok(())
     }
-}
 
-impl Directory {
-    /// Create a root directory.
+    /// Insert a symbolic link into a directory, given the full path to the symbolic
+    /// link and the path it points to.
     ///
     /// This function is usually used for testing and demonstation purposes.
-    pub fn root() -> Self {
-        Directory {
-            current: Location::Root,
-            sub_directories: Forest::root(),
-        }
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` has more than [`MAX_DEPTH`](constant.MAX_DEPTH.html) labels,
+    /// see [`try_insert_symlink`](#method.try_insert_symlink).
+    pub fn insert_symlink(&mut self, path: &Path, target: &str) {
+        assert_depth(self.try_insert_symlink(path, target), path)
     }
 
-    /// Create a directory, similar to `root`, except with a given name.
+    /// Insert a symbolic link into a directory, given the full path to the symbolic
+    /// link and the path it points to, unless `path` has more than
+    /// [`MAX_DEPTH`](constant.MAX_DEPTH.html) labels.
+    pub fn try_insert_symlink(&mut self, path: &Path, target: &str) -> Result<(), error::Error> {
+        let link = File::new(target.as_bytes()).with_entry_mode(FileMode::Symlink);
+        self.try_insert_file(path, link)
+    }
+
+    /// Insert a submodule into a directory, given the full path to the submodule
+    /// and the id of the commit it is pinned to.
     ///
     /// This function is usually used for testing and demonstation purposes.
-    pub fn new(label: Label) -> Self {
-        Directory {
-            current: Location::SubDirectory(label),
-            sub_directories: Forest::root(),
-        }
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` has more than [`MAX_DEPTH`](constant.MAX_DEPTH.html) labels,
+    /// see [`try_insert_submodule`](#method.try_insert_submodule).
+    pub fn insert_submodule(&mut self, path: &Path, commit: &str) {
+        assert_depth(self.try_insert_submodule(path, commit), path)
     }
 
-    /// List the current `Directory`'s files and sub-directories.
+    /// Insert a submodule into a directory, given the full path to the submodule
+    /// and the id of the commit it is pinned to, unless `path` has more than
+    /// [`MAX_DEPTH`](constant.MAX_DEPTH.html) labels.
+    pub fn try_insert_submodule(&mut self, path: &Path, commit: &str) -> Result<(), error::Error> {
+        let submodule = File::new(commit.as_bytes()).with_entry_mode(FileMode::Submodule);
+        self.try_insert_file(path, submodule)
+    }
+
+    /// Insert an empty directory into a directory, given the full path to it, unless
+    /// there is already a file or directory at `path`.
+    ///
+    /// Inserting anything into an empty directory makes it a regular directory, and
+    /// it is listed as a [`DirectoryContents::Directory`](enum.DirectoryContents.html#variant.Directory)
+    /// with no entries, see [`FileMode::EmptyDirectory`](enum.FileMode.html#variant.EmptyDirectory).
+    ///
+    /// # Examples
     ///
     /// ```
-    /// use nonempty::NonEmpty;
     /// use radicle_surf::file_system::{Directory, File, SystemType};
     /// use radicle_surf::file_system::unsound;
     ///
     /// let mut directory = Directory::root();
-    /// directory.insert_file(
-    ///     &unsound::path::new("foo.hs"),
-    ///     File::new(b"module BananaFoo ..."),
-    /// );
-    /// directory.insert_file(
-    ///     &unsound::path::new("bar.hs"),
-    ///     File::new(b"module BananaBar ..."),
-    /// );
-    /// directory.insert_file(
-    ///     &unsound::path::new("baz.hs"),
-    ///     File::new(b"module BananaBaz ..."),
-    /// );
+    /// directory.insert_empty_directory(&unsound::path::new("src"));
     ///
     /// assert_eq!(
     ///     directory.list_directory(),
-    ///     vec![
-    ///         SystemType::file(unsound::label::new("bar.hs")),
-    ///         SystemType::file(unsound::label::new("baz.hs")),
-    ///         SystemType::file(unsound::label::new("foo.hs")),
-    ///     ]
+    ///     vec![SystemType::directory(unsound::label::new("src"))]
+    /// );
+    /// assert_eq!(directory.directory_count(), 1);
+    /// assert_eq!(
+    ///     directory.find_directory(&unsound::path::new("src")),
+    ///     Some(Directory::new(unsound::label::new("src")))
     /// );
+    ///
+    /// directory.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// directory.insert_empty_directory(&unsound::path::new("src"));
+    /// assert_eq!(directory.file_count(), 1);
     /// ```
     ///
+    /// # Panics
+    ///
+    /// Panics if `path` has more than [`MAX_DEPTH`](constant.MAX_DEPTH.html) labels,
+    /// see [`try_insert_empty_directory`](#method.try_insert_empty_directory).
+    pub fn insert_empty_directory(&mut self, path: &Path) {
+        assert_depth(self.try_insert_empty_directory(path), path)
+    }
+
+    /// Insert an empty directory into a directory, given the full path to it, unless
+    /// there is already a file or directory at `path`, or `path` has more than
+    /// [`MAX_DEPTH`](constant.MAX_DEPTH.html) labels.
+    pub fn try_insert_empty_directory(&mut self, path: &Path) -> Result<(), error::Error> {
+        check_depth(path.0.len())?;
+        if self.sub_directories.find(&path.0).is_some() {
+            return Ok(());
+        }
+
+        let empty = File::new(&[]).with_entry_mode(FileMode::EmptyDirectory);
+        self.try_insert_file(path, empty)
+    }
+
+    /// Remove every empty directory in the `Directory`, e.g. before exporting it to
+    /// a backend that cannot hold them, like git.
+    ///
+    /// # Examples
+    ///
     /// ```
-    /// use nonempty::NonEmpty;
-    /// use radicle_surf::file_system::{Directory, File, SystemType};
+    /// use radicle_surf::file_system::{Directory, File};
     /// use radicle_surf::file_system::unsound;
     ///
     /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// directory.insert_empty_directory(&unsound::path::new("src/empty"));
+    /// directory.insert_empty_directory(&unsound::path::new("target"));
     ///
-    /// // Root files set up
-    /// let root_files = NonEmpty::from((
-    ///     (unsound::label::new("foo.rs"), File::new(b"use crate::bar")),
-    ///     vec![(
-    ///         unsound::label::new("bar.rs"),
-    ///         File::new(b"fn hello_world()"),
-    ///     )],
-    /// ));
-    /// directory.insert_files(&[], root_files);
+    /// directory.remove_empty_directories();
     ///
-    /// // Haskell files set up
-    /// let haskell_files = NonEmpty::from((
-    ///     (
-    ///         unsound::label::new("foo.hs"),
-    ///         File::new(b"module Foo where"),
-    ///     ),
-    ///     vec![(
-    ///         unsound::label::new("bar.hs"),
-    ///         File::new(b"module Bar where"),
-    ///     )],
-    /// ));
+    /// assert_eq!(directory.list_to_depth(2).len(), 2);
+    /// assert_eq!(directory.find_directory(&unsound::path::new("target")), None);
+    /// ```
+    pub fn remove_empty_directories(&mut self) {
+        self.stats.reset();
+        self.sub_directories
+            .prune(|_, _, file| file.mode != FileMode::EmptyDirectory);
+    }
+
+    /// Check whether the `Directory` has no entries at all.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.sub_directories.0.is_none()
+    }
+
+    /// Check whether any entry of the `Directory`, including its symbolic links and
+    /// submodules, carries [`Metadata`](struct.Metadata.html).
+    pub(crate) fn has_metadata(&self) -> bool {
+        self.sub_directories
+            .iter_paths()
+            .any(|(_, file)| !file.metadata().is_empty())
+    }
+
+    /// Insert files into a shared directory path.
     ///
-    /// directory.insert_files(&[unsound::label::new("haskell")], haskell_files);
+    /// `directory_path` is used as the prefix to where the files should go. If empty the
+    /// files will be placed in the current `Directory`.
     ///
-    /// let mut directory_contents = directory.list_directory();
-    /// directory_contents.sort();
+    /// `files` are pairs of file name and the `File` itself.
+    ///
+    /// This function is usually used for testing and demonstation purposes.
+    pub fn insert_files(&mut self, directory_path: &[Label], files: NonEmpty<(Label, File)>) {
+        match NonEmpty::from_slice(directory_path) {
+            None => {
+                for (file_name, file) in files.iter() {
+                    self.insert_file(&Path::new(file_name.clone()), file.clone())
+                }
+            }
+            Some(directory_path) => {
+                for (file_name, file) in files.iter() {
+                    let mut file_path = Path(directory_path.clone());
+                    file_path.push(file_name.clone());
+
+                    self.insert_file(&file_path, file.clone())
+                }
+            }
+        }
+    }
+
+    /// Create a [`DirectoryCursor`](struct.DirectoryCursor.html) focused on the root
+    /// of the `Directory`, to navigate and edit it a level at a time.
+    pub fn cursor(self) -> DirectoryCursor {
+        DirectoryCursor {
+            current: self.current,
+            cursor: self.sub_directories.cursor(),
+            empty: vec![],
+        }
+    }
+
+    /// Remove the file at `path` from the directory, returning it, or `None` if
+    /// there is no file at `path`. Symbolic links and submodules can be removed
+    /// too, but a directory cannot, see [`remove_directory`](#method.remove_directory).
     ///
+    /// Any directories that are left empty are removed as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    ///
+    /// assert_eq!(root.remove_file(&unsound::path::new("src")), None);
     /// assert_eq!(
-    ///     directory_contents,
-    ///     vec![
-    ///         SystemType::file(unsound::label::new("bar.rs")),
-    ///         SystemType::file(unsound::label::new("foo.rs")),
-    ///         SystemType::directory(unsound::label::new("haskell")),
-    ///     ]
+    ///     root.remove_file(&unsound::path::new("src/lib.rs")),
+    ///     Some(File::new(b"pub mod vcs;"))
     /// );
+    ///
+    /// // `src` is empty now, so it is gone too.
+    /// assert_eq!(root.find_directory(&unsound::path::new("src")), None);
+    /// assert_eq!(root.size(), 6);
     /// ```
-    pub fn list_directory(&self) -> Vec<(Label, SystemType)> {
-        let forest = &self.sub_directories;
-        match &forest.0 {
-            None => vec![],
-            Some(trees) => trees
-                .0
-                .iter()
-                .map(|tree| match tree {
-                    SubTree::Node { key: name, .. } => SystemType::file(name.clone()),
-                    SubTree::Branch { key: name, .. } => SystemType::directory(name.clone()),
-                })
-                .collect(),
+    pub fn remove_file(&mut self, path: &Path) -> Option<File> {
+        match self.sub_directories.find(&path.0)? {
+            SubTree::Node { value, .. } if value.mode != FileMode::EmptyDirectory => {}
+            SubTree::Node { .. } | SubTree::Branch { .. } => return None,
         }
+
+        self.stats.reset();
+        self.sub_directories.remove_node(&path.0)
     }
 
-    /// Get the `Label` of the current directory.
+    /// Remove the directory at `path`, and everything in it, returning it, or `None`
+    /// if there is no directory at `path`.
+    ///
+    /// Any directories that are left empty are removed as well.
     ///
     /// # Examples
     ///
     /// ```
-    /// use radicle_surf::file_system::{Directory, DirectoryContents, File, Label};
+    /// use radicle_surf::file_system::{Directory, File};
     /// use radicle_surf::file_system::unsound;
     ///
     /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
     ///
-    /// let main = File::new(b"println!(\"Hello, world!\")");
-    /// root.insert_file(&unsound::path::new("main.rs"), main.clone());
+    /// let vcs = root.remove_directory(&unsound::path::new("src/vcs")).unwrap();
     ///
-    /// let lib = File::new(b"struct Hello(String)");
-    /// root.insert_file(&unsound::path::new("lib.rs"), lib.clone());
+    /// assert_eq!(vcs.current(), unsound::label::new("vcs"));
+    /// assert_eq!(vcs.file_count(), 1);
+    /// assert_eq!(root.find_directory(&unsound::path::new("src")), None);
+    /// assert_eq!(root.remove_directory(&unsound::path::new("README.md")), None);
+    /// ```
+    pub fn remove_directory(&mut self, path: &Path) -> Option<Self> {
+        match self.sub_directories.find(&path.0)? {
+            SubTree::Branch { .. } => {}
+            SubTree::Node { value, .. } if value.mode == FileMode::EmptyDirectory => {}
+            SubTree::Node { .. } => return None,
+        }
+
+        self.stats.reset();
+        match self.sub_directories.remove(&path.0)? {
+            SubTree::Branch { key, forest } => Some(Directory {
+                current: Location::SubDirectory(key),
+                sub_directories: (*forest).into(),
+                stats: Memo::default(),
+            }),
+            SubTree::Node { key, .. } => Some(Directory::new(key)),
+        }
+    }
+
+    /// Move the file or directory at `from` to `to`, keeping its contents, and
+    /// returning whether it was moved.
     ///
-    /// let test_mod = File::new(b"assert_eq!(1 + 1, 2);");
-    /// root.insert_file(&unsound::path::new("test/mod.rs"), test_mod.clone());
+    /// Nothing is moved if there is nothing at `from`, if there is already a file
+    /// or directory at `to`, if `to` would be inside a file, if `to` is inside
+    /// `from`, or if the entries would be nested deeper than
+    /// [`MAX_DEPTH`](constant.MAX_DEPTH.html). Any directories that are left empty
+    /// by the move are removed.
     ///
-    /// let mut root_iter = root.iter();
+    /// # Examples
     ///
-    /// assert_eq!(root_iter.next(), Some(DirectoryContents::File {
-    ///     name: unsound::label::new("lib.rs"),
-    ///     file: lib
-    /// }));
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
     ///
-    /// assert_eq!(root_iter.next(), Some(DirectoryContents::File {
-    ///     name: unsound::label::new("main.rs"),
-    ///     file: main
-    /// }));
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
     ///
-    /// let mut test_dir = Directory::new(unsound::label::new("test"));
-    /// test_dir.insert_file(&unsound::path::new("mod.rs"), test_mod);
+    /// assert!(root.move_entry(&unsound::path::new("src/vcs"), &unsound::path::new("lib/vcs")));
+    /// assert_eq!(
+    ///     root.find_file(&unsound::path::new("lib/vcs/git.rs")),
+    ///     Some(File::new(b"pub mod error;"))
+    /// );
+    /// assert_eq!(root.find_directory(&unsound::path::new("src")), None);
     ///
-    /// assert_eq!(root_iter.next(), Some(DirectoryContents::Directory(test_dir)));
+    /// // The destination is taken, or inside a file.
+    /// assert!(!root.move_entry(&unsound::path::new("lib"), &unsound::path::new("README.md")));
+    /// assert!(!root.move_entry(&unsound::path::new("lib"), &unsound::path::new("README.md/lib")));
     /// ```
-    pub fn iter<'a>(&'a self) -> impl Iterator<Item = DirectoryContents> + 'a {
-        let mut empty_iter = None;
-        let mut trees_iter = None;
-        match &self.sub_directories.0 {
-            None => empty_iter = Some(std::iter::empty()),
-            Some(trees) => {
-                trees_iter = Some(
-                    trees
-                        .iter_subtrees()
-                        .cloned()
-                        .map(|sub_tree| sub_tree.into()),
-                )
-            }
+    pub fn move_entry(&mut self, from: &Path, to: &Path) -> bool {
+        if from == to {
+            return self.sub_directories.find(&from.0).is_some();
         }
 
-        empty_iter
-            .into_iter()
-            .flatten()
-            .chain(trees_iter.into_iter().flatten())
+        let to_is_inside_from =
+            to.0.len() > from.0.len() && from.iter().zip(to.iter()).all(|(l, r)| l == r);
+        let to_is_taken = self.sub_directories.find(&to.0).is_some();
+        let labels = to.iter().cloned().collect::<Vec<_>>();
+        let to_is_inside_file = (1..labels.len())
+            .filter_map(|len| NonEmpty::from_slice(&labels[..len]))
+            .any(|prefix| {
+                self.sub_directories
+                    .find_node(&prefix)
+                    .is_some_and(|node| node.mode != FileMode::EmptyDirectory)
+            });
+        let is_too_deep = self
+            .sub_directories
+            .find(&from.0)
+            .is_some_and(|sub_tree| to.0.len() - 1 + sub_tree.depth() > MAX_DEPTH);
+        if to_is_inside_from || to_is_taken || to_is_inside_file || is_too_deep {
+            return false;
+        }
+
+        match self.sub_directories.remove(&from.0) {
+            None => false,
+            Some(sub_tree) => {
+                self.stats.reset();
+                self.insert_sub_tree(to.clone(), sub_tree);
+                true
+            }
+        }
     }
 
-    /// Find a `File` in the directory given the `Path` to the `File`.
+    /// Merge the contents of `other` into this `Directory`, where `policy` decides
+    /// what happens when both have a file, symbolic link, or submodule at the same
+    /// `Path`, or one has a file where the other has a directory.
     ///
-    /// # Failures
-    /// This operation fails if the path does not lead to a `File`. If
-    /// the search is for a `Directory` then use `find_directory`.
+    /// Directories that are in both are merged, unless the policy is
+    /// [`MergePolicy::KeepLeft`](enum.MergePolicy.html#variant.KeepLeft) or
+    /// [`MergePolicy::KeepRight`](enum.MergePolicy.html#variant.KeepRight), which
+    /// keep the whole entry of this `Directory` or `other` respectively. Otherwise,
+    /// an empty directory merged with a directory that has entries is that directory.
     ///
-    /// # Examples
+    /// # Errors
     ///
-    /// Search for a file in the path: `foo.hs`.
+    /// With [`MergePolicy::Error`](enum.MergePolicy.html#variant.Error), merging
+    /// fails with the `Path` of the first conflict, unless both entries are the same,
+    /// and this `Directory` is left as it was.
+    ///
+    /// # Examples
     ///
     /// ```
-    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::{error, Directory, File, MergePolicy};
     /// use radicle_surf::file_system::unsound;
     ///
-    /// let file = File::new(b"module Banana ...");
-    /// let mut directory = Directory::root();
-    /// directory.insert_file(&unsound::path::new("foo.hs"), file.clone());
+    /// let mut left = Directory::root();
+    /// left.insert_file(&unsound::path::new("README.md"), File::new(b"# Left"));
+    /// left.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
     ///
+    /// let mut right = Directory::root();
+    /// right.insert_file(&unsound::path::new("README.md"), File::new(b"# Right"));
+    /// right.insert_file(&unsound::path::new("src/vcs.rs"), File::new(b"pub mod git;"));
+    ///
+    /// let mut combined = left.clone();
+    /// combined.merge(right.clone(), MergePolicy::Combine).unwrap();
     /// assert_eq!(
-    ///     directory.find_file(&unsound::path::new("foo.hs")),
-    ///     Some(file)
-    /// )
+    ///     combined.find_file(&unsound::path::new("README.md")),
+    ///     Some(File::new(b"# Right"))
+    /// );
+    /// assert!(combined.find_file(&unsound::path::new("src/lib.rs")).is_some());
+    /// assert!(combined.find_file(&unsound::path::new("src/vcs.rs")).is_some());
+    ///
+    /// let mut kept = left.clone();
+    /// kept.merge(right.clone(), MergePolicy::KeepLeft).unwrap();
+    /// assert_eq!(kept, left);
+    ///
+    /// let mut strict = left.clone();
+    /// assert_eq!(
+    ///     strict.merge(right, MergePolicy::Error),
+    ///     Err(error::Error::Conflict(unsound::path::new("README.md")))
+    /// );
+    /// assert_eq!(strict, left);
     /// ```
+    pub fn merge(&mut self, other: Self, policy: MergePolicy) -> Result<(), error::Error> {
+        let mut forest = match policy {
+            // Keep this `Directory` intact in case of a conflict.
+            MergePolicy::Error => self.sub_directories.clone(),
+            MergePolicy::KeepLeft | MergePolicy::KeepRight | MergePolicy::Combine => {
+                std::mem::take(&mut self.sub_directories)
+            }
+        };
+        let mut other = other.sub_directories;
+        // An empty directory merged with a directory is that directory, rather than
+        // a conflict between a file and a directory.
+        if let MergePolicy::Combine | MergePolicy::Error = policy {
+            remove_empty_directories_under(&mut forest, &other);
+            remove_empty_directories_under(&mut other, &forest);
+        }
+
+        self.stats.reset();
+        self.sub_directories = forest
+            .merge(other, policy)
+            .map_err(|conflict| error::Error::Conflict(Path(conflict.keys)))?;
+        Ok(())
+    }
+
+    fn insert_sub_tree(&mut self, path: Path, sub_tree: SubTree<Label, File>) {
+        let mut stack = vec![(path, sub_tree)];
+        while let Some((path, sub_tree)) = stack.pop() {
+            match sub_tree {
+                SubTree::Node { value, .. } => self.sub_directories.insert(&path.0, value),
+                SubTree::Branch { forest, .. } => stack.extend(
+                    forest
+                        .into_sub_trees()
+                        .into_iter()
+                        .map(|(key, child)| (path.join(key), child)),
+                ),
+            }
+        }
+    }
+
+    /// Get the `Directory` that a sparse checkout with the given `patterns` would
+    /// leave behind, keeping only the files, symbolic links, and submodules that
+    /// the `patterns` select.
     ///
-    /// Search for a file in the path: `foo/bar/baz.hs`.
+    /// # Examples
     ///
     /// ```
-    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::{Directory, File, SparsePatterns};
     /// use radicle_surf::file_system::unsound;
     ///
-    /// let file_path = unsound::path::new("foo/bar/baz.rs");
-    ///
-    /// let file = File::new(b"module Banana ...");
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// root.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    /// root.insert_file(&unsound::path::new("src/file_system/path.rs"), File::new(b"pub struct Path;"));
+    /// root.insert_symlink(&unsound::path::new("src/vcs/hg.rs"), "git.rs");
     ///
-    /// let mut directory = Directory::root();
-    /// directory.insert_file(&unsound::path::new("foo/bar/baz.rs"), file.clone());
+    /// let patterns = SparsePatterns::Cone(vec![unsound::path::new("src/vcs")]);
+    /// let view = root.sparse_view(&patterns);
     ///
-    /// assert_eq!(directory.find_file(&file_path), Some(file))
+    /// assert_eq!(
+    ///     view.walk().map(|(path, _)| path).collect::<Vec<_>>(),
+    ///     vec![
+    ///         unsound::path::new("README.md"),
+    ///         unsound::path::new("src"),
+    ///         unsound::path::new("src/lib.rs"),
+    ///         unsound::path::new("src/vcs"),
+    ///         unsound::path::new("src/vcs/git.rs"),
+    ///         unsound::path::new("src/vcs/hg.rs"),
+    ///     ]
+    /// );
     /// ```
+    pub fn sparse_view(&self, patterns: &SparsePatterns) -> Self {
+        let mut view = Directory {
+            current: self.current.clone(),
+            sub_directories: Forest::root(),
+            stats: Memo::default(),
+        };
+
+        for (path, contents) in self.walk() {
+            if !patterns.selects(&path) {
+                continue;
+            }
+            match contents {
+                DirectoryContents::File { file, .. } => view.insert_file(&path, file),
+                DirectoryContents::Symlink { target, .. } => view.insert_symlink(&path, &target),
+                DirectoryContents::Submodule { commit, .. } => {
+                    view.insert_submodule(&path, &commit)
+                }
+                DirectoryContents::Directory(directory) if directory.is_empty() => {
+                    view.insert_empty_directory(&path)
+                }
+                DirectoryContents::Directory(_) => {}
+            }
+        }
+
+        view
+    }
+
+    /// Render the `Directory` like the `tree` command does, drawing each entry on
+    /// its own line beneath the current `Label`, with symbolic links followed by their
+    /// target and submodules by their commit.
     ///
-    /// Search for a file in the path: `bar.hs`, which does not exist.
+    /// When `sizes` is set, files are followed by their size in bytes. The `Display`
+    /// instance of `Directory` renders it without sizes.
+    ///
+    /// # Examples
     ///
     /// ```
     /// use radicle_surf::file_system::{Directory, File};
     /// use radicle_surf::file_system::unsound;
     ///
-    /// let file_path = unsound::path::new("bar.hs");
-    ///
-    /// let file = File::new(b"module Banana ...");
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+    /// root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// root.insert_file(&unsound::path::new("src/vcs/git.rs"), File::new(b"pub mod error;"));
+    /// root.insert_symlink(&unsound::path::new("src/vcs/hg.rs"), "git.rs");
+    /// root.insert_file(&unsound::path::new("tests/vcs.rs"), File::new(b""));
     ///
-    /// let mut directory = Directory::root();
-    /// directory.insert_file(&unsound::path::new("foo.hs"), file);
+    /// assert_eq!(
+    ///     root.render_tree(true),
+    ///     "\
+    /// ~
+    /// ├── README.md (6 bytes)
+    /// ├── src
+    /// │   ├── lib.rs (12 bytes)
+    /// │   └── vcs
+    /// │       ├── git.rs (14 bytes)
+    /// │       └── hg.rs -> git.rs
+    /// └── tests
+    ///     └── vcs.rs (0 bytes)
+    /// "
+    /// );
     ///
-    /// assert_eq!(directory.find_file(&file_path), None)
+    /// assert!(root.to_string().starts_with("~\n├── README.md\n├── src\n"));
     /// ```
-    pub fn find_file(&self, path: &Path) -> Option<File> {
-        self.sub_directories.find_node(&path.0).cloned()
+    pub fn render_tree(&self, sizes: bool) -> String {
+        let mut rendered = format!("{}\n", self.current());
+        self.render_children(&mut rendered, sizes);
+        rendered
     }
 
-    /// Find a `Directory` in the directory given the `Path` to
-    /// the `Directory`.
-    ///
-    /// This operation fails if the path does not lead to
-    /// the `Directory`.
-    pub fn find_directory(&self, path: &Path) -> Option<Self> {
-        self.sub_directories
-            .find_branch(&path.0)
-            .cloned()
-            .map(|tree| {
-                let (_, current) = path.split_last();
-                Directory {
-                    current: Location::SubDirectory(current),
-                    sub_directories: tree.into(),
+    fn render_children(&self, rendered: &mut String, sizes: bool) {
+        // The children left to render in each directory, along with their prefix.
+        let mut stack = vec![(
+            self.iter().collect::<Vec<_>>().into_iter().peekable(),
+            String::new(),
+        )];
+        while let Some((children, prefix)) = stack.last_mut() {
+            let child = match children.next() {
+                Some(child) => child,
+                None => {
+                    stack.pop();
+                    continue;
                 }
-            })
+            };
+            let (branch, indent) = if children.peek().is_none() {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            rendered.push_str(prefix);
+            rendered.push_str(branch);
+            match child {
+                DirectoryContents::File { name, file } if sizes => {
+                    rendered.push_str(&format!("{} ({} bytes)\n", name, file.size))
+                }
+                DirectoryContents::File { name, .. } => rendered.push_str(&format!("{}\n", name)),
+                DirectoryContents::Symlink { name, target } => {
+                    rendered.push_str(&format!("{} -> {}\n", name, target))
+                }
+                DirectoryContents::Submodule { name, commit } => {
+                    rendered.push_str(&format!("{} @ {}\n", name, commit))
+                }
+                DirectoryContents::Directory(directory) => {
+                    rendered.push_str(&format!("{}\n", directory.current()));
+                    let prefix = format!("{}{}", prefix, indent);
+                    stack.push((
+                        directory.iter().collect::<Vec<_>>().into_iter().peekable(),
+                        prefix,
+                    ));
+                }
+            }
+        }
     }
 
-    /// Get the `Label` of the current directory.
+    /// Read the `Directory` at `path` on disk, e.g. the working directory of a repository.
+    ///
+    /// Entries whose names are not valid [`Label`](struct.Label.html)s under `rules`
+    /// are skipped, e.g. `.git` under `GitLabelRules`, and so are empty directories,
+    /// unless they are kept with [`from_disk_with`](#method.from_disk_with).
     ///
     /// # Examples
     ///
     /// ```
-    /// use radicle_surf::file_system::{Directory, File, Label};
+    /// use radicle_surf::file_system::{DefaultLabelRules, Directory, File};
     /// use radicle_surf::file_system::unsound;
     ///
-    /// let mut root = Directory::root();
-    /// root.insert_file(&unsound::path::new("main.rs"), File::new(b"println!(\"Hello, world!\")"));
-    /// root.insert_file(&unsound::path::new("lib.rs"), File::new(b"struct Hello(String)"));
-    /// root.insert_file(&unsound::path::new("test/mod.rs"), File::new(b"assert_eq!(1 + 1, 2);"));
+    /// let tempdir = tempfile::tempdir().unwrap();
+    /// let disk = tempdir.path();
+    /// std::fs::create_dir_all(disk.join("src/empty")).unwrap();
+    /// std::fs::write(disk.join("README.md"), "# Surf").unwrap();
+    /// std::fs::write(disk.join("src/lib.rs"), "pub mod vcs;").unwrap();
     ///
-    /// assert_eq!(root.current(), Label::root());
+    /// let directory = Directory::from_disk(disk, &DefaultLabelRules).unwrap();
     ///
-    /// let test = root.find_directory(
-    ///     &unsound::path::new("test")
-    /// ).expect("Missing test directory");
-    /// assert_eq!(test.current(), unsound::label::new("test"));
+    /// assert_eq!(
+    ///     directory.files().collect::<Vec<_>>(),
+    ///     vec![
+    ///         (unsound::path::new("README.md"), File::new(b"# Surf")),
+    ///         (unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;")),
+    ///     ]
+    /// );
     /// ```
-    pub fn current(&self) -> Label {
-        match &self.current {
-            Location::Root => Label::root(),
-            Location::SubDirectory(label) => label.clone(),
-        }
-    }
-
-    // TODO(fintan): This is going to be a bit trickier so going to leave it out for now
-    #[allow(dead_code)]
-    fn fuzzy_find(_label: Label) -> Vec<Self> {
-        unimplemented!()
+    pub fn from_disk<R>(path: &std::path::Path, rules: &R) -> std::io::Result<Self>
+    where
+        R: LabelRules,
+    {
+        Self::from_disk_with(path, rules, &DiskOptions::default())
     }
 
-    /// Get the total size, in bytes, of a `Directory`. The size is
-    /// the sum of all files that can be reached from this `Directory`.
+    /// Read the `Directory` at `path` on disk, like [`from_disk`](#method.from_disk),
+    /// with the given `options`.
     ///
     /// # Examples
     ///
     /// ```
-    /// use nonempty::NonEmpty;
-    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::{DefaultLabelRules, Directory, DiskOptions, SystemType};
     /// use radicle_surf::file_system::unsound;
     ///
-    /// let mut root = Directory::root();
-    /// root.insert_files(
-    ///     &[],
-    ///     NonEmpty::from((
-    ///         (
-    ///             unsound::label::new("main.rs"),
-    ///             File::new(b"println!(\"Hello, world!\")"),
-    ///         ),
-    ///         vec![
-    ///             (
-    ///                 unsound::label::new("lib.rs"),
-    ///                 File::new(b"struct Hello(String)"),
-    ///             ),
-    ///         ],
-    ///     )),
-    /// );
+    /// let tempdir = tempfile::tempdir().unwrap();
+    /// let disk = tempdir.path();
+    /// std::fs::create_dir_all(disk.join("src/empty")).unwrap();
+    /// std::fs::write(disk.join("src/lib.rs"), "pub mod vcs;").unwrap();
     ///
-    /// assert_eq!(root.size(), 45);
+    /// let options = DiskOptions {
+    ///     empty_directories: true,
+    /// };
+    /// let directory = Directory::from_disk_with(disk, &DefaultLabelRules, &options).unwrap();
+    ///
+    /// assert_eq!(
+    ///     directory.list_to_depth(2),
+    ///     vec![
+    ///         (unsound::path::new("src"), SystemType::Directory),
+    ///         (unsound::path::new("src/empty"), SystemType::Directory),
+    ///         (unsound::path::new("src/lib.rs"), SystemType::File),
+    ///     ]
+    /// );
     /// ```
+    pub fn from_disk_with<R>(
+        path: &std::path::Path,
+        rules: &R,
+        options: &DiskOptions,
+    ) -> std::io::Result<Self>
+    where
+        R: LabelRules,
+    {
+        let mut directory = Directory::root();
+        directory.read_disk(
+            path,
+            rules,
+            options,
+            &mut |_, entry| read_disk_file(entry),
+            None,
+        )?;
+        Ok(directory)
+    }
+
+    /// Read the entries of the directory at `disk_path` into this `Directory`, where
+    /// `read_file` gives the `File` for each file on disk.
+    ///
+    /// If there is a `tracked` `Directory`, the entries that are ignored by the
+    /// `.gitignore` files on disk are skipped without being read, unless `tracked`
+    /// has an entry at their `Path`, like git does for a working directory. Each
+    /// `.gitignore` is parsed once, when its directory is read.
+    pub(crate) fn read_disk<R, F>(
+        &mut self,
+        disk_path: &std::path::Path,
+        rules: &R,
+        options: &DiskOptions,
+        read_file: &mut F,
+        tracked: Option<&Directory>,
+    ) -> std::io::Result<()>
+    where
+        R: LabelRules,
+        F: FnMut(&Path, &std::fs::DirEntry) -> std::io::Result<File>,
+    {
+        // The directories left to read, along with their `Path`, where `None` is the
+        // root, and the `.gitignore` files of the directories above them.
+        let mut stack = vec![(disk_path.to_path_buf(), None::<Path>, Rc::new(vec![]))];
+        while let Some((disk_path, path, ignores)) = stack.pop() {
+            let ignores = match tracked {
+                Some(_) => {
+                    let depth = path.as_ref().map_or(0, |path| path.0.len());
+                    read_disk_ignore(&disk_path, depth, ignores)?
+                }
+                None => ignores,
+            };
+
+            for entry in std::fs::read_dir(&disk_path)? {
+                let entry = entry?;
+                let name = match disk_label(&entry.file_name(), rules) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let entry_path = match &path {
+                    Some(path) => path.join(name),
+                    None => Path::new(name),
+                };
+
+                let file_type = entry.file_type()?;
+                if let Some(tracked) = tracked {
+                    if ignores_match(&ignores, &entry_path, file_type.is_dir())
+                        && tracked.sub_directories.find(&entry_path.0).is_none()
+                    {
+                        continue;
+                    }
+                }
+
+                if file_type.is_symlink() {
+                    let target = std::fs::read_link(entry.path())?;
+                    self.insert_symlink(&entry_path, &target.to_string_lossy());
+                } else if file_type.is_dir() {
+                    // There must be room for the entries of the directory too.
+                    if check_depth(entry_path.0.len() + 1).is_err() {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("{} is nested deeper than MAX_DEPTH", entry_path),
+                        ));
+                    }
+                    // Any entries read into it later make it a regular directory.
+                    if options.empty_directories {
+                        self.insert_empty_directory(&entry_path);
+                    }
+                    stack.push((entry.path(), Some(entry_path), ignores.clone()));
+                } else if file_type.is_file() {
+                    let file = read_file(&entry_path, &entry)?;
+                    self.insert_file(&entry_path, file);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build a `Directory` from its files, each along with its `Path` relative to
+    /// the root.
+    ///
+    /// Symbolic links and submodules are `File`s with their
+    /// [`FileMode`](enum.FileMode.html), and the contents of those `File`s are the
+    /// link target and commit respectively. A later `File` replaces an earlier one
+    /// at the same `Path`.
+    ///
+    /// # Examples
     ///
     /// ```
     /// use radicle_surf::file_system::{Directory, File};
     /// use radicle_surf::file_system::unsound;
     ///
-    /// let mut root = Directory::root();
-    /// root.insert_file(&unsound::path::new("main.rs"), File::new(b"println!(\"Hello, world!\")"));
-    /// root.insert_file(&unsound::path::new("lib.rs"), File::new(b"struct Hello(String)"));
-    /// root.insert_file(&unsound::path::new("test/mod.rs"), File::new(b"assert_eq!(1 + 1, 2);"));
+    /// let directory = Directory::from_files(vec![
+    ///     (unsound::path::new("README.md"), File::new(b"# Surf")),
+    ///     (unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;")),
+    /// ]);
     ///
-    /// assert_eq!(root.size(), 66);
+    /// assert_eq!(
+    ///     directory.find_file(&unsound::path::new("src/lib.rs")),
+    ///     Some(File::new(b"pub mod vcs;"))
+    /// );
     /// ```
-    pub fn size(&self) -> usize {
-        self.sub_directories
-            .iter()
-            .fold(0, |size, file| size + file.size())
+    pub fn from_files<I>(files: I) -> Self
+    where
+        I: IntoIterator<Item = (Path, File)>,
+    {
+        let mut directory = Directory::root();
+        directory.extend(files);
+        directory
     }
 
-    /// Insert a file into a directory, given the full path to file (file name inclusive) and
-    /// the `File` itself.
+    /// Turn the `Directory` into a map of its files, keyed by their `Path`s relative
+    /// to this `Directory`, which is the inverse of
+    /// [`from_files`](struct.Directory.html#method.from_files).
     ///
-    /// This function is usually used for testing and demonstation purposes.
-    pub fn insert_file(&mut self, path: &Path, file: File) {
-        self.sub_directories.insert(&path.0, file)
-    }
-
-    /// Insert files into a shared directory path.
+    /// Symbolic links and submodules are included as `File`s with their
+    /// [`FileMode`](enum.FileMode.html), and empty directories are not included.
     ///
-    /// `directory_path` is used as the prefix to where the files should go. If empty the
-    /// files will be placed in the current `Directory`.
+    /// # Examples
     ///
-    /// `files` are pairs of file name and the `File` itself.
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File, FileMode};
+    /// use radicle_surf::file_system::unsound;
     ///
-    /// This function is usually used for testing and demonstation purposes.
-    pub fn insert_files(&mut self, directory_path: &[Label], files: NonEmpty<(Label, File)>) {
-        match NonEmpty::from_slice(directory_path) {
-            None => {
-                for (file_name, file) in files.iter() {
-                    self.insert_file(&Path::new(file_name.clone()), file.clone())
-                }
-            }
-            Some(directory_path) => {
-                for (file_name, file) in files.iter() {
-                    let mut file_path = Path(directory_path.clone());
-                    file_path.push(file_name.clone());
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;"));
+    /// directory.insert_symlink(&unsound::path::new("lib.rs"), "src/lib.rs");
+    ///
+    /// let files = directory.clone().into_files();
+    /// assert_eq!(files.len(), 2);
+    /// let link = &files[&unsound::path::new("lib.rs")];
+    /// assert_eq!(link.mode(), FileMode::Symlink);
+    /// assert_eq!(link.contents().unwrap(), b"src/lib.rs");
+    ///
+    /// assert_eq!(Directory::from_files(files), directory);
+    /// ```
+    pub fn into_files(self) -> HashMap<Path, File> {
+        let mut files = HashMap::new();
+        let mut stack = match self.sub_directories.0 {
+            None => vec![],
+            Some(trees) => vec![(None::<Path>, trees)],
+        };
 
-                    self.insert_file(&file_path, file.clone())
+        while let Some((parent, trees)) = stack.pop() {
+            for sub_tree in trees.into_sub_trees().into_values() {
+                let path = |key| match &parent {
+                    Some(parent) => parent.join(key),
+                    None => Path::new(key),
+                };
+                match sub_tree {
+                    SubTree::Node { key, value } => {
+                        files.insert(path(key), value);
+                    }
+                    SubTree::Branch { key, forest } => stack.push((Some(path(key)), *forest)),
                 }
             }
         }
+
+        files
     }
 
     pub(crate) fn from_hash_map(files: HashMap<Path, NonEmpty<(Label, File)>>) -> Self {
@@ -545,19 +4628,449 @@ impl Directory {
             }
         }
 
+        // Snapshots are built once and then read many times, so pay for the stats up front.
+        directory.stats();
         directory
     }
 }
 
+/// The `Label` of the `.gitignore` files in a `Directory`.
+#[cfg(feature = "gitignore")]
+fn gitignore_label() -> Label {
+    Label {
+        label: ".gitignore".into(),
+        hidden: false,
+        bytes: None,
+    }
+}
+
+/// Panic if inserting at `path` failed, which it only does when `path` is too deep
+/// to be inserted into a `Directory`, see [`MAX_DEPTH`](constant.MAX_DEPTH.html).
+fn assert_depth(inserted: Result<(), error::Error>, path: &Path) {
+    if inserted.is_err() {
+        panic!("{} is nested deeper than MAX_DEPTH", path)
+    }
+}
+
+/// The `.gitignore` files that apply to an entry, along with the number of labels
+/// of the directory each of them is in.
+type Ignores = Rc<Vec<(usize, Ignore)>>;
+
+/// Check whether any of the `ignores` match `path`, relative to their directories.
+fn ignores_match(ignores: &[(usize, Ignore)], path: &Path, is_directory: bool) -> bool {
+    let labels = path.iter().cloned().collect::<Vec<_>>();
+    ignores.iter().any(|(depth, ignore)| {
+        NonEmpty::from_slice(&labels[*depth..])
+            .is_some_and(|relative| ignore.matches(&Path(relative), is_directory))
+    })
+}
+
+/// Add the `.gitignore` of the directory at `disk_path`, which has `depth` labels,
+/// to the `ignores` of the directories above it, if it has one.
+fn read_disk_ignore(
+    disk_path: &std::path::Path,
+    depth: usize,
+    ignores: Ignores,
+) -> std::io::Result<Ignores> {
+    let ignore_path = disk_path.join(".gitignore");
+    if !ignore_path.is_file() {
+        return Ok(ignores);
+    }
+    let contents = std::fs::read(ignore_path)?;
+    let mut ignores = ignores.to_vec();
+    ignores.push((depth, Ignore::parse(&String::from_utf8_lossy(&contents))));
+    Ok(Rc::new(ignores))
+}
+
+/// Remove the empty directories of `forest` where `other` has a directory with
+/// entries, see [`Directory::merge`](struct.Directory.html#method.merge).
+fn remove_empty_directories_under(forest: &mut Forest<Label, File>, other: &Forest<Label, File>) {
+    let empty = forest
+        .iter_paths()
+        .filter(|(keys, file)| {
+            file.mode == FileMode::EmptyDirectory
+                && matches!(other.find(keys), Some(SubTree::Branch { .. }))
+        })
+        .map(|(keys, _)| keys)
+        .collect::<Vec<_>>();
+    for keys in empty {
+        forest.remove_node(&keys);
+    }
+}
+
+/// The score of every matched character.
+const FUZZY_MATCH: u32 = 16;
+/// The bonus for a character matched at the start of a word.
+const FUZZY_BOUNDARY: u32 = 8;
+/// The bonus for a character matched right after the previous one.
+const FUZZY_CONSECUTIVE: u32 = 8;
+/// The bonus for a character matched in the file name.
+const FUZZY_FILE_NAME: u32 = 4;
+/// The penalty for skipping characters between two matched characters.
+const FUZZY_GAP: u32 = 4;
+
+/// Score how well `query` matches `path`, or `None` if the characters of `query`
+/// do not all appear in `path` in order.
+///
+/// This picks the best scoring positions to match the characters of `query` at,
+/// by keeping, for each position in `path`, the best score of the query so far
+/// with its last character matched at that position.
+fn fuzzy_score(query: &[char], path: &str, case_sensitive: bool) -> Option<u32> {
+    let chars = path.chars().collect::<Vec<_>>();
+    let file_name_start = chars.iter().rposition(|c| *c == '/').map_or(0, |i| i + 1);
+    let normalise = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+
+    let bonus = |i: usize| {
+        let mut bonus = FUZZY_MATCH;
+        let at_boundary = match i.checked_sub(1).map(|prev| chars[prev]) {
+            None => true,
+            Some(prev) => {
+                !prev.is_alphanumeric() || (prev.is_lowercase() && chars[i].is_uppercase())
+            }
+        };
+        if at_boundary {
+            bonus += FUZZY_BOUNDARY;
+        }
+        if i >= file_name_start {
+            bonus += FUZZY_FILE_NAME;
+        }
+        bonus
+    };
+
+    // `scores[i]` is the best score with the last matched character at `i`.
+    let mut scores: Vec<Option<u32>> = vec![None; chars.len() + 1];
+    for (n, q) in query.iter().enumerate() {
+        let q = normalise(*q);
+        let mut next = vec![None; chars.len() + 1];
+        // The best score of the previous characters ending strictly before `i`.
+        let mut best_before = if n == 0 { Some(0) } else { None };
+        for i in 0..chars.len() {
+            if normalise(chars[i]) == q {
+                let gap = best_before.map(|score| {
+                    let score = score + bonus(i);
+                    if n > 0 {
+                        score.saturating_sub(FUZZY_GAP)
+                    } else {
+                        score
+                    }
+                });
+                let consecutive = if n > 0 && i > 0 {
+                    scores[i].map(|score| score + bonus(i) + FUZZY_CONSECUTIVE)
+                } else {
+                    None
+                };
+                next[i + 1] = gap.max(consecutive);
+            }
+            if n > 0 {
+                best_before = best_before.max(scores[i + 1]);
+            }
+        }
+        scores = next;
+    }
+
+    if query.is_empty() {
+        Some(0)
+    } else {
+        scores.into_iter().max().flatten()
+    }
+}
+
+/// Read the contents and mode of the file on disk at `entry`.
+pub(crate) fn read_disk_file(entry: &std::fs::DirEntry) -> std::io::Result<File> {
+    let contents = std::fs::read(entry.path())?;
+    let mode = if disk_is_executable(&entry.metadata()?) {
+        FileMode::Executable
+    } else {
+        FileMode::Regular
+    };
+    let mut file = File::from_vec(contents).with_mode(mode);
+    if let Ok(modified) = entry.metadata()?.modified() {
+        file = file.with_metadata(modified);
+    }
+    Ok(file)
+}
+
+#[cfg(unix)]
+fn disk_label<R: LabelRules>(name: &std::ffi::OsStr, rules: &R) -> Option<Label> {
+    use std::os::unix::ffi::OsStrExt;
+    Label::try_from_bytes(name.as_bytes(), rules).ok()
+}
+
+#[cfg(not(unix))]
+fn disk_label<R: LabelRules>(name: &std::ffi::OsStr, rules: &R) -> Option<Label> {
+    Label::try_from_rules(&name.to_string_lossy(), rules).ok()
+}
+
+#[cfg(unix)]
+fn disk_is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn disk_is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
 #[cfg(test)]
 pub mod tests {
+    use crate::file_system::directory::fuzzy_score;
     use crate::file_system::unsound;
     use crate::file_system::*;
+    use crate::strategies::directory_map_strategy;
     use nonempty::NonEmpty;
-    use proptest::collection;
     use proptest::prelude::*;
     use std::collections::HashMap;
 
+    #[test]
+    fn test_fuzzy_score() {
+        let query = |query: &str| query.chars().collect::<Vec<_>>();
+
+        // Matching at the start of words beats matching in the middle of one.
+        assert!(
+            fuzzy_score(&query("fb"), "foo_bar.rs", false)
+                > fuzzy_score(&query("fb"), "fabric.rs", false)
+        );
+        // Consecutive matches beat scattered ones.
+        assert!(
+            fuzzy_score(&query("lib"), "src/lib.rs", false)
+                > fuzzy_score(&query("lib"), "src/list_bits.rs", false)
+        );
+        // The file name beats the directories leading to it.
+        assert!(
+            fuzzy_score(&query("mod"), "src/diff/mod.rs", false)
+                > fuzzy_score(&query("mod"), "src/mod/diff.rs", false)
+        );
+        assert!(fuzzy_score(&query("fileSystem"), "src/file_system.rs", true).is_none());
+        assert!(fuzzy_score(&query("rsx"), "src/lib.rs", false).is_none());
+    }
+
+    #[test]
+    fn test_diff_structure_with_empty_directories() {
+        let mut old = Directory::root();
+        old.insert_empty_directory(&unsound::path::new("docs"));
+        old.insert_file(&unsound::path::new("target"), File::new(b""));
+        old.insert_empty_directory(&unsound::path::new("tmp"));
+
+        let mut new = Directory::root();
+        new.insert_file(&unsound::path::new("docs/intro.md"), File::new(b"# Surf"));
+        new.insert_empty_directory(&unsound::path::new("target"));
+        new.insert_empty_directory(&unsound::path::new("tmp"));
+
+        let diff = old.diff_structure(&new).unwrap();
+        assert_eq!(
+            diff.added,
+            vec![
+                unsound::path::new("docs/intro.md"),
+                unsound::path::new("target")
+            ]
+        );
+        assert_eq!(diff.removed, vec![unsound::path::new("target")]);
+        assert!(diff.modified.is_empty());
+    }
+
+    #[test]
+    fn test_cursor_keeps_empty_directories() {
+        let mut root = Directory::root();
+        root.insert_file(
+            &unsound::path::new("src/lib.rs"),
+            File::new(b"pub mod vcs;"),
+        );
+        root.insert_empty_directory(&unsound::path::new("docs"));
+        let original = root.clone();
+
+        let mut cursor = root.cursor();
+        assert!(cursor.down(&unsound::label::new("docs")));
+        assert_eq!(cursor.list_directory(), vec![]);
+        assert!(cursor.up());
+        assert!(cursor.down(&unsound::label::new("src")));
+        assert_eq!(
+            cursor.remove_file(&unsound::label::new("lib.rs")),
+            Some(File::new(b"pub mod vcs;"))
+        );
+
+        let mut expected = original;
+        expected.remove_file(&unsound::path::new("src/lib.rs"));
+        assert_eq!(cursor.into_directory(), expected);
+    }
+
+    #[test]
+    fn test_merge_empty_directory_with_directory() {
+        let mut left = Directory::root();
+        left.insert_file(
+            &unsound::path::new("src/lib.rs"),
+            File::new(b"pub mod vcs;"),
+        );
+        left.insert_empty_directory(&unsound::path::new("docs"));
+
+        let mut right = Directory::root();
+        right.insert_empty_directory(&unsound::path::new("src"));
+        right.insert_file(&unsound::path::new("docs/intro.md"), File::new(b"# Surf"));
+
+        let mut expected = Directory::root();
+        expected.insert_file(
+            &unsound::path::new("src/lib.rs"),
+            File::new(b"pub mod vcs;"),
+        );
+        expected.insert_file(&unsound::path::new("docs/intro.md"), File::new(b"# Surf"));
+
+        for policy in [MergePolicy::Combine, MergePolicy::Error] {
+            let mut merged = left.clone();
+            assert_eq!(merged.merge(right.clone(), policy), Ok(()));
+            assert_eq!(merged, expected);
+            assert_eq!(merged.file_count(), 2);
+        }
+    }
+
+    #[test]
+    fn test_max_depth_directory() {
+        let deep = unsound::path::new(&vec!["a"; MAX_DEPTH].join("/"));
+        let mut root = Directory::root();
+        root.insert_file(&deep, File::new(b"deep"));
+
+        assert_eq!(root.depth(), MAX_DEPTH);
+        assert_eq!(root.render_tree(false).lines().count(), MAX_DEPTH + 1);
+        let diff = crate::diff::Diff::diff(Directory::root(), root.clone()).unwrap();
+        assert_eq!(diff.created.len(), 1);
+        assert!(!root.move_entry(&unsound::path::new("a"), &unsound::path::new("b/a")));
+    }
+
+    #[test]
+    fn test_complete_fills_with_fuzzy_matches() {
+        let mut root = Directory::root();
+        root.insert_file(
+            &unsound::path::new("src/lib.rs"),
+            File::new(b"pub mod diff;"),
+        );
+        root.insert_file(
+            &unsound::path::new("src/diff/mod.rs"),
+            File::new(b"pub struct Diff;"),
+        );
+        root.insert_file(
+            &unsound::path::new("docs/diffing.md"),
+            File::new(b"# Diffing"),
+        );
+        root.insert_file(&unsound::path::new("Cargo.toml"), File::new(b"[package]"));
+
+        assert_eq!(
+            root.complete("diff", 10),
+            vec![
+                unsound::path::new("docs/diffing.md"),
+                unsound::path::new("src/diff/mod.rs"),
+            ]
+        );
+        assert_eq!(
+            root.complete("src/d", 10),
+            vec![
+                unsound::path::new("src/diff"),
+                unsound::path::new("src/diff/mod.rs"),
+            ]
+        );
+        assert_eq!(
+            root.complete("src/d", 1),
+            vec![unsound::path::new("src/diff")]
+        );
+    }
+
+    #[test]
+    fn test_find_ci_tries_every_match() {
+        let mut root = Directory::root();
+        root.insert_file(&unsound::path::new("Docs/a.md"), File::new(b"a"));
+        root.insert_file(&unsound::path::new("docs/b.md"), File::new(b"b"));
+        root.insert_file(&unsound::path::new("README"), File::new(b"# Surf"));
+        root.insert_file(&unsound::path::new("readme/intro.md"), File::new(b"intro"));
+
+        let found = |path: &str| {
+            root.find_file_ci(&unsound::path::new(path))
+                .map(|(path, _)| path)
+        };
+        assert_eq!(found("docs/a.md"), Some(unsound::path::new("Docs/a.md")));
+        assert_eq!(found("DOCS/b.md"), Some(unsound::path::new("docs/b.md")));
+        assert_eq!(found("readme"), Some(unsound::path::new("README")));
+        assert_eq!(
+            found("README/intro.md"),
+            Some(unsound::path::new("readme/intro.md"))
+        );
+
+        let found = |path: &str| {
+            root.find_directory_ci(&unsound::path::new(path))
+                .map(|(path, _)| path)
+        };
+        assert_eq!(found("README"), Some(unsound::path::new("readme")));
+        assert_eq!(found("docs"), Some(unsound::path::new("docs")));
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn test_find_nfc_tries_every_match() {
+        let mut root = Directory::root();
+        root.insert_file(&unsound::path::new("caf\u{e9}"), File::new(b"nfc"));
+        root.insert_file(
+            &unsound::path::new("cafe\u{301}/menu.md"),
+            File::new(b"nfd"),
+        );
+
+        assert_eq!(
+            root.find_file_nfc(&unsound::path::new("caf\u{e9}/menu.md"))
+                .map(|(path, _)| path),
+            Some(unsound::path::new("cafe\u{301}/menu.md"))
+        );
+        assert_eq!(
+            root.find_directory_nfc(&unsound::path::new("caf\u{e9}"))
+                .map(|(path, _)| path),
+            Some(unsound::path::new("cafe\u{301}"))
+        );
+    }
+
+    #[test]
+    fn test_try_insert_past_max_depth() {
+        let mut deep = unsound::path::new(&vec!["a"; MAX_DEPTH].join("/"));
+        deep.push(unsound::label::new("b"));
+        let too_deep = Err(error::Error::Path(error::Path::TooDeep));
+
+        let mut root = Directory::root();
+        assert_eq!(root.try_insert_symlink(&deep, "a"), too_deep);
+        assert_eq!(root.try_insert_submodule(&deep, "a"), too_deep);
+        assert_eq!(root.try_insert_empty_directory(&deep), too_deep);
+        assert!(root.is_empty());
+
+        root.insert_file(&unsound::path::new("a/lib.rs"), File::new(b"pub mod vcs;"));
+        let mut cursor = root.cursor();
+        assert!(cursor.down(&unsound::label::new("a")));
+        let deepest = unsound::path::new(&vec!["a"; MAX_DEPTH - 1].join("/"));
+        assert!(cursor.try_insert_file(&deepest, File::new(b"")).is_ok());
+        assert_eq!(
+            cursor.try_insert_file(&deepest.join(unsound::label::new("b")), File::new(b"")),
+            too_deep
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "EmptyDirectory is not the mode of a file")]
+    fn test_with_mode_rejects_entry_modes() {
+        assert_eq!(FileMode::EmptyDirectory.permissions(), None);
+        File::new(b"contents").with_mode(FileMode::EmptyDirectory);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_empty_directory_with_contents() {
+        let empty = r#"{"mode":"EmptyDirectory","contents":[]}"#;
+        assert_eq!(
+            serde_json::from_str::<File>(empty).unwrap().mode(),
+            FileMode::EmptyDirectory
+        );
+
+        let full = r#"{"mode":"EmptyDirectory","contents":[1]}"#;
+        assert!(serde_json::from_str::<File>(full).is_err());
+    }
+
     #[test]
     fn test_all_directories_and_files() {
         let mut directory_map = HashMap::new();
@@ -575,39 +5088,6 @@ pub mod tests {
         assert!(prop_all_directories_and_files(directory_map))
     }
 
-    fn label_strategy() -> impl Strategy<Value = Label> {
-        // ASCII regex, excluding '/' because of posix file paths
-        "[ -.|0-~]+".prop_map(|label| unsound::label::new(&label))
-    }
-
-    fn path_strategy(max_size: usize) -> impl Strategy<Value = Path> {
-        (
-            label_strategy(),
-            collection::vec(label_strategy(), 0..max_size),
-        )
-            .prop_map(|(label, labels)| Path((label, labels).into()))
-    }
-
-    fn file_strategy() -> impl Strategy<Value = (Label, File)> {
-        // ASCII regex, see: https://catonmat.net/my-favorite-regex
-        (label_strategy(), "[ -~]*")
-            .prop_map(|(name, contents)| (name, File::new(contents.as_bytes())))
-    }
-
-    fn directory_map_strategy(
-        path_size: usize,
-        n_files: usize,
-        map_size: usize,
-    ) -> impl Strategy<Value = HashMap<Path, NonEmpty<(Label, File)>>> {
-        collection::hash_map(
-            path_strategy(path_size),
-            collection::vec(file_strategy(), 1..n_files).prop_map(|files| {
-                NonEmpty::from_slice(&files).expect("Strategy generated files of length 0")
-            }),
-            0..map_size,
-        )
-    }
-
     // TODO(fintan): This is a bit slow. Could be time to benchmark some functions.
     proptest! {
         #[test]
@@ -616,6 +5096,15 @@ pub mod tests {
         }
     }
 
+    proptest! {
+        #[test]
+        fn prop_into_iter_is_walk(directory_map in directory_map_strategy(10, 10, 10)) {
+            let directory = Directory::from_hash_map(directory_map);
+            let walked = directory.walk().collect::<Vec<_>>();
+            prop_assert_eq!(directory.into_iter().collect::<Vec<_>>(), walked);
+        }
+    }
+
     fn prop_all_directories_and_files(
         directory_map: HashMap<Path, NonEmpty<(Label, File)>>,
     ) -> bool {
@@ -0,0 +1,92 @@
+//! Detecting the text encoding of a [`File`](struct.File.html), see
+//! [`File::decode`](struct.File.html#method.decode).
+
+use std::borrow::Cow;
+
+/// The text encodings that [`File::decode`](struct.File.html#method.decode) detects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// UTF-8, with or without a byte order mark.
+    Utf8,
+    /// UTF-16, little endian, detected by its byte order mark.
+    Utf16Le,
+    /// UTF-16, big endian, detected by its byte order mark.
+    Utf16Be,
+    /// ISO-8859-1, which every sequence of bytes is valid in, so it is used when no
+    /// other encoding fits.
+    Latin1,
+}
+
+impl Encoding {
+    /// Detect the encoding of `contents`, and decode them, without the byte order
+    /// mark, if any.
+    ///
+    /// Invalid UTF-16 is decoded with the
+    /// [`REPLACEMENT_CHARACTER`](https://doc.rust-lang.org/std/char/constant.REPLACEMENT_CHARACTER.html).
+    pub(crate) fn decode(contents: &[u8]) -> (Cow<'_, str>, Self) {
+        if let Some(rest) = contents.strip_prefix(b"\xef\xbb\xbf") {
+            if let Ok(text) = std::str::from_utf8(rest) {
+                return (Cow::Borrowed(text), Encoding::Utf8);
+            }
+        } else if let Some(rest) = contents.strip_prefix(b"\xff\xfe") {
+            return (decode_utf16(rest, u16::from_le_bytes), Encoding::Utf16Le);
+        } else if let Some(rest) = contents.strip_prefix(b"\xfe\xff") {
+            return (decode_utf16(rest, u16::from_be_bytes), Encoding::Utf16Be);
+        }
+
+        match std::str::from_utf8(contents) {
+            Ok(text) => (Cow::Borrowed(text), Encoding::Utf8),
+            Err(_) => (
+                Cow::Owned(contents.iter().map(|byte| char::from(*byte)).collect()),
+                Encoding::Latin1,
+            ),
+        }
+    }
+}
+
+fn decode_utf16<F>(contents: &[u8], unit: F) -> Cow<'_, str>
+where
+    F: Fn([u8; 2]) -> u16,
+{
+    let units = contents.chunks(2).map(|pair| match pair {
+        [first, second] => unit([*first, *second]),
+        // A trailing odd byte is not a code unit, and decodes to a replacement.
+        _ => 0xdc00,
+    });
+    Cow::Owned(
+        char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let decode = |contents: &[u8]| {
+            let (text, encoding) = Encoding::decode(contents);
+            (text.into_owned(), encoding)
+        };
+
+        assert_eq!(
+            decode(b"\xef\xbb\xbf# Surf"),
+            ("# Surf".to_string(), Encoding::Utf8)
+        );
+        assert_eq!(
+            decode(b"\xfe\xff\0c\0a\0f\0\xe9"),
+            ("caf\u{e9}".to_string(), Encoding::Utf16Be)
+        );
+        assert_eq!(
+            decode(b"\xff\xfeo\0k\0!"),
+            ("ok\u{fffd}".to_string(), Encoding::Utf16Le)
+        );
+        // A byte order mark followed by invalid UTF-8 is Latin-1.
+        assert_eq!(
+            decode(b"\xef\xbb\xbf\xe9"),
+            ("\u{ef}\u{bb}\u{bf}\u{e9}".to_string(), Encoding::Latin1)
+        );
+    }
+}
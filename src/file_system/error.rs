@@ -1,10 +1,17 @@
 //! Errors that can occur within the file system logic.
 
+use crate::file_system::path;
+use std::fmt;
+
 pub(crate) const EMPTY_PATH: Error = Error::Path(Path::Empty);
+pub(crate) const TOO_DEEP: Error = Error::Path(Path::TooDeep);
 
 pub(crate) const INVALID_UTF8: Error = Error::Label(Label::InvalidUTF8);
 pub(crate) const EMPTY_LABEL: Error = Error::Label(Label::Empty);
 pub(crate) const CONTAINS_SLASH: Error = Error::Label(Label::ContainsSlash);
+pub(crate) const CONTAINS_NUL: Error = Error::Label(Label::ContainsNul);
+pub(crate) const RESERVED_LABEL: Error = Error::Label(Label::Reserved);
+pub(crate) const TRAILING_DOT_OR_SPACE: Error = Error::Label(Label::TrailingDotOrSpace);
 
 /// Error type for all file system errors that can occur.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -13,6 +20,14 @@ pub enum Error {
     Label(Label),
     /// A `Path` specific error for parsing a `Path`.
     Path(Path),
+    /// A `Manifest` specific error for parsing a `Manifest`.
+    Manifest(Manifest),
+    /// An error reading the contents of a lazy `File` from its `Blob`, along with
+    /// its message.
+    Blob(String),
+    /// A conflict when merging two `Directory`s, along with the `Path` of the entry
+    /// that both have.
+    Conflict(path::Path),
 }
 
 impl From<Label> for Error {
@@ -27,11 +42,20 @@ impl From<Path> for Error {
     }
 }
 
+impl From<Manifest> for Error {
+    fn from(err: Manifest) -> Self {
+        Error::Manifest(err)
+    }
+}
+
 /// Parse errors for when parsing a string to a [`Path`](struct.Path.html).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Path {
     /// An error signifying that a `Path` is empty.
     Empty,
+    /// An error signifying that a `Path` has more than
+    /// [`MAX_DEPTH`](../constant.MAX_DEPTH.html) labels.
+    TooDeep,
 }
 
 /// Parse errors for when parsing a string to a [`Label`](struct.Label.html).
@@ -43,4 +67,80 @@ pub enum Label {
     ContainsSlash,
     /// An error signifying that a `Label` is empty.
     Empty,
+    /// An error signifying that a `Label` contains a NUL byte.
+    ContainsNul,
+    /// An error signifying that a `Label` is a name reserved by the VCS, e.g. `.git`.
+    Reserved,
+    /// An error signifying that a `Label` ends with a `.` or a space.
+    TrailingDotOrSpace,
+}
+
+/// Parse errors for when parsing a string to a [`Manifest`](struct.Manifest.html),
+/// along with the number of the line, counting from `1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Manifest {
+    /// An error signifying that the first line is not a `Manifest` header.
+    InvalidHeader(usize),
+    /// An error signifying that the header names a version of the format other than
+    /// [`MANIFEST_VERSION`](../constant.MANIFEST_VERSION.html).
+    UnsupportedVersion(usize),
+    /// An error signifying that a line does not have a digest, mode, and path.
+    MissingField(usize),
+    /// An error signifying that a digest is not valid hex.
+    InvalidDigest(usize),
+    /// An error signifying that a mode is not one of git's file modes.
+    InvalidMode(usize),
+    /// An error signifying that a path is not a valid `Path`.
+    InvalidPath(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Label(err) => write!(f, "invalid label: {}", err),
+            Error::Path(err) => write!(f, "invalid path: {}", err),
+            Error::Manifest(err) => write!(f, "invalid manifest: {}", err),
+            Error::Blob(message) => write!(f, "failed to read a lazy file: {}", message),
+            Error::Conflict(path) => write!(f, "both directories have an entry at {}", path),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Path::Empty => write!(f, "the path is empty"),
+            Path::TooDeep => write!(f, "the path is nested deeper than MAX_DEPTH"),
+        }
+    }
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Label::InvalidUTF8 => write!(f, "the label is not valid UTF-8"),
+            Label::ContainsSlash => write!(f, "the label contains a '/'"),
+            Label::Empty => write!(f, "the label is empty"),
+            Label::ContainsNul => write!(f, "the label contains a NUL byte"),
+            Label::Reserved => write!(f, "the label is reserved"),
+            Label::TrailingDotOrSpace => write!(f, "the label ends with a '.' or a space"),
+        }
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Manifest::InvalidHeader(line) => write!(f, "line {} is not a header", line),
+            Manifest::UnsupportedVersion(line) => {
+                write!(f, "line {} has an unsupported version", line)
+            }
+            Manifest::MissingField(line) => write!(f, "line {} is missing a field", line),
+            Manifest::InvalidDigest(line) => write!(f, "line {} has an invalid digest", line),
+            Manifest::InvalidMode(line) => write!(f, "line {} has an invalid mode", line),
+            Manifest::InvalidPath(line) => write!(f, "line {} has an invalid path", line),
+        }
+    }
 }
@@ -0,0 +1,500 @@
+//! Definition of [`Ignore`](struct.Ignore.html), the patterns of a `.gitignore` file.
+
+use crate::file_system::{Label, Path};
+use nonempty::NonEmpty;
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+/// A single pattern of an [`Ignore`](struct.Ignore.html) file, e.g. `/target/` or `!*.rs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnorePattern {
+    raw: String,
+    glob: String,
+    negated: bool,
+    directory_only: bool,
+    anchored: bool,
+}
+
+impl IgnorePattern {
+    /// Parse a non-blank, non-comment line of an ignore file.
+    pub(crate) fn parse(raw: &str) -> Self {
+        let mut glob = trim_unescaped_trailing_spaces(raw);
+
+        // A leading `!` negates the pattern, while a leading `\` escapes a `!` or `#`.
+        let negated = glob.starts_with('!');
+        if negated || glob.starts_with("\\!") || glob.starts_with("\\#") {
+            glob = &glob[1..];
+        }
+
+        let directory_only = glob.len() > 1 && glob.ends_with('/');
+        if directory_only {
+            glob = &glob[..glob.len() - 1];
+        }
+
+        let anchored = glob.contains('/');
+        let glob = glob.strip_prefix('/').unwrap_or(glob);
+
+        IgnorePattern {
+            raw: raw.to_string(),
+            glob: glob.to_string(),
+            negated,
+            directory_only,
+            anchored,
+        }
+    }
+
+    /// The glob of the pattern, without any leading `!` or `/`, or trailing `/`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::Ignore;
+    ///
+    /// let ignore = Ignore::parse("/target/\n!*.rs\n");
+    /// let globs = ignore.patterns().map(|pattern| pattern.glob()).collect::<Vec<_>>();
+    ///
+    /// assert_eq!(globs, vec!["target", "*.rs"]);
+    /// ```
+    pub fn glob(&self) -> &str {
+        &self.glob
+    }
+
+    /// Check whether the pattern starts with a `!`, re-including paths that an
+    /// earlier pattern excluded.
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// Check whether the pattern ends with a `/`, only matching directories.
+    pub fn is_directory_only(&self) -> bool {
+        self.directory_only
+    }
+
+    /// Check whether the pattern contains a `/` other than a trailing one, in which
+    /// case it is matched against the whole path rather than just the last `Label`.
+    pub fn is_anchored(&self) -> bool {
+        self.anchored
+    }
+
+    /// Check whether the pattern matches `path`, ignoring whether it is negated and
+    /// whether any of the parent directories of `path` match.
+    ///
+    /// `path` is relative to the directory of the ignore file, and `is_directory`
+    /// says whether it leads to a directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::Ignore;
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let ignore = Ignore::parse("/src/**/*.rs\nbuild/\n");
+    /// let patterns = ignore.patterns().collect::<Vec<_>>();
+    ///
+    /// assert!(patterns[0].matches(&unsound::path::new("src/lib.rs"), false));
+    /// assert!(patterns[0].matches(&unsound::path::new("src/vcs/git.rs"), false));
+    /// assert!(!patterns[0].matches(&unsound::path::new("tests/src/lib.rs"), false));
+    ///
+    /// assert!(patterns[1].matches(&unsound::path::new("docs/build"), true));
+    /// assert!(!patterns[1].matches(&unsound::path::new("docs/build"), false));
+    /// ```
+    pub fn matches(&self, path: &Path, is_directory: bool) -> bool {
+        if self.directory_only && !is_directory {
+            return false;
+        }
+
+        let labels = relative_labels(path);
+        if self.anchored {
+            let text = labels
+                .iter()
+                .map(|label| label.to_string())
+                .collect::<Vec<_>>()
+                .join("/");
+            glob_matches(&self.glob, &text)
+        } else {
+            labels
+                .last()
+                .is_some_and(|label| glob_matches(&self.glob, &label.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for IgnorePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+    Blank(String),
+    Comment(String),
+    Pattern(IgnorePattern),
+}
+
+/// The patterns of a `.gitignore` file, or of a similar file provided by a host,
+/// e.g. git's `core.excludesFile`.
+///
+/// Blank lines and comments are kept, so that parsing a file and writing it
+/// back out gives the same file, up to a trailing newline.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::Ignore;
+///
+/// let file = "# Build artifacts\n/target/\n\n*.log\n!keep.log\n";
+/// let ignore = Ignore::parse(file);
+///
+/// assert_eq!(ignore.patterns().count(), 3);
+/// assert_eq!(ignore.to_string(), file);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ignore {
+    lines: Vec<Line>,
+}
+
+impl Ignore {
+    /// Parse the contents of an ignore file.
+    ///
+    /// Every line of an ignore file is valid, so parsing cannot fail.
+    pub fn parse(contents: &str) -> Self {
+        let lines = contents
+            .lines()
+            .map(|line| {
+                if line.trim().is_empty() {
+                    Line::Blank(line.to_string())
+                } else if line.starts_with('#') {
+                    Line::Comment(line.to_string())
+                } else {
+                    Line::Pattern(IgnorePattern::parse(line))
+                }
+            })
+            .collect();
+
+        Ignore { lines }
+    }
+
+    /// Check whether there are no patterns, though there may still be blank
+    /// lines and comments.
+    pub fn is_empty(&self) -> bool {
+        self.patterns().next().is_none()
+    }
+
+    /// The patterns in the order they appear in the file. When more than one
+    /// pattern matches a path, the last one wins.
+    pub fn patterns(&self) -> impl Iterator<Item = &IgnorePattern> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Pattern(pattern) => Some(pattern),
+            Line::Blank(_) | Line::Comment(_) => None,
+        })
+    }
+
+    /// Merge the patterns of `overrides` after the patterns of `self`, so that
+    /// they take precedence when both match a path.
+    ///
+    /// git gives a repository's `.gitignore` precedence over the host's
+    /// `core.excludesFile`, so the repository's `Ignore` should be merged into
+    /// the host's.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::Ignore;
+    ///
+    /// let host = Ignore::parse("*.log\n");
+    /// let repo = Ignore::parse("!release.log\n");
+    ///
+    /// let merged = host.merge(&repo);
+    ///
+    /// assert_eq!(merged.to_string(), "*.log\n!release.log\n");
+    /// assert!(merged.patterns().last().unwrap().is_negated());
+    /// ```
+    pub fn merge(&self, overrides: &Self) -> Self {
+        let mut lines = self.lines.clone();
+        lines.extend(overrides.lines.iter().cloned());
+        Ignore { lines }
+    }
+
+    /// Check whether `path` is matched, i.e. ignored in the case of a `.gitignore`.
+    ///
+    /// `path` is relative to the directory of the ignore file, and `is_directory`
+    /// says whether it leads to a directory. As with git, the last pattern that
+    /// matches wins, and a path inside a matched directory is matched too, even
+    /// if a later negated pattern matches the path itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::Ignore;
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let ignore = Ignore::parse("*.log\n!keep.log\n/target/\n!/target/keep.rs\n");
+    ///
+    /// assert!(ignore.matches(&unsound::path::new("logs/debug.log"), false));
+    /// assert!(!ignore.matches(&unsound::path::new("logs/keep.log"), false));
+    /// assert!(ignore.matches(&unsound::path::new("target"), true));
+    /// assert!(ignore.matches(&unsound::path::new("target/keep.rs"), false));
+    /// assert!(!ignore.matches(&unsound::path::new("src/target"), true));
+    /// ```
+    pub fn matches(&self, path: &Path, is_directory: bool) -> bool {
+        let labels = relative_labels(path);
+        let parent_matches = (1..labels.len())
+            .filter_map(|len| NonEmpty::from_slice(&labels[..len]))
+            .any(|parent| self.matches_exactly(&Path(parent), true));
+
+        parent_matches || self.matches_exactly(path, is_directory)
+    }
+
+    /// Check whether the last pattern that matches `path` itself is not negated.
+    fn matches_exactly(&self, path: &Path, is_directory: bool) -> bool {
+        let patterns = self.patterns().collect::<Vec<_>>();
+        patterns
+            .into_iter()
+            .rev()
+            .find(|pattern| pattern.matches(path, is_directory))
+            .is_some_and(|pattern| !pattern.is_negated())
+    }
+}
+
+impl FromStr for Ignore {
+    type Err = Infallible;
+
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(contents))
+    }
+}
+
+impl fmt::Display for Ignore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            match line {
+                Line::Blank(line) | Line::Comment(line) => writeln!(f, "{}", line)?,
+                Line::Pattern(pattern) => writeln!(f, "{}", pattern)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Trailing spaces are ignored, unless they are escaped with a `\`.
+fn trim_unescaped_trailing_spaces(line: &str) -> &str {
+    let mut end = line.len();
+    while line[..end].ends_with(' ') && !line[..end - 1].ends_with('\\') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// The `Label`s of `path`, without any leading root `Label`.
+fn relative_labels(path: &Path) -> Vec<Label> {
+    path.iter()
+        .skip_while(|label| label.is_root())
+        .cloned()
+        .collect()
+}
+
+/// Match `text` against a `glob` in the syntax of git's ignore files.
+///
+/// `*` and `?` match anything but a `/`, `[...]` matches a class of characters, and
+/// `\` escapes the next character. A `**` between slashes, or at the start or end
+/// of the glob, matches any number of directories.
+pub(crate) fn glob_matches(glob: &str, text: &str) -> bool {
+    let glob = glob.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    wildmatch(&glob, &text)
+}
+
+/// Match `text` against `glob`, without recursion.
+///
+/// On a mismatch, the last `*` is made to match one more character, and once it
+/// can't, since it would match a `/`, the last `**/` is made to match one more
+/// directory. Only the last of each is ever retried, since a later `**/` can match
+/// whatever an earlier `*` or `**/` would have, which keeps the matching linear in
+/// the length of `text` for each star in `glob`.
+fn wildmatch(glob: &[char], text: &[char]) -> bool {
+    let (mut g, mut t) = (0, 0);
+    // Where to resume the glob and the text when retrying the last `*`, and `**/`.
+    let mut star: Option<(usize, usize)> = None;
+    let mut star_star: Option<(usize, usize)> = None;
+
+    loop {
+        let matched = match glob.get(g) {
+            None => {
+                if t == text.len() {
+                    return true;
+                }
+                false
+            }
+            Some('*') => {
+                let stars = glob[g..].iter().take_while(|c| **c == '*').count();
+                let after = g + stars;
+                let starts_segment = g == 0 || glob[g - 1] == '/';
+                let ends_segment = after == glob.len() || glob[after] == '/';
+                if stars == 2 && starts_segment && ends_segment {
+                    if after == glob.len() {
+                        return true;
+                    }
+                    // `**/` first matches no directories at all.
+                    star_star = Some((after + 1, t));
+                    star = None;
+                    g = after + 1;
+                } else {
+                    star = Some((after, t));
+                    g = after;
+                }
+                continue;
+            }
+            Some('?') => match text.get(t) {
+                Some(c) if *c != '/' => {
+                    g += 1;
+                    true
+                }
+                _ => false,
+            },
+            Some('[') => match (text.get(t), class_matches(&glob[g + 1..])) {
+                (Some(c), Some((class, len))) if *c != '/' && class(*c) => {
+                    g += 1 + len;
+                    true
+                }
+                // An unclosed class is matched literally.
+                (Some('['), None) => {
+                    g += 1;
+                    true
+                }
+                _ => false,
+            },
+            Some('\\') if g + 1 < glob.len() => match text.get(t) {
+                Some(c) if *c == glob[g + 1] => {
+                    g += 2;
+                    true
+                }
+                _ => false,
+            },
+            Some(literal) => match text.get(t) {
+                Some(c) if c == literal => {
+                    g += 1;
+                    true
+                }
+                _ => false,
+            },
+        };
+        if matched {
+            t += 1;
+            continue;
+        }
+
+        match (star, star_star) {
+            (Some((after, start)), _) if start < text.len() && text[start] != '/' => {
+                star = Some((after, start + 1));
+                g = after;
+                t = start + 1;
+            }
+            (_, Some((after, start))) => match text[start..].iter().position(|c| *c == '/') {
+                Some(slash) => {
+                    star_star = Some((after, start + slash + 1));
+                    star = None;
+                    g = after;
+                    t = start + slash + 1;
+                }
+                None => return false,
+            },
+            _ => return false,
+        }
+    }
+}
+
+/// Parse the character class after a `[`, returning a predicate for it and the
+/// number of characters it spans, including the closing `]`.
+fn class_matches(class: &[char]) -> Option<(impl Fn(char) -> bool, usize)> {
+    let negated = matches!(class.first(), Some('!') | Some('^'));
+    let start = if negated { 1 } else { 0 };
+
+    let mut ranges = vec![];
+    let mut index = start;
+    loop {
+        let mut low = *class.get(index)?;
+        if low == ']' && index > start {
+            break;
+        }
+        if low == '\\' {
+            index += 1;
+            low = *class.get(index)?;
+        }
+        match (class.get(index + 1), class.get(index + 2)) {
+            (Some('-'), Some(high)) if *high != ']' => {
+                ranges.push((low, *high));
+                index += 3;
+            }
+            _ => {
+                ranges.push((low, low));
+                index += 1;
+            }
+        }
+    }
+
+    let class = move |c: char| ranges.iter().any(|(low, high)| *low <= c && c <= *high) != negated;
+    Some((class, index + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pattern() {
+        let pattern = IgnorePattern::parse("!/src/*.rs/  ");
+        assert_eq!(pattern.glob(), "src/*.rs");
+        assert!(pattern.is_negated());
+        assert!(pattern.is_directory_only());
+        assert!(pattern.is_anchored());
+        assert_eq!(pattern.to_string(), "!/src/*.rs/  ");
+
+        let pattern = IgnorePattern::parse("\\#not-a-comment\\ ");
+        assert_eq!(pattern.glob(), "#not-a-comment\\ ");
+        assert!(!pattern.is_negated());
+        assert!(!pattern.is_anchored());
+
+        let pattern = IgnorePattern::parse("build/");
+        assert_eq!(pattern.glob(), "build");
+        assert!(pattern.is_directory_only());
+        assert!(!pattern.is_anchored());
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("*.rs", "lib.rs"));
+        assert!(!glob_matches("*.rs", "src/lib.rs"));
+        assert!(glob_matches("src/?ib.rs", "src/lib.rs"));
+        assert!(glob_matches("**/lib.rs", "lib.rs"));
+        assert!(glob_matches("**/lib.rs", "src/vcs/lib.rs"));
+        assert!(glob_matches("src/**", "src/vcs/git.rs"));
+        assert!(!glob_matches("src/**", "src"));
+        assert!(glob_matches("a/**/b", "a/b"));
+        assert!(glob_matches("a/**/b", "a/x/y/b"));
+        assert!(!glob_matches("a**b", "a/b"));
+        assert!(glob_matches("[a-c]at", "bat"));
+        assert!(!glob_matches("[!a-c]at", "bat"));
+        assert!(glob_matches("[]]", "]"));
+        assert!(glob_matches("[ab", "[ab"));
+        assert!(glob_matches("\\*", "*"));
+        assert!(!glob_matches("\\*", "a"));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(glob_matches("*/**/x*", "a/b/c/xy"));
+        assert!(!glob_matches("a*/b", "aX/Y/b"));
+    }
+
+    #[test]
+    fn test_glob_matches_pathological() {
+        // Each of these takes exponential time with naive backtracking.
+        let a = "a".repeat(64);
+        assert!(!glob_matches("a*a*a*a*a*a*a*a*a*a*a*a*b", &a));
+        assert!(glob_matches("a*a*a*a*a*a*a*a*a*a*a*a*a", &a));
+
+        let nested = vec!["a"; 64].join("/");
+        assert!(!glob_matches("**/**/**/**/**/**/**/**/b", &nested));
+        assert!(glob_matches("**/**/**/**/**/**/**/**/a", &nested));
+        assert!(!glob_matches("**/a*a*a*a*a*/**/a*a*a*a*b", &nested));
+    }
+}
@@ -0,0 +1,169 @@
+//! Identifying the license of a project by its text, see
+//! [`Directory::license`](struct.Directory.html#method.license).
+
+/// The licenses that are identified, as their SPDX identifier and the phrases
+/// that all appear in their text, normalized by [`normalize`].
+///
+/// Licenses whose text contains the phrases of another license come before it,
+/// e.g. the LGPL refers to the GPL, and the BSD-4-Clause contains the clauses of
+/// the BSD-3-Clause, which in turn contains those of the BSD-2-Clause.
+const TEMPLATES: &[(&str, &[&str])] = &[
+    (
+        "AGPL-3.0-only",
+        &["gnu affero general public license version 3 19 november 2007"],
+    ),
+    (
+        "LGPL-3.0-only",
+        &["gnu lesser general public license version 3 29 june 2007"],
+    ),
+    (
+        "LGPL-2.1-only",
+        &["gnu lesser general public license version 2 1 february 1999"],
+    ),
+    (
+        "GPL-3.0-only",
+        &["gnu general public license version 3 29 june 2007"],
+    ),
+    (
+        "GPL-2.0-only",
+        &["gnu general public license version 2 june 1991"],
+    ),
+    (
+        "Apache-2.0",
+        &["apache license version 2 0 january 2004"],
+    ),
+    ("MPL-2.0", &["mozilla public license version 2 0"]),
+    (
+        "MIT",
+        &[
+            "permission is hereby granted free of charge to any person obtaining a copy",
+            "the above copyright notice and this permission notice shall be included",
+        ],
+    ),
+    (
+        "ISC",
+        &["permission to use copy modify and or distribute this software for any purpose with or without fee is hereby granted"],
+    ),
+    (
+        "BSD-4-Clause",
+        &[
+            "redistribution and use in source and binary forms with or without modification are permitted",
+            "all advertising materials mentioning features or use of this software must display the following acknowledgement",
+        ],
+    ),
+    (
+        "BSD-3-Clause",
+        &[
+            "redistribution and use in source and binary forms with or without modification are permitted",
+            "neither the name of",
+        ],
+    ),
+    (
+        "BSD-2-Clause",
+        &["redistribution and use in source and binary forms with or without modification are permitted"],
+    ),
+    (
+        "Unlicense",
+        &["this is free and unencumbered software released into the public domain"],
+    ),
+    ("CC0-1.0", &["cc0 1 0 universal"]),
+];
+
+/// The SPDX identifiers of the licenses that may be used in any later version,
+/// by the identifier of their `-only` variant.
+const OR_LATER: &[(&str, &str)] = &[
+    ("AGPL-3.0-only", "AGPL-3.0-or-later"),
+    ("LGPL-3.0-only", "LGPL-3.0-or-later"),
+    ("LGPL-2.1-only", "LGPL-2.1-or-later"),
+    ("GPL-3.0-only", "GPL-3.0-or-later"),
+    ("GPL-2.0-only", "GPL-2.0-or-later"),
+];
+
+/// The phrase of the notice that a license may be used in any later version,
+/// normalized by [`normalize`].
+///
+/// The GNU licenses end with this notice as an example of how to apply them, so
+/// it only counts when it comes before the license text itself.
+const ANY_LATER_VERSION: &str = "or at your option any later version";
+
+/// Identify the license whose text is `text`, returning its SPDX identifier.
+pub(crate) fn identify(text: &str) -> Option<&'static str> {
+    let text = normalize(text);
+    let (id, phrases) = TEMPLATES
+        .iter()
+        .find(|(_, phrases)| phrases.iter().all(|phrase| text.contains(phrase)))?;
+
+    let or_later = OR_LATER.iter().find(|(only, _)| only == id).filter(|_| {
+        let start = phrases
+            .iter()
+            .filter_map(|phrase| text.find(phrase))
+            .min()
+            .unwrap_or(0);
+        text[..start].contains(ANY_LATER_VERSION)
+    });
+    Some(or_later.map_or(*id, |(_, or_later)| *or_later))
+}
+
+/// Lowercase `text` and keep only its words, separated by single spaces, so that
+/// punctuation, line wrapping, and indentation do not matter.
+fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identify() {
+        let mit = "Permission is hereby granted, free of charge, to any person obtaining a copy\n\
+                   of this software...\n\n\
+                   The above copyright notice and this permission notice shall be included in\n\
+                   all copies or substantial portions of the Software.";
+        assert_eq!(identify(mit), Some("MIT"));
+
+        let lgpl = "GNU LESSER GENERAL PUBLIC LICENSE\n    Version 3, 29 June 2007\n\n\
+                    This version of the GNU Lesser General Public License incorporates\n\
+                    the terms and conditions of version 3 of the GNU General Public\n\
+                    License";
+        assert_eq!(identify(lgpl), Some("LGPL-3.0-only"));
+
+        let gpl = "This program is free software: you can redistribute it and/or modify\n\
+                   it under the terms of the GNU General Public License as published by\n\
+                   the Free Software Foundation, either version 3 of the License, or\n\
+                   (at your option) any later version.\n\n\
+                   GNU GENERAL PUBLIC LICENSE\n    Version 3, 29 June 2007\n\n\
+                   ...\n\n\
+                   the Free Software Foundation, either version 3 of the License, or\n\
+                   (at your option) any later version.";
+        assert_eq!(identify(gpl), Some("GPL-3.0-or-later"));
+        let (_, gpl) = gpl.split_at(gpl.find("GNU GENERAL").unwrap());
+        assert_eq!(identify(gpl), Some("GPL-3.0-only"));
+
+        let bsd = "Redistribution and use in source and binary forms, with or without\n\
+                   modification, are permitted provided that the following conditions are met:";
+        assert_eq!(identify(bsd), Some("BSD-2-Clause"));
+
+        let bsd3 = format!(
+            "{}\n3. Neither the name of the copyright holder nor the names of its\n\
+             contributors may be used to endorse or promote products derived from\n\
+             this software without specific prior written permission.",
+            bsd
+        );
+        assert_eq!(identify(&bsd3), Some("BSD-3-Clause"));
+
+        let bsd4 = format!(
+            "{}\n3. All advertising materials mentioning features or use of this software\n\
+             must display the following acknowledgement: This product includes\n\
+             software developed by the organization.",
+            bsd3
+        );
+        assert_eq!(identify(&bsd4), Some("BSD-4-Clause"));
+
+        assert_eq!(identify("All rights reserved."), None);
+    }
+}
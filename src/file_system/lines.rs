@@ -0,0 +1,96 @@
+//! Splitting the contents of a [`File`](struct.File.html) into lines, see
+//! [`File::lines`](struct.File.html#method.lines).
+
+/// The line endings used by the contents of a [`File`](struct.File.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// Every line ends with `\n`.
+    Lf,
+    /// Every line ends with `\r\n`.
+    CrLf,
+    /// Some lines end with `\n`, and others with `\r\n`.
+    Mixed,
+}
+
+impl LineEnding {
+    /// Detect the line endings of `contents`, which is `None` if there are no line
+    /// breaks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::LineEnding;
+    ///
+    /// assert_eq!(LineEnding::detect(b"a\nb\n"), Some(LineEnding::Lf));
+    /// assert_eq!(LineEnding::detect(b"a\r\nb"), Some(LineEnding::CrLf));
+    /// assert_eq!(LineEnding::detect(b"a\r\nb\n"), Some(LineEnding::Mixed));
+    /// assert_eq!(LineEnding::detect(b"a\rb"), None);
+    /// ```
+    pub fn detect(contents: &[u8]) -> Option<Self> {
+        let mut ending = None;
+        for (index, _) in contents
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| **byte == b'\n')
+        {
+            let this = if index > 0 && contents[index - 1] == b'\r' {
+                LineEnding::CrLf
+            } else {
+                LineEnding::Lf
+            };
+            match ending {
+                None => ending = Some(this),
+                Some(previous) if previous != this => return Some(LineEnding::Mixed),
+                Some(_) => {}
+            }
+        }
+        ending
+    }
+}
+
+/// An iterator over the lines of a [`File`](struct.File.html), without their line
+/// endings, see [`File::lines`](struct.File.html#method.lines).
+#[derive(Debug, Clone)]
+pub struct Lines<'a> {
+    rest: &'a [u8],
+    line_ending: Option<LineEnding>,
+}
+
+impl<'a> Lines<'a> {
+    pub(crate) fn new(contents: &'a [u8]) -> Self {
+        Lines {
+            rest: contents,
+            line_ending: LineEnding::detect(contents),
+        }
+    }
+
+    /// The line endings of the whole `File`, which is `None` if there are no line
+    /// breaks.
+    pub fn line_ending(&self) -> Option<LineEnding> {
+        self.line_ending
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        match self.rest.iter().position(|byte| *byte == b'\n') {
+            Some(end) => {
+                let line = &self.rest[..end];
+                self.rest = &self.rest[end + 1..];
+                Some(line.strip_suffix(b"\r").unwrap_or(line))
+            }
+            // A `\r` is only part of a line ending when it comes before a `\n`.
+            None => {
+                let line = self.rest;
+                self.rest = &[];
+                Some(line)
+            }
+        }
+    }
+}
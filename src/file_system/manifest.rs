@@ -0,0 +1,352 @@
+//! Definition of [`Manifest`](struct.Manifest.html), a record of the files of a
+//! [`Directory`](struct.Directory.html) and their digests.
+
+use crate::file_system::digest::to_hex;
+use crate::file_system::error;
+use crate::file_system::{DefaultLabelRules, FileMode, Label, Path, MAX_DEPTH};
+use nonempty::NonEmpty;
+use std::fmt;
+use std::str::FromStr;
+
+/// A single file, symbolic link, or submodule of a [`Manifest`](struct.Manifest.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// The `Path` of the entry, relative to the `Directory` the `Manifest` was made from.
+    pub path: Path,
+    /// The mode of the entry.
+    pub mode: FileMode,
+    /// The digest of the entry's contents, or the target of a symbolic link, or the
+    /// commit of a submodule.
+    pub digest: Vec<u8>,
+}
+
+/// The version of the format a [`Manifest`](struct.Manifest.html) is written in.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// The paths, modes and digests of every file, symbolic link, and submodule of a
+/// [`Directory`](struct.Directory.html), sorted by their paths, along with the name
+/// of the [`Digest`](digest/trait.Digest.html) algorithm the digests were made with.
+///
+/// A `Manifest` is written out as a header, `radicle-surf-manifest`, followed by
+/// the [`MANIFEST_VERSION`](constant.MANIFEST_VERSION.html) and the algorithm,
+/// and then one entry per line, as the hex encoded digest, the mode in git's
+/// notation, and the path, separated by spaces. It can be parsed back from the same
+/// format.
+///
+/// Paths are written as their bytes, where a `\` is escaped as `\\`, and control
+/// characters and bytes that are not valid UTF-8 are escaped as `\x` followed by
+/// two hex digits, so that every path is written on a single line and is parsed
+/// back as it was.
+///
+/// See [`Directory::manifest`](struct.Directory.html#method.manifest) and
+/// [`Directory::verify`](struct.Directory.html#method.verify).
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::{DefaultLabelRules, FileMode, Label, Manifest, ManifestEntry};
+/// use radicle_surf::file_system::unsound;
+///
+/// // "café" encoded in Latin-1
+/// let latin1 = Label::try_from_bytes(b"caf\xe9", &DefaultLabelRules).unwrap();
+/// let manifest = Manifest::new(
+///     "blake3",
+///     vec![
+///         ManifestEntry {
+///             path: unsound::path::new("src/lib.rs"),
+///             mode: FileMode::Regular,
+///             digest: vec![0xab, 0xcd],
+///         },
+///         ManifestEntry {
+///             path: unsound::path::new("bin/surf"),
+///             mode: FileMode::Executable,
+///             digest: vec![0x01, 0x23],
+///         },
+///         ManifestEntry {
+///             path: unsound::path::new("docs").join(latin1),
+///             mode: FileMode::Regular,
+///             digest: vec![0x45, 0x67],
+///         },
+///     ],
+/// );
+///
+/// let written = manifest.to_string();
+/// assert_eq!(
+///     written,
+///     "radicle-surf-manifest v1 blake3\n\
+///      0123 100755 bin/surf\n\
+///      4567 100644 docs/caf\\xe9\n\
+///      abcd 100644 src/lib.rs\n"
+/// );
+/// assert_eq!(written.parse::<Manifest>(), Ok(manifest));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    algorithm: String,
+    entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Create a `Manifest` from its `entries`, whose digests were made with the
+    /// [`Digest`](digest/trait.Digest.html) algorithm named `algorithm`, sorting
+    /// them by their paths.
+    pub fn new(algorithm: &str, mut entries: Vec<ManifestEntry>) -> Self {
+        entries.sort_by(|left, right| left.path.cmp(&right.path));
+        Manifest {
+            algorithm: algorithm.to_string(),
+            entries,
+        }
+    }
+
+    /// The name of the [`Digest`](digest/trait.Digest.html) algorithm the digests
+    /// were made with, see [`Digest::NAME`](digest/trait.Digest.html#associatedconstant.NAME).
+    pub fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// The entries, sorted by their paths.
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.entries
+    }
+
+    /// Check whether there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} v{} {}", HEADER, MANIFEST_VERSION, self.algorithm)?;
+        for entry in &self.entries {
+            let path = entry
+                .path
+                .iter()
+                .map(|label| escape(label.as_bytes()))
+                .collect::<Vec<_>>()
+                .join("/");
+            writeln!(
+                f,
+                "{} {} {}",
+                to_hex(&entry.digest),
+                git_mode(entry.mode),
+                path
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = error::Error;
+
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        let mut lines = contents
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line))
+            .filter(|(_, line)| !line.is_empty());
+
+        let (number, header) = lines.next().ok_or(error::Manifest::InvalidHeader(1))?;
+        let algorithm = match header.split(' ').collect::<Vec<_>>()[..] {
+            [HEADER, version, algorithm] if !algorithm.is_empty() => {
+                if version != format!("v{}", MANIFEST_VERSION) {
+                    return Err(error::Manifest::UnsupportedVersion(number).into());
+                }
+                algorithm
+            }
+            _ => return Err(error::Manifest::InvalidHeader(number).into()),
+        };
+
+        let entries = lines
+            .map(|(number, line)| {
+                let mut fields = line.splitn(3, ' ');
+                let (digest, mode, path) = match (fields.next(), fields.next(), fields.next()) {
+                    (Some(digest), Some(mode), Some(path)) => (digest, mode, path),
+                    _ => return Err(error::Manifest::MissingField(number).into()),
+                };
+
+                Ok(ManifestEntry {
+                    path: parse_path(path).ok_or(error::Manifest::InvalidPath(number))?,
+                    mode: parse_git_mode(mode).ok_or(error::Manifest::InvalidMode(number))?,
+                    digest: parse_hex(digest).ok_or(error::Manifest::InvalidDigest(number))?,
+                })
+            })
+            .collect::<Result<_, Self::Err>>()?;
+
+        Ok(Manifest::new(algorithm, entries))
+    }
+}
+
+/// The first word of the header of a written `Manifest`.
+const HEADER: &str = "radicle-surf-manifest";
+
+/// Escape the bytes of a `Label`, see [`Manifest`](struct.Manifest.html).
+fn escape(mut bytes: &[u8]) -> String {
+    let mut escaped = String::new();
+    while !bytes.is_empty() {
+        let (valid, invalid) = match std::str::from_utf8(bytes) {
+            Ok(valid) => (valid, 0),
+            Err(err) => (
+                // Safe, since the bytes up to `valid_up_to` are valid UTF-8.
+                std::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap_or_default(),
+                err.error_len().unwrap_or(bytes.len() - err.valid_up_to()),
+            ),
+        };
+        for c in valid.chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                c if c.is_control() => {
+                    let mut buffer = [0; 4];
+                    for byte in c.encode_utf8(&mut buffer).bytes() {
+                        escaped.push_str(&format!("\\x{:02x}", byte));
+                    }
+                }
+                c => escaped.push(c),
+            }
+        }
+        let rest = &bytes[valid.len()..];
+        for byte in &rest[..invalid] {
+            escaped.push_str(&format!("\\x{:02x}", byte));
+        }
+        bytes = &rest[invalid..];
+    }
+    escaped
+}
+
+/// Parse a `Path` written by [`escape`]ing each of its labels.
+fn parse_path(path: &str) -> Option<Path> {
+    let mut labels = vec![];
+    for label in path.split('/') {
+        if labels.len() == MAX_DEPTH {
+            return None;
+        }
+        labels.push(Label::try_from_bytes(&unescape(label)?, &DefaultLabelRules).ok()?);
+    }
+    NonEmpty::from_slice(&labels).map(Path)
+}
+
+fn unescape(label: &str) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut rest = label.as_bytes();
+    while let Some((byte, tail)) = rest.split_first() {
+        rest = match (byte, tail) {
+            (b'\\', [b'\\', tail @ ..]) => {
+                bytes.push(b'\\');
+                tail
+            }
+            (b'\\', [b'x', high, low, tail @ ..]) => {
+                bytes.push((hex_digit(*high)? << 4) | hex_digit(*low)?);
+                tail
+            }
+            (b'\\', _) => return None,
+            (byte, tail) => {
+                bytes.push(*byte);
+                tail
+            }
+        };
+    }
+    Some(bytes)
+}
+
+pub(crate) fn git_mode(mode: FileMode) -> &'static str {
+    match mode {
+        FileMode::Regular => "100644",
+        FileMode::Executable => "100755",
+        FileMode::Symlink => "120000",
+        FileMode::Submodule => "160000",
+        FileMode::EmptyDirectory => "040000",
+    }
+}
+
+fn parse_git_mode(mode: &str) -> Option<FileMode> {
+    match mode {
+        "100644" => Some(FileMode::Regular),
+        "100755" => Some(FileMode::Executable),
+        "120000" => Some(FileMode::Symlink),
+        "160000" => Some(FileMode::Submodule),
+        "040000" => Some(FileMode::EmptyDirectory),
+        _ => None,
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Some((hex_digit(*high)? << 4) | hex_digit(*low)?),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hex_digit(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|digit| digit as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors() {
+        let parse = |contents: &str| {
+            contents.parse::<Manifest>().map_err(|err| match err {
+                error::Error::Manifest(err) => err,
+                err => panic!("unexpected error: {:?}", err),
+            })
+        };
+        let header = "radicle-surf-manifest v1 blake3\n";
+
+        assert_eq!(
+            parse("abcd 100644 README.md"),
+            Err(error::Manifest::InvalidHeader(1))
+        );
+        assert_eq!(
+            parse("radicle-surf-manifest v2 blake3\nabcd 100644 README.md"),
+            Err(error::Manifest::UnsupportedVersion(1))
+        );
+        assert_eq!(
+            parse(&format!("{}abcd 100644 README.md\nabcd 100644", header)),
+            Err(error::Manifest::MissingField(3))
+        );
+        assert_eq!(
+            parse(&format!("{}abc 100644 README.md", header)),
+            Err(error::Manifest::InvalidDigest(2))
+        );
+        assert_eq!(
+            parse(&format!("{}zz 100644 README.md", header)),
+            Err(error::Manifest::InvalidDigest(2))
+        );
+        assert_eq!(
+            parse(&format!("{}ab 100664 src", header)),
+            Err(error::Manifest::InvalidMode(2))
+        );
+        assert_eq!(
+            parse(&format!("{}ab 100644 src//lib.rs", header)),
+            Err(error::Manifest::InvalidPath(2))
+        );
+        assert_eq!(
+            parse(&format!("{}ab 100644 src\\q", header)),
+            Err(error::Manifest::InvalidPath(2))
+        );
+    }
+
+    #[test]
+    fn test_escape_round_trip() {
+        let labels: &[&[u8]] = &[
+            b"plain.rs",
+            b"back\\slash",
+            b"new\nline",
+            b"caf\xe9",
+            "caf\u{e9}".as_bytes(),
+            b"\xff\xfe",
+            b"x\\x41",
+        ];
+        for label in labels {
+            let escaped = escape(label);
+            assert!(!escaped.contains('\n'));
+            assert_eq!(unescape(&escaped).as_deref(), Some(*label));
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Definition of [`Metadata`](struct.Metadata.html), the typed metadata that a
+//! backend attaches to a [`File`](struct.File.html).
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Metadata that a backend attaches to the entries of a `Directory`, keyed by the
+/// type of each value, so that a `File` holds at most one value of each type.
+///
+/// This lets backend-specific information travel with a snapshot, without the
+/// file system knowing about the types of the backend, e.g. the git backend
+/// attaches the [`Sha1`](../vcs/git/struct.Sha1.html) of each blob, and
+/// [`Directory::from_disk`](struct.Directory.html#method.from_disk) the
+/// [`SystemTime`](https://doc.rust-lang.org/std/time/struct.SystemTime.html)
+/// each file was last modified.
+///
+/// Metadata is not part of the equality or checksum of a `File`, and values are
+/// shared between the clones of a `File`.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::{File, Metadata};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Author(&'static str);
+///
+/// let file = File::new(b"# Surf").with_metadata(Author("Monadic"));
+///
+/// assert_eq!(file.metadata().get::<Author>(), Some(&Author("Monadic")));
+/// assert_eq!(file.metadata().get::<u32>(), None);
+/// assert_eq!(file, File::new(b"# Surf"));
+/// ```
+#[derive(Clone, Default)]
+pub struct Metadata {
+    values: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Metadata {
+    /// Create `Metadata` without any values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert `value`, replacing any value of the same type.
+    pub fn insert<T>(&mut self, value: T)
+    where
+        T: Any + Send + Sync,
+    {
+        self.values.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// Get the value of type `T`, if any.
+    pub fn get<T>(&self) -> Option<&T>
+    where
+        T: Any + Send + Sync,
+    {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Remove the value of type `T`, returning whether there was one.
+    pub fn remove<T>(&mut self) -> bool
+    where
+        T: Any + Send + Sync,
+    {
+        self.values.remove(&TypeId::of::<T>()).is_some()
+    }
+
+    /// Get the number of values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Check whether there are no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl fmt::Debug for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metadata")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
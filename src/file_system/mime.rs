@@ -0,0 +1,161 @@
+//! Guessing the media type and programming language of a [`File`](struct.File.html),
+//! see [`File::mime_type`](struct.File.html#method.mime_type) and
+//! [`File::language`](struct.File.html#method.language).
+
+use crate::file_system::path::Label;
+
+/// The media type of text that has no more specific type.
+pub(crate) const TEXT_PLAIN: &str = "text/plain";
+
+/// The media type of binary contents that have no more specific type.
+pub(crate) const OCTET_STREAM: &str = "application/octet-stream";
+
+/// Known magic numbers at the start of binary contents, and their media types.
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\0asm", "application/wasm"),
+    (b"\x7fELF", "application/x-elf"),
+];
+
+/// File names that determine the language of a file regardless of its contents.
+const NAMES: &[(&str, &str)] = &[
+    ("CMakeLists.txt", "CMake"),
+    ("Dockerfile", "Dockerfile"),
+    ("GNUmakefile", "Makefile"),
+    ("Makefile", "Makefile"),
+    ("makefile", "Makefile"),
+    ("Rakefile", "Ruby"),
+    ("Gemfile", "Ruby"),
+];
+
+/// File extensions, their media types and, for source code, their languages.
+const EXTENSIONS: &[(&str, &str, Option<&str>)] = &[
+    ("bash", "application/x-sh", Some("Shell")),
+    ("c", "text/x-c", Some("C")),
+    ("cc", "text/x-c++", Some("C++")),
+    ("cpp", "text/x-c++", Some("C++")),
+    ("css", "text/css", Some("CSS")),
+    ("csv", "text/csv", None),
+    ("go", "text/x-go", Some("Go")),
+    ("h", "text/x-c", Some("C")),
+    ("hpp", "text/x-c++", Some("C++")),
+    ("hs", "text/x-haskell", Some("Haskell")),
+    ("htm", "text/html", Some("HTML")),
+    ("html", "text/html", Some("HTML")),
+    ("java", "text/x-java", Some("Java")),
+    ("js", "text/javascript", Some("JavaScript")),
+    ("json", "application/json", Some("JSON")),
+    ("md", "text/markdown", Some("Markdown")),
+    ("py", "text/x-python", Some("Python")),
+    ("rb", "text/x-ruby", Some("Ruby")),
+    ("rs", "text/x-rust", Some("Rust")),
+    ("sh", "application/x-sh", Some("Shell")),
+    ("svg", "image/svg+xml", Some("SVG")),
+    ("toml", "application/toml", Some("TOML")),
+    ("ts", "text/x-typescript", Some("TypeScript")),
+    ("txt", TEXT_PLAIN, None),
+    ("xml", "application/xml", Some("XML")),
+    ("yaml", "application/yaml", Some("YAML")),
+    ("yml", "application/yaml", Some("YAML")),
+    ("gif", "image/gif", None),
+    ("gz", "application/gzip", None),
+    ("ico", "image/vnd.microsoft.icon", None),
+    ("jpeg", "image/jpeg", None),
+    ("jpg", "image/jpeg", None),
+    ("pdf", "application/pdf", None),
+    ("png", "image/png", None),
+    ("wasm", "application/wasm", None),
+    ("zip", "application/zip", None),
+];
+
+/// Interpreters named by a shebang line, and their languages.
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("bash", "Shell"),
+    ("node", "JavaScript"),
+    ("python", "Python"),
+    ("python3", "Python"),
+    ("ruby", "Ruby"),
+    ("sh", "Shell"),
+    ("zsh", "Shell"),
+];
+
+/// Guess the media type of a file from its magic number, then its `name`.
+pub(crate) fn mime_type(name: &Label, contents: &[u8]) -> Option<&'static str> {
+    MAGIC
+        .iter()
+        .find(|(magic, _)| contents.starts_with(magic))
+        .map(|(_, mime)| *mime)
+        .or_else(|| extension(name).map(|(mime, _)| mime))
+}
+
+/// Guess the language of a file from its `name`, then from a shebang line.
+pub(crate) fn language(name: &Label, contents: &[u8]) -> Option<&'static str> {
+    NAMES
+        .iter()
+        .find(|(known, _)| *known == &*name.label)
+        .map(|(_, language)| *language)
+        .or_else(|| extension(name).and_then(|(_, language)| language))
+        .or_else(|| shebang(contents))
+}
+
+fn extension(name: &Label) -> Option<(&'static str, Option<&'static str>)> {
+    let extension = name.extension()?;
+    EXTENSIONS
+        .iter()
+        .find(|(known, _, _)| known.eq_ignore_ascii_case(extension))
+        .map(|(_, mime, language)| (*mime, *language))
+}
+
+/// Get the language of the interpreter in a `#!` line, either named directly, as in
+/// `#!/bin/sh`, or through `env`, as in `#!/usr/bin/env python3`.
+fn shebang(contents: &[u8]) -> Option<&'static str> {
+    let line = contents
+        .strip_prefix(b"#!")?
+        .split(|byte| *byte == b'\n')
+        .next()?;
+    let line = std::str::from_utf8(line).ok()?;
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+    INTERPRETERS
+        .iter()
+        .find(|(interpreter, _)| *interpreter == program)
+        .map(|(_, language)| *language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::unsound;
+
+    #[test]
+    fn test_shebang() {
+        assert_eq!(shebang(b"#!/bin/sh\necho surf\n"), Some("Shell"));
+        assert_eq!(shebang(b"#!/usr/bin/env python3\n"), Some("Python"));
+        assert_eq!(
+            shebang(b"#!/usr/bin/env -S node --harmony\n"),
+            Some("JavaScript")
+        );
+        assert_eq!(shebang(b"#!/usr/bin/perl\n"), None);
+        assert_eq!(shebang(b"echo surf\n"), None);
+    }
+
+    #[test]
+    fn test_magic_wins_over_extension() {
+        let name = unsound::label::new("logo.jpg");
+        assert_eq!(mime_type(&name, b"\x89PNG\r\n\x1a\n"), Some("image/png"));
+        assert_eq!(mime_type(&name, b"\xff\xd8\xff\xe0"), Some("image/jpeg"));
+        assert_eq!(
+            mime_type(&unsound::label::new("main.RS"), b"fn main() {}"),
+            Some("text/x-rust")
+        );
+    }
+}
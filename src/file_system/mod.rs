@@ -1,6 +1,28 @@
+pub mod digest;
 pub mod directory;
+#[cfg(feature = "encoding")]
+mod encoding;
 pub mod error;
+mod ignore;
+#[cfg(feature = "spdx")]
+mod license;
+mod lines;
+mod manifest;
+mod metadata;
+#[cfg(feature = "mime")]
+mod mime;
 mod path;
+mod sparse;
+mod watch;
 
 pub use self::directory::*;
+#[cfg(feature = "encoding")]
+pub use self::encoding::*;
+pub use self::ignore::*;
+pub use self::lines::*;
+pub use self::manifest::*;
+pub use self::metadata::*;
 pub use self::path::*;
+pub use self::sparse::*;
+pub use self::watch::*;
+pub use crate::tree::MergePolicy;
@@ -1,10 +1,13 @@
 use crate::file_system::error;
 use crate::nonempty::split_last;
 use nonempty::NonEmpty;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::path;
 use std::str::FromStr;
+use std::sync::Arc;
 
 pub mod unsound;
 
@@ -15,6 +18,13 @@ pub mod unsound;
 /// A `Label` should not be empty or contain `/`s. It is encouraged to use
 /// the `TryFrom` instance to create a `Label`.
 ///
+/// A `Label` can also be created from bytes that are not valid UTF-8,
+/// see [`Label::try_from_bytes`](struct.Label.html#method.try_from_bytes).
+/// In this case the original bytes are kept, and the `Label` is displayed lossily.
+///
+/// The contents of a `Label` are reference counted, so cloning a `Label` does
+/// not copy its contents.
+///
 /// # Examples
 ///
 /// ```
@@ -30,8 +40,11 @@ pub mod unsound;
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Label {
-    pub(crate) label: String,
+    pub(crate) label: Arc<str>,
     pub(crate) hidden: bool,
+    /// The original bytes if they are not valid UTF-8, in which case `label`
+    /// is their lossy conversion.
+    pub(crate) bytes: Option<Arc<[u8]>>,
 }
 
 impl Label {
@@ -52,12 +65,151 @@ impl Label {
         Label {
             label: "~".into(),
             hidden: false,
+            bytes: None,
         }
     }
 
     pub fn is_root(&self) -> bool {
         *self == Self::root()
     }
+
+    /// Check that two `Label`s are equal when ignoring case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let readme = unsound::label::new("README.md");
+    ///
+    /// assert!(readme.eq_ignore_case(&unsound::label::new("readme.md")));
+    /// assert!(!readme.eq_ignore_case(&unsound::label::new("readme.rst")));
+    /// ```
+    pub fn eq_ignore_case(&self, other: &Self) -> bool {
+        self.label
+            .chars()
+            .flat_map(char::to_lowercase)
+            .eq(other.label.chars().flat_map(char::to_lowercase))
+    }
+
+    /// Get the Unicode [NFC](https://unicode.org/reports/tr15/) normalized form of the `Label`.
+    ///
+    /// Files checked in on macOS are often in NFD form, so the normalized `Label`
+    /// can be used as a key when hashing, and for comparisons via
+    /// [`Label::eq_nfc`](struct.Label.html#method.eq_nfc).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let nfd = unsound::label::new("cafe\u{301}");
+    ///
+    /// assert_eq!(nfd.to_nfc(), unsound::label::new("caf\u{e9}"));
+    /// ```
+    #[cfg(feature = "unicode")]
+    pub fn to_nfc(&self) -> Self {
+        use unicode_normalization::UnicodeNormalization;
+
+        Label {
+            label: self.label.nfc().collect::<String>().into(),
+            hidden: self.hidden,
+            bytes: self.bytes.clone(),
+        }
+    }
+
+    /// Check that two `Label`s are equal after Unicode NFC normalization.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let nfd = unsound::label::new("cafe\u{301}");
+    ///
+    /// assert!(nfd.eq_nfc(&unsound::label::new("caf\u{e9}")));
+    /// assert!(!nfd.eq_nfc(&unsound::label::new("cafe")));
+    /// ```
+    #[cfg(feature = "unicode")]
+    pub fn eq_nfc(&self, other: &Self) -> bool {
+        use unicode_normalization::UnicodeNormalization;
+
+        self.label.nfc().eq(other.label.nfc())
+    }
+
+    /// Get the name of the `Label` without its extension. See
+    /// [`Label::extension`](struct.Label.html#method.extension) for what
+    /// is considered the extension.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// assert_eq!(unsound::label::new("lib.rs").file_stem(), "lib");
+    /// assert_eq!(unsound::label::new("archive.tar.gz").file_stem(), "archive.tar");
+    /// assert_eq!(unsound::label::new(".gitignore").file_stem(), ".gitignore");
+    /// assert_eq!(unsound::label::new("Makefile").file_stem(), "Makefile");
+    /// ```
+    pub fn file_stem(&self) -> &str {
+        self.split_extension().0
+    }
+
+    /// Get the extension of the `Label`, i.e. everything after the last `.`.
+    ///
+    /// There is no extension if the `Label` contains no `.`, or if the only `.` is
+    /// the first character, as is the case for hidden files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// assert_eq!(unsound::label::new("lib.rs").extension(), Some("rs"));
+    /// assert_eq!(unsound::label::new("archive.tar.gz").extension(), Some("gz"));
+    /// assert_eq!(unsound::label::new(".gitignore").extension(), None);
+    /// assert_eq!(unsound::label::new("Makefile").extension(), None);
+    /// ```
+    pub fn extension(&self) -> Option<&str> {
+        self.split_extension().1
+    }
+
+    /// Compare two `Label`s using a natural sort order, where runs of digits
+    /// are compared by their numeric value, e.g. `file2.rs` comes before `file10.rs`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File, Label, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut directory = Directory::root();
+    /// directory.insert_file(&unsound::path::new("file10.rs"), File::new(b"10"));
+    /// directory.insert_file(&unsound::path::new("file2.rs"), File::new(b"2"));
+    /// directory.insert_file(&unsound::path::new("file1.rs"), File::new(b"1"));
+    ///
+    /// let mut contents = directory.list_directory();
+    /// contents.sort_by(|(left, _), (right, _)| left.natural_cmp(right));
+    ///
+    /// assert_eq!(
+    ///     contents,
+    ///     vec![
+    ///         SystemType::file(unsound::label::new("file1.rs")),
+    ///         SystemType::file(unsound::label::new("file2.rs")),
+    ///         SystemType::file(unsound::label::new("file10.rs")),
+    ///     ]
+    /// );
+    /// ```
+    pub fn natural_cmp(&self, other: &Self) -> Ordering {
+        natural_cmp(&self.label, &other.label)
+    }
+
+    fn split_extension(&self) -> (&str, Option<&str>) {
+        match self.label.rfind('.') {
+            None | Some(0) => (&*self.label, None),
+            Some(index) => (&self.label[..index], Some(&self.label[index + 1..])),
+        }
+    }
 }
 
 impl fmt::Display for Label {
@@ -66,21 +218,161 @@ impl fmt::Display for Label {
     }
 }
 
-impl TryFrom<&str> for Label {
-    type Error = error::Error;
+/// The rules for what makes a valid [`Label`](struct.Label.html), which
+/// can differ between VCS backends.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::{DefaultLabelRules, Label, LabelRules};
+/// use radicle_surf::file_system::error as file_error;
+///
+/// struct NoSpaces;
+///
+/// impl LabelRules for NoSpaces {
+///     fn validate(&self, label: &str) -> Result<(), file_error::Error> {
+///         DefaultLabelRules.validate(label)?;
+///         if label.contains(' ') {
+///             Err(file_error::Error::Label(file_error::Label::TrailingDotOrSpace))
+///         } else {
+///             Ok(())
+///         }
+///     }
+/// }
+///
+/// assert!(Label::try_from_rules("my file.txt", &DefaultLabelRules).is_ok());
+/// assert!(Label::try_from_rules("my file.txt", &NoSpaces).is_err());
+/// ```
+pub trait LabelRules {
+    /// Check that `label` is a valid [`Label`](struct.Label.html).
+    fn validate(&self, label: &str) -> Result<(), error::Error>;
+}
 
-    fn try_from(item: &str) -> Result<Self, Self::Error> {
-        if item.is_empty() {
+/// The rules used by `Label`'s `TryFrom` instance: a `Label` must not be
+/// empty or contain a `/`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DefaultLabelRules;
+
+impl LabelRules for DefaultLabelRules {
+    fn validate(&self, label: &str) -> Result<(), error::Error> {
+        if label.is_empty() {
             Err(error::EMPTY_LABEL)
-        } else if item.contains('/') {
+        } else if label.contains('/') {
             Err(error::CONTAINS_SLASH)
         } else {
-            Ok(Label {
-                label: item.into(),
-                hidden: false,
-            })
+            Ok(())
+        }
+    }
+}
+
+impl Label {
+    /// Create a `Label`, validating it using the [`LabelRules`](trait.LabelRules.html)
+    /// provided, e.g. the rules of the VCS backend the `Label` is for.
+    pub fn try_from_rules<R>(item: &str, rules: &R) -> Result<Self, error::Error>
+    where
+        R: LabelRules,
+    {
+        rules.validate(item)?;
+        Ok(Label {
+            label: item.into(),
+            hidden: false,
+            bytes: None,
+        })
+    }
+
+    /// Create a `Label` from bytes, validating it using the
+    /// [`LabelRules`](trait.LabelRules.html) provided.
+    ///
+    /// If the bytes are not valid UTF-8 they are kept as they are, and
+    /// the rules are checked against their lossy conversion to a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{DefaultLabelRules, Label};
+    ///
+    /// // "café" encoded in Latin-1
+    /// let latin1 = Label::try_from_bytes(b"caf\xe9", &DefaultLabelRules).unwrap();
+    ///
+    /// assert!(!latin1.is_utf8());
+    /// assert_eq!(latin1.as_bytes(), b"caf\xe9");
+    /// assert_eq!(latin1.to_string(), "caf\u{fffd}");
+    ///
+    /// let utf8 = Label::try_from_bytes("café".as_bytes(), &DefaultLabelRules).unwrap();
+    ///
+    /// assert!(utf8.is_utf8());
+    /// assert_ne!(latin1, utf8);
+    ///
+    /// assert!(Label::try_from_bytes(b"a/\xe9", &DefaultLabelRules).is_err());
+    /// ```
+    pub fn try_from_bytes<R>(item: &[u8], rules: &R) -> Result<Self, error::Error>
+    where
+        R: LabelRules,
+    {
+        match std::str::from_utf8(item) {
+            Ok(item) => Label::try_from_rules(item, rules),
+            Err(_) => {
+                let label = String::from_utf8_lossy(item);
+                rules.validate(&label)?;
+                Ok(Label {
+                    label: label.into(),
+                    hidden: false,
+                    bytes: Some(item.into()),
+                })
+            }
+        }
+    }
+
+    /// Get the bytes of the `Label`, which are the original bytes if
+    /// the `Label` is not valid UTF-8.
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.bytes {
+            Some(bytes) => bytes,
+            None => self.label.as_bytes(),
+        }
+    }
+
+    /// Check whether the `Label` is valid UTF-8, i.e. it is displayed as it is
+    /// rather than lossily.
+    pub fn is_utf8(&self) -> bool {
+        self.bytes.is_none()
+    }
+}
+
+/// Interns `Label`s created from bytes, so that identical `Label`s share their
+/// contents, e.g. the many `src` and `mod.rs` entries of a large repository.
+pub(crate) struct LabelInterner<R> {
+    rules: R,
+    labels: HashMap<Box<[u8]>, Label>,
+}
+
+impl<R: LabelRules> LabelInterner<R> {
+    /// Create an interner whose `Label`s are validated using `rules`.
+    pub(crate) fn new(rules: R) -> Self {
+        LabelInterner {
+            rules,
+            labels: HashMap::new(),
         }
     }
+
+    /// Get the `Label` for `bytes`, see [`Label::try_from_bytes`].
+    pub(crate) fn intern(&mut self, bytes: &[u8]) -> Result<Label, error::Error> {
+        if let Some(label) = self.labels.get(bytes) {
+            return Ok(label.clone());
+        }
+
+        let label = Label::try_from_bytes(bytes, &self.rules)?;
+        self.labels.insert(bytes.into(), label.clone());
+        Ok(label)
+    }
+}
+
+impl TryFrom<&str> for Label {
+    type Error = error::Error;
+
+    fn try_from(item: &str) -> Result<Self, Self::Error> {
+        Label::try_from_rules(item, &DefaultLabelRules)
+    }
 }
 
 impl FromStr for Label {
@@ -90,11 +382,65 @@ impl FromStr for Label {
         Label::try_from(item)
     }
 }
+/// Construct a [`Path`](file_system/struct.Path.html) from one or more string labels.
+///
+/// This evaluates to a `Result<Path, file_system::error::Error>`, failing
+/// if any of the labels are not a valid [`Label`](file_system/struct.Label.html).
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::path;
+/// use radicle_surf::file_system::error as file_error;
+/// use radicle_surf::file_system::unsound;
+///
+/// assert_eq!(path!["src", "lib.rs"], Ok(unsound::path::new("src/lib.rs")));
+/// assert_eq!(path!["README.md"], Ok(unsound::path::new("README.md")));
+/// assert!(path!["src", "vcs/git.rs"].is_err());
+/// ```
+#[macro_export]
+macro_rules! path {
+    ($label:expr $(, $labels:expr)* $(,)?) => {
+        (|| -> Result<$crate::file_system::Path, $crate::file_system::error::Error> {
+            #[allow(unused_mut)]
+            let mut path = $crate::file_system::Path::new(
+                $label.parse::<$crate::file_system::Label>()?,
+            );
+            $(path.push($labels.parse::<$crate::file_system::Label>()?);)*
+            Ok(path)
+        })()
+    };
+}
+
+/// The largest number of [`Label`](struct.Label.html)s a [`Path`](struct.Path.html)
+/// parsed from a string, or read from a repository or the disk, may have.
+///
+/// Repositories can nest directories arbitrarily deep, and cloning, comparing, and
+/// dropping a [`Directory`](struct.Directory.html) recurse over its sub-directories,
+/// so deeper paths are refused with
+/// [`error::Path::TooDeep`](error/enum.Path.html#variant.TooDeep) rather than
+/// risking overflowing the stack, and nothing deeper can be inserted into a
+/// `Directory`.
+pub const MAX_DEPTH: usize = 512;
+
+/// Check that an entry `depth` labels deep is within [`MAX_DEPTH`].
+pub(crate) fn check_depth(depth: usize) -> Result<(), error::Error> {
+    if depth > MAX_DEPTH {
+        Err(error::TOO_DEEP)
+    } else {
+        Ok(())
+    }
+}
+
 /// A non-empty set of [`Label`](struct.Label.html)s to define a path
 /// to a directory or file.
 ///
 /// `Path` tends to be used for insertion or find operations.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// The `PartialOrd` and `Ord` instances compare the [`Label`](struct.Label.html)s
+/// of the `Path`s lexicographically. See
+/// [`Path::natural_cmp`](struct.Path.html#method.natural_cmp) for a natural sort order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Path(pub NonEmpty<Label>);
 
 impl fmt::Display for Path {
@@ -114,6 +460,7 @@ impl TryFrom<&str> for Path {
         let mut path = Vec::new();
 
         for label in item.trim_end_matches('/').split('/') {
+            check_depth(path.len() + 1)?;
             let l = Label::try_from(label)?;
             path.push(l);
         }
@@ -219,6 +566,82 @@ impl Path {
         self.0.pop()
     }
 
+    /// Create a new `Path` by pushing a [`Label`](struct.Label.html)
+    /// onto a copy of this `Path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::Path;
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let src = unsound::path::new("src");
+    /// let lib = src
+    ///     .join(unsound::label::new("vcs"))
+    ///     .join(unsound::label::new("mod.rs"));
+    ///
+    /// assert_eq!(lib, unsound::path::new("src/vcs/mod.rs"));
+    /// assert_eq!(src, unsound::path::new("src"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if this `Path` already has [`MAX_DEPTH`](constant.MAX_DEPTH.html) labels,
+    /// see [`try_join`](#method.try_join).
+    pub fn join(&self, label: Label) -> Self {
+        match self.try_join(label) {
+            Ok(path) => path,
+            Err(_) => panic!("{} is nested deeper than MAX_DEPTH", self),
+        }
+    }
+
+    /// Create a new `Path` by appending the `label` onto a copy of this `Path`, unless
+    /// this `Path` already has [`MAX_DEPTH`](constant.MAX_DEPTH.html) labels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{error, Path, MAX_DEPTH};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let src = unsound::path::new("src");
+    /// assert_eq!(
+    ///     src.try_join(unsound::label::new("lib.rs")),
+    ///     Ok(unsound::path::new("src/lib.rs"))
+    /// );
+    ///
+    /// let deep = unsound::path::new(&vec!["a"; MAX_DEPTH].join("/"));
+    /// assert_eq!(
+    ///     deep.try_join(unsound::label::new("b")),
+    ///     Err(error::Error::Path(error::Path::TooDeep))
+    /// );
+    /// ```
+    pub fn try_join(&self, label: Label) -> Result<Self, error::Error> {
+        check_depth(self.0.len() + 1)?;
+        let mut path = self.clone();
+        path.push(label);
+        Ok(path)
+    }
+
+    /// Create a new `Path` by appending `other` onto a copy of this `Path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::Path;
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let src = unsound::path::new("src");
+    /// let lib = src.joined(&unsound::path::new("vcs/mod.rs"));
+    ///
+    /// assert_eq!(lib, unsound::path::new("src/vcs/mod.rs"));
+    /// ```
+    pub fn joined(&self, other: &Self) -> Self {
+        let mut path = self.clone();
+        path.append(&mut other.clone());
+        path
+    }
+
     /// Iterator over the [`Label`](struct.Label.html)s in the `Path`.
     ///
     /// # Examples
@@ -308,6 +731,141 @@ impl Path {
         split_last(&self.0)
     }
 
+    /// Get the `Path` without its final [`Label`](struct.Label.html), or `None`
+    /// if the `Path` is a single `Label`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::Path;
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let path = unsound::path::new("~/src/lib.rs");
+    ///
+    /// assert_eq!(path.parent(), Some(unsound::path::new("~/src")));
+    /// assert_eq!(unsound::path::new("src").parent(), None);
+    /// assert_eq!(Path::root().parent(), None);
+    /// ```
+    pub fn parent(&self) -> Option<Self> {
+        let (prefix, _) = self.split_last();
+        NonEmpty::from_slice(&prefix).map(Path)
+    }
+
+    /// Iterator over the `Path` and its successively shorter prefixes,
+    /// as given by [`Path::parent`](struct.Path.html#method.parent).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let path = unsound::path::new("src/vcs/git.rs");
+    ///
+    /// assert_eq!(
+    ///     path.ancestors().collect::<Vec<_>>(),
+    ///     vec![
+    ///         unsound::path::new("src/vcs/git.rs"),
+    ///         unsound::path::new("src/vcs"),
+    ///         unsound::path::new("src"),
+    ///     ]
+    /// );
+    /// ```
+    pub fn ancestors(&self) -> impl Iterator<Item = Self> {
+        std::iter::successors(Some(self.clone()), Path::parent)
+    }
+
+    /// Compare two `Path`s, [`Label`](struct.Label.html) by `Label`, using
+    /// [`Label::natural_cmp`](struct.Label.html#method.natural_cmp).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let mut paths = vec![
+    ///     unsound::path::new("chapter10/intro.md"),
+    ///     unsound::path::new("chapter2/intro.md"),
+    ///     unsound::path::new("chapter2"),
+    /// ];
+    ///
+    /// paths.sort();
+    /// assert_eq!(paths, vec![
+    ///     unsound::path::new("chapter10/intro.md"),
+    ///     unsound::path::new("chapter2"),
+    ///     unsound::path::new("chapter2/intro.md"),
+    /// ]);
+    ///
+    /// paths.sort_by(|left, right| left.natural_cmp(right));
+    /// assert_eq!(paths, vec![
+    ///     unsound::path::new("chapter2"),
+    ///     unsound::path::new("chapter2/intro.md"),
+    ///     unsound::path::new("chapter10/intro.md"),
+    /// ]);
+    /// ```
+    pub fn natural_cmp(&self, other: &Self) -> Ordering {
+        let mut labels = self.iter();
+        let mut other_labels = other.iter();
+        loop {
+            match (labels.next(), other_labels.next()) {
+                (None, None) => return Ordering::Equal,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(label), Some(other_label)) => match label.natural_cmp(other_label) {
+                    Ordering::Equal => {}
+                    ordering => return ordering,
+                },
+            }
+        }
+    }
+
+    /// Get the final [`Label`](struct.Label.html) of the `Path`, i.e. the
+    /// name of the file or directory the `Path` points to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let path = unsound::path::new("src/file_system/mod.rs");
+    /// assert_eq!(path.file_name(), &unsound::label::new("mod.rs"));
+    /// ```
+    pub fn file_name(&self) -> &Label {
+        self.0.last()
+    }
+
+    /// Get the [`file_stem`](struct.Label.html#method.file_stem) of the
+    /// final [`Label`](struct.Label.html) of the `Path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let path = unsound::path::new("src/file_system/mod.rs");
+    /// assert_eq!(path.file_stem(), "mod");
+    /// ```
+    pub fn file_stem(&self) -> &str {
+        self.file_name().file_stem()
+    }
+
+    /// Get the [`extension`](struct.Label.html#method.extension) of the
+    /// final [`Label`](struct.Label.html) of the `Path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let path = unsound::path::new("src/file_system/mod.rs");
+    /// assert_eq!(path.extension(), Some("rs"));
+    ///
+    /// let path = unsound::path::new("src/file_system");
+    /// assert_eq!(path.extension(), None);
+    /// ```
+    pub fn extension(&self) -> Option<&str> {
+        self.file_name().extension()
+    }
+
     /// Construct a `Path` given at least one [`Label`](struct.Label)
     /// followed by 0 or more [`Label`](struct.Label)s.
     ///
@@ -373,6 +931,9 @@ impl TryFrom<path::PathBuf> for Path {
     fn try_from(path_buf: path::PathBuf) -> Result<Self, Self::Error> {
         let mut path = Path::root();
         for p in path_buf.iter() {
+            if path.0.len() > MAX_DEPTH {
+                return Err(error::TOO_DEEP);
+            }
             let p = p.to_str().ok_or(error::INVALID_UTF8)?;
             let l = Label::try_from(p)?;
             path.push(l);
@@ -381,3 +942,187 @@ impl TryFrom<path::PathBuf> for Path {
         Ok(path)
     }
 }
+
+/// Compare two strings, where runs of ASCII digits are compared by their numeric value.
+/// If the numeric values are equal, the run with fewer leading zeros comes first.
+pub(crate) fn natural_cmp(left: &str, right: &str) -> Ordering {
+    let mut left = left.chars().peekable();
+    let mut right = right.chars().peekable();
+
+    loop {
+        match (left.peek().copied(), right.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(l), Some(r)) if l.is_ascii_digit() && r.is_ascii_digit() => {
+                let take_digits = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                        digits.push(*c);
+                        chars.next();
+                    }
+                    digits
+                };
+                let l_digits = take_digits(&mut left);
+                let r_digits = take_digits(&mut right);
+                let l_number = l_digits.trim_start_matches('0');
+                let r_number = r_digits.trim_start_matches('0');
+
+                let ordering = l_number
+                    .len()
+                    .cmp(&r_number.len())
+                    .then_with(|| l_number.cmp(r_number))
+                    .then_with(|| l_digits.len().cmp(&r_digits.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(l), Some(r)) => {
+                if l != r {
+                    return l.cmp(&r);
+                }
+                left.next();
+                right.next();
+            }
+        }
+    }
+}
+
+/// `Label`s are serialized as strings, or as bytes if they are not valid UTF-8,
+/// and are validated by the [`DefaultLabelRules`](struct.DefaultLabelRules.html)
+/// when deserialized. Since a `Label` may be either, deserializing one needs a
+/// self-describing format, e.g. JSON.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::Label;
+/// use serde::de::value::{Error, StrDeserializer};
+/// use serde::de::IntoDeserializer;
+/// use serde::Deserialize;
+///
+/// let lib: StrDeserializer<Error> = "lib.rs".into_deserializer();
+/// assert_eq!(Label::deserialize(lib).map(|label| label.to_string()), Ok("lib.rs".to_string()));
+///
+/// let invalid: StrDeserializer<Error> = "src/lib.rs".into_deserializer();
+/// assert!(Label::deserialize(invalid).is_err());
+/// ```
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{DefaultLabelRules, Label};
+    use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+    use std::fmt;
+
+    impl Serialize for Label {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match &self.bytes {
+                None => serializer.serialize_str(&self.label),
+                Some(bytes) => serializer.serialize_bytes(bytes),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Label {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_any(LabelVisitor)
+        }
+    }
+
+    struct LabelVisitor;
+
+    impl<'de> Visitor<'de> for LabelVisitor {
+        type Value = Label;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a label as a string or bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, label: &str) -> Result<Label, E> {
+            Label::try_from_rules(label, &DefaultLabelRules)
+                .map_err(|err| E::custom(format!("invalid label {:?}: {:?}", label, err)))
+        }
+
+        fn visit_bytes<E: de::Error>(self, label: &[u8]) -> Result<Label, E> {
+            Label::try_from_bytes(label, &DefaultLabelRules).map_err(|err| {
+                E::custom(format!(
+                    "invalid label {:?}: {:?}",
+                    String::from_utf8_lossy(label),
+                    err
+                ))
+            })
+        }
+
+        fn visit_seq<S>(self, mut seq: S) -> Result<Label, S::Error>
+        where
+            S: SeqAccess<'de>,
+        {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            self.visit_bytes(&bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{natural_cmp, DefaultLabelRules, Label, LabelInterner, Path, MAX_DEPTH};
+    use crate::file_system::error;
+    use std::cmp::Ordering;
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_max_depth() {
+        let path = |depth: usize| vec!["a"; depth].join("/");
+
+        assert!(Path::try_from(path(MAX_DEPTH).as_str()).is_ok());
+        assert_eq!(
+            Path::try_from(path(MAX_DEPTH + 1).as_str()),
+            Err(error::TOO_DEEP)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "deeper than MAX_DEPTH")]
+    fn test_join_past_max_depth() {
+        let path = Path::try_from(vec!["a"; MAX_DEPTH].join("/").as_str()).unwrap();
+        path.join(Label::try_from("b").unwrap());
+    }
+
+    #[test]
+    fn test_interned_labels_share_contents() {
+        let mut interner = LabelInterner::new(DefaultLabelRules);
+        let src = interner.intern(b"src").unwrap();
+        let other_src = interner.intern(b"src").unwrap();
+        let lib = interner.intern(b"lib.rs").unwrap();
+
+        assert_eq!(src, other_src);
+        assert!(Arc::ptr_eq(&src.label, &other_src.label));
+        assert!(!Arc::ptr_eq(&src.label, &lib.label));
+        assert!(interner.intern(b"src/lib.rs").is_err());
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("file2.rs", "file10.rs"), Ordering::Less);
+        assert_eq!(natural_cmp("file10.rs", "file2.rs"), Ordering::Greater);
+        assert_eq!(natural_cmp("file2.rs", "file2.rs"), Ordering::Equal);
+        assert_eq!(natural_cmp("file02.rs", "file2.rs"), Ordering::Greater);
+        assert_eq!(natural_cmp("file2", "file2.rs"), Ordering::Less);
+        assert_eq!(natural_cmp("a10b2", "a10b10"), Ordering::Less);
+        assert_eq!(natural_cmp("abc", "abd"), Ordering::Less);
+        assert_eq!(
+            natural_cmp("99999999999999999999999", "100000000000000000000000"),
+            Ordering::Less
+        );
+    }
+}
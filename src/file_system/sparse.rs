@@ -0,0 +1,126 @@
+//! Definition of [`SparsePatterns`](enum.SparsePatterns.html), the patterns of git's
+//! `sparse-checkout` file.
+//!
+//! See [git-sparse-checkout](https://git-scm.com/docs/git-sparse-checkout) for the
+//! two pattern sets it supports.
+
+use crate::file_system::{Ignore, Label, Path};
+use std::convert::TryFrom;
+
+/// The paths selected by a sparse checkout, see
+/// [`Directory::sparse_view`](struct.Directory.html#method.sparse_view).
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::SparsePatterns;
+/// use radicle_surf::file_system::unsound;
+///
+/// let cone = SparsePatterns::parse("/*\n!/*/\n/src/\n!/src/*/\n/src/vcs/\n", true);
+///
+/// assert_eq!(cone, SparsePatterns::Cone(vec![unsound::path::new("src/vcs")]));
+/// assert!(cone.selects(&unsound::path::new("README.md")));
+/// assert!(cone.selects(&unsound::path::new("src/lib.rs")));
+/// assert!(cone.selects(&unsound::path::new("src/vcs/git/error.rs")));
+/// assert!(!cone.selects(&unsound::path::new("src/file_system/path.rs")));
+/// assert!(!cone.selects(&unsound::path::new("tests/vcs.rs")));
+///
+/// // Patterns that are not in the cone format are matched like a `.gitignore`.
+/// let patterns = SparsePatterns::parse("/*\n!/docs/\n", true);
+///
+/// assert!(patterns.selects(&unsound::path::new("README.md")));
+/// assert!(patterns.selects(&unsound::path::new("src/vcs/git.rs")));
+/// assert!(!patterns.selects(&unsound::path::new("docs/intro.md")));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SparsePatterns {
+    /// Cone mode, given by the directories whose contents are selected in full.
+    ///
+    /// The files at the root, and the files directly inside any parent of the
+    /// directories, are selected too.
+    Cone(Vec<Path>),
+    /// Non-cone mode, where the paths matched by the patterns are selected, see
+    /// [`Ignore::matches`](struct.Ignore.html#method.matches).
+    Patterns(Ignore),
+}
+
+impl SparsePatterns {
+    /// Parse the contents of a `sparse-checkout` file.
+    ///
+    /// When `cone` is set, i.e. git's `core.sparseCheckoutCone` is enabled, the
+    /// contents are parsed as cone mode patterns, falling back to non-cone mode
+    /// when they are not in the cone format, as git does.
+    pub fn parse(contents: &str, cone: bool) -> Self {
+        match Self::parse_cone(contents) {
+            Some(directories) if cone => SparsePatterns::Cone(directories),
+            _ => SparsePatterns::Patterns(Ignore::parse(contents)),
+        }
+    }
+
+    /// Check whether the file, symbolic link or submodule at `path` is selected.
+    ///
+    /// `path` is relative to the root of the checkout.
+    pub fn selects(&self, path: &Path) -> bool {
+        match self {
+            SparsePatterns::Cone(directories) => {
+                let (parent, _) = path.split_last();
+                let parent = parent
+                    .into_iter()
+                    .skip_while(Label::is_root)
+                    .collect::<Vec<_>>();
+
+                // The parent is either inside a selected directory, or leads to one.
+                parent.is_empty()
+                    || directories.iter().any(|directory| {
+                        directory
+                            .iter()
+                            .skip_while(|label| label.is_root())
+                            .zip(parent.iter())
+                            .all(|(label, parent)| label == parent)
+                    })
+            }
+            SparsePatterns::Patterns(ignore) => ignore.matches(path, false),
+        }
+    }
+
+    /// Parse the directories of cone mode patterns, or `None` if there is a
+    /// pattern that is not in the cone format.
+    fn parse_cone(contents: &str) -> Option<Vec<Path>> {
+        let mut included = vec![];
+        let mut parents = vec![];
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line == "/*" || line == "!/*/" {
+                continue;
+            }
+
+            if let Some(parent) = line
+                .strip_prefix("!/")
+                .and_then(|line| line.strip_suffix("/*/"))
+            {
+                parents.push(parse_cone_directory(parent)?);
+            } else {
+                let directory = line.strip_prefix('/')?.strip_suffix('/')?;
+                included.push(parse_cone_directory(directory)?);
+            }
+        }
+
+        included.retain(|directory| !parents.contains(directory));
+        Some(included)
+    }
+}
+
+/// Parse a directory of a cone mode pattern, in which git escapes glob characters
+/// with a `\`.
+fn parse_cone_directory(directory: &str) -> Option<Path> {
+    let mut unescaped = String::with_capacity(directory.len());
+    let mut chars = directory.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.push(chars.next()?),
+            '*' | '?' | '[' => return None,
+            c => unescaped.push(c),
+        }
+    }
+
+    Path::try_from(unescaped.as_str()).ok()
+}
@@ -0,0 +1,170 @@
+//! Watching a directory on disk for changes, see
+//! [`DiskWatcher`](struct.DiskWatcher.html).
+
+use crate::diff::Diff;
+use crate::file_system::directory::read_disk_file;
+use crate::file_system::{Directory, DiskOptions, File, LabelRules, Path};
+use std::collections::HashMap;
+use std::io;
+use std::time::SystemTime;
+
+/// The metadata of a file on disk that tells whether it may have changed since it
+/// was last read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    changed: Option<(i64, i64)>,
+    inode: Option<u64>,
+}
+
+impl Stamp {
+    #[cfg(unix)]
+    fn of(metadata: &std::fs::Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        Stamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            changed: Some((metadata.ctime(), metadata.ctime_nsec())),
+            inode: Some(metadata.ino()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn of(metadata: &std::fs::Metadata) -> Self {
+        Stamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            changed: None,
+            inode: None,
+        }
+    }
+
+    /// Check whether the file was modified after `since`, in which case it may have
+    /// been modified again without its modification time changing, like git's
+    /// "racy" entries.
+    fn is_racy(&self, since: SystemTime) -> bool {
+        self.modified.is_none_or(|modified| modified >= since)
+    }
+}
+
+/// A [`Directory`](struct.Directory.html) read from disk, see
+/// [`Directory::from_disk`](struct.Directory.html#method.from_disk), that can be
+/// scanned again to get the [`Diff`](../diff/struct.Diff.html) of what changed since.
+///
+/// Scanning again only reads the contents of the files whose size, modification
+/// time, or inode changed, and reuses the contents of the rest, so previewing a
+/// working directory as it is being edited doesn't read all of it on every change.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::{DefaultLabelRules, DiskWatcher};
+/// use radicle_surf::file_system::unsound;
+///
+/// let tempdir = tempfile::tempdir().unwrap();
+/// let disk = tempdir.path();
+/// std::fs::create_dir_all(disk.join("src")).unwrap();
+/// std::fs::write(disk.join("README.md"), "# Surf").unwrap();
+/// std::fs::write(disk.join("src/lib.rs"), "pub mod vcs;").unwrap();
+///
+/// let mut watcher = DiskWatcher::new(disk, DefaultLabelRules).unwrap();
+/// assert_eq!(watcher.files_read(), 2);
+///
+/// std::fs::write(disk.join("README.md"), "# Radicle Surf").unwrap();
+/// std::fs::write(disk.join("src/diff.rs"), "pub struct Diff;").unwrap();
+/// let diff = watcher.rescan().unwrap();
+///
+/// assert_eq!(diff.modified[0].path, unsound::path::new("~/README.md"));
+/// assert_eq!(diff.created[0].0, unsound::path::new("~/src/diff.rs"));
+/// assert!(diff.deleted.is_empty());
+/// assert!(watcher
+///     .directory()
+///     .find_file(&unsound::path::new("src/diff.rs"))
+///     .is_some());
+/// ```
+pub struct DiskWatcher<R> {
+    root: std::path::PathBuf,
+    rules: R,
+    directory: Directory,
+    files: HashMap<Path, (Stamp, File)>,
+    scanned_at: SystemTime,
+    files_read: usize,
+}
+
+impl<R> DiskWatcher<R>
+where
+    R: LabelRules,
+{
+    /// Read the directory at `root` on disk, where entries whose names are not valid
+    /// [`Label`](struct.Label.html)s under `rules` are skipped.
+    pub fn new(root: &std::path::Path, rules: R) -> io::Result<Self> {
+        let mut watcher = DiskWatcher {
+            root: root.to_path_buf(),
+            rules,
+            directory: Directory::root(),
+            files: HashMap::new(),
+            scanned_at: SystemTime::UNIX_EPOCH,
+            files_read: 0,
+        };
+        watcher.rescan()?;
+        Ok(watcher)
+    }
+
+    /// The `Directory` as of the last scan.
+    pub fn directory(&self) -> &Directory {
+        &self.directory
+    }
+
+    /// The number of files whose contents were read by the last scan.
+    pub fn files_read(&self) -> usize {
+        self.files_read
+    }
+
+    /// Scan the directory on disk again, returning the `Diff` from the `Directory`
+    /// of the last scan to the new one.
+    ///
+    /// Files that were modified after the last scan started are read again even if
+    /// their metadata is the same, since they may have changed within the resolution
+    /// of their modification time.
+    pub fn rescan(&mut self) -> io::Result<Diff> {
+        let scanned_at = SystemTime::now();
+        let previous_scan = self.scanned_at;
+        let previous = &self.files;
+        let mut files = HashMap::new();
+        let mut files_read = 0;
+
+        let mut directory = Directory::root();
+        let options = DiskOptions::default();
+        directory.read_disk(
+            &self.root,
+            &self.rules,
+            &options,
+            &mut |path, entry| {
+                let stamp = Stamp::of(&entry.metadata()?);
+                let file = match previous.get(path) {
+                    Some((previous, file))
+                        if *previous == stamp && !stamp.is_racy(previous_scan) =>
+                    {
+                        file.clone()
+                    }
+                    _ => {
+                        files_read += 1;
+                        read_disk_file(entry)?
+                    }
+                };
+                files.insert(path.clone(), (stamp, file.clone()));
+                Ok(file)
+            },
+            None,
+        )?;
+
+        let diff = Diff::diff(self.directory.clone(), directory.clone())
+            .map_err(|err| io::Error::other(format!("{:?}", err)))?;
+        self.directory = directory;
+        self.files = files;
+        self.scanned_at = scanned_at;
+        self.files_read = files_read;
+        Ok(diff)
+    }
+}
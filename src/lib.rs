@@ -22,7 +22,7 @@
 //! let mut browser = git::Browser::new(repo).expect("Failed to initialise browser");
 //!
 //! // Set the history to a particular commit
-//! browser.commit(git::Sha1::new("80ded66281a4de2889cc07293a8f10947c6d57fe"))
+//! browser.commit("80ded66281a4de2889cc07293a8f10947c6d57fe".parse::<git::Sha1>().unwrap())
 //!        .expect("Failed to set commit");
 //!
 //! // Get the snapshot of the directory for our current
@@ -61,12 +61,19 @@
 //!     SystemType::file(unsound::label::new("memory.rs")),
 //! ]);
 //! ```
+pub mod cache;
 pub mod diff;
 pub mod file_system;
+pub mod prelude;
+#[cfg(feature = "mime")]
+pub mod stats;
+pub mod tree;
 pub mod vcs;
 
+#[cfg(any(test, feature = "test-strategies"))]
+pub mod strategies;
+
 // Private modules
 mod nonempty;
-mod tree;
 
 pub use crate::vcs::git;
@@ -0,0 +1,27 @@
+//! The types that are needed for most uses of `radicle-surf`, so that they can be
+//! brought into scope with a single `use`.
+//!
+//! The error types are renamed after the module they come from, so that they can
+//! be used side by side.
+//!
+//! ```
+//! use radicle_surf::prelude::*;
+//! use radicle_surf::file_system::unsound;
+//!
+//! let mut directory = Directory::root();
+//! directory.insert_file(&unsound::path::new("README.md"), File::new(b"# Surf"));
+//!
+//! let readme: Option<File> = directory.find_file(&Path::new(unsound::label::new("README.md")));
+//! assert!(readme.is_some());
+//!
+//! let diff: Result<Diff, DiffError> = Diff::diff(Directory::root(), directory);
+//! assert!(diff.is_ok());
+//! ```
+
+pub use crate::diff::{Diff, DiffError};
+pub use crate::file_system::error::Error as FileSystemError;
+pub use crate::file_system::{Directory, DirectoryContents, File, FileMode, Label, Path};
+pub use crate::vcs::git::error::Error as GitError;
+pub use crate::vcs::git::{
+    BranchName, Browser, Commit, History, Oid, Repository, Sha1, Signature, TagName,
+};
@@ -0,0 +1,333 @@
+//! Statistics over a [`Directory`](../file_system/struct.Directory.html) snapshot,
+//! e.g. the breakdown of a project by language for a language bar.
+//!
+//! Languages are detected by [`File::language`](../file_system/struct.File.html#method.language),
+//! and can be overridden with the [linguist](https://github.com/github/linguist)
+//! attributes of the `.gitattributes` files of the `Directory`, see
+//! [`Attributes`](struct.Attributes.html).
+//!
+//! # Examples
+//!
+//! ```
+//! use radicle_surf::file_system::{Directory, File};
+//! use radicle_surf::file_system::unsound;
+//! use radicle_surf::stats;
+//!
+//! let mut root = Directory::root();
+//! root.insert_file(&unsound::path::new("src/lib.rs"), File::new(b"pub mod vcs;\npub mod diff;\n"));
+//! root.insert_file(&unsound::path::new("build.sh"), File::new(b"cargo build\n"));
+//! root.insert_file(&unsound::path::new("vendor/lib.js"), File::new(b"console.log('surf');\n"));
+//! root.insert_file(&unsound::path::new(".gitattributes"), File::new(b"vendor/** linguist-vendored\n"));
+//!
+//! let languages = stats::languages(&root).unwrap();
+//! let breakdown = languages
+//!     .iter()
+//!     .map(|stats| (stats.language.as_str(), stats.bytes, stats.lines))
+//!     .collect::<Vec<_>>();
+//!
+//! assert_eq!(breakdown, vec![("Rust", 27, 2), ("Shell", 12, 1)]);
+//! assert_eq!(languages[0].percentage.round(), 69.0);
+//! ```
+
+use crate::file_system::error::Error;
+use crate::file_system::{Directory, IgnorePattern, Label, Path};
+use nonempty::NonEmpty;
+use std::collections::HashMap;
+
+/// The size of the files of one language in a `Directory`, see
+/// [`languages`](fn.languages.html).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageStats {
+    /// The name of the language, e.g. `Rust`.
+    pub language: String,
+    /// The number of files in the language.
+    pub files: usize,
+    /// The number of bytes of the files in the language.
+    pub bytes: usize,
+    /// The number of lines of the files in the language.
+    pub lines: usize,
+    /// The share of the bytes of the language in the bytes of all languages, from
+    /// `0.0` to `100.0`.
+    pub percentage: f64,
+}
+
+/// The linguist attributes of a `.gitattributes` file, which override the language
+/// of the files they match, or exclude them from the statistics.
+///
+/// The attributes that are honoured are:
+///   * `linguist-language=<language>`, which sets the language of a file.
+///   * `linguist-vendored`, `linguist-generated`, and `linguist-documentation`,
+///     which exclude a file.
+///   * `linguist-detectable=false` or `-linguist-detectable`, which excludes a file.
+///
+/// As with git, the last line that sets an attribute for a file wins, where the
+/// lines of a `.gitattributes` in a sub-directory come after those of the
+/// directories above it, and an attribute can be unset with a leading `-` or a
+/// value of `false`.
+///
+/// Patterns are matched like those of a `.gitignore`, relative to the directory of
+/// their `.gitattributes`, except that, as in git, they can be quoted, negated
+/// patterns are skipped, and a pattern that matches a directory does not match the
+/// files in it, e.g. `vendor/**` has to be used rather than `vendor/`.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::unsound;
+/// use radicle_surf::stats::Attributes;
+///
+/// let attributes = Attributes::parse(
+///     "*.h linguist-language=C++\n\
+///      docs/** linguist-documentation\n\
+///      docs/api.rs -linguist-documentation\n\
+///      !src/surf.h linguist-language=C\n\
+///      \"src/surf *.h\" linguist-language=C\n",
+/// );
+///
+/// assert_eq!(attributes.language(&unsound::path::new("src/surf.h")), Some("C++"));
+/// assert_eq!(attributes.language(&unsound::path::new("src/surf 2.h")), Some("C"));
+/// assert!(attributes.is_excluded(&unsound::path::new("docs/guide.rs")));
+/// assert!(!attributes.is_excluded(&unsound::path::new("docs/api.rs")));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attributes {
+    /// The lines, along with the labels of the directory of their `.gitattributes`.
+    lines: Vec<(Vec<Label>, IgnorePattern, Vec<Attribute>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Attribute {
+    Language(String),
+    Excluded(bool),
+}
+
+impl Attributes {
+    /// Parse the contents of a `.gitattributes` file at the root of a `Directory`,
+    /// skipping the attributes that are not linguist's.
+    pub fn parse(contents: &str) -> Self {
+        Attributes {
+            lines: parse_lines(&[], contents),
+        }
+    }
+
+    /// Read the `.gitattributes` files of `directory` and its sub-directories.
+    ///
+    /// The contents of lazy `.gitattributes` files are read, so this fails if they
+    /// can't be.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::{Directory, File};
+    /// use radicle_surf::file_system::unsound;
+    /// use radicle_surf::stats::Attributes;
+    ///
+    /// let mut root = Directory::root();
+    /// root.insert_file(&unsound::path::new(".gitattributes"), File::new(b"*.h linguist-language=C\n"));
+    /// root.insert_file(
+    ///     &unsound::path::new("surf/.gitattributes"),
+    ///     File::new(b"*.h linguist-language=C++\n"),
+    /// );
+    ///
+    /// let attributes = Attributes::read(&root).unwrap();
+    /// assert_eq!(attributes.language(&unsound::path::new("surf.h")), Some("C"));
+    /// assert_eq!(attributes.language(&unsound::path::new("surf/surf.h")), Some("C++"));
+    /// ```
+    pub fn read(directory: &Directory) -> Result<Self, Error> {
+        let mut files = directory
+            .files()
+            .filter(|(path, _)| path.file_name().as_bytes() == b".gitattributes")
+            .map(|(path, file)| {
+                let (mut labels, _) = path.split_last();
+                labels.retain(|label| !label.is_root());
+                (labels, file)
+            })
+            .collect::<Vec<_>>();
+        files.sort_by_key(|(labels, _)| labels.len());
+
+        let mut lines = Vec::new();
+        for (labels, file) in files {
+            let contents = String::from_utf8_lossy(file.contents()?);
+            lines.extend(parse_lines(&labels, &contents));
+        }
+        Ok(Attributes { lines })
+    }
+
+    /// Get the language that is set for the file at `path`, if any.
+    pub fn language(&self, path: &Path) -> Option<&str> {
+        self.last(path, |attribute| match attribute {
+            Attribute::Language(language) => Some(language.as_str()),
+            Attribute::Excluded(_) => None,
+        })
+    }
+
+    /// Check whether the file at `path` is excluded from the statistics, e.g.
+    /// because it is vendored.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.last(path, |attribute| match attribute {
+            Attribute::Excluded(excluded) => Some(*excluded),
+            Attribute::Language(_) => None,
+        })
+        .unwrap_or(false)
+    }
+
+    fn last<'a, T, F>(&'a self, path: &Path, select: F) -> Option<T>
+    where
+        F: Fn(&'a Attribute) -> Option<T>,
+    {
+        let labels = path
+            .iter()
+            .filter(|label| !label.is_root())
+            .cloned()
+            .collect::<Vec<_>>();
+        self.lines
+            .iter()
+            .rev()
+            .filter(|(directory, pattern, _)| {
+                labels.starts_with(directory)
+                    && NonEmpty::from_slice(&labels[directory.len()..])
+                        .is_some_and(|relative| pattern.matches(&Path(relative), false))
+            })
+            .find_map(|(_, _, attributes)| attributes.iter().rev().find_map(&select))
+    }
+}
+
+/// Parse the lines of a `.gitattributes` in the directory with the given `labels`,
+/// skipping those with negated patterns, which git forbids.
+fn parse_lines(
+    labels: &[Label],
+    contents: &str,
+) -> Vec<(Vec<Label>, IgnorePattern, Vec<Attribute>)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (pattern, rest) = split_pattern(line)?;
+            if pattern.starts_with('!') {
+                return None;
+            }
+            let attributes = rest
+                .split_whitespace()
+                .filter_map(parse_attribute)
+                .collect::<Vec<_>>();
+            if attributes.is_empty() {
+                None
+            } else {
+                Some((labels.to_vec(), IgnorePattern::parse(&pattern), attributes))
+            }
+        })
+        .collect()
+}
+
+/// Split the pattern off the start of a `.gitattributes` `line`, unquoting it if it
+/// is quoted in the C style, as git does.
+fn split_pattern(line: &str) -> Option<(String, &str)> {
+    let quoted = match line.strip_prefix('"') {
+        Some(quoted) => quoted,
+        None => {
+            let end = line.find(char::is_whitespace).unwrap_or(line.len());
+            return Some((line[..end].to_string(), &line[end..]));
+        }
+    };
+
+    let mut pattern = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Some((pattern, &quoted[index + 1..])),
+            '\\' => pattern.push(match chars.next()?.1 {
+                'n' => '\n',
+                't' => '\t',
+                escaped => escaped,
+            }),
+            c => pattern.push(c),
+        }
+    }
+    None
+}
+
+fn parse_attribute(attribute: &str) -> Option<Attribute> {
+    let (unset, attribute) = match attribute.strip_prefix('-') {
+        Some(attribute) => (true, attribute),
+        None => (false, attribute),
+    };
+    let (name, value) = match attribute.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (attribute, None),
+    };
+    let set = !unset && value != Some("false");
+
+    match name {
+        "linguist-language" => value.map(|language| Attribute::Language(language.to_string())),
+        "linguist-vendored" | "linguist-generated" | "linguist-documentation" => {
+            Some(Attribute::Excluded(set))
+        }
+        "linguist-detectable" => Some(Attribute::Excluded(!set)),
+        _ => None,
+    }
+}
+
+/// Break the files of `directory` down by language, largest first by bytes, using
+/// the `.gitattributes` files of `directory`, see
+/// [`Attributes::read`](struct.Attributes.html#method.read).
+///
+/// Files whose language is not known are left out. The contents of lazy files are
+/// read, since their language and lines depend on them, so this fails if they
+/// can't be.
+pub fn languages(directory: &Directory) -> Result<Vec<LanguageStats>, Error> {
+    languages_with(directory, &Attributes::read(directory)?)
+}
+
+/// Break the files of `directory` down by language, like
+/// [`languages`](fn.languages.html), using the given `attributes`.
+pub fn languages_with(
+    directory: &Directory,
+    attributes: &Attributes,
+) -> Result<Vec<LanguageStats>, Error> {
+    let mut by_language: HashMap<String, LanguageStats> = HashMap::new();
+    for (path, file) in directory.files() {
+        if attributes.is_excluded(&path) {
+            continue;
+        }
+        let (_, name) = path.split_last();
+        let language = match attributes.language(&path) {
+            Some(language) => language.to_string(),
+            None => match file.language(&name)? {
+                Some(language) => language.to_string(),
+                None => continue,
+            },
+        };
+
+        let stats = by_language
+            .entry(language.clone())
+            .or_insert_with(|| LanguageStats {
+                language,
+                files: 0,
+                bytes: 0,
+                lines: 0,
+                percentage: 0.0,
+            });
+        stats.files += 1;
+        stats.bytes += file.size();
+        stats.lines += file.line_count()?.unwrap_or(0);
+    }
+
+    let total = by_language.values().map(|stats| stats.bytes).sum::<usize>();
+    let mut languages = by_language
+        .into_values()
+        .map(|mut stats| {
+            if total > 0 {
+                stats.percentage = stats.bytes as f64 * 100.0 / total as f64;
+            }
+            stats
+        })
+        .collect::<Vec<_>>();
+    languages.sort_by(|left, right| {
+        right
+            .bytes
+            .cmp(&left.bytes)
+            .then_with(|| left.language.cmp(&right.language))
+    });
+    Ok(languages)
+}
@@ -0,0 +1,274 @@
+//! [`proptest`](https://docs.rs/proptest) strategies for generating `radicle-surf` types,
+//! available when the `test-strategies` feature is enabled, so that code consuming
+//! `radicle-surf` types can be property tested.
+//!
+//! There are strategies for [`Label`](../file_system/struct.Label.html)s, [`Path`](../file_system/struct.Path.html)s,
+//! [`File`](../file_system/struct.File.html)s and maps of directory paths to their files,
+//! e.g. [`path_strategy`](fn.path_strategy.html).
+//!
+//! On top of that, [`Directory`](../file_system/struct.Directory.html) and [`Diff`](../diff/struct.Diff.html)
+//! implement [`Arbitrary`](https://docs.rs/proptest/0.9/proptest/arbitrary/trait.Arbitrary.html),
+//! taking [`DirectoryParameters`](struct.DirectoryParameters.html) to control how large
+//! the generated values are.
+//!
+//! Values can also be generated outside of `proptest!`, reproducibly, using
+//! [`generate`](fn.generate.html) with a fixed seed.
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use radicle_surf::diff::Diff;
+//! use radicle_surf::file_system::Directory;
+//! use radicle_surf::strategies::DirectoryParameters;
+//!
+//! proptest!(|(directory in any::<Directory>())| {
+//!     let diff = Diff::diff(directory.clone(), directory).unwrap();
+//!     prop_assert!(diff.created.is_empty() && diff.deleted.is_empty());
+//! });
+//!
+//! let small = DirectoryParameters { max_depth: 2, max_files: 4 };
+//! proptest!(|(diff in any_with::<Diff>(small))| {
+//!     prop_assert!(diff.created.len() <= 4);
+//! });
+//! ```
+
+use crate::diff::Diff;
+use crate::file_system::unsound;
+use crate::file_system::{Directory, File, Label, Path};
+use nonempty::NonEmpty;
+use proptest::collection;
+use proptest::prelude::*;
+use proptest::strategy::ValueTree;
+use proptest::test_runner::{Config, RngAlgorithm, TestRng, TestRunner};
+use std::collections::{BTreeMap, HashMap};
+
+/// Parameters for controlling the size of generated [`Directory`](../file_system/struct.Directory.html)s
+/// and [`Diff`](../diff/struct.Diff.html)s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirectoryParameters {
+    /// The maximum number of [`Label`](../file_system/struct.Label.html)s in a file's path.
+    pub max_depth: usize,
+    /// The maximum number of files in a generated `Directory`.
+    pub max_files: usize,
+}
+
+impl Default for DirectoryParameters {
+    fn default() -> Self {
+        DirectoryParameters {
+            max_depth: 4,
+            max_files: 16,
+        }
+    }
+}
+
+/// A strategy for generating a [`Label`](../file_system/struct.Label.html) made up of
+/// printable ASCII characters, excluding `/`.
+///
+/// # Examples
+///
+/// ```
+/// use proptest::prelude::*;
+/// use radicle_surf::strategies::label_strategy;
+///
+/// proptest!(|(label in label_strategy())| {
+///     prop_assert!(!label.to_string().contains('/'));
+/// });
+/// ```
+pub fn label_strategy() -> impl Strategy<Value = Label> {
+    // ASCII regex, excluding '/' because of posix file paths
+    "[ -.|0-~]+".prop_map(|label| unsound::label::new(&label))
+}
+
+/// A strategy for generating a [`Path`](../file_system/struct.Path.html) of at
+/// most `max_depth` [`label_strategy`](fn.label_strategy.html) `Label`s.
+///
+/// # Examples
+///
+/// ```
+/// use proptest::prelude::*;
+/// use radicle_surf::strategies::path_strategy;
+///
+/// proptest!(|(path in path_strategy(3))| {
+///     prop_assert!(path.iter().count() <= 3);
+/// });
+/// ```
+pub fn path_strategy(max_depth: usize) -> impl Strategy<Value = Path> {
+    path_strategy_from(label_strategy, max_depth)
+}
+
+/// A strategy for generating a [`File`](../file_system/struct.File.html) with
+/// printable ASCII contents.
+pub fn file_strategy() -> impl Strategy<Value = File> {
+    // ASCII regex, see: https://catonmat.net/my-favorite-regex
+    "[ -~]*".prop_map(|contents| File::new(contents.as_bytes()))
+}
+
+/// A strategy for generating a map of at most `map_size` directory `Path`s,
+/// of at most `path_size` `Label`s, to fewer than `n_files` named `File`s in
+/// that directory.
+///
+/// # Examples
+///
+/// ```
+/// use proptest::prelude::*;
+/// use radicle_surf::strategies::directory_map_strategy;
+///
+/// proptest!(|(directory_map in directory_map_strategy(3, 4, 5))| {
+///     prop_assert!(directory_map.len() < 5);
+///     prop_assert!(directory_map.values().all(|files| files.len() < 4));
+/// });
+/// ```
+pub fn directory_map_strategy(
+    path_size: usize,
+    n_files: usize,
+    map_size: usize,
+) -> impl Strategy<Value = HashMap<Path, NonEmpty<(Label, File)>>> {
+    collection::hash_map(
+        path_strategy(path_size),
+        collection::vec((label_strategy(), file_strategy()), 1..n_files).prop_map(|files| {
+            NonEmpty::from_slice(&files).expect("Strategy generated files of length 0")
+        }),
+        0..map_size,
+    )
+}
+
+/// A [`TestRunner`](https://docs.rs/proptest/0.9/proptest/test_runner/struct.TestRunner.html)
+/// whose random number generator is seeded with `seed`, so that the values it
+/// generates are the same on every run and on every machine.
+pub fn seeded_runner(seed: u64) -> TestRunner {
+    let mut bytes = [0; 32];
+    for chunk in bytes.chunks_mut(8) {
+        chunk.copy_from_slice(&seed.to_le_bytes());
+    }
+    TestRunner::new_with_rng(
+        Config::default(),
+        TestRng::from_seed(RngAlgorithm::ChaCha, &bytes),
+    )
+}
+
+/// Generate a single value from `strategy` using a runner seeded with `seed`.
+///
+/// The same `strategy` and `seed` always produce the same value, which makes
+/// it suitable for fixtures that need to be shared between tests or replicas.
+///
+/// # Examples
+///
+/// ```
+/// use proptest::prelude::*;
+/// use radicle_surf::file_system::Directory;
+/// use radicle_surf::strategies::generate;
+///
+/// let first = generate(any::<Directory>(), 42);
+/// let second = generate(any::<Directory>(), 42);
+/// assert_eq!(first, second);
+/// ```
+pub fn generate<S: Strategy>(strategy: S, seed: u64) -> S::Value {
+    strategy
+        .new_tree(&mut seeded_runner(seed))
+        .expect("failed to generate a value from a seeded strategy")
+        .current()
+}
+
+fn path_strategy_from<S>(labels: fn() -> S, max_depth: usize) -> impl Strategy<Value = Path>
+where
+    S: Strategy<Value = Label>,
+{
+    (labels(), collection::vec(labels(), 0..max_depth.max(1)))
+        .prop_map(|(label, labels)| Path((label, labels).into()))
+}
+
+fn small_label_strategy() -> impl Strategy<Value = Label> {
+    // A small alphabet so that generated paths share directories.
+    "[a-e][a-e0-9_.-]{0,4}".prop_map(|label| unsound::label::new(&label))
+}
+
+fn files_strategy(parameters: DirectoryParameters) -> impl Strategy<Value = BTreeMap<Path, File>> {
+    collection::btree_map(
+        path_strategy_from(small_label_strategy, parameters.max_depth),
+        file_strategy(),
+        0..=parameters.max_files,
+    )
+    .prop_map(without_conflicts)
+}
+
+/// Remove any file whose path is also the prefix of another file's path,
+/// since it cannot be both a file and a directory.
+fn without_conflicts(files: BTreeMap<Path, File>) -> BTreeMap<Path, File> {
+    let paths = files.keys().cloned().collect::<Vec<_>>();
+    let mut files = files;
+    // Paths are sorted, so if a path is the prefix of any other
+    // path it will be the prefix of the one that follows it.
+    for window in paths.windows(2) {
+        let (path, next) = (&window[0], &window[1]);
+        if next.0.len() > path.0.len() && path.iter().zip(next.iter()).all(|(l, r)| l == r) {
+            files.remove(path);
+        }
+    }
+    files
+}
+
+fn to_directory(files: BTreeMap<Path, File>) -> Directory {
+    let mut directory = Directory::root();
+    for (path, file) in files {
+        directory.insert_file(&path, file);
+    }
+    directory
+}
+
+#[derive(Debug, Clone)]
+enum Edit {
+    Keep,
+    Modify(File),
+    Delete,
+}
+
+fn edit_strategy() -> impl Strategy<Value = Edit> {
+    prop_oneof![
+        Just(Edit::Keep),
+        file_strategy().prop_map(Edit::Modify),
+        Just(Edit::Delete),
+    ]
+}
+
+impl Arbitrary for Directory {
+    type Parameters = DirectoryParameters;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(parameters: Self::Parameters) -> Self::Strategy {
+        files_strategy(parameters).prop_map(to_directory).boxed()
+    }
+}
+
+impl Arbitrary for Diff {
+    type Parameters = DirectoryParameters;
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Generates a `Diff` between a `Directory` and an edited version of it, where the
+    /// edits keep, modify or delete the existing files and create new ones.
+    fn arbitrary_with(parameters: Self::Parameters) -> Self::Strategy {
+        files_strategy(parameters)
+            .prop_flat_map(move |files| {
+                let edits = collection::vec(edit_strategy(), files.len());
+                let created = files_strategy(parameters);
+                (Just(files), edits, created)
+            })
+            .prop_map(|(files, edits, created)| {
+                let mut edited = BTreeMap::new();
+                for ((path, file), edit) in files.iter().zip(edits) {
+                    match edit {
+                        Edit::Keep => {
+                            edited.insert(path.clone(), file.clone());
+                        }
+                        Edit::Modify(file) => {
+                            edited.insert(path.clone(), file);
+                        }
+                        Edit::Delete => {}
+                    }
+                }
+                edited.extend(created);
+
+                (to_directory(files), to_directory(without_conflicts(edited)))
+            })
+            .prop_filter_map("Diff failed", |(left, right)| Diff::diff(left, right).ok())
+            .boxed()
+    }
+}
@@ -1,11 +1,114 @@
-use crate::nonempty::split_last;
-use nonempty::NonEmpty;
+//! A generic tree keyed by paths, the structure underneath
+//! [`Directory`](../file_system/struct.Directory.html).
+//!
+//! A [`Forest`] is a possibly empty set of sub-trees. Each [`SubTree`] is either a
+//! `Node`, which holds a value, or a `Branch`, which holds a non-empty [`Tree`] of
+//! further sub-trees. The sub-trees at every level are kept ordered by their keys,
+//! and a value is addressed by the keys of the branches it is in followed by its
+//! own key, e.g. `["src", "lib.rs"]`.
+//!
+//! # Examples
+//!
+//! ```
+//! use radicle_surf::tree::{Forest, NonEmpty, SubTree};
+//!
+//! let keys = |keys: &[&str]| {
+//!     NonEmpty::from_slice(&keys.iter().map(|key| key.to_string()).collect::<Vec<_>>()).unwrap()
+//! };
+//!
+//! let mut config = Forest::root();
+//! config.insert(&keys(&["server", "port"]), 8080);
+//! config.insert(&keys(&["server", "host"]), 80);
+//! config.insert(&keys(&["workers"]), 4);
+//!
+//! assert_eq!(config.find_node(&keys(&["server", "port"])), Some(&8080));
+//! assert!(matches!(config.find(&keys(&["server"])), Some(SubTree::Branch { .. })));
+//! assert_eq!(config.iter().copied().collect::<Vec<_>>(), vec![80, 8080, 4]);
+//!
+//! config.remove(&keys(&["server"]));
+//! assert_eq!(config.iter_keys().collect::<Vec<_>>(), vec!["workers"]);
+//! ```
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::mem;
 
+pub use nonempty::NonEmpty;
+
+/// An entry of a [`Tree`], either a `Node` with a value or a `Branch` with more
+/// entries.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum SubTree<K, A> {
-    Node { key: K, value: A },
-    Branch { key: K, forest: Box<Tree<K, A>> },
+    /// A leaf of the tree, holding a `value`.
+    Node {
+        /// The key of the node within its branch.
+        key: K,
+        /// The value of the node.
+        value: A,
+    },
+    /// A branch of the tree, holding further entries.
+    Branch {
+        /// The key of the branch within its parent branch.
+        key: K,
+        /// The entries of the branch.
+        forest: Box<Tree<K, A>>,
+    },
+}
+
+/// What happens when merging two trees that both have an entry at the same
+/// place, and at least one of them is a leaf, e.g. a file when merging two
+/// `Directory`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep the whole entry of the left tree, without merging branches.
+    KeepLeft,
+    /// Keep the whole entry of the right tree, without merging branches.
+    KeepRight,
+    /// Merge branches, and keep the leaf or branch of the right tree when there
+    /// is a leaf in either, like copying one directory over another.
+    Combine,
+    /// Merge branches, and fail when there is a leaf in either, unless both have
+    /// the same leaf.
+    Error,
+}
+
+/// The keys at which `Forest::merge` found a conflict with [`MergePolicy::Error`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict<K> {
+    /// The keys of the conflicting entries.
+    pub keys: NonEmpty<K>,
+}
+
+/// The keys at which two `Forest`s differ, see [`Forest::diff`].
+///
+/// Each list of keys is sorted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForestDiff<K> {
+    /// The keys of the branches and nodes that are only in the other `Forest`.
+    pub added: Vec<NonEmpty<K>>,
+    /// The keys of the branches and nodes that are only in this `Forest`.
+    pub removed: Vec<NonEmpty<K>>,
+    /// The keys of the nodes that are in both `Forest`s, but whose values differ.
+    pub changed: Vec<NonEmpty<K>>,
+}
+
+impl<K> Default for ForestDiff<K> {
+    fn default() -> Self {
+        ForestDiff {
+            added: vec![],
+            removed: vec![],
+            changed: vec![],
+        }
+    }
+}
+
+impl<K> ForestDiff<K> {
+    /// Check whether the `Forest`s are the same.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
 }
 
 impl<K, A> SubTree<K, A> {
@@ -20,13 +123,22 @@ impl<K, A> SubTree<K, A> {
         }
     }
 
-    fn key(&self) -> &K {
+    /// Get the number of keys on the longest path to a node, counting the key of
+    /// this `SubTree`.
+    pub fn depth(&self) -> usize {
+        Size::of(self).depth
+    }
+
+    /// Get the key of the `SubTree`.
+    pub fn key(&self) -> &K {
         match self {
             SubTree::Node { key, .. } => key,
             SubTree::Branch { key, .. } => key,
         }
     }
 
+    /// Find the `SubTree` at `keys`, where the first key is the key of this
+    /// `SubTree`.
     pub fn find(&self, keys: &NonEmpty<K>) -> Option<&Self>
     where
         K: Ord + Clone,
@@ -46,14 +158,16 @@ impl<K, A> SubTree<K, A> {
         }
     }
 
-    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &A> + 'a> {
+    /// Iterate over the values of the `SubTree`, in the order of their keys.
+    pub fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &A> + 'a> {
         match self {
             SubTree::Node { value, .. } => Box::new(std::iter::once(value)),
             SubTree::Branch { ref forest, .. } => Box::new(forest.iter()),
         }
     }
 
-    fn iter_keys<'a>(&'a self) -> Box<dyn Iterator<Item = &K> + 'a> {
+    /// Iterate over the keys of the `SubTree`, including its own, depth-first.
+    pub fn iter_keys<'a>(&'a self) -> Box<dyn Iterator<Item = &K> + 'a> {
         match self {
             SubTree::Node { key, .. } => Box::new(std::iter::once(key)),
             SubTree::Branch {
@@ -96,6 +210,7 @@ impl<K, A> SubTree<K, A> {
         }
     }
 
+    /// Get the greatest value of the `SubTree`, compared by `f`.
     pub fn maximum_by<F>(&self, f: &F) -> &A
     where
         F: Fn(&A, &A) -> Ordering,
@@ -106,10 +221,12 @@ impl<K, A> SubTree<K, A> {
         }
     }
 
-    pub fn map<F, B>(&self, f: F) -> SubTree<K, B>
+    /// Create a `SubTree` with the same keys, whose values are `f` of the values
+    /// of this one.
+    pub fn map<F, B>(&self, mut f: F) -> SubTree<K, B>
     where
-        K: Clone,
-        F: Fn(&A) -> B,
+        K: Ord + Clone,
+        F: FnMut(&A) -> B,
     {
         match self {
             SubTree::Node { key, value } => SubTree::Node {
@@ -118,14 +235,150 @@ impl<K, A> SubTree<K, A> {
             },
             SubTree::Branch { key, ref forest } => SubTree::Branch {
                 key: key.clone(),
-                forest: Box::new(forest.map(&f)),
+                forest: Box::new(forest.map(f)),
             },
         }
     }
 }
 
+/// What [`Forest::visit`] does after calling a [`TreeVisitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visit {
+    /// Carry on with the next branch or node.
+    Continue,
+    /// From [`TreeVisitor::enter_branch`], skip the sub-trees of the branch, without
+    /// leaving it. From [`TreeVisitor::visit_node`] or [`TreeVisitor::leave_branch`],
+    /// skip the rest of the sub-trees of the branch they are in, which is then left
+    /// as usual.
+    Skip,
+    /// Stop visiting straight away.
+    Stop,
+}
+
+/// Callbacks for the branches and nodes of a [`Forest`], called by [`Forest::visit`]
+/// in the order of their keys, depth-first.
+///
+/// The callbacks are given the keys of the branches that the branch or node is in,
+/// and return how the visit carries on, see [`Visit`]. Each has a default that
+/// does nothing and continues, so a visitor only implements those that it is
+/// interested in.
+pub trait TreeVisitor<K, A> {
+    /// Called before the sub-trees of the branch `key` are visited.
+    fn enter_branch(&mut self, _keys: &[K], _key: &K) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called after the sub-trees of the branch `key` are visited.
+    fn leave_branch(&mut self, _keys: &[K], _key: &K) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for the node `key`.
+    fn visit_node(&mut self, _keys: &[K], _key: &K, _value: &A) -> Visit {
+        Visit::Continue
+    }
+}
+
+/// A non-empty map of [`SubTree`]s, ordered by their keys.
+///
+/// Each level of the tree is a `BTreeMap`, so finding, inserting, and removing an
+/// entry takes logarithmic time in the number of its siblings.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Tree<K, A>(pub(crate) NonEmpty<SubTree<K, A>>);
+pub struct Tree<K, A>(pub(crate) BTreeMap<K, SubTree<K, A>>, Size);
+
+/// The size of a [`Tree`], which is kept up to date as the `Tree` changes, so that
+/// it is never walked to be measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Size {
+    /// The number of sub-trees at any depth, branches and nodes alike.
+    sub_trees: usize,
+    /// The number of nodes at any depth.
+    nodes: usize,
+    /// The number of keys on the longest path.
+    depth: usize,
+    /// The number of top-level sub-trees on a longest path.
+    deepest: usize,
+}
+
+impl Size {
+    /// The size of a `Tree` whose only sub-tree is `sub_tree`.
+    fn of<K, A>(sub_tree: &SubTree<K, A>) -> Self {
+        match sub_tree {
+            SubTree::Node { .. } => Size {
+                sub_trees: 1,
+                nodes: 1,
+                depth: 1,
+                deepest: 1,
+            },
+            SubTree::Branch { forest, .. } => Size::branch(forest.1),
+        }
+    }
+
+    /// The size of a `Tree` whose only sub-tree is a branch of size `forest`.
+    fn branch(forest: Size) -> Self {
+        Size {
+            sub_trees: forest.sub_trees + 1,
+            nodes: forest.nodes,
+            depth: forest.depth + 1,
+            deepest: 1,
+        }
+    }
+
+    /// Count a top-level sub-tree of depth `depth` towards the depth.
+    fn deepen(&mut self, depth: usize) {
+        if depth > self.depth {
+            self.depth = depth;
+            self.deepest = 1;
+        } else if depth > 0 && depth == self.depth {
+            self.deepest += 1;
+        }
+    }
+
+    /// Replace a top-level sub-tree of size `old` by one of size `new`, where
+    /// `None` is no sub-tree. The sizes of the `others` top-level sub-trees are only
+    /// looked at when the last of the deepest sub-trees gets shallower.
+    fn replace<F, I>(&mut self, old: Option<Size>, new: Option<Size>, others: F)
+    where
+        F: FnOnce() -> I,
+        I: Iterator<Item = Size>,
+    {
+        let old = old.unwrap_or_default();
+        let new = new.unwrap_or_default();
+        self.sub_trees = self.sub_trees - old.sub_trees + new.sub_trees;
+        self.nodes = self.nodes - old.nodes + new.nodes;
+
+        if old.depth == 0 || old.depth < self.depth {
+            self.deepen(new.depth);
+        } else if new.depth > old.depth {
+            self.depth = new.depth;
+            self.deepest = 1;
+        } else if new.depth < old.depth {
+            self.deepest -= 1;
+            if self.deepest == 0 {
+                self.depth = 0;
+                for other in others() {
+                    self.deepen(other.depth);
+                }
+                self.deepen(new.depth);
+            }
+        }
+    }
+}
+
+/// Dropping is done iteratively, since the default recursive drop of a deep
+/// `Tree` overflows the stack.
+impl<K, A> Drop for Tree<K, A> {
+    fn drop(&mut self) {
+        let mut trees = vec![mem::take(&mut self.0)];
+        while let Some(tree) = trees.pop() {
+            for sub_tree in tree.into_values() {
+                if let SubTree::Branch { mut forest, .. } = sub_tree {
+                    trees.push(mem::take(&mut forest.0));
+                }
+            }
+        }
+    }
+}
 
 impl<K, A> From<Tree<K, A>> for Forest<K, A> {
     fn from(tree: Tree<K, A>) -> Self {
@@ -133,81 +386,232 @@ impl<K, A> From<Tree<K, A>> for Forest<K, A> {
     }
 }
 
+/// Create a `Tree` from sub-trees, where a later sub-tree replaces an earlier one
+/// with the same key.
+impl<K: Ord + Clone, A> From<NonEmpty<SubTree<K, A>>> for Tree<K, A> {
+    fn from(sub_trees: NonEmpty<SubTree<K, A>>) -> Self {
+        let sub_trees: Vec<_> = sub_trees.into();
+        Tree::from_sub_trees(
+            sub_trees
+                .into_iter()
+                .map(|sub_tree| (sub_tree.key().clone(), sub_tree))
+                .collect(),
+        )
+    }
+}
+
+/// A possibly empty [`Tree`], which is the usual entry point for building and
+/// searching trees.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Forest<K, A>(pub(crate) Option<Tree<K, A>>);
 
+impl<K, A> Default for Forest<K, A> {
+    fn default() -> Self {
+        Forest::root()
+    }
+}
+
 impl<K, A> Tree<K, A> {
+    /// Create a `Tree` from its top-level sub-trees, keyed by their keys.
+    fn from_sub_trees(sub_trees: BTreeMap<K, SubTree<K, A>>) -> Self {
+        let mut size = Size::default();
+        for sub_tree in sub_trees.values() {
+            size.replace(None, Some(Size::of(sub_tree)), std::iter::empty);
+        }
+        Tree(sub_trees, size)
+    }
+
     /// Create a new `Tree` containing a single `Branch` given
     /// the key and sub-tree.
-    fn branch(key: K, forest: Self) -> Self {
-        Tree(NonEmpty::new(SubTree::branch(key, forest)))
+    fn branch(key: K, forest: Self) -> Self
+    where
+        K: Ord + Clone,
+    {
+        Tree::from(NonEmpty::new(SubTree::branch(key, forest)))
     }
 
     /// Create a new `Tree` containing a single `Node`.
-    fn node(key: K, value: A) -> Self {
-        Tree(NonEmpty::new(SubTree::Node { key, value }))
+    fn node(key: K, value: A) -> Self
+    where
+        K: Ord + Clone,
+    {
+        Tree::from(NonEmpty::new(SubTree::Node { key, value }))
     }
 
     /// Create a new `Tree` that creates a series of
     /// `Branch`es built using the `keys`. The final `Branch`
     /// will contain the `node`.
-    fn new(keys: &NonEmpty<K>, node: A) -> Self
+    pub fn new(keys: &NonEmpty<K>, node: A) -> Self
     where
         K: Ord + Clone,
     {
         let (start, middle, last) = keys.split();
 
-        if start == last && middle.is_empty() {
+        if keys.len() == 1 {
             Tree::node(last.clone(), node)
         } else {
             let mut branch = Tree::node(last.clone(), node);
 
             for key in middle.iter().rev() {
-                branch = Tree(NonEmpty::new(SubTree::branch(key.clone(), branch)))
+                branch = Tree::branch(key.clone(), branch)
             }
 
             Tree::branch(start.clone(), branch)
         }
     }
 
-    /// Perform a binary search in the sub-trees, based on comparing
-    /// each of the sub-trees' key to the provided `key`.
-    fn search(&self, key: &K) -> Result<usize, usize>
+    /// Take the top-level sub-trees out of the `Tree`, keyed by their keys.
+    pub(crate) fn into_sub_trees(mut self) -> BTreeMap<K, SubTree<K, A>> {
+        mem::take(&mut self.0)
+    }
+
+    /// Get the number of top-level sub-trees of the `Tree`.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check whether the `Tree` has no sub-trees, which is never the case, since a
+    /// `Tree` is non-empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Create a `Tree` with the same keys, whose values are `f` of the values of
+    /// this one.
+    pub fn map<F, B>(&self, mut f: F) -> Tree<K, B>
+    where
+        K: Ord + Clone,
+        F: FnMut(&A) -> B,
+    {
+        // The sub-trees left to map in each branch being mapped, along with the key
+        // of the branch, its size, and its sub-trees mapped so far.
+        let mut stack = vec![(None, self.0.iter(), self.1, BTreeMap::new())];
+        loop {
+            let (_, sub_trees, _, mapped) = stack.last_mut().expect("the root is popped last");
+            match sub_trees.next() {
+                Some((key, SubTree::Node { value, .. })) => {
+                    mapped.insert(
+                        key.clone(),
+                        SubTree::Node {
+                            key: key.clone(),
+                            value: f(value),
+                        },
+                    );
+                }
+                Some((key, SubTree::Branch { forest, .. })) => {
+                    stack.push((Some(key), forest.0.iter(), forest.1, BTreeMap::new()))
+                }
+                None => {
+                    let (key, _, size, mapped) = stack.pop().expect("the root is popped last");
+                    let tree = Tree(mapped, size);
+                    match (key, stack.last_mut()) {
+                        (Some(key), Some((_, _, _, parent))) => {
+                            parent.insert(key.clone(), SubTree::branch(key.clone(), tree));
+                        }
+                        _ => return tree,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Take the top-level sub-tree `key` out of the `Tree`, which can leave it
+    /// empty.
+    fn take(&mut self, key: &K) -> Option<SubTree<K, A>>
     where
         K: Ord,
     {
-        self.0.binary_search_by(|tree| tree.key().cmp(key))
+        let sub_tree = self.0.remove(key)?;
+        let Tree(sub_trees, size) = self;
+        size.replace(Some(Size::of(&sub_tree)), None, || {
+            sub_trees.values().map(Size::of)
+        });
+        Some(sub_tree)
     }
 
-    pub fn map<F, B>(&self, f: &F) -> Tree<K, B>
+    /// Put `sub_tree` into the top-level sub-trees, replacing the one with the
+    /// same key, if there is one.
+    fn put(&mut self, sub_tree: SubTree<K, A>)
     where
-        K: Clone,
-        F: Fn(&A) -> B,
+        K: Ord + Clone,
     {
-        Tree(self.0.map(|tree| tree.map(f)))
+        let key = sub_tree.key().clone();
+        let new = Size::of(&sub_tree);
+        let old = self.0.insert(key.clone(), sub_tree);
+        let Tree(sub_trees, size) = self;
+        size.replace(old.as_ref().map(Size::of), Some(new), || {
+            sub_trees
+                .iter()
+                .filter(move |(other, _)| **other != key)
+                .map(|(_, sub_tree)| Size::of(sub_tree))
+        });
     }
 
-    /// Insert a `node` into the list of sub-trees.
-    ///
-    /// The node's position will be based on the `Ord` instance
-    /// of `K`.
-    fn insert_node_with<F>(&mut self, key: K, value: A, f: F)
+    /// Get the `Tree` and the branches below it along `keys`, stopping at the last
+    /// key, or at the first key that isn't a branch.
+    fn path<'a>(&'a self, keys: &[&K]) -> Vec<&'a Self>
     where
-        F: FnOnce(&mut A),
         K: Ord,
     {
-        let result = self.search(&key);
-
-        match result {
-            Ok(index) => {
-                let old_node = self.0.get_mut(index).unwrap();
-                match old_node {
-                    SubTree::Node { value: old, .. } => f(old),
-                    SubTree::Branch { .. } => *old_node = SubTree::Node { key, value },
+        let mut tree = self;
+        let mut path = vec![tree];
+        for key in &keys[..keys.len() - 1] {
+            match tree.0.get(*key) {
+                Some(SubTree::Branch { forest, .. }) => {
+                    tree = forest;
+                    path.push(tree);
                 }
+                _ => break,
             }
-            Err(index) => self.0.insert(index, SubTree::Node { key, value }),
         }
+        path
+    }
+
+    /// Get the sizes that the `Tree`s along `path`, as given by [`Tree::path`], have
+    /// once the sub-tree at `keys` is replaced by one of size `new`, or is removed
+    /// if `new` is `None`.
+    fn resize(path: &[&Self], keys: &[&K], new: Option<Size>) -> Vec<Size>
+    where
+        K: Ord,
+    {
+        let mut sizes = vec![Size::default(); path.len()];
+        let mut new = new;
+        for (index, (tree, key)) in path.iter().zip(keys).enumerate().rev() {
+            let mut size = tree.1;
+            size.replace(tree.0.get(*key).map(Size::of), new, || {
+                tree.0
+                    .iter()
+                    .filter(move |(other, _)| other != key)
+                    .map(|(_, sub_tree)| Size::of(sub_tree))
+            });
+            sizes[index] = size;
+            // A branch that is left empty is removed.
+            new = if size.sub_trees == 0 {
+                None
+            } else {
+                Some(Size::branch(size))
+            };
+        }
+        sizes
+    }
+
+    /// Descend along the branches at `keys`, setting the size of each `Tree` on
+    /// the way to the one in `sizes`, as given by [`Tree::resize`], and get the
+    /// last one.
+    fn resize_mut(&mut self, keys: &[&K], sizes: &[Size]) -> &mut Self
+    where
+        K: Ord,
+    {
+        let mut tree = self;
+        for (key, size) in keys.iter().zip(sizes) {
+            tree.1 = *size;
+            tree = match tree.0.get_mut(*key) {
+                Some(SubTree::Branch { forest, .. }) => forest,
+                _ => panic!("the keys of a resized path are branches"),
+            };
+        }
+        tree.1 = sizes[keys.len()];
+        tree
     }
 
     /// Insert the `node` in the position given by `keys`.
@@ -224,65 +628,54 @@ impl<K, A> Tree<K, A> {
     /// then the node `c` will be replaced by a branch `c/d`.
     ///
     /// If the path does not exist it will be inserted into the set of sub-trees.
+    ///
+    /// The tree is descended iteratively, so that deep paths don't overflow the stack:
+    /// once to find where the node goes and what the sizes of the branches along the
+    /// way become, and once more to make the changes.
     fn insert_with<F>(&mut self, keys: &NonEmpty<K>, value: A, f: F)
     where
         F: FnOnce(&mut A),
         K: Ord + Clone,
     {
-        let (head, tail) = keys.split_first();
-        let maybe_keys = NonEmpty::from_slice(tail);
-        match self.search(head) {
-            // Found the label in our set of sub-trees
-            Ok(index) => match maybe_keys {
-                // The keys have been exhausted and so its time to insert the node
-                None => {
-                    let sub_tree = self.0.get_mut(index).unwrap();
-                    match sub_tree {
-                        // Our sub-tree was a node.
-                        SubTree::Node { key, value } => {
-                            std::mem::replace(key, head.clone());
-                            f(value);
-                        }
-                        SubTree::Branch { .. } => {
-                            *sub_tree = SubTree::Node {
-                                key: head.clone(),
-                                value,
-                            }
-                        }
-                    }
+        let keys = keys.iter().collect::<Vec<_>>();
+        let path = self.path(&keys);
+        let last = path.len() - 1;
+        let (key, rest) = (keys[last], &keys[last + 1..]);
+
+        let sub_tree = match (rest.split_first(), path[last].0.get(key)) {
+            // The keys have been exhausted and there is a node to update.
+            (None, Some(SubTree::Node { .. })) => {
+                let sizes = path.iter().map(|tree| tree.1).collect::<Vec<_>>();
+                if let Some(SubTree::Node { value, .. }) =
+                    self.resize_mut(&keys[..last], &sizes).0.get_mut(key)
+                {
+                    f(value)
                 }
-                Some(ref keys) => {
-                    let sub_tree = self.0.get_mut(index).unwrap();
-                    match sub_tree {
-                        // We have reached a node, but still have keys left to get through.
-                        SubTree::Node { .. } => {
-                            let new_tree = SubTree::branch(head.clone(), Tree::new(keys, value));
-                            *sub_tree = new_tree
-                        }
-                        // We keep moving down the set of keys to find where to insert this node.
-                        SubTree::Branch { forest, .. } => forest.insert_with(keys, value, f),
-                    }
-                }
-            },
-            // The label was not found and we have an index for insertion.
-            Err(index) => match maybe_keys {
-                // We create the branch with the head label and node, since there are
-                // no more labels left.
-                None => self.0.insert(
-                    index,
-                    SubTree::Node {
-                        key: head.clone(),
-                        value,
-                    },
-                ),
-                // We insert an entirely new branch with the full list of keys.
-                Some(ref tail) => self
-                    .0
-                    .insert(index, SubTree::branch(head.clone(), Tree::new(tail, value))),
+                return;
+            }
+            // The keys have been exhausted and so its time to insert the node.
+            (None, _) => SubTree::Node {
+                key: key.clone(),
+                value,
             },
-        }
+            // We have reached a node, or nothing, but still have keys left to get
+            // through, so we insert an entirely new branch with the rest of the keys.
+            (Some((next, rest)), _) => {
+                let rest = NonEmpty::from((
+                    (*next).clone(),
+                    rest.iter().map(|key| (*key).clone()).collect(),
+                ));
+                SubTree::branch(key.clone(), Tree::new(&rest, value))
+            }
+        };
+
+        let sizes = Tree::resize(&path, &keys, Some(Size::of(&sub_tree)));
+        self.resize_mut(&keys[..last], &sizes)
+            .0
+            .insert(key.clone(), sub_tree);
     }
 
+    /// Insert the `value` in the position given by `keys`, see [`Forest::insert`].
     pub fn insert(&mut self, keys: &NonEmpty<K>, value: A)
     where
         A: Clone,
@@ -291,18 +684,103 @@ impl<K, A> Tree<K, A> {
         self.insert_with(keys, value.clone(), |old| *old = value)
     }
 
+    /// Remove the sub-tree in the position given by `keys`, returning it, if there
+    /// was one.
+    ///
+    /// Any branches that are left empty by the removal are removed as well, which
+    /// can leave the `Tree` itself empty, so the caller has to check for that.
+    fn remove(&mut self, keys: &NonEmpty<K>) -> Option<SubTree<K, A>>
+    where
+        K: Ord + Clone,
+    {
+        let keys = keys.iter().collect::<Vec<_>>();
+        let path = self.path(&keys);
+        let last = keys.len() - 1;
+        if path.len() != keys.len() || !path[last].0.contains_key(keys[last]) {
+            return None;
+        }
+
+        // The sub-tree goes along with the highest branch that it leaves empty.
+        let sizes = Tree::resize(&path, &keys, None);
+        let cut = sizes
+            .iter()
+            .position(|size| size.sub_trees == 0)
+            .map_or(last, |emptied| emptied.saturating_sub(1));
+        let mut removed = self.resize_mut(&keys[..cut], &sizes).0.remove(keys[cut])?;
+        for key in &keys[cut + 1..] {
+            removed = match removed {
+                SubTree::Branch { mut forest, .. } => forest.0.remove(*key)?,
+                SubTree::Node { .. } => return None,
+            };
+        }
+        Some(removed)
+    }
+
+    /// Merge the sub-trees of two `Tree`s, keeping them ordered by their keys, where
+    /// `f` merges the sub-trees that have the same keys, given those keys. Two
+    /// branches are merged entry by entry instead, if `merge_branches` is set.
+    fn merge_by<F, E>(self, other: Self, merge_branches: bool, mut f: F) -> Result<Self, E>
+    where
+        K: Ord + Clone,
+        F: FnMut(&NonEmpty<K>, SubTree<K, A>, SubTree<K, A>) -> Result<SubTree<K, A>, E>,
+    {
+        // The keys of the branches being merged, and for each of them and the
+        // root, the merged entries so far along with the entries of `other` left.
+        let mut keys: Vec<K> = vec![];
+        let mut stack = vec![(self.into_sub_trees(), other.into_sub_trees().into_iter())];
+        loop {
+            let (merged, rest) = stack.last_mut().expect("the root is popped last");
+            let (key, right) = match rest.next() {
+                Some(entry) => entry,
+                None => {
+                    let (merged, _) = stack.pop().expect("the root is popped last");
+                    let tree = Tree::from_sub_trees(merged);
+                    match (keys.pop(), stack.last_mut()) {
+                        (Some(key), Some((parent, _))) => {
+                            parent.insert(key.clone(), SubTree::branch(key, tree));
+                            continue;
+                        }
+                        _ => return Ok(tree),
+                    }
+                }
+            };
+            let sub_tree = match (merged.remove(&key), right) {
+                (None, right) => right,
+                (Some(SubTree::Branch { forest, .. }), SubTree::Branch { forest: other, .. })
+                    if merge_branches =>
+                {
+                    keys.push(key);
+                    stack.push((forest.into_sub_trees(), other.into_sub_trees().into_iter()));
+                    continue;
+                }
+                (Some(left), right) => {
+                    let mut path = keys.clone();
+                    path.push(key.clone());
+                    let path = NonEmpty::from_slice(&path).expect("the key was just pushed");
+                    f(&path, left, right)?
+                }
+            };
+            merged.insert(key, sub_tree);
+        }
+    }
+
+    /// Iterate over the values of the `Tree`, in the order of their keys.
     pub fn iter<'a>(&'a self) -> impl Iterator<Item = &A> + 'a {
-        self.0.iter().flat_map(|tree| tree.iter())
+        self.0.values().flat_map(|tree| tree.iter())
     }
 
+    /// Iterate over the keys of the `Tree`, branches before their entries.
     pub fn iter_keys<'a>(&'a self) -> impl Iterator<Item = &K> + 'a {
-        self.0.iter().flat_map(|tree| tree.iter_keys())
+        self.0.values().flat_map(|tree| tree.iter_keys())
     }
 
+    /// Iterate over the top-level sub-trees of the `Tree`, in the order of their
+    /// keys.
     pub fn iter_subtrees<'a>(&'a self) -> impl Iterator<Item = &SubTree<K, A>> + 'a {
-        self.0.iter()
+        self.0.values()
     }
 
+    /// Find the value of the node at `keys`, if there is a node there.
     pub fn find_node(&self, keys: &NonEmpty<K>) -> Option<&A>
     where
         K: Ord + Clone,
@@ -313,47 +791,103 @@ impl<K, A> Tree<K, A> {
         })
     }
 
-    pub fn find_branch(&self, keys: &NonEmpty<K>) -> Option<&Self>
+    /// Find a `SubTree` given a search path. If the path does not match
+    /// it will return `None`.
+    pub fn find(&self, keys: &NonEmpty<K>) -> Option<&SubTree<K, A>>
     where
         K: Ord + Clone,
     {
-        self.find(keys).and_then(|tree| match tree {
-            SubTree::Node { .. } => None,
-            SubTree::Branch { ref forest, .. } => Some(&**forest),
-        })
+        let mut tree = self;
+        let (mut head, mut tail) = keys.split_first();
+        loop {
+            let sub_tree = tree.0.get(head)?;
+            match (tail.split_first(), sub_tree) {
+                (None, _) => return Some(sub_tree),
+                (Some(_), SubTree::Node { .. }) => return None,
+                (Some((next, rest)), SubTree::Branch { forest, .. }) => {
+                    tree = forest;
+                    head = next;
+                    tail = rest;
+                }
+            }
+        }
     }
 
-    /// Find a `SubTree` given a search path. If the path does not match
-    /// it will return `None`.
-    pub fn find(&self, keys: &NonEmpty<K>) -> Option<&SubTree<K, A>>
+    /// Find a `SubTree` given a search path, using `eq` to match each key
+    /// instead of the `Ord` instance of `K`, that is `accept`ed.
+    ///
+    /// Every key that matches via `eq` is tried, since more than one sibling may
+    /// match, e.g. a file `README` and a directory `readme` when ignoring case.
+    /// An exact match is always tried before the ones found via `eq`.
+    ///
+    /// The keys as they are stored in the tree are returned alongside the `SubTree`.
+    pub fn find_by<F, P>(
+        &self,
+        keys: &NonEmpty<K>,
+        eq: &F,
+        accept: &P,
+    ) -> Option<(NonEmpty<K>, &SubTree<K, A>)>
     where
+        F: Fn(&K, &K) -> bool,
+        P: Fn(&SubTree<K, A>) -> bool,
         K: Ord + Clone,
     {
-        let (head, tail) = keys.split_first();
-        let tail = NonEmpty::from_slice(tail);
-        match self.search(head) {
-            Err(_) => None,
-            Ok(index) => {
-                let sub_tree = self.0.get(index).unwrap();
-                match tail {
-                    None => match sub_tree {
-                        SubTree::Node { .. } => Some(sub_tree),
-                        SubTree::Branch { .. } => Some(sub_tree),
-                    },
-                    Some(_) => sub_tree.find(&keys),
+        let keys = keys.iter().collect::<Vec<_>>();
+        let mut found = vec![];
+        let mut stack = self
+            .candidates(keys[0], eq)
+            .into_iter()
+            .map(|sub_tree| (0, sub_tree))
+            .collect::<Vec<_>>();
+        while let Some((depth, sub_tree)) = stack.pop() {
+            found.truncate(depth);
+            found.push(sub_tree.key().clone());
+
+            match sub_tree {
+                _ if depth + 1 == keys.len() => {
+                    if accept(sub_tree) {
+                        return NonEmpty::from_slice(&found).map(|found| (found, sub_tree));
+                    }
                 }
+                SubTree::Node { .. } => {}
+                SubTree::Branch { forest, .. } => stack.extend(
+                    forest
+                        .candidates(keys[depth + 1], eq)
+                        .into_iter()
+                        .map(|sub_tree| (depth + 1, sub_tree)),
+                ),
             }
         }
+        None
+    }
+
+    /// Get the `SubTree`s whose keys match `key` via `eq`, with the exact match last,
+    /// so that it is the first to be popped off a stack.
+    fn candidates<F>(&self, key: &K, eq: &F) -> Vec<&SubTree<K, A>>
+    where
+        F: Fn(&K, &K) -> bool,
+        K: Ord,
+    {
+        self.0
+            .values()
+            .filter(|sub_tree| sub_tree.key() != key && eq(sub_tree.key(), key))
+            .chain(self.0.get(key))
+            .collect()
     }
 
+    /// Get the greatest value of the `Tree`, compared by `f`.
     pub fn maximum_by<F>(&self, f: &F) -> &A
     where
         F: Fn(&A, &A) -> Ordering,
     {
-        self.0.maximum_by(|s, t| s.compare_by(t, f)).maximum_by(f)
+        self.0
+            .values()
+            .max_by(|s, t| s.compare_by(t, f))
+            .expect("a tree is non-empty")
+            .maximum_by(f)
     }
 
-    #[allow(dead_code)]
+    /// Get the greatest value of the `Tree`.
     pub fn maximum(&self) -> &A
     where
         A: Ord,
@@ -363,15 +897,74 @@ impl<K, A> Tree<K, A> {
 }
 
 impl<K, A> Forest<K, A> {
+    /// Create an empty `Forest`.
     pub fn root() -> Self {
         Forest(None)
     }
 
-    #[allow(dead_code)]
+    /// Check whether the `Forest` has no entries.
     pub fn is_empty(&self) -> bool {
         self.0.is_none()
     }
 
+    /// Get the [`Tree`] of the `Forest`, unless it is empty.
+    pub fn tree(&self) -> Option<&Tree<K, A>> {
+        self.0.as_ref()
+    }
+
+    /// Get the number of sub-trees in the `Forest`, branches and nodes alike, at
+    /// any depth.
+    ///
+    /// The counts and the depth of a `Forest` are kept up to date as it changes,
+    /// so getting them doesn't walk it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::tree::{Forest, NonEmpty};
+    ///
+    /// let mut forest = Forest::root();
+    /// assert_eq!((forest.node_count(), forest.leaf_count(), forest.depth()), (0, 0, 0));
+    ///
+    /// forest.insert(&NonEmpty::from(("src", vec!["vcs", "git.rs"])), 1);
+    /// forest.insert(&NonEmpty::from(("src", vec!["lib.rs"])), 2);
+    /// forest.insert(&NonEmpty::new("README.md"), 3);
+    /// assert_eq!((forest.node_count(), forest.leaf_count(), forest.depth()), (5, 3, 3));
+    ///
+    /// forest.remove(&NonEmpty::from(("src", vec!["vcs", "git.rs"])));
+    /// assert_eq!((forest.node_count(), forest.leaf_count(), forest.depth()), (3, 2, 2));
+    /// ```
+    pub fn node_count(&self) -> usize {
+        self.0.as_ref().map_or(0, |tree| tree.1.sub_trees)
+    }
+
+    /// Get the number of nodes in the `Forest`, at any depth, i.e. the number of
+    /// values.
+    pub fn leaf_count(&self) -> usize {
+        self.0.as_ref().map_or(0, |tree| tree.1.nodes)
+    }
+
+    /// Get the number of keys on the longest path to a node, or `0` if the
+    /// `Forest` is empty.
+    pub fn depth(&self) -> usize {
+        self.0.as_ref().map_or(0, |tree| tree.1.depth)
+    }
+
+    /// Create a `Forest` from the sub-trees of a level, which is empty if there are
+    /// none.
+    fn from_sub_trees(sub_trees: BTreeMap<K, SubTree<K, A>>) -> Self {
+        if sub_trees.is_empty() {
+            Forest(None)
+        } else {
+            Forest(Some(Tree::from_sub_trees(sub_trees)))
+        }
+    }
+
+    /// Create a [`Cursor`] focused on the whole `Forest`.
+    pub fn cursor(self) -> Cursor<K, A> {
+        Cursor::new(self)
+    }
+
     fn insert_forest(&mut self, forest: Tree<K, A>) {
         self.0 = Some(forest)
     }
@@ -390,7 +983,6 @@ impl<K, A> Forest<K, A> {
     /// then the node `c` will be replaced by a branch `c/d`.
     ///
     /// If the path does not exist it will be inserted into the set of sub-trees.
-    #[allow(dead_code)]
     pub fn insert(&mut self, keys: &NonEmpty<K>, node: A)
     where
         A: Clone,
@@ -399,65 +991,1185 @@ impl<K, A> Forest<K, A> {
         self.insert_with(keys, node.clone(), |old| *old = node)
     }
 
-    pub fn insert_with<F>(&mut self, keys: &NonEmpty<K>, node: A, f: F)
+    /// Insert the `node` in the position given by `keys`, like [`Forest::insert`],
+    /// except that an existing node is updated by `f` instead of being replaced.
+    pub fn insert_with<F>(&mut self, keys: &NonEmpty<K>, node: A, f: F)
+    where
+        F: FnOnce(&mut A),
+        K: Ord + Clone,
+    {
+        match self.0.as_mut() {
+            Some(forest) => forest.insert_with(keys, node, f),
+            None => self.insert_forest(Tree::new(keys, node)),
+        }
+    }
+
+    /// Remove the node or branch in the position given by `keys`, returning it.
+    ///
+    /// Any branches that are left empty by the removal are removed as well, so if
+    /// `a/b/c` is the only node under `a`, then removing it removes `a` and `a/b` too.
+    pub fn remove(&mut self, keys: &NonEmpty<K>) -> Option<SubTree<K, A>>
+    where
+        K: Ord + Clone,
+    {
+        let tree = self.0.as_mut()?;
+        let removed = tree.remove(keys);
+        if tree.0.is_empty() {
+            self.0 = None;
+        }
+        removed
+    }
+
+    /// Remove the node in the position given by `keys`, returning its value, or
+    /// `None` if there is no node there, in which case the `Forest` is left as it is.
+    ///
+    /// As with [`Forest::remove`], any branches that are left empty are removed.
+    pub fn remove_node(&mut self, keys: &NonEmpty<K>) -> Option<A>
+    where
+        K: Ord + Clone,
+    {
+        match self.find(keys)? {
+            SubTree::Node { .. } => {}
+            SubTree::Branch { .. } => return None,
+        }
+        match self.remove(keys)? {
+            SubTree::Node { value, .. } => Some(value),
+            SubTree::Branch { .. } => None,
+        }
+    }
+
+    /// Keep only the nodes for which `keep` holds, given the keys of the branches
+    /// each node is in, its key, and its value. Branches that are left empty are
+    /// removed, as with [`Forest::remove`].
+    ///
+    /// The `Forest` is walked iteratively, so that deep branches don't overflow the
+    /// stack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::tree::{Forest, NonEmpty};
+    ///
+    /// let mut forest = Forest::root();
+    /// forest.insert(&NonEmpty::from(("a", vec!["b"])), 1);
+    /// forest.insert(&NonEmpty::from(("a", vec!["c"])), 2);
+    /// forest.insert(&NonEmpty::from(("d", vec!["e"])), 3);
+    ///
+    /// forest.prune(|_keys, _key, value| value % 2 == 0);
+    ///
+    /// let mut expected = Forest::root();
+    /// expected.insert(&NonEmpty::from(("a", vec!["c"])), 2);
+    /// assert_eq!(forest, expected);
+    /// ```
+    pub fn prune<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&[K], &K, &A) -> bool,
+        K: Ord + Clone,
+    {
+        /// A branch being pruned: its key, the sub-trees left to look at, and the
+        /// sub-trees that are kept so far.
+        struct Pruning<K, A> {
+            key: Option<K>,
+            pending: std::collections::btree_map::IntoIter<K, SubTree<K, A>>,
+            kept: BTreeMap<K, SubTree<K, A>>,
+        }
+
+        let tree = match self.0.take() {
+            Some(tree) => tree,
+            None => return,
+        };
+        let mut keys = vec![];
+        let mut stack = vec![Pruning {
+            key: None,
+            pending: tree.into_sub_trees().into_iter(),
+            kept: BTreeMap::new(),
+        }];
+
+        while let Some(pruning) = stack.last_mut() {
+            match pruning.pending.next() {
+                Some((key, SubTree::Node { value, .. })) => {
+                    if keep(&keys, &key, &value) {
+                        pruning
+                            .kept
+                            .insert(key.clone(), SubTree::Node { key, value });
+                    }
+                }
+                Some((key, SubTree::Branch { forest, .. })) => {
+                    keys.push(key.clone());
+                    stack.push(Pruning {
+                        key: Some(key),
+                        pending: forest.into_sub_trees().into_iter(),
+                        kept: BTreeMap::new(),
+                    });
+                }
+                None => {
+                    let Pruning { key, kept, .. } = stack.pop().expect("the branch was peeked");
+                    let forest = Forest::from_sub_trees(kept);
+                    match (key, stack.last_mut()) {
+                        (Some(key), Some(parent)) => {
+                            keys.pop();
+                            if let Some(tree) = forest.0 {
+                                parent.kept.insert(key.clone(), SubTree::branch(key, tree));
+                            }
+                        }
+                        _ => self.0 = forest.0,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Visit the branches and nodes of the `Forest` with `visitor`, depth-first, in
+    /// the order of their keys, until the `visitor` stops it, see [`Visit`].
+    ///
+    /// The `Forest` is walked iteratively, so that deep branches don't overflow the
+    /// stack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::tree::{Forest, NonEmpty, TreeVisitor, Visit};
+    ///
+    /// /// Finds the first even value, without looking into branches called `skip`.
+    /// struct FirstEven(Option<u32>);
+    ///
+    /// impl TreeVisitor<&str, u32> for FirstEven {
+    ///     fn enter_branch(&mut self, _keys: &[&str], key: &&str) -> Visit {
+    ///         if *key == "skip" { Visit::Skip } else { Visit::Continue }
+    ///     }
+    ///
+    ///     fn visit_node(&mut self, _keys: &[&str], _key: &&str, value: &u32) -> Visit {
+    ///         if value % 2 == 0 {
+    ///             self.0 = Some(*value);
+    ///             Visit::Stop
+    ///         } else {
+    ///             Visit::Continue
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut forest = Forest::root();
+    /// forest.insert(&NonEmpty::from(("a", vec!["skip", "b"])), 2);
+    /// forest.insert(&NonEmpty::from(("a", vec!["c"])), 3);
+    /// forest.insert(&NonEmpty::from(("d", vec!["e"])), 4);
+    /// forest.insert(&NonEmpty::new("f"), 6);
+    ///
+    /// let mut visitor = FirstEven(None);
+    /// forest.visit(&mut visitor);
+    /// assert_eq!(visitor.0, Some(4));
+    /// ```
+    pub fn visit<V>(&self, visitor: &mut V)
+    where
+        V: TreeVisitor<K, A>,
+        K: Clone,
+    {
+        let mut keys = vec![];
+        let mut stack: Vec<Box<dyn Iterator<Item = &SubTree<K, A>>>> = match &self.0 {
+            None => return,
+            Some(tree) => vec![Box::new(tree.iter_subtrees())],
+        };
+
+        while let Some(sub_trees) = stack.last_mut() {
+            let visit = match sub_trees.next() {
+                None => {
+                    stack.pop();
+                    match keys.pop() {
+                        Some(key) => visitor.leave_branch(&keys, &key),
+                        None => Visit::Continue,
+                    }
+                }
+                Some(SubTree::Node { key, value }) => visitor.visit_node(&keys, key, value),
+                Some(SubTree::Branch { key, forest }) => match visitor.enter_branch(&keys, key) {
+                    Visit::Continue => {
+                        keys.push(key.clone());
+                        stack.push(Box::new(forest.iter_subtrees()));
+                        Visit::Continue
+                    }
+                    // Skipping a branch only skips its own sub-trees.
+                    Visit::Skip => Visit::Continue,
+                    Visit::Stop => Visit::Stop,
+                },
+            };
+
+            match visit {
+                Visit::Continue => {}
+                Visit::Skip => {
+                    if let Some(sub_trees) = stack.last_mut() {
+                        *sub_trees = Box::new(std::iter::empty());
+                    }
+                }
+                Visit::Stop => return,
+            }
+        }
+    }
+
+    /// Merge `other` into this `Forest`, where `policy` decides what happens when
+    /// both have a node, or a node and a branch, at the same keys.
+    ///
+    /// Branches that are only in one of the `Forest`s are kept as they are, and
+    /// branches that are in both are merged, unless the policy is
+    /// [`MergePolicy::KeepLeft`] or [`MergePolicy::KeepRight`].
+    pub fn merge(self, other: Self, policy: MergePolicy) -> Result<Self, MergeConflict<K>>
+    where
+        K: Ord + Clone,
+        A: PartialEq,
+    {
+        let merge_branches = match policy {
+            MergePolicy::KeepLeft | MergePolicy::KeepRight => false,
+            MergePolicy::Combine | MergePolicy::Error => true,
+        };
+        self.merge_by(other, merge_branches, |keys, left, right| match policy {
+            MergePolicy::KeepLeft => Ok(left),
+            MergePolicy::KeepRight | MergePolicy::Combine => Ok(right),
+            MergePolicy::Error if left == right => Ok(left),
+            MergePolicy::Error => Err(MergeConflict { keys: keys.clone() }),
+        })
+    }
+
+    /// Merge `other` into this `Forest`, where `f` decides the sub-tree at the keys
+    /// where both have a node, or one has a node and the other a branch, given the
+    /// keys and the sub-trees of this `Forest` and `other`. The sub-tree that `f`
+    /// returns is kept at those keys, and an error from `f` stops the merge.
+    ///
+    /// Branches that are in both are merged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::tree::{Forest, NonEmpty, SubTree};
+    ///
+    /// let mut left = Forest::root();
+    /// left.insert(&NonEmpty::from(("src", vec!["lib.rs"])), 1);
+    /// left.insert(&NonEmpty::new("README.md"), 2);
+    /// left.insert(&NonEmpty::new("docs"), 3);
+    ///
+    /// let mut right = Forest::root();
+    /// right.insert(&NonEmpty::from(("src", vec!["lib.rs"])), 10);
+    /// right.insert(&NonEmpty::from(("src", vec!["vcs.rs"])), 20);
+    /// right.insert(&NonEmpty::from(("docs", vec!["index.md"])), 30);
+    ///
+    /// let merged = left
+    ///     .clone()
+    ///     .merge_with(right, |keys, left, right| match (left, right) {
+    ///         (SubTree::Node { key, value: left }, SubTree::Node { value: right, .. }) => {
+    ///             Ok(SubTree::Node { key, value: left + right })
+    ///         }
+    ///         // A node and a branch, like a file and a directory.
+    ///         _ => Err(keys.clone()),
+    ///     });
+    /// assert_eq!(merged, Err(NonEmpty::new("docs")));
+    ///
+    /// let mut right = Forest::root();
+    /// right.insert(&NonEmpty::from(("src", vec!["lib.rs"])), 10);
+    /// right.insert(&NonEmpty::from(("src", vec!["vcs.rs"])), 20);
+    ///
+    /// let merged = left
+    ///     .merge_with(right, |_keys, left, right| match (left, right) {
+    ///         (SubTree::Node { key, value: left }, SubTree::Node { value: right, .. }) => {
+    ///             Ok::<_, ()>(SubTree::Node { key, value: left + right })
+    ///         }
+    ///         (_, right) => Ok(right),
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(merged.find_node(&NonEmpty::from(("src", vec!["lib.rs"]))), Some(&11));
+    /// assert_eq!(merged.find_node(&NonEmpty::from(("src", vec!["vcs.rs"]))), Some(&20));
+    /// assert_eq!(merged.find_node(&NonEmpty::new("README.md")), Some(&2));
+    /// ```
+    pub fn merge_with<F, E>(self, other: Self, f: F) -> Result<Self, E>
+    where
+        K: Ord + Clone,
+        F: FnMut(&NonEmpty<K>, SubTree<K, A>, SubTree<K, A>) -> Result<SubTree<K, A>, E>,
+    {
+        self.merge_by(other, true, f)
+    }
+
+    fn merge_by<F, E>(self, other: Self, merge_branches: bool, f: F) -> Result<Self, E>
+    where
+        K: Ord + Clone,
+        F: FnMut(&NonEmpty<K>, SubTree<K, A>, SubTree<K, A>) -> Result<SubTree<K, A>, E>,
+    {
+        match (self.0, other.0) {
+            (Some(left), Some(right)) => left.merge_by(right, merge_branches, f).map(Forest::from),
+            (left, right) => Ok(Forest(left.or(right))),
+        }
+    }
+
+    /// Find the value of the node at `keys`, if there is a node there.
+    pub fn find_node(&self, keys: &NonEmpty<K>) -> Option<&A>
+    where
+        K: Ord + Clone,
+    {
+        self.0.as_ref().and_then(|trees| trees.find_node(keys))
+    }
+
+    /// Find a `SubTree` given a search path. If the path does not match
+    /// it will return `None`.
+    pub fn find(&self, keys: &NonEmpty<K>) -> Option<&SubTree<K, A>>
+    where
+        K: Ord + Clone,
+    {
+        self.0.as_ref().and_then(|trees| trees.find(&keys))
+    }
+
+    /// Find a `SubTree` given a search path, matching keys using `eq`, that is
+    /// `accept`ed. See [`Tree::find_by`].
+    pub fn find_by<F, P>(
+        &self,
+        keys: &NonEmpty<K>,
+        eq: F,
+        accept: P,
+    ) -> Option<(NonEmpty<K>, &SubTree<K, A>)>
+    where
+        F: Fn(&K, &K) -> bool,
+        P: Fn(&SubTree<K, A>) -> bool,
+        K: Ord + Clone,
+    {
+        self.0
+            .as_ref()
+            .and_then(|trees| trees.find_by(keys, &eq, &accept))
+    }
+
+    /// Get the greatest value of the `Forest`, compared by `f`, unless it is empty.
+    pub fn maximum_by<F>(&self, f: F) -> Option<&A>
+    where
+        F: Fn(&A, &A) -> Ordering,
+    {
+        self.0.as_ref().map(|trees| trees.maximum_by(&f))
+    }
+
+    /// Iterate over the values of the `Forest`, in the order of their keys.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = &A> + 'a {
+        self.0.iter().flat_map(|trees| trees.iter())
+    }
+
+    /// Create a `Forest` with the same branches and keys, whose values are `f` of the
+    /// values of this one, which are visited in the order of their keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::tree::{Forest, NonEmpty};
+    ///
+    /// let mut forest = Forest::root();
+    /// forest.insert(&NonEmpty::from(("src", vec!["lib.rs"])), "pub mod vcs;");
+    /// forest.insert(&NonEmpty::new("README.md"), "# Surf");
+    ///
+    /// let mut count = 0;
+    /// let numbered = forest.map(|contents| {
+    ///     count += 1;
+    ///     (count, contents.len())
+    /// });
+    ///
+    /// let mut expected = Forest::root();
+    /// expected.insert(&NonEmpty::from(("src", vec!["lib.rs"])), (2, 12));
+    /// expected.insert(&NonEmpty::new("README.md"), (1, 6));
+    /// assert_eq!(numbered, expected);
+    /// ```
+    pub fn map<F, B>(&self, f: F) -> Forest<K, B>
+    where
+        K: Ord + Clone,
+        F: FnMut(&A) -> B,
+    {
+        Forest(self.0.as_ref().map(|tree| tree.map(f)))
+    }
+
+    /// Create a `Forest` from the nodes for which `f` gives a value, given the keys
+    /// of the branches each node is in, its key, and its value. Branches that are
+    /// left empty are removed, as with [`Forest::prune`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::tree::{Forest, NonEmpty};
+    ///
+    /// let mut forest = Forest::root();
+    /// forest.insert(&NonEmpty::from(("src", vec!["lib.rs"])), "pub mod vcs;");
+    /// forest.insert(&NonEmpty::from(("docs", vec!["intro.md"])), "# Surf");
+    ///
+    /// let lengths = forest.filter_map(|_keys, key, contents| {
+    ///     if key.ends_with(".rs") { Some(contents.len()) } else { None }
+    /// });
+    ///
+    /// let mut expected = Forest::root();
+    /// expected.insert(&NonEmpty::from(("src", vec!["lib.rs"])), 12);
+    /// assert_eq!(lengths, expected);
+    /// ```
+    pub fn filter_map<F, B>(&self, f: F) -> Forest<K, B>
+    where
+        K: Ord + Clone,
+        F: FnMut(&[K], &K, &A) -> Option<B>,
+    {
+        let mut mapper = FilterMapper {
+            f,
+            kept: vec![BTreeMap::new()],
+        };
+        self.visit(&mut mapper);
+        Forest::from_sub_trees(mapper.kept.pop().unwrap_or_default())
+    }
+
+    /// Iterate over the nodes of the `Forest`, in the order of their keys, along with
+    /// the full keys of each node, i.e. the keys of the branches it is in followed
+    /// by its own key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::tree::{Forest, NonEmpty};
+    ///
+    /// let mut forest = Forest::root();
+    /// forest.insert(&NonEmpty::from(("src", vec!["vcs", "git.rs"])), 1);
+    /// forest.insert(&NonEmpty::from(("src", vec!["lib.rs"])), 2);
+    /// forest.insert(&NonEmpty::new("README.md"), 3);
+    ///
+    /// assert_eq!(
+    ///     forest.iter_paths().collect::<Vec<_>>(),
+    ///     vec![
+    ///         (NonEmpty::new("README.md"), &3),
+    ///         (NonEmpty::from(("src", vec!["lib.rs"])), &2),
+    ///         (NonEmpty::from(("src", vec!["vcs", "git.rs"])), &1),
+    ///     ]
+    /// );
+    /// ```
+    pub fn iter_paths<'a>(&'a self) -> impl Iterator<Item = (NonEmpty<K>, &'a A)> + 'a
+    where
+        K: Clone,
+    {
+        Paths {
+            keys: vec![],
+            stack: self
+                .0
+                .iter()
+                .map(|tree| Box::new(tree.iter_subtrees()) as Box<dyn Iterator<Item = _>>)
+                .collect(),
+        }
+    }
+
+    /// Iterate over the nodes of the `Forest` in parallel, along with their full
+    /// keys, see [`Forest::iter_paths`].
+    ///
+    /// The keys are gathered in a single walk of the `Forest` up front, and the
+    /// nodes are then spread over rayon's thread pool, which pays off when dealing
+    /// with each value takes a while, e.g. hashing the contents of a file.
+    #[cfg(feature = "rayon")]
+    pub fn par_iter<'a>(&'a self) -> impl IndexedParallelIterator<Item = (NonEmpty<K>, &'a A)> + 'a
+    where
+        K: Clone + Send,
+        A: Sync,
+    {
+        self.iter_paths().collect::<Vec<_>>().into_par_iter()
+    }
+
+    /// Fold the nodes of the `Forest` in parallel, see [`Forest::par_iter`].
+    ///
+    /// Each thread folds the nodes it is given into a value starting from
+    /// `identity()`, and these values are then combined by `reduce`. The nodes are
+    /// given out in no particular order, so `reduce` should be associative, with
+    /// `identity()` as its neutral value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::tree::{Forest, NonEmpty};
+    ///
+    /// let mut forest = Forest::root();
+    /// forest.insert(&NonEmpty::from(("src", vec!["vcs", "git.rs"])), 1);
+    /// forest.insert(&NonEmpty::from(("src", vec!["lib.rs"])), 2);
+    /// forest.insert(&NonEmpty::new("README.md"), 3);
+    ///
+    /// let sum_in_src = forest.par_fold(
+    ///     || 0,
+    ///     |sum, keys, value| if *keys.first() == "src" { sum + value } else { sum },
+    ///     |left, right| left + right,
+    /// );
+    /// assert_eq!(sum_in_src, 3);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_fold<B, I, F, R>(&self, identity: I, fold: F, reduce: R) -> B
+    where
+        K: Clone + Send,
+        A: Sync,
+        B: Send,
+        I: Fn() -> B + Send + Sync,
+        F: Fn(B, &NonEmpty<K>, &A) -> B + Send + Sync,
+        R: Fn(B, B) -> B + Send + Sync,
+    {
+        self.par_iter()
+            .fold(&identity, |acc, (keys, value)| fold(acc, &keys, value))
+            .reduce(&identity, reduce)
+    }
+
+    /// Compare this `Forest` to an `other` one, finding the keys of the branches
+    /// and nodes that were added or removed, and of the nodes whose values changed,
+    /// going by `eq`.
+    ///
+    /// When a node is in one `Forest` and a branch in the other at the same keys, the
+    /// node and the branch, along with everything in it, are both removed and added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::tree::{Forest, NonEmpty};
+    ///
+    /// let mut old = Forest::root();
+    /// old.insert(&NonEmpty::new("README.md"), 1);
+    /// old.insert(&NonEmpty::from(("src", vec!["lib.rs"])), 2);
+    /// old.insert(&NonEmpty::from(("src", vec!["vcs.rs"])), 3);
+    ///
+    /// let mut new = Forest::root();
+    /// new.insert(&NonEmpty::new("README.md"), 4);
+    /// new.insert(&NonEmpty::from(("src", vec!["lib.rs"])), 2);
+    /// new.insert(&NonEmpty::from(("src", vec!["vcs", "git.rs"])), 5);
+    ///
+    /// let diff = old.diff(&new, |old, new| old == new);
+    ///
+    /// assert_eq!(
+    ///     diff.added,
+    ///     vec![NonEmpty::from(("src", vec!["vcs"])), NonEmpty::from(("src", vec!["vcs", "git.rs"]))]
+    /// );
+    /// assert_eq!(diff.removed, vec![NonEmpty::from(("src", vec!["vcs.rs"]))]);
+    /// assert_eq!(diff.changed, vec![NonEmpty::new("README.md")]);
+    ///
+    /// assert!(new.diff(&new, |old, new| old == new).is_empty());
+    /// ```
+    pub fn diff<F>(&self, other: &Self, eq: F) -> ForestDiff<K>
+    where
+        K: Ord + Clone,
+        F: Fn(&A, &A) -> bool,
+    {
+        fn sub_trees<K, A>(tree: Option<&Tree<K, A>>) -> Vec<&SubTree<K, A>> {
+            tree.into_iter()
+                .flat_map(|tree| tree.iter_subtrees())
+                .collect()
+        }
+
+        fn join<K: Clone>(prefix: &[K], key: &K) -> NonEmpty<K> {
+            let mut keys = prefix.to_vec();
+            keys.push(key.clone());
+            NonEmpty::from_slice(&keys).expect("the key was just pushed")
+        }
+
+        // Push the keys of `sub_tree`, and of everything in it, to `out`.
+        fn push_all<K: Clone, A>(
+            prefix: &[K],
+            sub_tree: &SubTree<K, A>,
+            out: &mut Vec<NonEmpty<K>>,
+        ) {
+            let mut stack = vec![(prefix.to_vec(), sub_tree)];
+            while let Some((mut prefix, sub_tree)) = stack.pop() {
+                out.push(join(&prefix, sub_tree.key()));
+                if let SubTree::Branch { key, forest } = sub_tree {
+                    prefix.push(key.clone());
+                    stack.extend(
+                        forest
+                            .iter_subtrees()
+                            .map(|sub_tree| (prefix.clone(), sub_tree)),
+                    );
+                }
+            }
+        }
+
+        let mut diff = ForestDiff::default();
+        let mut stack = vec![(
+            vec![],
+            sub_trees(self.0.as_ref()),
+            sub_trees(other.0.as_ref()),
+        )];
+        while let Some((prefix, left, right)) = stack.pop() {
+            let mut left = left.into_iter().peekable();
+            let mut right = right.into_iter().peekable();
+            loop {
+                let ordering = match (left.peek(), right.peek()) {
+                    (None, None) => break,
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some(left), Some(right)) => left.key().cmp(right.key()),
+                };
+                let (left, right) = match ordering {
+                    Ordering::Less => (left.next(), None),
+                    Ordering::Greater => (None, right.next()),
+                    Ordering::Equal => (left.next(), right.next()),
+                };
+                match (left, right) {
+                    (
+                        Some(SubTree::Node { key, value }),
+                        Some(SubTree::Node { value: other, .. }),
+                    ) => {
+                        if !eq(value, other) {
+                            diff.changed.push(join(&prefix, key));
+                        }
+                    }
+                    (
+                        Some(SubTree::Branch { key, forest }),
+                        Some(SubTree::Branch { forest: other, .. }),
+                    ) => {
+                        let mut prefix = prefix.clone();
+                        prefix.push(key.clone());
+                        stack.push((prefix, sub_trees(Some(forest)), sub_trees(Some(other))));
+                    }
+                    (left, right) => {
+                        if let Some(left) = left {
+                            push_all(&prefix, left, &mut diff.removed);
+                        }
+                        if let Some(right) = right {
+                            push_all(&prefix, right, &mut diff.added);
+                        }
+                    }
+                }
+            }
+        }
+
+        diff.added.sort();
+        diff.removed.sort();
+        diff.changed.sort();
+        diff
+    }
+
+    /// Iterate over the keys of the `Forest`, branches before their entries.
+    pub fn iter_keys<'a>(&'a self) -> impl Iterator<Item = &K> + 'a {
+        self.0.iter().flat_map(|trees| trees.iter_keys())
+    }
+}
+
+/// Builds the result of [`Forest::filter_map`], keeping the sub-trees of every
+/// branch being visited, with the sub-trees of the `Forest` itself at the bottom.
+struct FilterMapper<F, K, B> {
+    f: F,
+    kept: Vec<BTreeMap<K, SubTree<K, B>>>,
+}
+
+impl<F, K, A, B> TreeVisitor<K, A> for FilterMapper<F, K, B>
+where
+    K: Ord + Clone,
+    F: FnMut(&[K], &K, &A) -> Option<B>,
+{
+    fn enter_branch(&mut self, _keys: &[K], _key: &K) -> Visit {
+        self.kept.push(BTreeMap::new());
+        Visit::Continue
+    }
+
+    fn leave_branch(&mut self, _keys: &[K], key: &K) -> Visit {
+        let branch = Forest::from_sub_trees(self.kept.pop().unwrap_or_default());
+        if let (Some(tree), Some(kept)) = (branch.0, self.kept.last_mut()) {
+            kept.insert(key.clone(), SubTree::branch(key.clone(), tree));
+        }
+        Visit::Continue
+    }
+
+    fn visit_node(&mut self, keys: &[K], key: &K, value: &A) -> Visit {
+        if let (Some(value), Some(kept)) = ((self.f)(keys, key, value), self.kept.last_mut()) {
+            kept.insert(
+                key.clone(),
+                SubTree::Node {
+                    key: key.clone(),
+                    value,
+                },
+            );
+        }
+        Visit::Continue
+    }
+}
+
+/// The iterator of [`Forest::iter_paths`], which keeps the keys of the branches it
+/// is in alongside the sub-trees left to visit in each of them.
+struct Paths<'a, K, A> {
+    keys: Vec<&'a K>,
+    stack: Vec<Box<dyn Iterator<Item = &'a SubTree<K, A>> + 'a>>,
+}
+
+impl<'a, K: Clone, A> Iterator for Paths<'a, K, A> {
+    type Item = (NonEmpty<K>, &'a A);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                None => {
+                    self.stack.pop();
+                    self.keys.pop();
+                }
+                Some(SubTree::Node { key, value }) => {
+                    let keys = match self.keys.split_first() {
+                        None => NonEmpty::new(key.clone()),
+                        Some((first, rest)) => {
+                            let mut rest =
+                                rest.iter().map(|key| (*key).clone()).collect::<Vec<_>>();
+                            rest.push(key.clone());
+                            NonEmpty::from(((*first).clone(), rest))
+                        }
+                    };
+                    return Some((keys, value));
+                }
+                Some(SubTree::Branch { key, forest }) => {
+                    self.keys.push(key);
+                    self.stack.push(Box::new(forest.iter_subtrees()));
+                }
+            }
+        }
+    }
+}
+
+/// A cursor into a [`Forest`], focused on the entries of one of its branches, or on
+/// the whole `Forest` at first.
+///
+/// Moving the cursor [`down`](Cursor::down) into a branch and back [`up`](Cursor::up)
+/// only takes apart and puts back together the levels on the way, so navigating and
+/// editing at the focus does not search from the root nor clone the `Forest`.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::tree::{Forest, NonEmpty};
+///
+/// let mut forest = Forest::root();
+/// forest.insert(&NonEmpty::from(("src", vec!["lib.rs"])), 1);
+/// forest.insert(&NonEmpty::from(("src", vec!["vcs", "git.rs"])), 2);
+/// forest.insert(&NonEmpty::new("README.md"), 3);
+///
+/// let mut cursor = forest.cursor();
+/// assert!(cursor.down(&"src"));
+/// assert!(cursor.down(&"vcs"));
+/// assert_eq!(cursor.keys(), vec![&"src", &"vcs"]);
+/// assert_eq!(cursor.siblings().map(|sub_tree| *sub_tree.key()).collect::<Vec<_>>(), vec!["lib.rs"]);
+///
+/// cursor.focus_mut().insert(&NonEmpty::new("git2.rs"), 4);
+/// assert!(cursor.up());
+/// assert!(!cursor.down(&"lib.rs"));
+///
+/// let forest = cursor.into_forest();
+/// assert_eq!(forest.find_node(&NonEmpty::from(("src", vec!["vcs", "git2.rs"]))), Some(&4));
+/// assert_eq!(forest.iter().copied().collect::<Vec<_>>(), vec![3, 1, 2, 4]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cursor<K, A> {
+    focus: Forest<K, A>,
+    crumbs: Vec<Crumb<K, A>>,
+}
+
+/// The parts of a branch that are put aside while the cursor is below it: the key
+/// of the branch, and the sub-trees next to it, which may be none.
+#[derive(Debug, Clone)]
+struct Crumb<K, A> {
+    key: K,
+    siblings: Tree<K, A>,
+}
+
+impl<K, A> Cursor<K, A> {
+    /// Create a `Cursor` focused on the whole of `forest`.
+    pub fn new(forest: Forest<K, A>) -> Self {
+        Cursor {
+            focus: forest,
+            crumbs: vec![],
+        }
+    }
+
+    /// Get the keys of the branches from the root down to the focus, which are
+    /// empty when the focus is the whole `Forest`.
+    pub fn keys(&self) -> Vec<&K> {
+        self.crumbs.iter().map(|crumb| &crumb.key).collect()
+    }
+
+    /// Check whether the focus is the whole `Forest`.
+    pub fn is_root(&self) -> bool {
+        self.crumbs.is_empty()
+    }
+
+    /// Get the entries of the branch in focus.
+    pub fn focus(&self) -> &Forest<K, A> {
+        &self.focus
+    }
+
+    /// Get the entries of the branch in focus, to edit them.
+    ///
+    /// If all the entries are removed, the branch is removed when the cursor moves
+    /// [`up`](Cursor::up), as with [`Forest::remove`].
+    pub fn focus_mut(&mut self) -> &mut Forest<K, A> {
+        &mut self.focus
+    }
+
+    /// Get the sub-trees next to the branch in focus, in the order of their keys,
+    /// which are none when the focus is the whole `Forest`.
+    pub fn siblings<'a>(&'a self) -> impl Iterator<Item = &'a SubTree<K, A>> + 'a {
+        self.crumbs
+            .last()
+            .into_iter()
+            .flat_map(|crumb| crumb.siblings.0.values())
+    }
+
+    /// Move the focus down into the branch `key` of the focus, returning whether
+    /// there is such a branch. The cursor stays where it is otherwise.
+    pub fn down(&mut self, key: &K) -> bool
+    where
+        K: Ord,
+    {
+        match self.take(key, |sub_tree| matches!(sub_tree, SubTree::Branch { .. })) {
+            Some((siblings, SubTree::Branch { key, forest })) => {
+                self.crumbs.push(Crumb { key, siblings });
+                self.focus = Forest::from(*forest);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Move the focus down into the node `key` of the focus, which is taken out of
+    /// the `Forest`, leaving the focus empty.
+    ///
+    /// This is for nodes that stand in for empty branches, e.g. empty directories.
+    pub(crate) fn down_node(&mut self, key: &K) -> Option<A>
+    where
+        K: Ord,
+    {
+        match self.take(key, |sub_tree| matches!(sub_tree, SubTree::Node { .. })) {
+            Some((siblings, SubTree::Node { key, value })) => {
+                self.crumbs.push(Crumb { key, siblings });
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Take the sub-tree `key` out of the focus, if it is `wanted`, along with the
+    /// rest of the focus, which may be empty, leaving the focus empty. The focus is
+    /// left as it is otherwise.
+    fn take<F>(&mut self, key: &K, wanted: F) -> Option<(Tree<K, A>, SubTree<K, A>)>
+    where
+        K: Ord,
+        F: Fn(&SubTree<K, A>) -> bool,
+    {
+        let tree = self.focus.0.as_mut()?;
+        if !tree.0.get(key).is_some_and(wanted) {
+            return None;
+        }
+        let sub_tree = tree.take(key)?;
+        let siblings = self.focus.0.take()?;
+        Some((siblings, sub_tree))
+    }
+
+    /// Move the focus up to the branch above it, returning whether there is one,
+    /// i.e. whether the focus was not the whole `Forest`.
+    pub fn up(&mut self) -> bool
+    where
+        K: Ord + Clone,
+    {
+        let Crumb { key, mut siblings } = match self.crumbs.pop() {
+            Some(crumb) => crumb,
+            None => return false,
+        };
+
+        if let Some(tree) = self.focus.0.take() {
+            siblings.put(SubTree::branch(key, tree));
+        }
+        self.focus = if siblings.is_empty() {
+            Forest(None)
+        } else {
+            Forest(Some(siblings))
+        };
+        true
+    }
+
+    /// Move the focus up to the whole `Forest`, and return it.
+    pub fn into_forest(mut self) -> Forest<K, A>
+    where
+        K: Ord + Clone,
+    {
+        while self.up() {}
+        self.focus
+    }
+}
+
+impl<K, A> From<Forest<K, A>> for Cursor<K, A> {
+    fn from(forest: Forest<K, A>) -> Self {
+        Cursor::new(forest)
+    }
+}
+
+/// A [`Forest`] or [`Tree`] is serialized as a sequence of its top-level
+/// sub-trees, ordered by their keys. A [`SubTree`] is serialized as a `Node`
+/// variant with the fields `key` and `value`, or a `Branch` variant with the
+/// fields `key` and `forest`.
+///
+/// When deserializing, a sub-tree with the same key as an earlier one is an
+/// error, and so is a `Tree`, or the `forest` of a `Branch`, without any
+/// sub-trees.
+///
+/// [`Label`](../file_system/struct.Label.html) and
+/// [`File`](../file_system/struct.File.html) are serializable too, so that the
+/// `Forest<Label, File>` of a `Directory` can be persisted.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::tree::{Forest, Tree};
+/// use serde::de::value::{Error, SeqDeserializer};
+/// use serde::Deserialize;
+///
+/// let empty: SeqDeserializer<std::vec::IntoIter<u8>, Error> = SeqDeserializer::new(vec![].into_iter());
+/// assert_eq!(Forest::<String, u8>::deserialize(empty), Ok(Forest::root()));
+///
+/// let empty: SeqDeserializer<std::vec::IntoIter<u8>, Error> = SeqDeserializer::new(vec![].into_iter());
+/// assert!(Tree::<String, u8>::deserialize(empty).is_err());
+/// ```
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{Forest, SubTree, Tree};
+    use serde::de::{
+        self, Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+    };
+    use serde::ser::{Serialize, SerializeStructVariant, Serializer};
+    use std::collections::btree_map::Entry;
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    impl<K: Serialize, A: Serialize> Serialize for SubTree<K, A> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match self {
+                SubTree::Node { key, value } => {
+                    let mut node = serializer.serialize_struct_variant("SubTree", 0, "Node", 2)?;
+                    node.serialize_field("key", key)?;
+                    node.serialize_field("value", value)?;
+                    node.end()
+                }
+                SubTree::Branch { key, forest } => {
+                    let mut branch =
+                        serializer.serialize_struct_variant("SubTree", 1, "Branch", 2)?;
+                    branch.serialize_field("key", key)?;
+                    branch.serialize_field("forest", forest)?;
+                    branch.end()
+                }
+            }
+        }
+    }
+
+    impl<K: Serialize, A: Serialize> Serialize for Tree<K, A> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.collect_seq(self.0.values())
+        }
+    }
+
+    impl<K: Serialize, A: Serialize> Serialize for Forest<K, A> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match &self.0 {
+                None => serializer.collect_seq(std::iter::empty::<&SubTree<K, A>>()),
+                Some(tree) => tree.serialize(serializer),
+            }
+        }
+    }
+
+    /// Deserialize a sequence of sub-trees, keyed by their keys.
+    fn sub_trees<'de, D, K, A>(deserializer: D) -> Result<BTreeMap<K, SubTree<K, A>>, D::Error>
     where
-        F: FnOnce(&mut A),
-        K: Ord + Clone,
+        D: Deserializer<'de>,
+        K: Deserialize<'de> + Ord + Clone,
+        A: Deserialize<'de>,
     {
-        let (prefix, node_key) = split_last(&keys);
-        match self.0.as_mut() {
-            Some(forest) => match NonEmpty::from_slice(&prefix) {
-                None => {
-                    // Insert the node at the root
-                    forest.insert_node_with(node_key, node, f)
+        let mut sub_trees = BTreeMap::new();
+        for sub_tree in Vec::<SubTree<K, A>>::deserialize(deserializer)? {
+            match sub_trees.entry(sub_tree.key().clone()) {
+                Entry::Occupied(_) => {
+                    return Err(de::Error::custom("duplicate key in tree"));
                 }
-                Some(_) => forest.insert_with(keys, node, f),
-            },
-            None => match NonEmpty::from_slice(&prefix) {
-                None => self.insert_forest(Tree::node(node_key, node)),
-                Some(_) => self.insert_forest(Tree::new(keys, node)),
-            },
+                Entry::Vacant(entry) => {
+                    entry.insert(sub_tree);
+                }
+            }
         }
+        Ok(sub_trees)
     }
 
-    pub fn find_node(&self, keys: &NonEmpty<K>) -> Option<&A>
+    impl<'de, K, A> Deserialize<'de> for Forest<K, A>
     where
-        K: Ord + Clone,
+        K: Deserialize<'de> + Ord + Clone,
+        A: Deserialize<'de>,
     {
-        self.0.as_ref().and_then(|trees| trees.find_node(keys))
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Ok(Forest::from_sub_trees(sub_trees(deserializer)?))
+        }
     }
 
-    pub fn find_branch(&self, keys: &NonEmpty<K>) -> Option<&Tree<K, A>>
+    impl<'de, K, A> Deserialize<'de> for Tree<K, A>
     where
-        K: Ord + Clone,
+        K: Deserialize<'de> + Ord + Clone,
+        A: Deserialize<'de>,
     {
-        self.0.as_ref().and_then(|trees| trees.find_branch(keys))
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let sub_trees = sub_trees(deserializer)?;
+            if sub_trees.is_empty() {
+                return Err(de::Error::invalid_length(0, &"a non-empty tree"));
+            }
+            Ok(Tree::from_sub_trees(sub_trees))
+        }
     }
 
-    /// Find a `SubTree` given a search path. If the path does not match
-    /// it will return `None`.
-    pub fn find(&self, keys: &NonEmpty<K>) -> Option<&SubTree<K, A>>
+    impl<'de, K, A> Deserialize<'de> for SubTree<K, A>
     where
-        K: Ord + Clone,
+        K: Deserialize<'de> + Ord + Clone,
+        A: Deserialize<'de>,
     {
-        self.0.as_ref().and_then(|trees| trees.find(&keys))
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_enum("SubTree", VARIANTS, SubTreeVisitor(PhantomData))
+        }
     }
 
-    #[allow(dead_code)]
-    pub fn maximum_by<F>(&self, f: F) -> Option<&A>
+    const VARIANTS: &[&str] = &["Node", "Branch"];
+
+    enum Variant {
+        Node,
+        Branch,
+    }
+
+    impl<'de> Deserialize<'de> for Variant {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_identifier(VariantVisitor)
+        }
+    }
+
+    struct VariantVisitor;
+
+    impl<'de> Visitor<'de> for VariantVisitor {
+        type Value = Variant;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a sub-tree variant")
+        }
+
+        fn visit_u64<E: de::Error>(self, index: u64) -> Result<Variant, E> {
+            match index {
+                0 => Ok(Variant::Node),
+                1 => Ok(Variant::Branch),
+                _ => Err(E::invalid_value(de::Unexpected::Unsigned(index), &self)),
+            }
+        }
+
+        fn visit_str<E: de::Error>(self, name: &str) -> Result<Variant, E> {
+            match name {
+                "Node" => Ok(Variant::Node),
+                "Branch" => Ok(Variant::Branch),
+                _ => Err(E::unknown_variant(name, VARIANTS)),
+            }
+        }
+    }
+
+    struct SubTreeVisitor<K, A>(PhantomData<(K, A)>);
+
+    impl<'de, K, A> Visitor<'de> for SubTreeVisitor<K, A>
     where
-        F: Fn(&A, &A) -> Ordering,
+        K: Deserialize<'de> + Ord + Clone,
+        A: Deserialize<'de>,
     {
-        self.0.as_ref().map(|trees| trees.maximum_by(&f))
+        type Value = SubTree<K, A>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a sub-tree")
+        }
+
+        fn visit_enum<E>(self, data: E) -> Result<Self::Value, E::Error>
+        where
+            E: EnumAccess<'de>,
+        {
+            match data.variant()? {
+                (Variant::Node, variant) => {
+                    let (key, value) =
+                        variant.struct_variant(&["key", "value"], Fields::new("value"))?;
+                    Ok(SubTree::Node { key, value })
+                }
+                (Variant::Branch, variant) => {
+                    let (key, forest) =
+                        variant.struct_variant(&["key", "forest"], Fields::new("forest"))?;
+                    Ok(SubTree::Branch {
+                        key,
+                        forest: Box::new(forest),
+                    })
+                }
+            }
+        }
     }
 
-    pub fn iter<'a>(&'a self) -> impl Iterator<Item = &A> + 'a {
-        self.0.iter().flat_map(|trees| trees.iter())
+    /// Visits the fields of a sub-tree variant, i.e. `key` followed by the field
+    /// named `second`.
+    struct Fields<K, V> {
+        second: &'static str,
+        marker: PhantomData<(K, V)>,
     }
 
-    #[allow(dead_code)]
-    pub fn iter_keys<'a>(&'a self) -> impl Iterator<Item = &K> + 'a {
-        self.0.iter().flat_map(|trees| trees.iter_keys())
+    impl<K, V> Fields<K, V> {
+        fn new(second: &'static str) -> Self {
+            Fields {
+                second,
+                marker: PhantomData,
+            }
+        }
+    }
+
+    impl<'de, K, V> Visitor<'de> for Fields<K, V>
+    where
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+    {
+        type Value = (K, V);
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a sub-tree with the fields `key` and `{}`", self.second)
+        }
+
+        fn visit_seq<S>(self, mut seq: S) -> Result<Self::Value, S::Error>
+        where
+            S: SeqAccess<'de>,
+        {
+            let key = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            let second = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+            Ok((key, second))
+        }
+
+        fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+        where
+            M: MapAccess<'de>,
+        {
+            let mut key = None;
+            let mut second = None;
+            while let Some(field) = map.next_key::<String>()? {
+                if field == "key" {
+                    if key.is_some() {
+                        return Err(de::Error::duplicate_field("key"));
+                    }
+                    key = Some(map.next_value()?);
+                } else if field == self.second {
+                    if second.is_some() {
+                        return Err(de::Error::duplicate_field(self.second));
+                    }
+                    second = Some(map.next_value()?);
+                } else {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+            let key = key.ok_or_else(|| de::Error::missing_field("key"))?;
+            let second = second.ok_or_else(|| de::Error::missing_field(self.second))?;
+            Ok((key, second))
+        }
     }
 }
 
@@ -598,7 +2310,7 @@ mod tests {
                 String::from("a"),
                 Tree::branch(
                     String::from("b"),
-                    Tree(NonEmpty::from((
+                    Tree::from(NonEmpty::from((
                         SubTree::Node {
                             key: String::from("c"),
                             value: c_node
@@ -636,7 +2348,7 @@ mod tests {
                 String::from("a"),
                 Tree::branch(
                     String::from("b"),
-                    Tree(NonEmpty::new(SubTree::Node {
+                    Tree::from(NonEmpty::new(SubTree::Node {
                         key: String::from("c"),
                         value: new_c_node
                     },))
@@ -743,7 +2455,7 @@ mod tests {
                 String::from("a"),
                 Tree::branch(
                     String::from("b"),
-                    Tree(NonEmpty::new(SubTree::Node {
+                    Tree::from(NonEmpty::new(SubTree::Node {
                         key: String::from("c"),
                         value: new_c_node
                     },))
@@ -811,7 +2523,7 @@ mod tests {
                 String::from("a"),
                 Tree::branch(
                     String::from("b"),
-                    Tree(NonEmpty::from((
+                    Tree::from(NonEmpty::from((
                         SubTree::Node {
                             key: String::from("c"),
                             value: c_node
@@ -855,10 +2567,10 @@ mod tests {
             tree,
             Forest(Some(Tree::branch(
                 String::from("a"),
-                Tree(NonEmpty::from((
+                Tree::from(NonEmpty::from((
                     SubTree::Branch {
                         key: String::from("b"),
-                        forest: Box::new(Tree(NonEmpty::from((
+                        forest: Box::new(Tree::from(NonEmpty::from((
                             SubTree::Node {
                                 key: String::from("c"),
                                 value: c_node
@@ -901,7 +2613,7 @@ mod tests {
 
         assert_eq!(
             tree,
-            Forest(Some(Tree(NonEmpty::from((
+            Forest(Some(Tree::from(NonEmpty::from((
                 SubTree::Branch {
                     key: String::from("a"),
                     forest: Box::new(Tree::branch(
@@ -954,10 +2666,10 @@ mod tests {
             tree,
             Forest(Some(Tree::branch(
                 String::from("a"),
-                Tree(NonEmpty::from((
+                Tree::from(NonEmpty::from((
                     SubTree::Branch {
                         key: String::from("b"),
-                        forest: Box::new(Tree(NonEmpty::from((
+                        forest: Box::new(Tree::from(NonEmpty::from((
                             SubTree::Node {
                                 key: String::from("c"),
                                 value: c_node
@@ -1061,6 +2773,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_find_by_ignoring_case() {
+        let path = NonEmpty::from((String::from("A"), vec![String::from("b")]));
+
+        let mut tree = Forest::root();
+
+        let b_node = TestNode { id: 1 };
+
+        tree.insert(&path, b_node.clone());
+
+        let eq = |k: &String, l: &String| k.to_lowercase() == l.to_lowercase();
+
+        assert_eq!(
+            tree.find_by(
+                &NonEmpty::from((String::from("a"), vec![String::from("B")])),
+                eq,
+                |_| true
+            ),
+            Some((
+                path,
+                &SubTree::Node {
+                    key: String::from("b"),
+                    value: b_node
+                }
+            ))
+        );
+
+        assert_eq!(
+            tree.find_by(
+                &NonEmpty::from((String::from("a"), vec![String::from("c")])),
+                eq,
+                |_| true
+            ),
+            None
+        );
+    }
+
     #[test]
     fn test_maximum_by_root_nodes() {
         let mut tree = Forest::root();
@@ -1161,4 +2910,487 @@ mod tests {
 
         assert_eq!(tree.iter().fold(0, |b, a| a.id + b), 4);
     }
+
+    #[test]
+    fn test_remove_prunes_empty_branches() {
+        let mut tree = Forest::root();
+
+        let a_node = TestNode { id: 1 };
+
+        let b_node = TestNode { id: 2 };
+
+        let c_path = NonEmpty::from((
+            String::from("a"),
+            vec![String::from("b"), String::from("c")],
+        ));
+        let d_path = NonEmpty::from((String::from("a"), vec![String::from("d")]));
+
+        tree.insert(&c_path, a_node.clone());
+        tree.insert(&d_path, b_node.clone());
+
+        assert_eq!(
+            tree.remove(&c_path),
+            Some(SubTree::Node {
+                key: String::from("c"),
+                value: a_node,
+            })
+        );
+        assert_eq!(tree.remove(&c_path), None);
+        assert_eq!(
+            tree.find(&NonEmpty::from((
+                String::from("a"),
+                vec![String::from("b")]
+            ))),
+            None
+        );
+
+        let mut expected = Forest::root();
+        expected.insert(&d_path, b_node);
+        assert_eq!(tree, expected);
+
+        assert!(tree.remove(&NonEmpty::new(String::from("a"))).is_some());
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_insert_and_find_deep_paths() {
+        let mut tree = Forest::root();
+        let keys = (0..10_000).map(|key| key.to_string()).collect::<Vec<_>>();
+        let path = NonEmpty::from_slice(&keys).unwrap();
+
+        tree.insert(&path, TestNode { id: 1 });
+        tree.insert(&path, TestNode { id: 2 });
+
+        assert_eq!(tree.find_node(&path), Some(&TestNode { id: 2 }));
+        assert_eq!(
+            tree.find_by(&path, |key, other| key == other, |_| true)
+                .map(|(found, _)| found),
+            Some(path)
+        );
+    }
+
+    fn merge_fixtures() -> (Forest<String, TestNode>, Forest<String, TestNode>) {
+        let path = |keys: &[&str]| {
+            NonEmpty::from_slice(&keys.iter().map(|key| key.to_string()).collect::<Vec<_>>())
+                .unwrap()
+        };
+
+        let mut left = Forest::root();
+        left.insert(&path(&["a", "b"]), TestNode { id: 1 });
+        left.insert(&path(&["a", "c"]), TestNode { id: 2 });
+        left.insert(&path(&["d"]), TestNode { id: 3 });
+
+        let mut right = Forest::root();
+        right.insert(&path(&["a", "c"]), TestNode { id: 4 });
+        right.insert(&path(&["a", "e"]), TestNode { id: 5 });
+        right.insert(&path(&["f"]), TestNode { id: 6 });
+
+        (left, right)
+    }
+
+    #[test]
+    fn test_merge_keep_left_and_right() {
+        let (left, right) = merge_fixtures();
+
+        let kept_left = left
+            .clone()
+            .merge(right.clone(), MergePolicy::KeepLeft)
+            .unwrap();
+        assert_eq!(
+            kept_left.iter().map(|node| node.id).collect::<Vec<_>>(),
+            vec![1, 2, 3, 6]
+        );
+
+        let kept_right = left.merge(right, MergePolicy::KeepRight).unwrap();
+        assert_eq!(
+            kept_right.iter().map(|node| node.id).collect::<Vec<_>>(),
+            vec![4, 5, 3, 6]
+        );
+    }
+
+    #[test]
+    fn test_merge_combine() {
+        let (mut left, right) = merge_fixtures();
+        left.insert(&NonEmpty::new(String::from("f")), TestNode { id: 7 });
+        left.insert(
+            &NonEmpty::from((String::from("d"), vec![String::from("g")])),
+            TestNode { id: 8 },
+        );
+
+        let merged = left.merge(right, MergePolicy::Combine).unwrap();
+
+        assert_eq!(
+            merged.iter().map(|node| node.id).collect::<Vec<_>>(),
+            vec![1, 4, 5, 8, 6]
+        );
+        assert!(merged.merge(Forest::root(), MergePolicy::Error).is_ok());
+    }
+
+    #[test]
+    fn test_merge_with_node_and_branch() {
+        let (left, right) = merge_fixtures();
+        let mut seen = vec![];
+        let merged = left
+            .clone()
+            .merge_with(right, |keys, left, right| {
+                seen.push(keys.clone());
+                match (left, right) {
+                    (SubTree::Node { key, value: left }, SubTree::Node { value: right, .. }) => {
+                        Ok::<_, ()>(SubTree::Node {
+                            key,
+                            value: TestNode {
+                                id: left.id * 10 + right.id,
+                            },
+                        })
+                    }
+                    (_, right) => Ok(right),
+                }
+            })
+            .unwrap();
+        assert_eq!(
+            seen,
+            vec![NonEmpty::from((String::from("a"), vec![String::from("c")]))]
+        );
+        assert_eq!(
+            merged.iter().map(|node| node.id).collect::<Vec<_>>(),
+            vec![1, 24, 5, 3, 6]
+        );
+
+        // The node `d` of the left `Forest` and the branch `d` of the right one are
+        // given to the closure, which decides which one is kept.
+        let d = NonEmpty::new(String::from("d"));
+        let mut branch = Forest::root();
+        branch.insert(
+            &NonEmpty::from((String::from("d"), vec![String::from("g")])),
+            TestNode { id: 8 },
+        );
+        let keep_left = |keys: &NonEmpty<String>, left, _| {
+            assert_eq!(keys, &d);
+            Ok::<_, ()>(left)
+        };
+        let merged = left.clone().merge_with(branch.clone(), keep_left).unwrap();
+        assert_eq!(
+            merged.iter().map(|node| node.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        let merged = branch.clone().merge_with(left.clone(), keep_left).unwrap();
+        assert_eq!(
+            merged.iter().map(|node| node.id).collect::<Vec<_>>(),
+            vec![1, 2, 8]
+        );
+        assert_eq!(
+            left.merge_with(branch, |keys, _, _| Err(keys.clone())),
+            Err(d)
+        );
+    }
+
+    #[test]
+    fn test_merge_error_on_conflict() {
+        let (left, right) = merge_fixtures();
+
+        assert_eq!(
+            left.clone().merge(right, MergePolicy::Error),
+            Err(MergeConflict {
+                keys: NonEmpty::from((String::from("a"), vec![String::from("c")])),
+            })
+        );
+        assert_eq!(
+            left.clone().merge(left.clone(), MergePolicy::Error),
+            Ok(left)
+        );
+    }
+
+    #[test]
+    fn test_cursor_prunes_empty_branches() {
+        let mut forest = Forest::root();
+        forest.insert(&NonEmpty::from(("a", vec!["b", "c"])), 1);
+        forest.insert(&NonEmpty::from(("a", vec!["d"])), 2);
+
+        let mut cursor = forest.cursor();
+        assert!(cursor.down(&"a"));
+        assert!(cursor.down(&"b"));
+        assert!(!cursor.down(&"c"));
+        assert_eq!(
+            cursor.focus_mut().remove(&NonEmpty::new("c")).is_some(),
+            true
+        );
+        assert!(cursor.up());
+        assert_eq!(cursor.keys(), vec![&"a"]);
+        assert_eq!(cursor.focus().iter_keys().collect::<Vec<_>>(), vec![&"d"]);
+
+        let mut expected = Forest::root();
+        expected.insert(&NonEmpty::from(("a", vec!["d"])), 2);
+        assert_eq!(cursor.into_forest(), expected);
+    }
+
+    #[test]
+    fn test_visit() {
+        #[derive(Default)]
+        struct Events(Vec<String>);
+
+        impl TreeVisitor<String, TestNode> for Events {
+            fn enter_branch(&mut self, keys: &[String], key: &String) -> Visit {
+                self.0.push(format!("enter {}/{}", keys.join("/"), key));
+                Visit::Continue
+            }
+
+            fn leave_branch(&mut self, keys: &[String], key: &String) -> Visit {
+                self.0.push(format!("leave {}/{}", keys.join("/"), key));
+                Visit::Continue
+            }
+
+            fn visit_node(&mut self, keys: &[String], key: &String, value: &TestNode) -> Visit {
+                self.0
+                    .push(format!("node {}/{} {}", keys.join("/"), key, value.id));
+                Visit::Continue
+            }
+        }
+
+        let (tree, _) = merge_fixtures();
+        let mut events = Events::default();
+        tree.visit(&mut events);
+
+        assert_eq!(
+            events.0,
+            vec![
+                "enter /a",
+                "node a/b 1",
+                "node a/c 2",
+                "leave /a",
+                "node /d 3"
+            ]
+        );
+
+        let mut events = Events::default();
+        Forest::root().visit(&mut events);
+        assert!(events.0.is_empty());
+    }
+
+    #[test]
+    fn test_visit_skip_and_stop() {
+        /// Records the nodes it visits, doing what `visits` says for each key.
+        struct Scripted {
+            visits: Vec<(&'static str, Visit)>,
+            events: Vec<String>,
+        }
+
+        impl Scripted {
+            fn visit(&self, key: &str) -> Visit {
+                self.visits
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map_or(Visit::Continue, |(_, visit)| *visit)
+            }
+        }
+
+        impl TreeVisitor<&'static str, u32> for Scripted {
+            fn enter_branch(&mut self, _keys: &[&'static str], key: &&'static str) -> Visit {
+                self.events.push(format!("enter {}", key));
+                self.visit(key)
+            }
+
+            fn leave_branch(&mut self, _keys: &[&'static str], key: &&'static str) -> Visit {
+                self.events.push(format!("leave {}", key));
+                Visit::Continue
+            }
+
+            fn visit_node(&mut self, _keys: &[&'static str], key: &&'static str, _: &u32) -> Visit {
+                self.events.push(format!("node {}", key));
+                self.visit(key)
+            }
+        }
+
+        let mut forest = Forest::root();
+        forest.insert(&NonEmpty::from(("a", vec!["b"])), 1);
+        forest.insert(&NonEmpty::from(("c", vec!["d"])), 2);
+        forest.insert(&NonEmpty::from(("c", vec!["e"])), 3);
+        forest.insert(&NonEmpty::new("f"), 4);
+        forest.insert(&NonEmpty::new("g"), 5);
+
+        let scripted = |visits| {
+            let mut visitor = Scripted {
+                visits,
+                events: vec![],
+            };
+            forest.visit(&mut visitor);
+            visitor.events
+        };
+
+        assert_eq!(
+            scripted(vec![("a", Visit::Skip), ("d", Visit::Skip)]),
+            vec!["enter a", "enter c", "node d", "leave c", "node f", "node g"]
+        );
+        assert_eq!(
+            scripted(vec![("f", Visit::Stop)]),
+            vec![
+                "enter a", "node b", "leave a", "enter c", "node d", "node e", "leave c", "node f"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prune_removes_emptied_branches() {
+        let mut forest = Forest::root();
+        forest.insert(&NonEmpty::from(("a", vec!["b", "c"])), 1);
+        forest.insert(&NonEmpty::from(("a", vec!["d"])), 2);
+        forest.insert(&NonEmpty::new("e"), 3);
+
+        let mut seen = vec![];
+        forest.prune(|keys, key, value| {
+            seen.push(format!("{}/{}", keys.join("/"), key));
+            *value != 1
+        });
+        assert_eq!(seen, vec!["a/b/c", "a/d", "/e"]);
+
+        let mut expected = Forest::root();
+        expected.insert(&NonEmpty::from(("a", vec!["d"])), 2);
+        expected.insert(&NonEmpty::new("e"), 3);
+        assert_eq!(forest, expected);
+
+        assert_eq!(forest.remove_node(&NonEmpty::new("a")), None);
+        assert_eq!(
+            forest.remove_node(&NonEmpty::from(("a", vec!["d"]))),
+            Some(2)
+        );
+        forest.prune(|_, _, _| false);
+        assert!(forest.is_empty());
+    }
+
+    #[test]
+    fn test_insert_repeated_keys() {
+        let mut forest = Forest::root();
+        forest.insert(&NonEmpty::from(("a", vec!["a"])), 1);
+        assert_eq!(
+            forest.find_node(&NonEmpty::from(("a", vec!["a"]))),
+            Some(&1)
+        );
+        assert_eq!(forest.find_node(&NonEmpty::new("a")), None);
+    }
+
+    #[test]
+    fn test_sizes_follow_changes() {
+        // The sizes of a `Forest` rebuilt by `filter_map` are counted from scratch,
+        // so they have to agree with the ones kept up to date along the way.
+        let check = |forest: &Forest<u8, u32>| {
+            let rebuilt = forest.filter_map(|_, _, value| Some(*value));
+            assert_eq!(forest, &rebuilt);
+            let paths = forest.iter_paths().collect::<Vec<_>>();
+            assert_eq!(forest.leaf_count(), paths.len());
+            assert_eq!(
+                forest.depth(),
+                paths.iter().map(|(keys, _)| keys.len()).max().unwrap_or(0)
+            );
+        };
+
+        let mut forest = Forest::root();
+        let mut seed = 7u32;
+        for value in 0..500 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let len = (seed >> 8) % 5 + 1;
+            let keys = (0..len)
+                .map(|index| ((seed >> (index * 3)) % 3) as u8)
+                .collect::<Vec<_>>();
+            let keys = NonEmpty::from_slice(&keys).unwrap();
+            match (seed >> 20) % 3 {
+                0 => {
+                    forest.remove(&keys);
+                }
+                _ => forest.insert(&keys, value),
+            }
+            check(&forest);
+        }
+
+        let mut cursor = forest.cursor();
+        for key in 0..3 {
+            if cursor.down(&key) {
+                cursor.focus_mut().remove(&NonEmpty::new(0));
+                cursor.up();
+            }
+        }
+        let forest = cursor.into_forest();
+        check(&forest);
+        assert_eq!(forest.node_count(), forest.iter_keys().count());
+    }
+
+    #[test]
+    fn test_diff_node_and_branch() {
+        let mut old = Forest::root();
+        old.insert(&NonEmpty::from(("a", vec!["b", "c"])), 1);
+        old.insert(&NonEmpty::new("d"), 2);
+
+        let mut new = Forest::root();
+        new.insert(&NonEmpty::new("a"), 1);
+        new.insert(&NonEmpty::from(("d", vec!["e"])), 2);
+
+        let diff = old.diff(&new, |old, new| old == new);
+        assert_eq!(
+            diff.removed,
+            vec![
+                NonEmpty::new("a"),
+                NonEmpty::from(("a", vec!["b"])),
+                NonEmpty::from(("a", vec!["b", "c"])),
+                NonEmpty::new("d"),
+            ]
+        );
+        assert_eq!(
+            diff.added,
+            vec![
+                NonEmpty::new("a"),
+                NonEmpty::new("d"),
+                NonEmpty::from(("d", vec!["e"])),
+            ]
+        );
+        assert!(diff.changed.is_empty());
+
+        let diff = Forest::root().diff(&old, |old, new| old == new);
+        assert_eq!(diff.added.len(), 4);
+        assert!(diff.removed.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use crate::file_system::{unsound, DefaultLabelRules, File, FileMode, Label};
+
+        let path = |path: &str| unsound::path::new(path).0;
+        let latin1 = Label::try_from_bytes(b"caf\xe9", &DefaultLabelRules).unwrap();
+
+        let mut forest: Forest<Label, File> = Forest::root();
+        forest.insert(&path("README.md"), File::new(b"# Surf"));
+        forest.insert(&path("src/lib.rs"), File::new(b"pub mod vcs;"));
+        forest.insert(
+            &path("src/vcs/git/build.sh"),
+            File::new(b"cargo build").with_mode(FileMode::Executable),
+        );
+        forest.insert(
+            &path("src/vcs/link"),
+            File::new(b"git").with_entry_mode(FileMode::Symlink),
+        );
+        forest.insert(
+            &path("docs/empty"),
+            File::new(b"").with_entry_mode(FileMode::EmptyDirectory),
+        );
+        forest.insert(
+            &NonEmpty::from((unsound::label::new("docs"), vec![latin1])),
+            File::new(b"\xff\x00"),
+        );
+
+        let json = serde_json::to_string(&forest).unwrap();
+        let deserialized: Forest<Label, File> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, forest);
+
+        let tree = forest.0.clone().unwrap();
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Tree<Label, File>>(&json).unwrap(),
+            tree
+        );
+
+        let duplicate = r#"[
+            {"Node": {"key": "lib.rs", "value": {"mode": "Regular", "contents": []}}},
+            {"Node": {"key": "lib.rs", "value": {"mode": "Regular", "contents": [1]}}}
+        ]"#;
+        assert!(serde_json::from_str::<Forest<Label, File>>(duplicate).is_err());
+        assert!(serde_json::from_str::<Label>(r#""src/lib.rs""#).is_err());
+    }
 }
@@ -40,19 +40,46 @@
 pub use git2;
 pub use git2::{BranchType, Error as Git2Error, Oid, Time};
 
+mod blame;
+mod blob;
 pub mod error;
+#[cfg(test)]
+mod fixture;
+mod history_cache;
+mod history_page;
+mod mailmap;
+mod name;
+mod retry;
+mod slow_op;
+mod snapshot;
+
+pub use blame::BlameLine;
+pub use history_cache::FileHistoryCache;
+use history_page::Walk;
+pub use history_page::{Commits, HistoryCursor, HistoryPage};
+pub use mailmap::{Mailmap, MailmapEntry};
+pub use name::{BranchName, RefName, Sha1, TagName};
+pub use retry::RetryPolicy;
+pub use snapshot::Snapshot;
 
+use crate::cache::Cache;
 use crate::file_system;
+use crate::file_system::digest::Digest;
 use crate::file_system::directory;
+use crate::file_system::error as file_error;
 use crate::tree::*;
 use crate::vcs;
+use crate::vcs::git::blob::GitBlob;
 use crate::vcs::git::error::*;
+use crate::vcs::git::slow_op::SlowOp;
 use crate::vcs::VCS;
 use nonempty::NonEmpty;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::str;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct Signature {
@@ -83,6 +110,20 @@ impl<'repo> TryFrom<git2::Signature<'repo>> for Signature {
     }
 }
 
+impl Signature {
+    /// Convert a `git2::Signature`, replacing any bytes of its name or email that
+    /// are not valid UTF-8, rather than failing.
+    pub(crate) fn lossy(signature: &git2::Signature) -> Self {
+        Signature {
+            name: String::from_utf8_lossy(signature.name_bytes()).into_owned(),
+            email: String::from_utf8_lossy(signature.email_bytes()).into_owned(),
+            time: signature.when(),
+        }
+    }
+}
+
+/// A commit, whose signatures and message are decoded lossily if they are not valid
+/// UTF-8.
 #[derive(Debug, Clone)]
 pub struct Commit {
     pub id: Oid,
@@ -90,6 +131,10 @@ pub struct Commit {
     pub committer: Signature,
     pub message: String,
     pub summary: String,
+    /// The id of the commit's tree.
+    pub tree: Oid,
+    /// The ids of the commit's own parents, regardless of any grafts.
+    pub parents: Vec<Oid>,
 }
 
 impl<'repo> TryFrom<git2::Commit<'repo>> for Commit {
@@ -97,12 +142,16 @@ impl<'repo> TryFrom<git2::Commit<'repo>> for Commit {
 
     fn try_from(commit: git2::Commit) -> Result<Self, Self::Error> {
         let id = commit.id();
-        let author = Signature::try_from(commit.author())?;
-        let committer = Signature::try_from(commit.committer())?;
-        let message_raw = commit.message_bytes();
-        let message = str::from_utf8(message_raw)?.into();
-        let summary_raw = commit.summary_bytes().expect("TODO");
-        let summary = str::from_utf8(summary_raw)?.into();
+        // Anything that is not valid UTF-8, e.g. a commit made with
+        // `i18n.commitEncoding` set to Latin-1, is decoded lossily, rather than
+        // failing every history the commit is in.
+        let author = Signature::lossy(&commit.author());
+        let committer = Signature::lossy(&commit.committer());
+        let message = String::from_utf8_lossy(commit.message_bytes()).into_owned();
+        let summary = commit
+            .summary_bytes()
+            .map(|summary| String::from_utf8_lossy(summary).into_owned())
+            .unwrap_or_default();
 
         Ok(Commit {
             id,
@@ -110,6 +159,8 @@ impl<'repo> TryFrom<git2::Commit<'repo>> for Commit {
             committer,
             message,
             summary,
+            tree: commit.tree_id(),
+            parents: commit.parent_ids().collect(),
         })
     }
 }
@@ -117,10 +168,95 @@ impl<'repo> TryFrom<git2::Commit<'repo>> for Commit {
 /// A `History` that uses `git2::Commit` as the underlying artifact.
 pub type History = vcs::History<Commit>;
 
+impl History {
+    /// Filter out the commits whose tree is the same as their parent's, i.e. that
+    /// don't change any files, keeping merge commits.
+    ///
+    /// A commit is only compared to its parent if its parent is part of the `History`,
+    /// and the trees are compared by their ids, without reading them. `None` is
+    /// returned if every commit is empty.
+    pub fn skip_empty(&self) -> Option<Self> {
+        let trees = self
+            .iter()
+            .map(|commit| (commit.id, commit.tree))
+            .collect::<HashMap<_, _>>();
+        let commits = self
+            .iter()
+            .filter(|commit| match commit.parents.as_slice() {
+                [parent] => trees.get(parent) != Some(&commit.tree),
+                _ => true,
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        NonEmpty::from_slice(&commits).map(vcs::History)
+    }
+
+    /// Keep only the first commit, in the order of the `History`, for each tree, so
+    /// that commits which result in the same files as a commit before them, e.g.
+    /// reverts of reverts, are filtered out.
+    pub fn dedup_by_tree(&self) -> Self {
+        let mut trees = HashSet::new();
+        let commits = self
+            .iter()
+            .filter(|commit| trees.insert(commit.tree))
+            .cloned()
+            .collect::<Vec<_>>();
+        vcs::History(NonEmpty::from_slice(&commits).expect("the first commit is always kept"))
+    }
+
+    /// Keep only the merge commits, i.e. the commits that have more than one parent,
+    /// returning `None` if there are none.
+    pub fn merges_only(&self) -> Option<Self> {
+        let commits = self
+            .iter()
+            .filter(|commit| commit.parents.len() > 1)
+            .cloned()
+            .collect::<Vec<_>>();
+        NonEmpty::from_slice(&commits).map(vcs::History)
+    }
+}
+
+impl History {
+    /// Build a `History` of all the commits reachable from any of `tips`, e.g.
+    /// the tips of all branches, where each commit appears once.
+    ///
+    /// The commits are ordered from most recent to least recent commit time,
+    /// with every commit coming before its parents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{History, Oid, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    ///
+    /// let master = Oid::from_str("3873745c8f6ffb45c990eb23b491d4b4b6182f95").unwrap();
+    /// let initial = Oid::from_str("e24124b7538658220b5aaf3b6ef53758f0a106dc").unwrap();
+    ///
+    /// let history = History::from_tips(&repo, &[initial, master]).unwrap();
+    ///
+    /// assert_eq!(history.first().id, master);
+    /// assert_eq!(history.iter().filter(|commit| commit.id == initial).count(), 1);
+    /// ```
+    pub fn from_tips(repo: &Repository, tips: &[Oid]) -> Result<Self, Error> {
+        repo.retry_policy
+            .retry(|| repo.tips_history(tips))
+            .context(|| Context::new("history_from_tips"))
+    }
+}
+
 /// Wrapper around the `git2`'s `git2::Repository` type.
 /// This is to to limit the functionality that we can do
 /// on the underlying object.
-pub struct Repository(pub(crate) git2::Repository);
+///
+/// It also holds the [`RetryPolicy`](struct.RetryPolicy.html) for operations on the repository,
+/// and the [`Snapshot`](struct.Snapshot.html) of its references that operations are pinned to, if any.
+pub struct Repository {
+    pub(crate) repo: git2::Repository,
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) slow_op_threshold: Option<Duration>,
+    pub(crate) snapshot: Option<Snapshot>,
+}
 
 #[derive(Clone)]
 struct OrderedCommit {
@@ -166,21 +302,71 @@ impl<'repo> Repository {
     /// assert_eq!(
     ///     branches,
     ///     vec![
-    ///         Branch::local(BranchName::new("master")),
-    ///         Branch::remote(BranchName::new("origin/HEAD")),
-    ///         Branch::remote(BranchName::new("origin/dev")),
-    ///         Branch::remote(BranchName::new("origin/master")),
+    ///         Branch::local("master".parse::<BranchName>().unwrap()),
+    ///         Branch::remote("origin/HEAD".parse::<BranchName>().unwrap()),
+    ///         Branch::remote("origin/dev".parse::<BranchName>().unwrap()),
+    ///         Branch::remote("origin/master".parse::<BranchName>().unwrap()),
     ///     ]
     /// );
     /// ```
     pub fn new(repo_uri: &str) -> Result<Self, Error> {
         git2::Repository::open(repo_uri)
-            .map(Repository)
+            .map(Repository::from)
             .map_err(Error::from)
     }
 
+    /// Set the [`RetryPolicy`](struct.RetryPolicy.html) for operations that fail because the
+    /// repository is being maintained concurrently. By default operations are not retried.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository, RetryPolicy};
+    ///
+    /// let repo = Repository::new("./data/git-platinum")
+    ///     .unwrap()
+    ///     .with_retry_policy(RetryPolicy::default());
+    /// let browser = Browser::new(repo).unwrap();
+    /// ```
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Set the threshold after which an operation on the repository, e.g. rendering
+    /// a snapshot or walking a history, is reported as slow, or `None` to stop
+    /// reporting slow operations, which is the default.
+    ///
+    /// Slow operations are reported as a [`tracing`](https://docs.rs/tracing) warning
+    /// with the fields `operation`, `repo`, `rev`, `elapsed_ms` and `objects_read`,
+    /// so that operators can find pathological repositories.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository};
+    /// use std::time::Duration;
+    ///
+    /// let repo = Repository::new("./data/git-platinum")
+    ///     .unwrap()
+    ///     .with_slow_op_threshold(Some(Duration::from_millis(500)));
+    /// let browser = Browser::new(repo).unwrap();
+    /// ```
+    pub fn with_slow_op_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.slow_op_threshold = threshold;
+        self
+    }
+
     pub fn list_branches(&self, filter: Option<BranchType>) -> Result<Vec<Branch>, Error> {
-        self.0
+        if let Some(snapshot) = &self.snapshot {
+            return Ok(snapshot
+                .refs()
+                .filter_map(|(name, _)| Branch::from_ref_name(name))
+                .filter(|branch| filter.is_none() || filter == Some(branch.locality))
+                .collect());
+        }
+
+        self.repo
             .branches(filter)
             .map_err(Error::from)
             .and_then(|mut branches| {
@@ -195,38 +381,456 @@ impl<'repo> Repository {
                     Ok(acc)
                 })
             })
+            .context(|| Context::new("list_branches"))
+    }
+
+    pub fn list_branch_tips(
+        &self,
+        filter: Option<BranchType>,
+    ) -> Result<Vec<(Branch, Oid)>, Error> {
+        if let Some(snapshot) = &self.snapshot {
+            return Ok(snapshot
+                .refs()
+                .filter_map(|(name, tip)| Some((Branch::from_ref_name(name)?, tip)))
+                .filter(|(branch, _)| filter.is_none() || filter == Some(branch.locality))
+                .collect());
+        }
+
+        self.repo
+            .branches(filter)
+            .map_err(Error::from)
+            .and_then(|mut branches| {
+                branches.try_fold(vec![], |mut acc, branch| {
+                    let (branch, branch_type) = branch?;
+                    let name = str::from_utf8(branch.name_bytes()?)?;
+                    // Symbolic references that do not resolve, e.g. a dangling
+                    // `origin/HEAD`, are left out, as in a `Snapshot`.
+                    if let Some(tip) = branch.get().resolve().ok().and_then(|r| r.target()) {
+                        let branch = Branch {
+                            name: BranchName(name.to_string()),
+                            locality: branch_type,
+                        };
+                        acc.push((branch, tip));
+                    }
+                    Ok(acc)
+                })
+            })
+            .context(|| Context::new("list_branch_tips"))
     }
 
     pub fn list_tags(&self) -> Result<Vec<TagName>, Error> {
-        let tags = self.0.tag_names(None)?;
+        if let Some(snapshot) = &self.snapshot {
+            return Ok(snapshot
+                .refs()
+                .filter_map(|(name, _)| name.strip_prefix("refs/tags/"))
+                .map(TagName::new)
+                .collect());
+        }
+
+        let tags = self
+            .repo
+            .tag_names(None)
+            .context(|| Context::new("list_tags"))?;
         Ok(tags
             .into_iter()
             .filter_map(|tag| tag.map(TagName::new))
             .collect())
     }
 
+    pub fn list_tag_details(&self) -> Result<Vec<Tag>, Error> {
+        let refs: Vec<(String, Oid)> = match &self.snapshot {
+            Some(snapshot) => snapshot
+                .refs()
+                .filter(|(name, _)| name.starts_with("refs/tags/"))
+                .map(|(name, target)| (name.to_string(), target))
+                .collect(),
+            None => self
+                .repo
+                .references_glob("refs/tags/*")
+                .map_err(Error::from)
+                .and_then(|mut references| {
+                    references.try_fold(vec![], |mut acc, reference| {
+                        // Tags whose names are not valid UTF-8 are left out, as in
+                        // `list_tags`.
+                        let reference = reference?;
+                        if let (Ok(name), Some(target)) =
+                            (str::from_utf8(reference.name_bytes()), reference.target())
+                        {
+                            acc.push((name.to_string(), target));
+                        }
+                        Ok(acc)
+                    })
+                })
+                .context(|| Context::new("list_tag_details"))?,
+        };
+
+        refs.into_iter()
+            .filter_map(|(name, target)| self.tag_details(&name, target).transpose())
+            .collect::<Result<Vec<_>, Error>>()
+            .context(|| Context::new("list_tag_details"))
+    }
+
+    /// Read the `Tag` for the reference `name` pointing to `target`, which is either
+    /// an annotated tag object or the commit itself.
+    fn tag_details(&self, name: &str, target: Oid) -> Result<Option<Tag>, Error> {
+        let name = TagName::new(name.trim_start_matches("refs/tags/"));
+        let object = self.repo.find_object(target, None)?;
+        let commit = match object.peel_to_commit() {
+            Ok(commit) => commit.id(),
+            Err(err) if err.code() == git2::ErrorCode::Peel => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        // A tagger or message that is not valid UTF-8 is decoded lossily, rather
+        // than failing the listing of every other tag.
+        let (tagger, message) = match object.as_tag() {
+            Some(tag) => (
+                tag.tagger().map(|tagger| Signature::lossy(&tagger)),
+                Some(String::from_utf8_lossy(tag.message_bytes().unwrap_or_default()).into_owned()),
+            ),
+            None => (None, None),
+        };
+
+        Ok(Some(Tag {
+            name,
+            target: commit,
+            tagger,
+            message,
+        }))
+    }
+
+    /// Find a sensible `History` to view, see
+    /// [`Browser::default_view`](struct.Browser.html#method.default_view).
+    pub(crate) fn default_view(&self) -> Result<(DefaultView, History), Error> {
+        // Only a missing `HEAD`, branch or tag falls through to the next choice, and
+        // any other error, e.g. a reference that is locked by a fetch, is returned.
+        match self.head() {
+            Ok(history) => return Ok((DefaultView::Head, history)),
+            Err(err) if err.is_not_found() => {}
+            Err(err) => return Err(err),
+        }
+
+        for branch in self.default_branches()? {
+            match self.get_history(Object::Branch(branch.name.clone())) {
+                Ok(history) => return Ok((DefaultView::DefaultBranch(branch), history)),
+                Err(Error::NotBranch) => {}
+                Err(err) if err.is_not_found() => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        // Local branches come first, and then the most recent tip. A remote's `HEAD`
+        // only points to one of its branches, and is not a branch itself.
+        let mut latest_branch = None;
+        for branch in self.list_branches(None)? {
+            if branch.locality == BranchType::Remote && branch.name.0.ends_with("/HEAD") {
+                continue;
+            }
+            if let Some(time) = self.tip_time(&branch.name.0)? {
+                let key = (branch.locality == BranchType::Local, time);
+                if latest_branch
+                    .as_ref()
+                    .is_none_or(|(latest, _)| key > *latest)
+                {
+                    latest_branch = Some((key, branch));
+                }
+            }
+        }
+        if let Some((_, branch)) = latest_branch {
+            let history = self.get_history(Object::Branch(branch.name.clone()))?;
+            return Ok((DefaultView::Branch(branch), history));
+        }
+
+        let mut latest_tag = None;
+        for tag in self.list_tags()? {
+            if let Some(time) = self.tip_time(&tag.0)? {
+                if latest_tag.as_ref().is_none_or(|(latest, _)| time > *latest) {
+                    latest_tag = Some((time, tag));
+                }
+            }
+        }
+        if let Some((_, tag)) = latest_tag {
+            let history = self.get_history(Object::Tag(tag.clone()))?;
+            return Ok((DefaultView::Tag(tag), history));
+        }
+
+        Err(Error::EmptyCommitHistory)
+    }
+
+    /// The branches that may be the default branch, in order: the branch
+    /// `refs/remotes/origin/HEAD` points to, as the local branch of the same name
+    /// and then as the remote branch, followed by `main` and `master`.
+    fn default_branches(&self) -> Result<Vec<Branch>, Error> {
+        let mut branches = vec![];
+        match self.repo.find_reference("refs/remotes/origin/HEAD") {
+            Ok(origin_head) => {
+                let target = origin_head
+                    .symbolic_target()
+                    .and_then(|target| target.strip_prefix("refs/remotes/origin/"));
+                if let Some(name) = target {
+                    branches.push(Branch::local(BranchName::new(name)));
+                    branches.push(Branch::remote(BranchName::new(&format!("origin/{}", name))));
+                }
+            }
+            Err(err) if err.code() == git2::ErrorCode::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        branches.push(Branch::local(BranchName::new("main")));
+        branches.push(Branch::local(BranchName::new("master")));
+        Ok(branches)
+    }
+
+    /// The commit time, in seconds, of the commit the reference `name` points to,
+    /// or `None` if there is no such reference.
+    fn tip_time(&self, name: &str) -> Result<Option<i64>, Error> {
+        let tip = match self.resolve_reference(name) {
+            Ok((_, tip)) => tip,
+            Err(err) if err.is_not_found() => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let commit = self.repo.find_object(tip, None)?.peel_to_commit()?;
+        Ok(Some(commit.time().seconds()))
+    }
+
+    /// Find the tips of the branch `branch_name` in each `Namespace`, using the pinned
+    /// `Snapshot` if there is one. See [`Browser::peer_views`](struct.Browser.html#method.peer_views).
+    pub fn peer_views(&self, branch_name: &BranchName) -> Result<Vec<(Namespace, Oid)>, Error> {
+        let branch = format!("refs/heads/{}", branch_name.0);
+        let refs: Vec<(String, Oid)> = match &self.snapshot {
+            Some(snapshot) => snapshot
+                .refs()
+                .map(|(name, target)| (name.to_string(), target))
+                .collect(),
+            None => self
+                .retry_policy
+                .retry(|| {
+                    // `*` matches across `/`, so the glob also matches nested namespaces,
+                    // which are told apart from other names by `Namespace::split`.
+                    let glob = format!("refs/namespaces/*/{}", branch);
+                    let mut refs = vec![];
+                    for reference in self.repo.references_glob(&glob)? {
+                        let reference = reference?;
+                        let name = String::from_utf8_lossy(reference.name_bytes()).into_owned();
+                        if let Some(target) = reference.resolve().ok().and_then(|r| r.target()) {
+                            refs.push((name, target));
+                        }
+                    }
+                    Ok(refs)
+                })
+                .context(|| Context::new("peer_views").rev(&branch))?,
+        };
+
+        let mut views = vec![];
+        for (name, target) in refs {
+            let namespace = match Namespace::split(&name) {
+                Some((namespace, name)) if name == branch => namespace,
+                _ => continue,
+            };
+            let commit = self
+                .retry_policy
+                .retry(|| Ok(self.repo.find_object(target, None)?.peel_to_commit()?.id()));
+            match commit {
+                Ok(commit) => views.push((namespace, commit)),
+                Err(err) if err.is_concurrent_maintenance() => {
+                    return Err(err).context(|| Context::new("peer_views").rev(&name))
+                }
+                // A reference to something other than a commit, e.g. a tree, is no
+                // view of the branch.
+                Err(_) => {}
+            }
+        }
+        views.sort();
+        Ok(views)
+    }
+
+    /// Count the commits reachable from `from` but not `to`, and the other way around.
+    pub fn divergence(&self, from: Oid, to: Oid) -> Result<Divergence, Error> {
+        let (ahead, behind) = self
+            .retry_policy
+            .retry(|| Ok(self.repo.graph_ahead_behind(from, to)?))
+            .context(|| Context::new("divergence").rev(from))?;
+        Ok(Divergence { ahead, behind })
+    }
+
+    /// Read the git object identified by `oid` from the object database.
+    /// See [`Browser::cat_object`](struct.Browser.html#method.cat_object).
+    pub fn cat_object(&self, oid: Oid) -> Result<RawObject, Error> {
+        self.retry_policy.retry(|| self.read_object(oid))
+    }
+
+    fn read_object(&self, oid: Oid) -> Result<RawObject, Error> {
+        let context = || Context::new("cat_object").rev(oid);
+        let odb = self.repo.odb().context(context)?;
+        let object = odb.read(oid).context(context)?;
+        let kind = object.kind();
+
+        let bytes = match kind {
+            // Trees are stored in a binary format, so we print
+            // one line per entry as `git cat-file -p` would.
+            git2::ObjectType::Tree => {
+                let tree = self.repo.find_tree(oid).context(context)?;
+                let mut bytes = vec![];
+                for entry in tree.iter() {
+                    let kind = entry.kind().map_or("unknown", |kind| kind.str());
+                    bytes.extend_from_slice(
+                        format!("{:06o} {} {}\t", entry.filemode(), kind, entry.id()).as_bytes(),
+                    );
+                    bytes.extend_from_slice(entry.name_bytes());
+                    bytes.push(b'\n');
+                }
+                bytes
+            }
+            _ => object.data().to_vec(),
+        };
+
+        Ok(RawObject { kind, bytes })
+    }
+
+    /// Get the [`GitLabelRules`](struct.GitLabelRules.html) used to validate the
+    /// names of tree entries in this repository.
+    pub fn label_rules(&self) -> GitLabelRules {
+        GitLabelRules::from_repository(&self.repo)
+    }
+
     /// Get a particular `Commit`.
     pub(crate) fn get_commit(&'repo self, sha: Sha1) -> Result<git2::Commit<'repo>, Error> {
-        let oid = git2::Oid::from_str(&sha.0)?;
-        let commit = self.0.find_commit(oid)?;
-        Ok(commit)
+        let context = || Context::new("commit").rev(&sha.0);
+        self.retry_policy
+            .retry(|| self.find_commit_by_prefix(&sha).context(context))
+    }
+
+    /// Find the commit whose id starts with the hex digits of `sha`, which
+    /// `Oid::from_str` would pad with zeros if it is abbreviated.
+    fn find_commit_by_prefix(&'repo self, sha: &Sha1) -> Result<git2::Commit<'repo>, Error> {
+        let prefix = Oid::from_str(&sha.0)?;
+        let oid = self
+            .repo
+            .odb()?
+            .exists_prefix(prefix, sha.0.len())
+            .map_err(|err| match err.code() {
+                git2::ErrorCode::NotFound => Error::RevNotFound(sha.0.clone()),
+                git2::ErrorCode::Ambiguous => Error::AmbiguousRev(sha.0.clone()),
+                _ => err.into(),
+            })?;
+        Ok(self.repo.find_commit(oid)?)
     }
 
     /// Build a `History` using the `head` reference.
     pub(crate) fn head(&'repo self) -> Result<History, Error> {
-        let head = self.0.head()?;
-        self.to_history(&head)
+        let context = || Context::new("history").rev("HEAD");
+        self.retry_policy.retry(|| {
+            let head = match &self.snapshot {
+                Some(snapshot) => snapshot.head().ok_or(Error::EmptyCommitHistory)?,
+                None => self
+                    .repo
+                    .head()
+                    .and_then(|head| head.peel_to_commit())?
+                    .id(),
+            };
+            self.to_history(head).context(context)
+        })
     }
 
-    /// Turn a `git2::Reference` into a `History` by completing
-    /// a revwalk over the first commit in the reference.
-    pub(crate) fn to_history(
-        &'repo self,
-        history: &git2::Reference<'repo>,
-    ) -> Result<History, Error> {
-        let head = history.peel_to_commit()?;
+    /// Build a `History` from the commit `rev` names, see
+    /// [`Browser::rev_parse`](struct.Browser.html#method.rev_parse).
+    pub(crate) fn rev(&'repo self, rev: &str) -> Result<History, Error> {
+        let context = || Context::new("history").rev(rev);
+        self.retry_policy.retry(|| {
+            let target = self.parse_rev(rev).context(context)?;
+            self.to_history(target).context(context)
+        })
+    }
+
+    /// Resolve the revision `rev` to the commit it names.
+    /// See [`Browser::rev_parse`](struct.Browser.html#method.rev_parse).
+    pub fn rev_parse(&self, rev: &str) -> Result<Oid, Error> {
+        self.retry_policy
+            .retry(|| self.parse_rev(rev))
+            .context(|| Context::new("rev_parse").rev(rev))
+    }
+
+    fn parse_rev(&self, rev: &str) -> Result<Oid, Error> {
+        let spec = match &self.snapshot {
+            Some(snapshot) => Self::pin_rev(snapshot, rev)?,
+            None => rev.to_string(),
+        };
+        let object = self
+            .repo
+            .revparse_single(&spec)
+            .map_err(|err| match err.code() {
+                git2::ErrorCode::NotFound => Error::RevNotFound(rev.to_string()),
+                git2::ErrorCode::Ambiguous => Error::AmbiguousRev(rev.to_string()),
+                _ => err.into(),
+            })?;
+        Ok(object.peel_to_commit()?.id())
+    }
+
+    /// Replace the reference `rev` starts with, e.g. `master` in `master~2`, with
+    /// its target in `snapshot`, so that it is not looked up in the repository.
+    ///
+    /// Reflog entries, e.g. `master@{1}`, are not part of a `Snapshot` and are left
+    /// as they are, as are object ids.
+    fn pin_rev(snapshot: &Snapshot, rev: &str) -> Result<String, Error> {
+        let end = rev
+            .find(&['~', '^', ':'][..])
+            .into_iter()
+            .chain(rev.find("@{"))
+            .min()
+            .unwrap_or(rev.len());
+        let (base, suffix) = rev.split_at(end);
+        if suffix.starts_with("@{") {
+            return Ok(rev.to_string());
+        }
+
+        let target = match base {
+            "HEAD" | "@" => snapshot.head(),
+            _ => snapshot.resolve(base).map(|(_, target)| target),
+        };
+        match target {
+            Some(target) => Ok(format!("{}{}", target, suffix)),
+            None if base.len() >= 4 && base.chars().all(|c| c.is_ascii_hexdigit()) => {
+                Ok(rev.to_string())
+            }
+            None => Err(Error::RevNotFound(rev.to_string())),
+        }
+    }
+
+    /// Capture a [`Snapshot`](struct.Snapshot.html) of the references of this repository.
+    pub fn snapshot(&self) -> Result<Snapshot, Error> {
+        self.retry_policy
+            .retry(|| Snapshot::capture(&self.repo))
+            .context(|| Context::new("snapshot_refs"))
+    }
+
+    /// Look up a reference by its short `name`, returning its full name and
+    /// target, using the pinned `Snapshot` if there is one.
+    fn resolve_reference(&self, name: &str) -> Result<(String, Oid), Error> {
+        match &self.snapshot {
+            Some(snapshot) => snapshot
+                .resolve(name)
+                .map(|(name, target)| (name.to_string(), target))
+                .ok_or_else(|| Error::RevNotFound(name.to_string())),
+            None => {
+                let reference =
+                    self.repo
+                        .resolve_reference_from_short_name(name)
+                        .map_err(|err| match err.code() {
+                            git2::ErrorCode::NotFound => Error::RevNotFound(name.to_string()),
+                            _ => err.into(),
+                        })?;
+                let name = String::from_utf8_lossy(reference.name_bytes()).into_owned();
+                Ok((name, reference.peel_to_commit()?.id()))
+            }
+        }
+    }
+
+    /// Turn the object `head`, e.g. the target of a reference, into a `History`
+    /// by completing a revwalk over the commit it points to.
+    pub(crate) fn to_history(&'repo self, head: Oid) -> Result<History, Error> {
+        let head = self.repo.find_object(head, None)?.peel_to_commit()?;
+        let mut slow_op = SlowOp::start("history", self, head.id());
         let mut commits = Vec::new();
-        let mut revwalk = self.0.revwalk()?;
+        let mut revwalk = self.repo.revwalk()?;
 
         // Set the revwalk to the head commit
         revwalk.push(head.id())?;
@@ -235,7 +839,77 @@ impl<'repo> Repository {
             // The revwalk iter returns results so
             // we unpack these and push them to the history
             let commit_id: Oid = commit_result_id?;
-            let commit = Commit::try_from(self.0.find_commit(commit_id)?)?;
+            let commit = Commit::try_from(self.repo.find_commit(commit_id)?)?;
+            slow_op.read_object();
+            commits.push(commit);
+        }
+
+        NonEmpty::from_slice(&commits)
+            .map(vcs::History)
+            .ok_or(Error::EmptyCommitHistory)
+    }
+
+    /// Build a `History` from `tip`, following the parents given by `grafts`
+    /// instead of the commits' own parents where they are provided.
+    ///
+    /// The commits are ordered from most recent to least recent commit time.
+    pub(crate) fn grafted_history(
+        &'repo self,
+        tip: Oid,
+        grafts: &Grafts,
+    ) -> Result<History, Error> {
+        let mut slow_op = SlowOp::start("grafted_history", self, tip);
+        let mut commits = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = BinaryHeap::new();
+
+        let tip = self.repo.find_commit(tip)?;
+        seen.insert(tip.id());
+        queue.push((tip.time().seconds(), tip.id()));
+
+        while let Some((_, commit_id)) = queue.pop() {
+            let commit = self.repo.find_commit(commit_id)?;
+            slow_op.read_object();
+            let parents = match grafts.parents(&commit_id) {
+                Some(parents) => parents.to_vec(),
+                None => commit.parent_ids().collect(),
+            };
+
+            for parent_id in parents {
+                if seen.insert(parent_id) {
+                    let parent = self.repo.find_commit(parent_id)?;
+                    queue.push((parent.time().seconds(), parent_id));
+                }
+            }
+
+            commits.push(Commit::try_from(commit)?);
+        }
+
+        NonEmpty::from_slice(&commits)
+            .map(vcs::History)
+            .ok_or(Error::EmptyCommitHistory)
+    }
+
+    /// Build a `History` of the union of the histories of `tips`, containing
+    /// each commit once.
+    ///
+    /// The commits are ordered from most recent to least recent commit time,
+    /// with every commit coming before its parents.
+    pub(crate) fn tips_history(&'repo self, tips: &[Oid]) -> Result<History, Error> {
+        let first = tips.first().ok_or(Error::EmptyCommitHistory)?;
+        let mut slow_op = SlowOp::start("tips_history", self, first);
+        let mut commits = Vec::new();
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME);
+
+        for tip in tips {
+            let tip = self.repo.find_object(*tip, None)?.peel_to_commit()?;
+            revwalk.push(tip.id())?;
+        }
+
+        for commit_id in revwalk {
+            let commit = Commit::try_from(self.repo.find_commit(commit_id?)?)?;
+            slow_op.read_object();
             commits.push(commit);
         }
 
@@ -258,7 +932,8 @@ impl<'repo> Repository {
         commit_id: &Oid,
         file_histories: &mut Forest<file_system::Label, NonEmpty<OrderedCommit>>,
     ) -> Result<(), Error> {
-        let mut revwalk = self.0.revwalk()?;
+        let mut slow_op = SlowOp::start("file_history", self, commit_id);
+        let mut revwalk = self.repo.revwalk()?;
 
         // Set the revwalk to the head commit
         revwalk.push(commit_id.clone())?;
@@ -266,7 +941,8 @@ impl<'repo> Repository {
         for (id, commit_result) in revwalk.enumerate() {
             let parent_id = commit_result?;
 
-            let parent = self.0.find_commit(parent_id)?;
+            let parent = self.repo.find_commit(parent_id)?;
+            slow_op.read_object();
             let paths = self.diff_commit_and_parents(&parent)?;
             let parent_commit = Commit::try_from(parent)?;
             for path in paths {
@@ -285,74 +961,484 @@ impl<'repo> Repository {
         Ok(())
     }
 
-    fn diff_commit_and_parents(
+    /// Walk the history from `tip`, stopping at `hide` if it is given, collecting the
+    /// commits that touched `path`, or anything below it, most recent first, see
+    /// [`touches`](#method.touches).
+    pub(crate) fn path_history(
         &'repo self,
-        commit: &'repo git2::Commit,
-    ) -> Result<Vec<file_system::Path>, Error> {
-        let mut parents = commit.parents();
-        let head = parents.next();
-        let mut touched_files = vec![];
+        tip: Oid,
+        hide: Option<Oid>,
+        path: &file_system::Path,
+    ) -> Result<Vec<Commit>, Error> {
+        let mut slow_op = SlowOp::start("path_history", self, &tip);
+        // The commits that are not reachable from `hide`, whose ids are cheap to walk.
+        let shown = match hide {
+            Some(hide) => {
+                let mut revwalk = self.repo.revwalk()?;
+                revwalk.push(tip)?;
+                revwalk.hide(hide)?;
+                Some(revwalk.collect::<Result<HashSet<_>, _>>()?)
+            }
+            None => None,
+        };
 
-        let mut add_deltas = |diff: git2::Diff| -> Result<(), Error> {
-            let deltas = diff.deltas();
+        if shown.as_ref().is_some_and(|shown| !shown.contains(&tip)) {
+            return Ok(vec![]);
+        }
 
-            for delta in deltas {
-                let new = delta.new_file().path().ok_or(Error::FileDiffException)?;
-                let path = file_system::Path::try_from(new.to_path_buf())?;
-                touched_files.push(path);
+        let mut walk = Walk::new(&self.repo, &[tip])?;
+        let mut commits = vec![];
+        while let Some(commit) = walk.pop()? {
+            slow_op.read_object();
+            let (touched, parents) = self.touches(&commit, path)?;
+            for parent in parents {
+                if shown.as_ref().is_none_or(|shown| shown.contains(&parent)) {
+                    walk.push(parent)?;
+                }
+            }
+            if touched {
+                commits.push(Commit::try_from(commit)?);
             }
+        }
 
-            Ok(())
+        Ok(commits)
+    }
+
+    /// Check whether `commit` touched `path`, or anything below it, the way git's
+    /// default history simplification does, along with the parents to walk on to.
+    ///
+    /// A commit that has the same `path` as one of its parents did not touch it, and
+    /// only that parent is walked on to, so that the changes of the other side of a
+    /// merge that took `path` from one side are left out. Otherwise, all parents are
+    /// walked on to, and a commit without parents touched `path` if it has it.
+    pub(crate) fn touches(
+        &'repo self,
+        commit: &git2::Commit,
+        path: &file_system::Path,
+    ) -> Result<(bool, Vec<Oid>), Error> {
+        let labels = path
+            .iter()
+            .skip_while(|label| label.is_root())
+            .collect::<Vec<_>>();
+        let entry = self.entry_at(commit.tree()?, &labels)?;
+        for parent in commit.parents() {
+            if self.entry_at(parent.tree()?, &labels)? == entry {
+                return Ok((false, vec![parent.id()]));
+            }
+        }
+        Ok((
+            entry.is_some() || commit.parent_count() > 0,
+            commit.parent_ids().collect(),
+        ))
+    }
+
+    /// Get the id and mode of the entry at `labels` in `tree`, or of `tree` itself if
+    /// there are no `labels`.
+    fn entry_at(
+        &'repo self,
+        tree: git2::Tree<'repo>,
+        labels: &[&file_system::Label],
+    ) -> Result<Option<(Oid, i32)>, Error> {
+        let (name, directories) = match labels.split_last() {
+            Some(split) => split,
+            None => return Ok(Some((tree.id(), 0o040_000))),
         };
+        let mut tree = tree;
+        for directory in directories {
+            let id = match tree
+                .iter()
+                .find(|entry| entry.name_bytes() == directory.as_bytes())
+            {
+                Some(entry) if entry.kind() == Some(git2::ObjectType::Tree) => entry.id(),
+                _ => return Ok(None),
+            };
+            tree = self.repo.find_tree(id)?;
+        }
+        Ok(tree
+            .iter()
+            .find(|entry| entry.name_bytes() == name.as_bytes())
+            .map(|entry| (entry.id(), entry.filemode())))
+    }
 
-        match head {
-            None => {
-                let diff = self.diff_commits(&commit, None)?;
-                add_deltas(diff)?;
+    /// Find the commits reachable from `head` but not `base`, oldest first, along with
+    /// the commit reachable from `base` but not `head` that has the same patch id, if any.
+    /// See [`Browser::cherry`](struct.Browser.html#method.cherry).
+    pub(crate) fn cherry(&'repo self, base: Oid, head: Oid) -> Result<Vec<Cherry>, Error> {
+        let mut slow_op = SlowOp::start("cherry", self, &head);
+        let mut labels =
+            file_system::LabelInterner::new(GitLabelRules::from_repository(&self.repo));
+
+        let mut upstream = HashMap::new();
+        for commit in self.range(base, head)? {
+            slow_op.read_object();
+            if commit.parent_count() <= 1 {
+                let patch_id = self.patch_id(&commit, &mut labels)?;
+                upstream.entry(patch_id).or_insert_with(|| commit.id());
             }
-            Some(parent) => {
-                let diff = self.diff_commits(&commit, Some(&parent))?;
-                add_deltas(diff)?;
+        }
 
-                for parent in parents {
-                    let diff = self.diff_commits(&commit, Some(&parent))?;
-                    add_deltas(diff)?;
-                }
+        let mut cherries = vec![];
+        for commit in self.range(head, base)? {
+            slow_op.read_object();
+            if commit.parent_count() <= 1 {
+                let patch_id = self.patch_id(&commit, &mut labels)?;
+                cherries.push(Cherry {
+                    upstream: upstream.get(&patch_id).copied(),
+                    commit: Commit::try_from(commit)?,
+                });
             }
         }
 
-        Ok(touched_files)
+        Ok(cherries)
     }
 
-    fn diff_commits(
-        &'repo self,
-        left: &'repo git2::Commit,
-        right: Option<&'repo git2::Commit>,
-    ) -> Result<git2::Diff, Error> {
-        let left_tree = left.tree()?;
-        let right_tree = right.map_or(Ok(None), |commit| commit.tree().map(Some))?;
+    /// The commits reachable from `tip` but not `hide`, oldest first.
+    fn range(&'repo self, tip: Oid, hide: Oid) -> Result<Vec<git2::Commit<'repo>>, Error> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE);
+        revwalk.push(tip)?;
+        revwalk.hide(hide)?;
+        revwalk
+            .map(|oid| Ok(self.repo.find_commit(oid?)?))
+            .collect()
+    }
 
-        let diff = self
-            .0
-            .diff_tree_to_tree(Some(&left_tree), right_tree.as_ref(), None)?;
+    /// Get the patch id of the changes `commit` made to its first parent, see
+    /// [`Diff::patch_id`](../../diff/struct.Diff.html#method.patch_id).
+    ///
+    /// Only the files that changed are read, into `Directory`s that hold nothing else.
+    /// The patch id is the hash of the patch, see [`PatchHash`].
+    fn patch_id(
+        &'repo self,
+        commit: &git2::Commit,
+        labels: &mut file_system::LabelInterner<GitLabelRules>,
+    ) -> Result<Oid, Error> {
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+        let git_diff = self
+            .repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+        let (old, new) = self.changed_files(&git_diff, parent_tree.as_ref(), &tree, labels)?;
 
-        Ok(diff)
+        let diff = crate::diff::Diff::diff(old.clone(), new.clone()).map_err(Error::Diff)?;
+        Ok(diff.patch_id_with::<PatchHash>(&old, &new)?)
     }
-}
 
-impl vcs::GetVCS<Error> for Repository {
-    type RepoId = String;
+    /// Compute the [`Diff`](../../diff/struct.Diff.html) between the trees of the
+    /// commits `from` and `to`.
+    /// See [`Browser::diff`](struct.Browser.html#method.diff).
+    pub(crate) fn diff(&'repo self, from: Oid, to: Oid) -> Result<crate::diff::Diff, Error> {
+        let mut labels =
+            file_system::LabelInterner::new(GitLabelRules::from_repository(&self.repo));
+        let old_tree = self.repo.find_commit(from)?.tree()?;
+        let new_tree = self.repo.find_commit(to)?.tree()?;
+        let mut git_diff = self
+            .repo
+            .diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+        git_diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
+        let (old, new) = self.changed_files(&git_diff, Some(&old_tree), &new_tree, &mut labels)?;
+
+        let mut diff = crate::diff::Diff::diff(old, new).map_err(Error::Diff)?;
+
+        // `Diff::diff` only sees the files on either side, so a rename found by git
+        // shows up as a deletion and a creation, which are replaced by a move.
+        for delta in git_diff.deltas() {
+            if delta.status() != git2::Delta::Renamed {
+                continue;
+            }
+            let (old_path, new_path) = match (
+                self.diff_path(&delta.old_file(), &mut labels),
+                self.diff_path(&delta.new_file(), &mut labels),
+            ) {
+                (Some(old_path), Some(new_path)) => (old_path, new_path),
+                _ => continue,
+            };
+            let deleted = diff.deleted.iter().position(|file| file.0 == old_path);
+            let created = diff.created.iter().position(|file| file.0 == new_path);
+            if let (Some(deleted), Some(created)) = (deleted, created) {
+                diff.deleted.remove(deleted);
+                diff.created.remove(created);
+                diff.moved
+                    .push(crate::diff::MoveFile { old_path, new_path });
+            }
+        }
+
+        Ok(diff)
+    }
+
+    /// Read the files of `git_diff`, the diff of `old_tree` and `new_tree`, into a
+    /// `Directory` for each side, which hold nothing else.
+    fn changed_files(
+        &'repo self,
+        git_diff: &git2::Diff,
+        old_tree: Option<&git2::Tree>,
+        new_tree: &git2::Tree,
+        labels: &mut file_system::LabelInterner<GitLabelRules>,
+    ) -> Result<(directory::Directory, directory::Directory), Error> {
+        let mut old = directory::Directory::root();
+        let mut new = directory::Directory::root();
+        for delta in git_diff.deltas() {
+            self.insert_diff_file(&mut old, old_tree, &delta.old_file(), labels)?;
+            self.insert_diff_file(&mut new, Some(new_tree), &delta.new_file(), labels)?;
+        }
+
+        Ok((old, new))
+    }
+
+    /// Insert the blob or submodule of one side of a diff delta into `directory`,
+    /// unless that side is absent.
+    fn insert_diff_file(
+        &'repo self,
+        directory: &mut directory::Directory,
+        tree: Option<&git2::Tree>,
+        file: &git2::DiffFile,
+        labels: &mut file_system::LabelInterner<GitLabelRules>,
+    ) -> Result<(), Error> {
+        let (tree, path) = match (tree, file.path_bytes()) {
+            (Some(tree), Some(path)) if !file.id().is_zero() => (tree, path),
+            _ => return Ok(()),
+        };
+        let entry = match file.path() {
+            Some(path) => tree.get_path(path)?,
+            None => return Ok(()),
+        };
+        let path = match path
+            .split(|byte| *byte == b'/')
+            .map(|label| labels.intern(label))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(path) => path,
+            // Left out, like the entry is when rendering the tree, see `collect_tree`.
+            Err(err) => {
+                tracing::warn!(
+                    path = %String::from_utf8_lossy(path),
+                    error = ?err,
+                    "skipping diff entry with an invalid name"
+                );
+                return Ok(());
+            }
+        };
+        file_system::check_depth(path.len())?;
+        let path = match NonEmpty::from_slice(&path) {
+            Some(path) => file_system::Path(path),
+            None => return Ok(()),
+        };
+
+        match entry.kind() {
+            Some(git2::ObjectType::Blob) => {
+                let blob = self.repo.find_blob(entry.id())?;
+                let mode = match entry.filemode() {
+                    0o100_755 => directory::FileMode::Executable,
+                    0o120_000 => directory::FileMode::Symlink,
+                    _ => directory::FileMode::Regular,
+                };
+                let file = directory::File::new(blob.content())
+                    .with_entry_mode(mode)
+                    .with_metadata(Sha1::from(entry.id()));
+                directory.insert_file(&path, file);
+            }
+            Some(git2::ObjectType::Commit) => {
+                directory.insert_submodule(&path, &entry.id().to_string())
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// The `Path` of one side of a diff delta, as `Diff::diff` reports it, unless that
+    /// side is absent or its name is invalid.
+    fn diff_path(
+        &'repo self,
+        file: &git2::DiffFile,
+        labels: &mut file_system::LabelInterner<GitLabelRules>,
+    ) -> Option<file_system::Path> {
+        let labels = file
+            .path_bytes()?
+            .split(|byte| *byte == b'/')
+            .map(|label| labels.intern(label))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        Some(file_system::Path::with_root(&labels))
+    }
+
+    /// Walk the history from `commit`, finding the most recent commit that touched each
+    /// entry of the directory at `path`, in a single walk that stops once every entry
+    /// has been seen.
+    pub(crate) fn last_commits_for(
+        &'repo self,
+        commit: &Commit,
+        path: &file_system::Path,
+    ) -> Result<HashMap<file_system::Label, Commit>, Error> {
+        let mut slow_op = SlowOp::start("last_commits_for", self, &commit.id);
+        let prefix = path
+            .iter()
+            .skip_while(|label| label.is_root())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let tree = self.repo.find_commit(commit.id)?.tree()?;
+        slow_op.read_object();
+        let tree = if prefix.is_empty() {
+            tree
+        } else {
+            // The tree is walked by the bytes of each label, since they may not be
+            // valid UTF-8.
+            let mut tree = tree;
+            for label in &prefix {
+                let sub_tree = tree
+                    .iter()
+                    .find(|entry| entry.name_bytes() == label.as_bytes())
+                    .filter(|entry| entry.kind() == Some(git2::ObjectType::Tree))
+                    .map(|entry| entry.id());
+                tree = match sub_tree {
+                    Some(sub_tree) => self.repo.find_tree(sub_tree)?,
+                    None => return Ok(HashMap::new()),
+                };
+                slow_op.read_object();
+            }
+            tree
+        };
+
+        let mut pending = tree
+            .iter()
+            .filter_map(|entry| {
+                file_system::Label::try_from_bytes(
+                    entry.name_bytes(),
+                    &file_system::DefaultLabelRules,
+                )
+                .ok()
+            })
+            .collect::<HashSet<_>>();
+        let mut last_commits = HashMap::new();
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push(commit.id)?;
+
+        for commit_result in revwalk {
+            if pending.is_empty() {
+                break;
+            }
+
+            let commit = self.repo.find_commit(commit_result?)?;
+            slow_op.read_object();
+            let touched = self
+                .diff_commit_and_parents(&commit)?
+                .into_iter()
+                .filter_map(|touched_path| {
+                    let labels = touched_path
+                        .iter()
+                        .skip_while(|label| label.is_root())
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    if labels.len() > prefix.len() && labels.starts_with(&prefix) {
+                        Some(labels[prefix.len()].clone())
+                    } else {
+                        None
+                    }
+                })
+                .filter(|entry| pending.remove(entry))
+                .collect::<Vec<_>>();
+
+            if !touched.is_empty() {
+                let commit = Commit::try_from(commit)?;
+                for entry in touched {
+                    last_commits.insert(entry, commit.clone());
+                }
+            }
+        }
+
+        Ok(last_commits)
+    }
+
+    fn diff_commit_and_parents(
+        &'repo self,
+        commit: &'repo git2::Commit,
+    ) -> Result<Vec<file_system::Path>, Error> {
+        let mut parents = commit.parents();
+        let head = parents.next();
+        let mut touched_files = vec![];
+
+        let mut add_deltas = |diff: git2::Diff| -> Result<(), Error> {
+            let deltas = diff.deltas();
+
+            for delta in deltas {
+                let new = delta
+                    .new_file()
+                    .path_bytes()
+                    .ok_or(Error::FileDiffException)?;
+                // The labels are made from the bytes of the path, so that paths that
+                // are not valid UTF-8 are kept too.
+                let mut path = file_system::Path::root();
+                for (depth, label) in new.split(|byte| *byte == b'/').enumerate() {
+                    file_system::check_depth(depth + 1)?;
+                    path.push(file_system::Label::try_from_bytes(
+                        label,
+                        &file_system::DefaultLabelRules,
+                    )?);
+                }
+                touched_files.push(path);
+            }
+
+            Ok(())
+        };
+
+        match head {
+            None => {
+                let diff = self.diff_commits(&commit, None)?;
+                add_deltas(diff)?;
+            }
+            Some(parent) => {
+                let diff = self.diff_commits(&commit, Some(&parent))?;
+                add_deltas(diff)?;
+
+                for parent in parents {
+                    let diff = self.diff_commits(&commit, Some(&parent))?;
+                    add_deltas(diff)?;
+                }
+            }
+        }
+
+        Ok(touched_files)
+    }
+
+    fn diff_commits(
+        &'repo self,
+        left: &'repo git2::Commit,
+        right: Option<&'repo git2::Commit>,
+    ) -> Result<git2::Diff, Error> {
+        let left_tree = left.tree()?;
+        let right_tree = right.map_or(Ok(None), |commit| commit.tree().map(Some))?;
+
+        let diff = self
+            .repo
+            .diff_tree_to_tree(Some(&left_tree), right_tree.as_ref(), None)?;
+
+        Ok(diff)
+    }
+}
+
+impl vcs::GetVCS<Error> for Repository {
+    type RepoId = String;
 
     fn get_repo(repo_id: Self::RepoId) -> Result<Self, Error> {
         git2::Repository::open(&repo_id)
-            .map(Repository)
+            .map(Repository::from)
             .map_err(Error::from)
     }
 }
 
 impl From<git2::Repository> for Repository {
     fn from(repo: git2::Repository) -> Self {
-        Repository(repo)
+        Repository {
+            repo,
+            retry_policy: RetryPolicy::none(),
+            slow_op_threshold: None,
+            snapshot: None,
+        }
     }
 }
 
@@ -394,51 +1480,314 @@ impl Branch {
             locality: git2::BranchType::Local,
         }
     }
+
+    /// The `Branch` for the full name of a reference, e.g. `refs/remotes/origin/dev`,
+    /// if it is a branch.
+    fn from_ref_name(name: &str) -> Option<Self> {
+        if let Some(name) = name.strip_prefix("refs/heads/") {
+            Some(Branch::local(BranchName::new(name)))
+        } else {
+            name.strip_prefix("refs/remotes/")
+                .map(|name| Branch::remote(BranchName::new(name)))
+        }
+    }
 }
 
-/// A newtype wrapper over `String` to separate out
-/// the fact that a caller wants to fetch a branch.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct BranchName(String);
+/// A tag along with the commit it points to and, if it is an annotated tag, who
+/// tagged it, when, and why.
+///
+/// See [`Browser::list_tag_details`](struct.Browser.html#method.list_tag_details).
+#[derive(Debug, Clone)]
+pub struct Tag {
+    /// The name of the tag, without the `refs/tags/` prefix.
+    pub name: TagName,
+    /// The commit the tag points to, through any annotated tag objects.
+    pub target: Oid,
+    /// The tagger of an annotated tag, including the date it was tagged. It is
+    /// `None` for lightweight tags, and annotated tags without a tagger.
+    pub tagger: Option<Signature>,
+    /// The message of an annotated tag, or `None` for lightweight tags.
+    ///
+    /// The tagger and message are decoded lossily if they are not valid UTF-8.
+    pub message: Option<String>,
+}
 
-impl BranchName {
-    pub fn new(name: &str) -> Self {
-        BranchName(name.into())
+impl Tag {
+    /// Whether this is an annotated tag, i.e. it has a message.
+    pub fn is_annotated(&self) -> bool {
+        self.message.is_some()
     }
+}
 
-    pub fn name(&self) -> String {
-        self.0.clone()
+/// The [`LabelRules`](../../file_system/trait.LabelRules.html) for git tree entries.
+///
+/// On top of the [`DefaultLabelRules`](../../file_system/struct.DefaultLabelRules.html),
+/// a name must not contain a NUL byte, and must not be `.`, `..` or `.git`
+/// (ignoring case). If `protect_ntfs` is set, a name must also not end in a `.` or
+/// a space, since NTFS would strip these and alias the entry with another.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::{Label, LabelRules};
+/// use radicle_surf::vcs::git::GitLabelRules;
+///
+/// let rules = GitLabelRules::default();
+///
+/// assert!(Label::try_from_rules("README.md", &rules).is_ok());
+/// assert!(Label::try_from_rules(".GIT", &rules).is_err());
+/// assert!(Label::try_from_rules("nul\0byte", &rules).is_err());
+/// assert!(rules.validate("trailing.").is_ok());
+///
+/// let rules = GitLabelRules { protect_ntfs: true };
+/// assert!(rules.validate("trailing.").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GitLabelRules {
+    /// Reject names that are not valid on NTFS. This follows the
+    /// `core.protectNTFS` setting of the repository.
+    pub protect_ntfs: bool,
+}
+
+impl GitLabelRules {
+    /// Get the `GitLabelRules` for the given repository, using its configuration.
+    fn from_repository(repo: &git2::Repository) -> Self {
+        let protect_ntfs = repo
+            .config()
+            .and_then(|config| config.get_bool("core.protectNTFS"))
+            .unwrap_or(false);
+        GitLabelRules { protect_ntfs }
     }
 }
 
-/// A newtype wrapper over `String` to separate out
-/// the fact that a caller wants to fetch a tag.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct TagName(String);
+impl file_system::LabelRules for GitLabelRules {
+    fn validate(&self, label: &str) -> Result<(), file_error::Error> {
+        file_system::DefaultLabelRules.validate(label)?;
 
-impl TagName {
-    pub fn new(name: &str) -> Self {
-        TagName(name.into())
+        if label.contains('\0') {
+            Err(file_error::CONTAINS_NUL)
+        } else if label == "." || label == ".." || label.eq_ignore_ascii_case(".git") {
+            Err(file_error::RESERVED_LABEL)
+        } else if self.protect_ntfs && (label.ends_with('.') || label.ends_with(' ')) {
+            Err(file_error::TRAILING_DOT_OR_SPACE)
+        } else {
+            Ok(())
+        }
     }
+}
 
-    pub fn name(&self) -> String {
-        self.0.clone()
+/// A set of synthetic parent links between commits, used to build a
+/// virtual [`History`](type.History.html) without writing to the repository,
+/// e.g. for previewing a stack of patches on top of a different base.
+///
+/// See [`Browser::graft`](struct.Browser.html#method.graft).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Grafts(HashMap<Oid, Vec<Oid>>);
+
+impl Grafts {
+    /// Create an empty set of `Grafts`.
+    pub fn new() -> Self {
+        Grafts(HashMap::new())
+    }
+
+    /// Give `commit` the synthetic `parents`, replacing its own parents
+    /// and any parents previously grafted onto it.
+    ///
+    /// Grafting an empty list of parents turns `commit` into a root commit.
+    pub fn graft(&mut self, commit: Oid, parents: Vec<Oid>) {
+        self.0.insert(commit, parents);
+    }
+
+    /// Get the synthetic parents of `commit`, if it was grafted.
+    pub fn parents(&self, commit: &Oid) -> Option<&[Oid]> {
+        self.0.get(commit).map(|parents| parents.as_slice())
     }
 }
 
-/// A newtype wrapper over `String` to separate out
-/// the fact that a caller wants to fetch a commit.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Sha1(String);
+/// A newtype wrapper over `String` for a git namespace, e.g. the namespace of a
+/// peer, whose references are stored under `refs/namespaces/<namespace>/`.
+/// Nested namespaces are separated by `/`, so the references of the namespace
+/// `carol/dev` are stored under `refs/namespaces/carol/refs/namespaces/dev/`.
+///
+/// See [`Browser::peer_views`](struct.Browser.html#method.peer_views).
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::vcs::git::Namespace;
+///
+/// let namespace = Namespace::new("carol/dev");
+/// assert_eq!(namespace.name(), "carol/dev");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Namespace(String);
 
-impl Sha1 {
+impl Namespace {
+    /// Create a `Namespace` from its name, where nested namespaces are separated by `/`.
     pub fn new(name: &str) -> Self {
-        Sha1(name.into())
+        Namespace(name.into())
     }
 
+    /// The name of the `Namespace`, where nested namespaces are separated by `/`.
     pub fn name(&self) -> String {
         self.0.clone()
     }
+
+    /// Split the full name of a reference, e.g. `refs/namespaces/a/refs/heads/main`,
+    /// into its `Namespace` and its name within the namespace, if it is namespaced.
+    fn split(refname: &str) -> Option<(Self, &str)> {
+        let mut namespaces = vec![];
+        let mut rest = refname;
+        while let Some(namespaced) = rest.strip_prefix("refs/namespaces/") {
+            let (namespace, name) = namespaced.split_once('/')?;
+            namespaces.push(namespace);
+            rest = name;
+        }
+
+        if namespaces.is_empty() {
+            None
+        } else {
+            Some((Namespace(namespaces.join("/")), rest))
+        }
+    }
+}
+
+/// How far two commits diverged, counted in commits reachable from one but not the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// The number of commits only reachable from the first commit.
+    pub ahead: usize,
+    /// The number of commits only reachable from the second commit.
+    pub behind: usize,
+}
+
+impl Divergence {
+    /// Check whether both commits have commits the other one does not have,
+    /// i.e. neither can be fast-forwarded to the other.
+    pub fn is_diverged(&self) -> bool {
+        self.ahead > 0 && self.behind > 0
+    }
+
+    /// Check whether the commits are the same.
+    pub fn is_even(&self) -> bool {
+        self.ahead == 0 && self.behind == 0
+    }
+}
+
+/// The files of a working directory that differ from the commit a `Browser` is
+/// viewing, see [`Browser::status`](struct.Browser.html#method.status).
+///
+/// Each list of paths is sorted, and relative to the root of the working directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Status {
+    /// The files whose contents or modes differ from the commit.
+    pub modified: Vec<file_system::Path>,
+    /// The files that are not in the commit, and are not ignored by a `.gitignore`.
+    pub untracked: Vec<file_system::Path>,
+    /// The files that are in the commit, but not in the working directory.
+    pub deleted: Vec<file_system::Path>,
+}
+
+impl Status {
+    /// Compare the `Directory` of a commit to the `Directory` of a working directory.
+    ///
+    /// Submodules are skipped, along with the contents of their working directories.
+    ///
+    /// The `workdir` is expected to leave out the untracked files that are ignored,
+    /// as [`Browser::status`](struct.Browser.html#method.status) reads it.
+    fn new(commit: &directory::Directory, workdir: &directory::Directory) -> Self {
+        let entries = |directory: &directory::Directory| {
+            directory
+                .walk()
+                .filter(|(_, contents)| match contents {
+                    directory::DirectoryContents::File { .. }
+                    | directory::DirectoryContents::Symlink { .. } => true,
+                    directory::DirectoryContents::Directory(_)
+                    | directory::DirectoryContents::Submodule { .. } => false,
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        let submodules = commit
+            .walk()
+            .filter_map(|(path, contents)| match contents {
+                directory::DirectoryContents::Submodule { .. } => Some(path),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let in_submodule = |path: &file_system::Path| {
+            path.ancestors()
+                .any(|ancestor| submodules.contains(&ancestor))
+        };
+
+        let tracked = entries(commit);
+        let on_disk = entries(workdir);
+        let mut status = Status::default();
+        for (path, contents) in &tracked {
+            match on_disk.get(path) {
+                None => status.deleted.push(path.clone()),
+                Some(on_disk) if on_disk != contents => status.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        for path in on_disk.into_keys() {
+            if !tracked.contains_key(&path) && !in_submodule(&path) {
+                status.untracked.push(path);
+            }
+        }
+
+        status
+    }
+
+    /// Check whether the working directory has no changes.
+    pub fn is_clean(&self) -> bool {
+        self.modified.is_empty() && self.untracked.is_empty() && self.deleted.is_empty()
+    }
+}
+
+/// The digest of the patch ids `Repository::cherry` compares, which is the id git
+/// gives the patch as a blob, so that it doesn't depend on a digest algorithm's
+/// feature.
+struct PatchHash;
+
+impl Digest for PatchHash {
+    const NAME: &'static str = "git-blob";
+    type Output = Oid;
+
+    fn digest(bytes: &[u8]) -> Self::Output {
+        Oid::hash_object(git2::ObjectType::Blob, bytes).expect("a blob can always be hashed")
+    }
+}
+
+/// A commit of the range given to [`Browser::cherry`](struct.Browser.html#method.cherry),
+/// along with the commit of the other range that makes the same change, if any.
+#[derive(Debug, Clone)]
+pub struct Cherry {
+    /// The commit.
+    pub commit: Commit,
+    /// The commit with the same patch id, i.e. the same change, that is already
+    /// in the base, if any.
+    pub upstream: Option<Oid>,
+}
+
+impl Cherry {
+    /// Check whether the change of the commit is already in the base.
+    pub fn is_applied(&self) -> bool {
+        self.upstream.is_some()
+    }
+}
+
+/// What [`Browser::default_view`](struct.Browser.html#method.default_view) chose to view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefaultView {
+    /// The commit `HEAD` points to.
+    Head,
+    /// The default branch, i.e. the branch `refs/remotes/origin/HEAD` points to,
+    /// preferring the local branch of the same name, or else `main` or `master`.
+    DefaultBranch(Branch),
+    /// The branch with the most recent tip, preferring local branches.
+    Branch(Branch),
+    /// The tag of the most recent commit.
+    Tag(TagName),
 }
 
 /// An enumeration of git objects we can fetch and turn
@@ -466,38 +1815,60 @@ impl Object {
     }
 }
 
+/// The contents of a git object, as printed by `git cat-file -p`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawObject {
+    /// The type of the object, i.e. a blob, tree, commit or tag.
+    pub kind: git2::ObjectType,
+    /// The raw bytes of the object. For blobs, commits and tags these are the bytes
+    /// as they are stored by git. For trees there is a line per entry
+    /// of the form `<mode> <type> <oid>\t<name>`.
+    pub bytes: Vec<u8>,
+}
+
 impl VCS<Commit, Error> for Repository {
     type HistoryId = Object;
     type ArtefactId = Oid;
 
     fn get_history(&self, history_id: Self::HistoryId) -> Result<History, Error> {
-        let reference = self
-            .0
-            .resolve_reference_from_short_name(&history_id.get_name())?;
-        let to_history = |pred, err| {
-            if pred {
-                self.to_history(&reference)
-            } else {
-                Err(err)
+        let context = || Context::new("history").rev(history_id.get_name());
+        self.retry_policy.retry(|| {
+            let (name, target) = self
+                .resolve_reference(&history_id.get_name())
+                .context(context)?;
+            let to_history = |pred, err| {
+                if pred {
+                    self.to_history(target)
+                } else {
+                    Err(err)
+                }
+            };
+            match &history_id {
+                Object::Branch(_) => to_history(
+                    name.starts_with("refs/heads/") || name.starts_with("refs/remotes/"),
+                    Error::NotBranch,
+                ),
+                Object::Tag(_) => to_history(name.starts_with("refs/tags/"), Error::NotTag),
             }
-        };
-        match history_id {
-            Object::Branch(_) => to_history(
-                reference.is_branch() || reference.is_remote(),
-                Error::NotBranch,
-            ),
-            Object::Tag(_) => to_history(reference.is_tag(), Error::NotTag),
-        }
+            .context(context)
+        })
     }
 
     fn get_histories(&self) -> Result<Vec<History>, Error> {
-        self.0
+        if let Some(snapshot) = &self.snapshot {
+            return snapshot
+                .refs()
+                .map(|(_, target)| self.to_history(target))
+                .collect();
+        }
+
+        self.repo
             .references()
             .map_err(Error::from)
             .and_then(|mut references| {
                 references.try_fold(vec![], |mut acc, reference| {
                     reference.map_err(Error::from).and_then(|r| {
-                        let history = self.to_history(&r)?;
+                        let history = self.to_history(r.peel_to_commit()?.id())?;
                         acc.push(history);
                         Ok(acc)
                     })
@@ -535,15 +1906,7 @@ impl Browser {
     /// ```
     pub fn new(repository: Repository) -> Result<Self, Error> {
         let history = repository.head()?;
-        let snapshot = Box::new(|repository: &Repository, history: &History| {
-            let tree = Self::get_tree(&repository.0, history.0.first())?;
-            Ok(directory::Directory::from_hash_map(tree))
-        });
-        Ok(vcs::Browser {
-            snapshot,
-            history,
-            repository,
-        })
+        Ok(Self::with_history(repository, history))
     }
 
     /// Create a new browser to interact with.
@@ -562,62 +1925,247 @@ impl Browser {
     /// ```
     pub fn new_with_branch(repository: Repository, branch_name: BranchName) -> Result<Self, Error> {
         let history = repository.get_history(Object::Branch(branch_name))?;
+        Ok(Self::with_history(repository, history))
+    }
+
+    /// Create a new browser to interact with, viewing the
+    /// [`default_view`](#method.default_view) of the repository.
+    ///
+    /// Unlike [`new`](#method.new), this does not fail if `HEAD` does not point
+    /// to a commit, as long as the repository has a branch or tag.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, DefaultView, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let (browser, view) = Browser::new_with_default_view(repo).unwrap();
+    ///
+    /// assert_eq!(view, DefaultView::Head);
+    /// ```
+    pub fn new_with_default_view(repository: Repository) -> Result<(Self, DefaultView), Error> {
+        let (view, history) = repository
+            .default_view()
+            .context(|| Context::new("default_view"))?;
+        Ok((Self::with_history(repository, history), view))
+    }
+
+    /// Create a new browser viewing `history`, rendering the tree of its first commit.
+    fn with_history(repository: Repository, history: History) -> Self {
         let snapshot = Box::new(|repository: &Repository, history: &History| {
-            let tree = Self::get_tree(&repository.0, history.0.first())?;
+            let tree = repository
+                .retry_policy
+                .retry(|| Self::get_tree(repository, history.0.first(), None))?;
             Ok(directory::Directory::from_hash_map(tree))
         });
-        Ok(vcs::Browser {
+        vcs::Browser {
             snapshot,
             history,
             repository,
-        })
+        }
     }
 
-    /// Set the current `Browser` history to the `HEAD` commit of the underlying repository.
+    /// Set the current `Browser` history to a sensible default, trying in order:
+    /// `HEAD`, the default branch, the branch with the most recent tip, and the tag
+    /// of the most recent commit. It returns which of these was chosen.
     ///
     /// # Examples
     ///
     /// ```
-    /// use radicle_surf::vcs::git::{Browser, Repository};
+    /// use radicle_surf::vcs::git::{Browser, DefaultView, Repository};
     ///
     /// let repo = Repository::new("./data/git-platinum").unwrap();
     /// let mut browser = Browser::new(repo).unwrap();
     ///
-    /// // ensure we're at HEAD
-    /// browser.head();
-    ///
-    /// let directory = browser.get_directory();
-    ///
-    /// // We are able to render the directory
-    /// assert!(directory.is_ok());
+    /// // The repository has a `HEAD`, so it is viewed.
+    /// assert_eq!(browser.default_view().unwrap(), DefaultView::Head);
     /// ```
-    pub fn head(&mut self) -> Result<(), Error> {
-        let history = self.repository.head()?;
+    pub fn default_view(&mut self) -> Result<DefaultView, Error> {
+        let (view, history) = self
+            .repository
+            .default_view()
+            .context(|| Context::new("default_view"))?;
         self.set_history(history);
-        Ok(())
+        Ok(view)
     }
 
-    /// Set the current `Browser` history to the [`BranchName`](struct.BranchName.html)
-    /// provided.
+    /// Set the [`RetryPolicy`](struct.RetryPolicy.html) of the underlying repository.
     ///
     /// # Examples
     ///
     /// ```
-    /// use radicle_surf::vcs::git::{BranchName, Browser, Repository};
+    /// use radicle_surf::vcs::git::{Browser, Repository, RetryPolicy};
     ///
     /// let repo = Repository::new("./data/git-platinum").unwrap();
     /// let mut browser = Browser::new(repo).unwrap();
     ///
-    /// // ensure we're on 'master'
-    /// browser.branch(BranchName::new("master"));
+    /// browser.set_retry_policy(RetryPolicy::default());
+    /// assert!(browser.get_directory().is_ok());
+    /// ```
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.repository.retry_policy = policy;
+    }
+
+    /// Set the threshold after which an operation on the underlying repository is
+    /// reported as slow, see
+    /// [`Repository::with_slow_op_threshold`](struct.Repository.html#method.with_slow_op_threshold).
     ///
-    /// let directory = browser.get_directory();
+    /// # Examples
     ///
-    /// // We are able to render the directory
-    /// assert!(directory.is_ok());
     /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository};
+    /// use std::time::Duration;
     ///
-    /// ```
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
+    ///
+    /// browser.set_slow_op_threshold(Some(Duration::from_secs(1)));
+    /// assert!(browser.get_directory().is_ok());
+    /// ```
+    pub fn set_slow_op_threshold(&mut self, threshold: Option<Duration>) {
+        self.repository.slow_op_threshold = threshold;
+    }
+
+    /// Capture the targets of all references of the underlying repository, and pin
+    /// subsequent operations, e.g. [`branch`](#method.branch) and
+    /// [`list_tags`](#method.list_tags), to them until [`thaw`](#method.thaw) is called.
+    ///
+    /// This keeps a series of operations consistent with each other, even if the
+    /// references are updated while they run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{BranchName, Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
+    ///
+    /// let snapshot = browser.freeze().unwrap();
+    /// assert!(snapshot.target("refs/heads/master").is_some());
+    ///
+    /// // Resolved using the snapshot, rather than the current state of the repository.
+    /// browser.branch("master".parse::<BranchName>().unwrap()).unwrap();
+    /// assert_eq!(browser.get_history().first().id, snapshot.target("refs/heads/master").unwrap());
+    ///
+    /// browser.thaw();
+    /// ```
+    pub fn freeze(&mut self) -> Result<Snapshot, Error> {
+        let snapshot = self.repository.snapshot()?;
+        self.repository.snapshot = Some(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    /// Check whether any reference pinned by [`freeze`](#method.freeze) moved since,
+    /// e.g. to decide whether to render the page again. If the `Browser` is not
+    /// frozen, nothing is pinned and this is `false`.
+    ///
+    /// See [`Snapshot::has_drifted`](struct.Snapshot.html#method.has_drifted).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
+    ///
+    /// browser.freeze().unwrap();
+    /// let directory = browser.get_directory().unwrap();
+    ///
+    /// if browser.has_drifted().unwrap() {
+    ///     println!("The repository was updated while rendering");
+    /// }
+    /// ```
+    pub fn has_drifted(&self) -> Result<bool, Error> {
+        match &self.repository.snapshot {
+            Some(snapshot) => self
+                .repository
+                .retry_policy
+                .retry(|| snapshot.has_drifted(&self.repository))
+                .context(|| Context::new("has_drifted")),
+            None => Ok(false),
+        }
+    }
+
+    /// Stop pinning operations to the [`Snapshot`](struct.Snapshot.html) taken by
+    /// [`freeze`](#method.freeze), returning it if there was one.
+    pub fn thaw(&mut self) -> Option<Snapshot> {
+        self.repository.snapshot.take()
+    }
+
+    /// Build a `History` of all the commits reachable from any of `tips`.
+    /// See [`History::from_tips`](type.History.html#method.from_tips).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
+    ///
+    /// let snapshot = browser.freeze().unwrap();
+    /// let tips = snapshot
+    ///     .refs()
+    ///     .filter(|(name, _)| name.starts_with("refs/heads/"))
+    ///     .map(|(_, tip)| tip)
+    ///     .collect::<Vec<_>>();
+    ///
+    /// // The commits of all local branches.
+    /// let history = browser.history_from_tips(&tips).unwrap();
+    /// browser.set_history(history);
+    /// ```
+    pub fn history_from_tips(&self, tips: &[Oid]) -> Result<History, Error> {
+        History::from_tips(&self.repository, tips)
+    }
+
+    /// Set the current `Browser` history to the `HEAD` commit of the underlying repository.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
+    ///
+    /// // ensure we're at HEAD
+    /// browser.head();
+    ///
+    /// let directory = browser.get_directory();
+    ///
+    /// // We are able to render the directory
+    /// assert!(directory.is_ok());
+    /// ```
+    pub fn head(&mut self) -> Result<(), Error> {
+        let history = self.repository.head()?;
+        self.set_history(history);
+        Ok(())
+    }
+
+    /// Set the current `Browser` history to the [`BranchName`](struct.BranchName.html)
+    /// provided.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{BranchName, Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
+    ///
+    /// // ensure we're on 'master'
+    /// browser.branch("master".parse::<BranchName>().unwrap());
+    ///
+    /// let directory = browser.get_directory();
+    ///
+    /// // We are able to render the directory
+    /// assert!(directory.is_ok());
+    /// ```
+    ///
+    /// ```
     /// use radicle_surf::vcs::git::{BranchName, Browser, Repository};
     /// use radicle_surf::file_system::{Label, Path, SystemType};
     /// use radicle_surf::file_system::unsound;
@@ -625,7 +2173,7 @@ impl Browser {
     /// let repo = Repository::new("./data/git-platinum").unwrap();
     /// let mut browser = Browser::new(repo).unwrap();
     /// browser
-    ///     .branch(BranchName::new("origin/dev"))
+    ///     .branch("origin/dev".parse::<BranchName>().unwrap())
     ///     .expect("Failed to change branch to dev");
     ///
     /// let directory = browser.get_directory().expect("Failed to get directory");
@@ -680,7 +2228,7 @@ impl Browser {
     /// let mut browser = Browser::new(repo).unwrap();
     ///
     /// // Switch to "v0.3.0"
-    /// browser.tag(TagName::new("v0.3.0")).expect("Failed to switch tag");
+    /// browser.tag("v0.3.0".parse::<TagName>().unwrap()).expect("Failed to switch tag");
     ///
     /// let expected_history = History((
     ///     Oid::from_str("19bec071db6474af89c866a1bd0e4b1ff76e2b97").unwrap(),
@@ -705,6 +2253,8 @@ impl Browser {
     /// Set the current `Browser` history to the [`Sha1`](struct.Sha1.html)
     /// provided. The history will consist of a single [`Commit`](struct.Commit.html).
     ///
+    /// The `Sha1` may be abbreviated, as long as no other object's id starts with it.
+    ///
     /// # Examples
     ///
     /// ```
@@ -718,7 +2268,7 @@ impl Browser {
     ///
     /// // Set to the initial commit
     /// browser
-    ///     .commit(Sha1::new("e24124b7538658220b5aaf3b6ef53758f0a106dc"))
+    ///     .commit("e24124b7538658220b5aaf3b6ef53758f0a106dc".parse::<Sha1>().unwrap())
     ///     .expect("Missing commit");
     ///
     /// let directory = browser.get_directory().unwrap();
@@ -745,263 +2295,1018 @@ impl Browser {
         Ok(())
     }
 
-    /// List the names of the branches that are contained in the
-    /// underlying [`Repository`](struct.Repository.hmtl).
+    /// Set the current `Browser` history to the commit `rev` names and its
+    /// ancestors, where `rev` is the name of a branch or tag, e.g. `master` or
+    /// `v0.1.0`, or a commit sha, which may be abbreviated, e.g. for a permalink.
+    /// Any revision [`rev_parse`](#method.rev_parse) accepts can be used, e.g. `HEAD~3`.
+    ///
+    /// Reference names take precedence over abbreviated shas. If the `Browser`
+    /// is [frozen](#method.freeze), references are looked up in its `Snapshot`.
     ///
     /// # Examples
     ///
     /// ```
-    /// use radicle_surf::vcs::git::{Branch, BranchType, BranchName, Browser, Repository};
+    /// use radicle_surf::vcs::git::{Browser, Oid, Repository};
     ///
     /// let repo = Repository::new("./data/git-platinum").unwrap();
     /// let mut browser = Browser::new(repo).unwrap();
     ///
-    /// let branches = browser.list_branches(None).unwrap();
-    ///
-    /// // 'master' exists in the list of branches
-    /// assert!(branches.contains(&Branch::local(BranchName::new("master"))));
-    ///
-    /// // Filter the branches by `Remote`.
-    /// let mut branches = browser.list_branches(Some(BranchType::Remote)).unwrap();
-    /// branches.sort();
+    /// browser.rev("e24124b").unwrap();
+    /// assert_eq!(
+    ///     browser.get_history().first().id,
+    ///     Oid::from_str("e24124b7538658220b5aaf3b6ef53758f0a106dc").unwrap()
+    /// );
     ///
-    /// assert_eq!(branches, vec![
-    ///     Branch::remote(BranchName::new("origin/HEAD")),
-    ///     Branch::remote(BranchName::new("origin/dev")),
-    ///     Branch::remote(BranchName::new("origin/master")),
-    /// ]);
+    /// browser.rev("v0.3.0").unwrap();
+    /// assert_eq!(browser.get_history().0.len(), 4);
     /// ```
-    pub fn list_branches(&self, filter: Option<BranchType>) -> Result<Vec<Branch>, Error> {
-        self.repository.list_branches(filter)
+    pub fn rev(&mut self, rev: &str) -> Result<(), Error> {
+        let history = self.repository.rev(rev)?;
+        self.set_history(history);
+        Ok(())
     }
 
-    /// List the names of the tags that are contained in the
-    /// underlying [`Repository`](struct.Repository.hmtl).
+    /// Resolve the revision `rev` to the commit it names, using git's revision
+    /// syntax, e.g. `HEAD~3`, `v1.0^{}`, `master@{2}` or an abbreviated commit sha.
+    /// Annotated tags are peeled to the commit they point to.
+    ///
+    /// If the `Browser` is [frozen](#method.freeze), the reference `rev` starts
+    /// with is looked up in its `Snapshot`, except for reflog entries such as
+    /// `master@{2}`, which are read from the repository.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::RevNotFound`](error/enum.Error.html#variant.RevNotFound) if `rev`
+    ///   does not name an object.
+    /// * [`Error::AmbiguousRev`](error/enum.Error.html#variant.AmbiguousRev) if `rev`
+    ///   names more than one object, e.g. a commit sha abbreviated too much.
     ///
     /// # Examples
     ///
     /// ```
-    /// use radicle_surf::vcs::git::{Browser, Repository, TagName};
+    /// use radicle_surf::vcs::git::{Browser, Oid, Repository};
+    /// use radicle_surf::vcs::git::error::Error;
     ///
     /// let repo = Repository::new("./data/git-platinum").unwrap();
-    /// let mut browser = Browser::new(repo).unwrap();
-    ///
-    /// let tags = browser.list_tags().unwrap();
-    ///
-    /// // We currently have no tags :(
+    /// let browser = Browser::new(repo).unwrap();
     ///
     /// assert_eq!(
-    ///     tags,
-    ///     vec![
-    ///         TagName::new("v0.1.0"),
-    ///         TagName::new("v0.2.0"),
-    ///         TagName::new("v0.3.0"),
-    ///         TagName::new("v0.4.0"),
-    ///         TagName::new("v0.5.0")
-    ///     ]
+    ///     browser.rev_parse("v0.3.0~3").unwrap(),
+    ///     Oid::from_str("d3464e33d75c75c99bfb90fa2e9d16efc0b7d0e3").unwrap()
     /// );
+    ///
+    /// let err = browser.rev_parse("not-a-branch").unwrap_err();
+    /// assert_eq!(err.root_cause(), &Error::RevNotFound("not-a-branch".to_string()));
     /// ```
-    pub fn list_tags(&self) -> Result<Vec<TagName>, Error> {
-        self.repository.list_tags()
+    pub fn rev_parse(&self, rev: &str) -> Result<Oid, Error> {
+        self.repository.rev_parse(rev)
     }
 
-    /// Given a [`Path`](../../file_system/struct.Path.html) to a file, return the last `Commit`
-    /// that touched that file.
+    /// Get the first `limit` commits reachable from the commit `from_rev` names, see
+    /// [`rev_parse`](#method.rev_parse), most recent commit time first, without
+    /// walking the rest of the history.
+    ///
+    /// The [`HistoryPage`](struct.HistoryPage.html) holds the `HistoryCursor` of
+    /// the next page, which is passed to [`history_page_at`](#method.history_page_at).
     ///
     /// # Examples
     ///
     /// ```
-    /// use radicle_surf::vcs::git::{Browser, Repository, Sha1};
-    /// use radicle_surf::file_system::{Label, Path, SystemType};
-    /// use radicle_surf::file_system::unsound;
-    ///
-    /// use git2;
-    ///
-    /// let repo = Repository::new("./data/git-platinum")
-    ///     .expect("Could not retrieve ./data/git-test as git repository");
-    /// let mut browser = Browser::new(repo).expect("Could not initialise Browser");
+    /// use radicle_surf::vcs::git::{Browser, Repository};
     ///
-    /// // Clamp the Browser to a particular commit
-    /// browser.commit(Sha1::new("d6880352fc7fda8f521ae9b7357668b17bb5bad5")).expect("Failed to set
-    /// commit");
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
     ///
-    /// let head_commit = browser.get_history().0.first().clone();
-    /// let expected_commit = git2::Oid::from_str("d3464e33d75c75c99bfb90fa2e9d16efc0b7d0e3")
-    ///     .expect("Failed to create Oid");
+    /// let mut page = browser.history_page("master", 5).unwrap();
+    /// let mut commits = page.commits.len();
+    /// while let Some(cursor) = page.next {
+    ///     page = browser.history_page_at(cursor, 5).unwrap();
+    ///     commits += page.commits.len();
+    /// }
     ///
-    /// let readme_last_commit = browser
-    ///     .last_commit(&Path::with_root(&[unsound::label::new("README.md")]))
-    ///     .expect("Failed to get last commit")
-    ///     .map(|commit| commit.id);
+    /// browser.rev("master").unwrap();
+    /// assert_eq!(commits, browser.get_history().iter().count());
+    /// ```
+    pub fn history_page(&self, from_rev: &str, limit: usize) -> Result<HistoryPage, Error> {
+        let tip = self.repository.rev_parse(from_rev)?;
+        self.history_page_at(HistoryCursor::new(tip), limit)
+    }
+
+    /// Get the `limit` commits at `cursor`, e.g. the `next` cursor of a
+    /// [`HistoryPage`](struct.HistoryPage.html) returned by
+    /// [`history_page`](#method.history_page).
     ///
-    /// assert_eq!(readme_last_commit, Some(expected_commit));
+    /// The walk picks up where the page before stopped, so later pages take no
+    /// longer to get than earlier ones.
+    pub fn history_page_at(
+        &self,
+        cursor: HistoryCursor,
+        limit: usize,
+    ) -> Result<HistoryPage, Error> {
+        self.repository
+            .history_page(&cursor, limit, None)
+            .context(|| Context::new("history_page").rev(cursor.tip))
+    }
+
+    /// Get the first `limit` commits that touched the file, or directory, at `path`,
+    /// e.g. `git log <from_rev> -- <path>`, walking the history from the commit
+    /// `from_rev` names, see [`rev_parse`](#method.rev_parse).
     ///
-    /// let expected_commit = git2::Oid::from_str("e24124b7538658220b5aaf3b6ef53758f0a106dc")
-    ///     .expect("Failed to create Oid");
+    /// As with git, a merge that has the same `path` as one of its parents is left
+    /// out, and the walk only goes on to that parent, so that the changes of a side
+    /// branch whose `path` was not taken are left out too.
     ///
-    /// let memory_last_commit = browser
-    ///     .last_commit(&Path::with_root(&[unsound::label::new("src"), unsound::label::new("memory.rs")]))
-    ///     .expect("Failed to get last commit")
-    ///     .map(|commit| commit.id);
+    /// The [`HistoryPage`](struct.HistoryPage.html) holds the `HistoryCursor` of
+    /// the next page, which is passed to [`file_history_at`](#method.file_history_at).
+    /// To get the whole history of a file as of the commit the `Browser` is viewing,
+    /// see [`file_history_cached`](#method.file_history_cached).
     ///
-    /// assert_eq!(memory_last_commit, Some(expected_commit));
-    /// ```
+    /// # Examples
     ///
     /// ```
-    /// use radicle_surf::vcs::git::{Browser, Repository, Sha1};
-    /// use radicle_surf::file_system::{Label, Path, SystemType};
     /// use radicle_surf::file_system::unsound;
+    /// use radicle_surf::vcs::git::{Browser, Repository};
     ///
-    /// let repo = Repository::new("./data/git-platinum")
-    ///     .expect("Could not retrieve ./data/git-platinum as git repository");
-    /// let mut browser = Browser::new(repo).expect("Could not initialise Browser");
-    ///
-    /// // Set the browser history to the initial commit
-    /// browser.commit(Sha1::new("d3464e33d75c75c99bfb90fa2e9d16efc0b7d0e3")).unwrap();
-    ///
-    /// let head_commit = browser.get_history().0.first().clone();
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
+    /// let readme = unsound::path::new("~/README.md");
     ///
-    /// // memory.rs is commited later so it should not exist here.
-    /// let memory_last_commit = browser
-    ///     .last_commit(&Path::with_root(&[unsound::label::new("src"), unsound::label::new("memory.rs")]))
-    ///     .expect("Failed to get last commit")
-    ///     .map(|commit| commit.id);
+    /// let mut page = browser.file_history(&readme, "master", 1).unwrap();
+    /// let mut commits = page.commits.clone();
+    /// while let Some(cursor) = page.next {
+    ///     page = browser.file_history_at(&readme, cursor, 1).unwrap();
+    ///     commits.extend(page.commits);
+    /// }
     ///
-    /// assert_eq!(memory_last_commit, None);
+    /// assert!(!commits.is_empty());
+    /// ```
+    pub fn file_history(
+        &self,
+        path: &file_system::Path,
+        from_rev: &str,
+        limit: usize,
+    ) -> Result<HistoryPage, Error> {
+        let tip = self.repository.rev_parse(from_rev)?;
+        self.file_history_at(path, HistoryCursor::new(tip), limit)
+    }
+
+    /// Get the `limit` commits that touched the file, or directory, at `path` at
+    /// `cursor`, e.g. the `next` cursor of a [`HistoryPage`](struct.HistoryPage.html)
+    /// returned by [`file_history`](#method.file_history).
+    pub fn file_history_at(
+        &self,
+        path: &file_system::Path,
+        cursor: HistoryCursor,
+        limit: usize,
+    ) -> Result<HistoryPage, Error> {
+        self.repository
+            .history_page(&cursor, limit, Some(path))
+            .context(|| {
+                Context::new("file_history")
+                    .rev(cursor.tip)
+                    .path(path.clone())
+            })
+    }
+
+    /// Annotate each line of the file at `path`, as of the commit `rev` names, see
+    /// [`rev_parse`](#method.rev_parse), with the commit that last changed it, its
+    /// author, and the number the line had in that commit, like `git blame`.
     ///
-    /// // README.md exists in this commit.
-    /// let readme_last_commit = browser
-    ///     .last_commit(&Path::with_root(&[unsound::label::new("README.md")]))
-    ///     .expect("Failed to get last commit")
-    ///     .map(|commit| commit.id);
+    /// The `BlameLine`s are in the order of the lines of the file, i.e. the first
+    /// `BlameLine` is for the first line.
     ///
-    /// assert_eq!(readme_last_commit, Some(head_commit.id));
-    /// ```
+    /// # Examples
     ///
     /// ```
-    /// use radicle_surf::vcs::git::{BranchName, Browser, Oid, Repository, Sha1};
-    /// use radicle_surf::file_system::{Label, Path, SystemType};
     /// use radicle_surf::file_system::unsound;
+    /// use radicle_surf::vcs::git::{Browser, Repository};
     ///
-    /// let repo = Repository::new("./data/git-platinum")
-    ///     .expect("Could not retrieve ./data/git-platinum as git repository");
-    /// let mut browser = Browser::new(repo).expect("Could not initialise Browser");
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
     ///
-    /// // Check that last commit is the actual last commit even if head commit differs.
-    /// browser.commit(Sha1::new("19bec071db6474af89c866a1bd0e4b1ff76e2b97")).unwrap();
+    /// let blame = browser.blame(&unsound::path::new("~/src/memory.rs"), "master").unwrap();
+    /// for (number, line) in blame.iter().enumerate() {
+    ///     println!(
+    ///         "{} {} {} ({})",
+    ///         number + 1,
+    ///         line.commit,
+    ///         line.author.name,
+    ///         line.original_line
+    ///     );
+    /// }
+    /// ```
+    pub fn blame(&self, path: &file_system::Path, rev: &str) -> Result<Vec<BlameLine>, Error> {
+        let tip = self.repository.rev_parse(rev)?;
+        let repository = &self.repository;
+        repository.retry_policy.retry(|| {
+            repository
+                .blame(tip, path)
+                .context(|| Context::new("blame").rev(rev).path(path.clone()))
+        })
+    }
+
+    /// Compute the [`Diff`](../../diff/struct.Diff.html) between the commits
+    /// `from_rev` and `to_rev` name, see [`rev_parse`](#method.rev_parse), e.g. for a
+    /// compare view, or `<commit>^` and `<commit>` for the page of a single commit.
     ///
-    /// let expected_commit_id =
-    ///     Oid::from_str("f3a089488f4cfd1a240a9c01b3fcc4c34a4e97b2").unwrap();
+    /// Only the files that differ between the two commits are read. Unlike with
+    /// [`Diff::diff`](../../diff/struct.Diff.html#method.diff), a file that git finds
+    /// was renamed, with mostly the same contents, shows up as moved rather than as
+    /// deleted and created.
     ///
-    /// let gitignore_last_commit_id = browser
-    ///     .last_commit(&unsound::path::new("~/examples/Folder.svelte"))
-    ///     .expect("Failed to get last commit")
-    ///     .map(|commit| commit.id);
+    /// # Examples
     ///
-    /// assert_eq!(gitignore_last_commit_id, Some(expected_commit_id));
     /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository};
     ///
-    /// ```
-    /// use radicle_surf::vcs::git::{BranchName, Browser, Repository, Sha1};
-    /// use radicle_surf::vcs::git::git2::{Oid};
-    /// use radicle_surf::file_system::{Label, Path, SystemType};
-    /// use radicle_surf::file_system::unsound;
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
     ///
-    /// let repo = Repository::new("./data/git-platinum")
-    ///     .expect("Could not retrieve ./data/git-platinum as git repository");
-    /// let mut browser = Browser::new(repo).expect("Could not initialise Browser");
+    /// let diff = browser.diff("v0.2.0", "v0.3.0").unwrap();
+    /// let reverse = browser.diff("v0.3.0", "v0.2.0").unwrap();
     ///
-    /// // Check that last commit is the actual last commit even if head commit differs.
-    /// browser.commit(Sha1::new("19bec071db6474af89c866a1bd0e4b1ff76e2b97")).unwrap();
+    /// // Comparing the other way around swaps the files that were created and deleted.
+    /// assert_eq!(
+    ///     diff.created.iter().map(|created| &created.0).collect::<Vec<_>>(),
+    ///     reverse.deleted.iter().map(|deleted| &deleted.0).collect::<Vec<_>>(),
+    /// );
+    /// assert_eq!(
+    ///     diff.modified.iter().map(|modified| &modified.path).collect::<Vec<_>>(),
+    ///     reverse.modified.iter().map(|modified| &modified.path).collect::<Vec<_>>(),
+    /// );
     ///
-    /// let expected_commit_id =
-    ///     Oid::from_str("2429f097664f9af0c5b7b389ab998b2199ffa977").unwrap();
+    /// assert!(browser.diff("master", "master").unwrap().modified.is_empty());
+    /// ```
+    pub fn diff(&self, from_rev: &str, to_rev: &str) -> Result<crate::diff::Diff, Error> {
+        let from = self.repository.rev_parse(from_rev)?;
+        let to = self.repository.rev_parse(to_rev)?;
+        let repository = &self.repository;
+        repository.retry_policy.retry(|| {
+            repository
+                .diff(from, to)
+                .context(|| Context::new("diff").rev(format!("{}..{}", from_rev, to_rev)))
+        })
+    }
+
+    /// Iterate over the commits reachable from the commit `from_rev` names, see
+    /// [`rev_parse`](#method.rev_parse), most recent commit time first, in the same
+    /// order as the pages of [`history_page`](#method.history_page), reading each
+    /// commit as it is reached.
     ///
-    /// let nested_directory_tree_commit_id = browser
-    ///     .last_commit(&unsound::path::new("~/this/is/a/really/deeply/nested/directory/tree"))
-    ///     .expect("Failed to get last commit")
-    ///     .map(|commit| commit.id);
+    /// # Examples
     ///
-    /// assert_eq!(nested_directory_tree_commit_id, Some(expected_commit_id));
     /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository};
     ///
-    /// ```
-    /// use radicle_surf::vcs::git::{BranchName, Browser, Repository, Oid, Sha1};
-    /// use radicle_surf::file_system::{Label, Path, SystemType};
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
+    ///
+    /// let authors = browser
+    ///     .commits("master")
+    ///     .unwrap()
+    ///     .take(3)
+    ///     .map(|commit| commit.map(|commit| commit.author.name))
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(authors.len(), 3);
+    /// ```
+    pub fn commits(&self, from_rev: &str) -> Result<Commits<'_>, Error> {
+        let tip = self.repository.rev_parse(from_rev)?;
+        Commits::new(&self.repository.repo, tip).context(|| Context::new("commits").rev(from_rev))
+    }
+
+    /// Build a virtual [`History`](type.History.html) starting at `tip`, where
+    /// the parents of the commits are replaced by the ones provided in `grafts`.
+    ///
+    /// Nothing is written to the underlying repository. The resulting `History` can be
+    /// set on the `Browser` using [`set_history`](struct.Browser.html#method.set_history)
+    /// to render it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Grafts, Oid, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
+    ///
+    /// let tip = Oid::from_str("19bec071db6474af89c866a1bd0e4b1ff76e2b97").unwrap();
+    /// let base = Oid::from_str("d3464e33d75c75c99bfb90fa2e9d16efc0b7d0e3").unwrap();
+    ///
+    /// // Place the tip directly on top of the initial commit
+    /// let mut grafts = Grafts::new();
+    /// grafts.graft(tip, vec![base]);
+    ///
+    /// let history = browser.graft(tip, &grafts).unwrap();
+    /// let history_ids = history.iter().map(|commit| commit.id).collect::<Vec<_>>();
+    ///
+    /// assert_eq!(history_ids, vec![tip, base]);
+    ///
+    /// browser.set_history(history);
+    /// assert!(browser.get_directory().is_ok());
+    /// ```
+    pub fn graft(&self, tip: Oid, grafts: &Grafts) -> Result<History, Error> {
+        let repository = &self.repository;
+        repository.retry_policy.retry(|| {
+            repository
+                .grafted_history(tip, grafts)
+                .context(|| Context::new("graft").rev(tip))
+        })
+    }
+
+    /// List the names of the branches that are contained in the
+    /// underlying [`Repository`](struct.Repository.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Branch, BranchType, BranchName, Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
+    ///
+    /// let branches = browser.list_branches(None).unwrap();
+    ///
+    /// // 'master' exists in the list of branches
+    /// assert!(branches.contains(&Branch::local("master".parse::<BranchName>().unwrap())));
+    ///
+    /// // Filter the branches by `Remote`.
+    /// let mut branches = browser.list_branches(Some(BranchType::Remote)).unwrap();
+    /// branches.sort();
+    ///
+    /// assert_eq!(branches, vec![
+    ///     Branch::remote("origin/HEAD".parse::<BranchName>().unwrap()),
+    ///     Branch::remote("origin/dev".parse::<BranchName>().unwrap()),
+    ///     Branch::remote("origin/master".parse::<BranchName>().unwrap()),
+    /// ]);
+    /// ```
+    pub fn list_branches(&self, filter: Option<BranchType>) -> Result<Vec<Branch>, Error> {
+        self.repository.list_branches(filter)
+    }
+
+    /// List the branches that are contained in the underlying
+    /// [`Repository`](struct.Repository.html), like
+    /// [`list_branches`](#method.list_branches), along with the `Oid` of the commit
+    /// each of them points to, e.g. for a branch dropdown.
+    ///
+    /// If the `Browser` is [frozen](#method.freeze), the tips are looked up in its
+    /// `Snapshot`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Branch, BranchName, BranchType, Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
+    ///
+    /// let branches = browser.list_branch_tips(Some(BranchType::Local)).unwrap();
+    /// let (_, tip) = branches
+    ///     .iter()
+    ///     .find(|(branch, _)| *branch == Branch::local("master".parse::<BranchName>().unwrap()))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(tip.to_string(), "a0dd9122d33dff2a35f564d564db127152c88e02");
+    /// ```
+    pub fn list_branch_tips(
+        &self,
+        filter: Option<BranchType>,
+    ) -> Result<Vec<(Branch, Oid)>, Error> {
+        self.repository.list_branch_tips(filter)
+    }
+
+    /// List the names of the tags that are contained in the
+    /// underlying [`Repository`](struct.Repository.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository, TagName};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
+    ///
+    /// let tags = browser.list_tags().unwrap();
+    ///
+    /// // We currently have no tags :(
+    ///
+    /// assert_eq!(
+    ///     tags,
+    ///     vec![
+    ///         "v0.1.0".parse::<TagName>().unwrap(),
+    ///         "v0.2.0".parse::<TagName>().unwrap(),
+    ///         "v0.3.0".parse::<TagName>().unwrap(),
+    ///         "v0.4.0".parse::<TagName>().unwrap(),
+    ///         "v0.5.0".parse::<TagName>().unwrap()
+    ///     ]
+    /// );
+    /// ```
+    pub fn list_tags(&self) -> Result<Vec<TagName>, Error> {
+        self.repository.list_tags()
+    }
+
+    /// List the tags that are contained in the underlying
+    /// [`Repository`](struct.Repository.html), like [`list_tags`](#method.list_tags),
+    /// along with the commit each of them points to and, for annotated tags, the
+    /// tagger, date and message, e.g. for a page of releases.
+    ///
+    /// Tags that do not point to a commit, e.g. a tag of a blob, are left out, and
+    /// so are tags whose names are not valid UTF-8. A tagger or message that is not
+    /// valid UTF-8 is decoded lossily. If the `Browser` is [frozen](#method.freeze),
+    /// the tags are looked up in its `Snapshot`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository, TagName};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
+    ///
+    /// let tags = browser.list_tag_details().unwrap();
+    /// let tag = tags.iter().find(|tag| tag.name == "v0.1.0".parse::<TagName>().unwrap()).unwrap();
+    ///
+    /// if let Some(tagger) = &tag.tagger {
+    ///     println!("{} tagged {} at {}", tagger.name, tag.target, tagger.time.seconds());
+    /// }
+    /// ```
+    pub fn list_tag_details(&self) -> Result<Vec<Tag>, Error> {
+        self.repository.list_tag_details()
+    }
+
+    /// Find the tips of the branch `branch_name` across all namespaces, e.g. the `main`
+    /// branch of each peer, stored as `refs/namespaces/<namespace>/refs/heads/<branch_name>`.
+    /// The views are sorted by `Namespace`.
+    ///
+    /// If the `Browser` is [frozen](#method.freeze), the tips are looked up in its `Snapshot`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{BranchName, Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
+    ///
+    /// for (namespace, tip) in browser.peer_views(&"master".parse::<BranchName>().unwrap()).unwrap() {
+    ///     println!("{} is at {}", namespace.name(), tip);
+    /// }
+    /// ```
+    pub fn peer_views(&self, branch_name: &BranchName) -> Result<Vec<(Namespace, Oid)>, Error> {
+        self.repository.peer_views(branch_name)
+    }
+
+    /// Compute the [`Divergence`](struct.Divergence.html) between each pair of
+    /// [`peer_views`](#method.peer_views) of the branch `branch_name`, where the
+    /// first `Namespace` of a pair is the one compared, i.e. `ahead` counts its commits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{BranchName, Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
+    ///
+    /// for (left, right, divergence) in browser.peer_divergence(&"master".parse::<BranchName>().unwrap()).unwrap() {
+    ///     if divergence.is_diverged() {
+    ///         println!("{} and {} have diverged", left.name(), right.name());
+    ///     }
+    /// }
+    /// ```
+    pub fn peer_divergence(
+        &self,
+        branch_name: &BranchName,
+    ) -> Result<Vec<(Namespace, Namespace, Divergence)>, Error> {
+        let views = self.peer_views(branch_name)?;
+        let mut divergences = vec![];
+        for (i, (left, left_tip)) in views.iter().enumerate() {
+            for (right, right_tip) in &views[i + 1..] {
+                let divergence = self.repository.divergence(*left_tip, *right_tip)?;
+                divergences.push((left.clone(), right.clone(), divergence));
+            }
+        }
+        Ok(divergences)
+    }
+
+    /// Read the git object identified by `oid`, for inspecting
+    /// commits, trees, tags and blobs in the underlying [`Repository`](struct.Repository.html).
+    ///
+    /// This mirrors `git cat-file -p`, see [`RawObject`](struct.RawObject.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Oid, Repository};
+    /// use radicle_surf::vcs::git::git2::ObjectType;
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
+    ///
+    /// let commit = browser
+    ///     .cat_object(Oid::from_str("e24124b7538658220b5aaf3b6ef53758f0a106dc").unwrap())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(commit.kind, ObjectType::Commit);
+    /// assert!(commit.bytes.starts_with(b"tree "));
+    /// ```
+    pub fn cat_object(&self, oid: Oid) -> Result<RawObject, Error> {
+        self.repository.cat_object(oid)
+    }
+
+    /// Given a [`Path`](../../file_system/struct.Path.html) to a file, return the last `Commit`
+    /// that touched that file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository, Sha1};
+    /// use radicle_surf::file_system::{Label, Path, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// use git2;
+    ///
+    /// let repo = Repository::new("./data/git-platinum")
+    ///     .expect("Could not retrieve ./data/git-test as git repository");
+    /// let mut browser = Browser::new(repo).expect("Could not initialise Browser");
+    ///
+    /// // Clamp the Browser to a particular commit
+    /// browser.commit("d6880352fc7fda8f521ae9b7357668b17bb5bad5".parse::<Sha1>().unwrap()).expect("Failed to set
+    /// commit");
+    ///
+    /// let head_commit = browser.get_history().0.first().clone();
+    /// let expected_commit = git2::Oid::from_str("d3464e33d75c75c99bfb90fa2e9d16efc0b7d0e3")
+    ///     .expect("Failed to create Oid");
+    ///
+    /// let readme_last_commit = browser
+    ///     .last_commit(&Path::with_root(&[unsound::label::new("README.md")]))
+    ///     .expect("Failed to get last commit")
+    ///     .map(|commit| commit.id);
+    ///
+    /// assert_eq!(readme_last_commit, Some(expected_commit));
+    ///
+    /// let expected_commit = git2::Oid::from_str("e24124b7538658220b5aaf3b6ef53758f0a106dc")
+    ///     .expect("Failed to create Oid");
+    ///
+    /// let memory_last_commit = browser
+    ///     .last_commit(&Path::with_root(&[unsound::label::new("src"), unsound::label::new("memory.rs")]))
+    ///     .expect("Failed to get last commit")
+    ///     .map(|commit| commit.id);
+    ///
+    /// assert_eq!(memory_last_commit, Some(expected_commit));
+    /// ```
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository, Sha1};
+    /// use radicle_surf::file_system::{Label, Path, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let repo = Repository::new("./data/git-platinum")
+    ///     .expect("Could not retrieve ./data/git-platinum as git repository");
+    /// let mut browser = Browser::new(repo).expect("Could not initialise Browser");
+    ///
+    /// // Set the browser history to the initial commit
+    /// browser.commit("d3464e33d75c75c99bfb90fa2e9d16efc0b7d0e3".parse::<Sha1>().unwrap()).unwrap();
+    ///
+    /// let head_commit = browser.get_history().0.first().clone();
+    ///
+    /// // memory.rs is commited later so it should not exist here.
+    /// let memory_last_commit = browser
+    ///     .last_commit(&Path::with_root(&[unsound::label::new("src"), unsound::label::new("memory.rs")]))
+    ///     .expect("Failed to get last commit")
+    ///     .map(|commit| commit.id);
+    ///
+    /// assert_eq!(memory_last_commit, None);
+    ///
+    /// // README.md exists in this commit.
+    /// let readme_last_commit = browser
+    ///     .last_commit(&Path::with_root(&[unsound::label::new("README.md")]))
+    ///     .expect("Failed to get last commit")
+    ///     .map(|commit| commit.id);
+    ///
+    /// assert_eq!(readme_last_commit, Some(head_commit.id));
+    /// ```
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{BranchName, Browser, Oid, Repository, Sha1};
+    /// use radicle_surf::file_system::{Label, Path, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let repo = Repository::new("./data/git-platinum")
+    ///     .expect("Could not retrieve ./data/git-platinum as git repository");
+    /// let mut browser = Browser::new(repo).expect("Could not initialise Browser");
+    ///
+    /// // Check that last commit is the actual last commit even if head commit differs.
+    /// browser.commit("19bec071db6474af89c866a1bd0e4b1ff76e2b97".parse::<Sha1>().unwrap()).unwrap();
+    ///
+    /// let expected_commit_id =
+    ///     Oid::from_str("f3a089488f4cfd1a240a9c01b3fcc4c34a4e97b2").unwrap();
+    ///
+    /// let gitignore_last_commit_id = browser
+    ///     .last_commit(&unsound::path::new("~/examples/Folder.svelte"))
+    ///     .expect("Failed to get last commit")
+    ///     .map(|commit| commit.id);
+    ///
+    /// assert_eq!(gitignore_last_commit_id, Some(expected_commit_id));
+    /// ```
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{BranchName, Browser, Repository, Sha1};
+    /// use radicle_surf::vcs::git::git2::{Oid};
+    /// use radicle_surf::file_system::{Label, Path, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let repo = Repository::new("./data/git-platinum")
+    ///     .expect("Could not retrieve ./data/git-platinum as git repository");
+    /// let mut browser = Browser::new(repo).expect("Could not initialise Browser");
+    ///
+    /// // Check that last commit is the actual last commit even if head commit differs.
+    /// browser.commit("19bec071db6474af89c866a1bd0e4b1ff76e2b97".parse::<Sha1>().unwrap()).unwrap();
+    ///
+    /// let expected_commit_id =
+    ///     Oid::from_str("2429f097664f9af0c5b7b389ab998b2199ffa977").unwrap();
+    ///
+    /// let nested_directory_tree_commit_id = browser
+    ///     .last_commit(&unsound::path::new("~/this/is/a/really/deeply/nested/directory/tree"))
+    ///     .expect("Failed to get last commit")
+    ///     .map(|commit| commit.id);
+    ///
+    /// assert_eq!(nested_directory_tree_commit_id, Some(expected_commit_id));
+    /// ```
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{BranchName, Browser, Repository, Oid, Sha1};
+    /// use radicle_surf::file_system::{Label, Path, SystemType};
+    /// use radicle_surf::file_system::unsound;
+    ///
+    /// let repo = Repository::new("./data/git-platinum")
+    ///     .expect("Could not retrieve ./data/git-platinum as git repository");
+    /// let mut browser = Browser::new(repo).expect("Could not initialise Browser");
+    ///
+    /// let expected_commit_id =
+    ///     Oid::from_str("3873745c8f6ffb45c990eb23b491d4b4b6182f95").unwrap();
+    ///
+    /// let root_last_commit_id = browser
+    ///     .last_commit(&Path::root())
+    ///     .expect("Failed to get last commit")
+    ///     .map(|commit| commit.id);
+    ///
+    /// assert_eq!(root_last_commit_id, Some(expected_commit_id));
+    pub fn last_commit(&self, path: &file_system::Path) -> Result<Option<Commit>, Error> {
+        let commit = self.history.first();
+        let repository = &self.repository;
+        let file_history = repository.retry_policy.retry(|| {
+            repository.file_history(commit.clone()).context(|| {
+                Context::new("last_commit")
+                    .rev(commit.id)
+                    .path(path.clone())
+            })
+        })?;
+
+        Ok(file_history.find(&path.0).map(|tree| {
+            tree.maximum_by(&|c: &NonEmpty<OrderedCommit>, d| c.first().compare_by_id(&d.first()))
+                .first()
+                .commit
+                .clone()
+        }))
+    }
+
+    /// Get the last commit that touched each entry of the directory at `path`, e.g.
+    /// to show alongside a directory listing.
+    ///
+    /// Unlike calling [`last_commit`](#method.last_commit) for every entry, this walks
+    /// the history once, stopping as soon as a commit has been found for every entry.
+    /// If `path` is not a directory the result is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Repository, Sha1};
     /// use radicle_surf::file_system::unsound;
     ///
     /// let repo = Repository::new("./data/git-platinum")
     ///     .expect("Could not retrieve ./data/git-platinum as git repository");
     /// let mut browser = Browser::new(repo).expect("Could not initialise Browser");
+    /// browser.commit("19bec071db6474af89c866a1bd0e4b1ff76e2b97".parse::<Sha1>().unwrap()).unwrap();
     ///
-    /// let expected_commit_id =
-    ///     Oid::from_str("3873745c8f6ffb45c990eb23b491d4b4b6182f95").unwrap();
+    /// let last_commits = browser
+    ///     .last_commits_for(&unsound::path::new("~/src"))
+    ///     .expect("Failed to get last commits");
     ///
-    /// let root_last_commit_id = browser
-    ///     .last_commit(&Path::root())
-    ///     .expect("Failed to get last commit")
-    ///     .map(|commit| commit.id);
+    /// for (label, commit) in &last_commits {
+    ///     let path = unsound::path::new(&format!("~/src/{}", label));
+    ///     let expected = browser.last_commit(&path).unwrap().map(|commit| commit.id);
+    ///     assert_eq!(Some(commit.id), expected);
+    /// }
     ///
-    /// assert_eq!(root_last_commit_id, Some(expected_commit_id));
-    pub fn last_commit(&self, path: &file_system::Path) -> Result<Option<Commit>, Error> {
-        let file_history = self
-            .repository
-            .file_history(self.get_history().first().clone())?;
+    /// assert!(last_commits.contains_key(&unsound::label::new("memory.rs")));
+    /// ```
+    pub fn last_commits_for(
+        &self,
+        path: &file_system::Path,
+    ) -> Result<HashMap<file_system::Label, Commit>, Error> {
+        let commit = self.history.first();
+        let repository = &self.repository;
+        repository.retry_policy.retry(|| {
+            repository.last_commits_for(commit, path).context(|| {
+                Context::new("last_commits_for")
+                    .rev(commit.id)
+                    .path(path.clone())
+            })
+        })
+    }
 
-        Ok(file_history.find(&path.0).map(|tree| {
-            tree.maximum_by(&|c: &NonEmpty<OrderedCommit>, d| c.first().compare_by_id(&d.first()))
-                .first()
-                .commit
-                .clone()
-        }))
+    /// Get the commits that touched the file, or directory, at `path`, most recent
+    /// first, looking them up in the `cache` by `path` and the id of the commit the
+    /// `Browser` is viewing.
+    ///
+    /// If the `cache` holds the history of `path` from an earlier commit, only the
+    /// commits since then are walked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::cache::Budget;
+    /// use radicle_surf::file_system::unsound;
+    /// use radicle_surf::vcs::git::{Browser, Commit, FileHistoryCache, Repository, Sha1};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
+    /// let mut cache = FileHistoryCache::new(Budget::new(1024 * 1024));
+    /// let readme = unsound::path::new("~/README.md");
+    ///
+    /// browser.commit("d6880352fc7fda8f521ae9b7357668b17bb5bad5".parse::<Sha1>().unwrap()).unwrap();
+    /// let older = browser.file_history_cached(&readme, &mut cache).unwrap();
+    ///
+    /// browser.head().unwrap();
+    /// let newer = browser.file_history_cached(&readme, &mut cache).unwrap();
+    ///
+    /// let ids = |commits: Vec<Commit>| commits.into_iter().map(|commit| commit.id).collect::<Vec<_>>();
+    /// assert!(ids(newer).ends_with(&ids(older)));
+    /// assert_eq!(cache.extensions(), 1);
+    /// ```
+    pub fn file_history_cached(
+        &self,
+        path: &file_system::Path,
+        cache: &mut FileHistoryCache,
+    ) -> Result<Vec<Commit>, Error> {
+        let tip = self.history.first().id;
+        cache
+            .get_or_walk(&self.repository, path, tip)
+            .context(|| Context::new("file_history").rev(tip).path(path.clone()))
+    }
+
+    /// Find the commits reachable from `head` but not from `base`, oldest first, and
+    /// mark the ones whose change is already reachable from `base`, like `git cherry`.
+    ///
+    /// Two commits make the same change when they have the same patch id, see
+    /// [`Diff::patch_id`](../../diff/struct.Diff.html#method.patch_id), e.g. a commit
+    /// and its cherry-pick onto `base`. Merge commits are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, Oid, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
+    ///
+    /// let base = Oid::from_str("d3464e33d75c75c99bfb90fa2e9d16efc0b7d0e3").unwrap();
+    /// let head = browser.get_history().first().id;
+    ///
+    /// let cherries = browser.cherry(base, head).unwrap();
+    ///
+    /// assert!(cherries.iter().all(|cherry| !cherry.is_applied()));
+    /// assert_eq!(cherries.last().map(|cherry| cherry.commit.id), Some(head));
+    /// ```
+    pub fn cherry(&self, base: Oid, head: Oid) -> Result<Vec<Cherry>, Error> {
+        let repository = &self.repository;
+        repository.retry_policy.retry(|| {
+            repository
+                .cherry(base, head)
+                .context(|| Context::new("cherry").rev(head))
+        })
+    }
+
+    /// Compare the working directory at `workdir` on disk to the commit the `Browser`
+    /// is viewing, finding the files that were modified, deleted, or are untracked.
+    ///
+    /// Untracked files that are ignored by a `.gitignore` in the working directory
+    /// are left out. Unlike `git status`, the index is not consulted, so staged
+    /// changes are reported like any other change.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use radicle_surf::vcs::git::{Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
+    ///
+    /// let status = browser.status(std::path::Path::new("./data/git-platinum")).unwrap();
+    ///
+    /// // A file that was modified is still in the working directory, and was
+    /// // in the commit.
+    /// assert!(status.modified.iter().all(|path| !status.deleted.contains(path)));
+    /// assert!(status.modified.iter().all(|path| !status.untracked.contains(path)));
+    /// ```
+    pub fn status(&self, workdir: &std::path::Path) -> Result<Status, Error> {
+        let commit = self.history.first();
+        let directory = self.get_directory()?;
+        let mut on_disk = directory::Directory::root();
+        on_disk
+            .read_disk(
+                workdir,
+                &self.repository.label_rules(),
+                &directory::DiskOptions::default(),
+                &mut |_, entry| directory::read_disk_file(entry),
+                Some(&directory),
+            )
+            .context(|| Context::new("status").rev(commit.id))?;
+
+        Ok(Status::new(&directory, &on_disk))
+    }
+
+    /// Render the `Directory` for this `Browser`, looking it up in the `cache` by
+    /// the id of the commit the `Browser` is viewing, and inserting it if it is missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::cache::{Budget, Cache};
+    /// use radicle_surf::vcs::git::{Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
+    ///
+    /// let mut cache = Cache::new(Budget::new(1024 * 1024));
+    /// let directory = browser.get_directory_cached(&mut cache).unwrap();
+    ///
+    /// assert_eq!(browser.get_directory_cached(&mut cache).unwrap(), directory);
+    /// assert_eq!(cache.metrics().hits, 1);
+    /// ```
+    pub fn get_directory_cached(
+        &self,
+        cache: &mut Cache<Oid, directory::Directory>,
+    ) -> Result<directory::Directory, Error> {
+        cache.get_or_insert_with(self.history.first().id, || self.get_directory())
+    }
+
+    /// Render the `Directory` for this `Browser` without reading the contents of its
+    /// files, which are lazy instead, see [`File::lazy`](../../file_system/struct.File.html#method.lazy).
+    ///
+    /// The contents of a file are read from the repository the first time they are
+    /// needed, see [`File::contents`](../../file_system/struct.File.html#method.contents),
+    /// so building the `Directory` of a large repository only costs memory for its tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::file_system::unsound;
+    /// use radicle_surf::vcs::git::{Browser, Repository};
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let browser = Browser::new(repo).unwrap();
+    ///
+    /// let lazy = browser.get_directory_lazy().unwrap();
+    /// let readme = lazy.find_file(&unsound::path::new("README.md")).unwrap();
+    /// assert!(!readme.is_loaded());
+    ///
+    /// let eager = browser.get_directory().unwrap();
+    /// let expected = eager.find_file(&unsound::path::new("README.md")).unwrap();
+    /// assert_eq!(readme.contents(), expected.contents());
+    /// assert_eq!(readme, expected);
+    /// ```
+    pub fn get_directory_lazy(&self) -> Result<directory::Directory, Error> {
+        let commit = self.history.first();
+        let blobs = git2::Repository::open(self.repository.repo.path())
+            .context(|| Context::new("snapshot").rev(commit.id))?;
+        let blobs = Arc::new(Mutex::new(blobs));
+        let tree = self
+            .repository
+            .retry_policy
+            .retry(|| Self::get_tree(&self.repository, commit, Some(&blobs)))?;
+        Ok(directory::Directory::from_hash_map(tree))
     }
 
     /// Do a pre-order TreeWalk of the given commit. This turns a Tree
     /// into a HashMap of Paths and a list of Files. We can then turn that
     /// into a Directory.
+    ///
+    /// When `blobs` is given, the contents of files are not read, and the files are
+    /// lazy instead, reading their contents from `blobs` when they are loaded.
     fn get_tree(
-        repo: &git2::Repository,
+        repository: &Repository,
         commit: &Commit,
+        blobs: Option<&Arc<Mutex<git2::Repository>>>,
     ) -> Result<HashMap<file_system::Path, NonEmpty<(file_system::Label, directory::File)>>, Error>
     {
-        let mut file_paths_or_error: Result<
-            HashMap<file_system::Path, NonEmpty<(file_system::Label, directory::File)>>,
-            Error,
-        > = Ok(HashMap::new());
-
-        let commit = repo.find_commit(commit.id)?;
-        let tree = commit.as_object().peel_to_tree()?;
-
-        tree.walk(
-            git2::TreeWalkMode::PreOrder,
-            |s, entry| match Self::tree_entry_to_file_and_path(repo, s, entry) {
-                Ok((path, name, file)) => {
-                    match file_paths_or_error.as_mut() {
-                        Ok(mut files) => Self::update_file_map(path, name, file, &mut files),
-
-                        // We don't need to update, we want to keep the error.
-                        Err(_err) => {}
-                    }
-                    git2::TreeWalkResult::Ok
-                }
-                Err(err) => match err {
-                    // We want to continue if the entry was not a Blob.
-                    TreeWalkError::NotBlob => git2::TreeWalkResult::Ok,
-
-                    // We found a ObjectType::Commit (likely a submodule) and
-                    // so we can skip it.
-                    TreeWalkError::Commit => git2::TreeWalkResult::Ok,
-
-                    // But we want to keep the error and abort otherwise.
-                    TreeWalkError::Git(err) => {
-                        file_paths_or_error = Err(err);
-                        git2::TreeWalkResult::Abort
+        let repo = &repository.repo;
+        let mut files = HashMap::new();
+
+        let context = || Context::new("snapshot").rev(commit.id);
+        let commit = repo.find_commit(commit.id).context(context)?;
+        let tree = commit.as_object().peel_to_tree().context(context)?;
+        let mut labels = file_system::LabelInterner::new(GitLabelRules::from_repository(repo));
+        let mut slow_op = SlowOp::start("snapshot", repository, commit.id());
+
+        Self::collect_tree(repo, &mut labels, tree, &mut files, &mut slow_op, blobs)
+            .context(context)?;
+
+        Ok(files)
+    }
+
+    /// Collect the blobs of a `tree`, keyed by the `Path` of their parent directory.
+    ///
+    /// We walk the tree ourselves rather than using `git2::Tree::walk`, since
+    /// the latter aborts on directory names that are not valid UTF-8. Entries whose
+    /// names are not valid under the `labels`' rules are skipped with a warning.
+    fn collect_tree(
+        repo: &git2::Repository,
+        labels: &mut file_system::LabelInterner<GitLabelRules>,
+        tree: git2::Tree,
+        files: &mut HashMap<file_system::Path, NonEmpty<(file_system::Label, directory::File)>>,
+        slow_op: &mut SlowOp<Oid>,
+        blobs: Option<&Arc<Mutex<git2::Repository>>>,
+    ) -> Result<(), Error> {
+        // The trees left to collect, along with their `Path`, where `None` is the root.
+        let mut trees = vec![(tree, None::<file_system::Path>)];
+        while let Some((tree, path)) = trees.pop() {
+            for entry in tree.iter() {
+                let name = match labels.intern(entry.name_bytes()) {
+                    Ok(name) => name,
+                    // Other tools can write entries that git itself would refuse, e.g. a
+                    // `.git` directory, which are left out rather than failing the snapshot.
+                    Err(err) => {
+                        tracing::warn!(
+                            tree = %tree.id(),
+                            name = %String::from_utf8_lossy(entry.name_bytes()),
+                            error = ?err,
+                            "skipping tree entry with an invalid name"
+                        );
+                        continue;
                     }
-                },
-            },
-        )?;
+                };
 
-        file_paths_or_error
+                match entry.kind() {
+                    Some(git2::ObjectType::Tree) => {
+                        let sub_path = match &path {
+                            Some(path) => path.try_join(name)?,
+                            None => file_system::Path::new(name),
+                        };
+                        // There must be room for the entries of the sub-tree too.
+                        file_system::check_depth(sub_path.0.len() + 1)?;
+                        let sub_tree = entry.to_object(repo)?.peel_to_tree()?;
+                        slow_op.read_object();
+                        trees.push((sub_tree, Some(sub_path)));
+                    }
+                    Some(git2::ObjectType::Blob) => {
+                        let mode = match entry.filemode() {
+                            0o100_755 => directory::FileMode::Executable,
+                            0o120_000 => directory::FileMode::Symlink,
+                            _ => directory::FileMode::Regular,
+                        };
+                        // The targets of symbolic links are always read, since they are
+                        // needed to tell where the links point to.
+                        let file = match blobs {
+                            Some(blobs) if mode != directory::FileMode::Symlink => {
+                                let (size, _) = repo.odb()?.read_header(entry.id())?;
+                                let blob = GitBlob {
+                                    repo: blobs.clone(),
+                                    oid: entry.id(),
+                                };
+                                directory::File::lazy(Arc::new(blob), size)
+                                    .with_entry_mode(mode)
+                                    .with_metadata(Sha1::from(entry.id()))
+                            }
+                            _ => {
+                                let object = entry.to_object(repo)?;
+                                let blob = object.peel_to_blob()?;
+                                slow_op.read_object();
+                                directory::File::new(blob.content())
+                                    .with_entry_mode(mode)
+                                    .with_metadata(Sha1::from(entry.id()))
+                            }
+                        };
+                        let path = path.clone().unwrap_or_else(file_system::Path::root);
+                        Self::update_file_map(path, name, file, files);
+                    }
+                    // A Commit object in the Tree is a submodule, pinned to the commit,
+                    // which is not in this repository.
+                    Some(git2::ObjectType::Commit) => {
+                        let commit = entry.id().to_string();
+                        let file = directory::File::new(commit.as_bytes())
+                            .with_entry_mode(directory::FileMode::Submodule)
+                            .with_metadata(Sha1::from(entry.id()));
+                        let path = path.clone().unwrap_or_else(file_system::Path::root);
+                        Self::update_file_map(path, name, file, files);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
     }
 
     fn update_file_map(
@@ -1015,45 +3320,837 @@ impl Browser {
             .and_modify(|entries| entries.push((name.clone(), file.clone())))
             .or_insert_with(|| NonEmpty::new((name, file)));
     }
+}
 
-    fn tree_entry_to_file_and_path(
-        repo: &git2::Repository,
-        tree_path: &str,
-        entry: &git2::TreeEntry,
-    ) -> Result<(file_system::Path, file_system::Label, directory::File), TreeWalkError> {
-        // Account for the "root" of git being the empty string
-        let path = if tree_path.is_empty() {
-            Ok(file_system::Path::root())
-        } else {
-            file_system::Path::try_from(tree_path)
-        }?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::git::fixture::Fixture;
+
+    #[test]
+    fn test_status() {
+        let fixture = Fixture::new();
+        fixture.commit(
+            Some("HEAD"),
+            &[
+                (".gitignore", b"*.log\n"),
+                ("README.md", b"# Surf"),
+                ("lib.rs", b"mod vcs;"),
+                ("vcs.rs", b"mod git;"),
+            ],
+            &[],
+        );
+
+        let repo_dir = fixture.path();
+        std::fs::write(repo_dir.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(repo_dir.join("README.md"), "# Surf").unwrap();
+        std::fs::write(repo_dir.join("lib.rs"), "mod vcs;\nmod fs;").unwrap();
+        std::fs::create_dir_all(repo_dir.join("src")).unwrap();
+        std::fs::write(repo_dir.join("src/fs.rs"), "").unwrap();
+        std::fs::write(repo_dir.join("src/debug.log"), "").unwrap();
+
+        let browser = Browser::new(fixture.repository()).unwrap();
+        let status = browser.status(repo_dir);
+
+        assert_eq!(
+            status,
+            Ok(Status {
+                modified: vec![file_system::unsound::path::new("lib.rs")],
+                untracked: vec![file_system::unsound::path::new("src/fs.rs")],
+                deleted: vec![file_system::unsound::path::new("vcs.rs")],
+            })
+        );
+    }
+
+    #[test]
+    fn test_status_prunes_ignored_directories() {
+        let fixture = Fixture::new();
+        fixture.commit(
+            Some("HEAD"),
+            &[
+                (".gitignore", b"target/\n"),
+                ("src/.gitignore", b"*.tmp\n"),
+                ("src/lib.rs", b"mod vcs;"),
+                ("target/keep.rs", b"mod kept;"),
+            ],
+            &[],
+        );
+
+        let repo_dir = fixture.path();
+        std::fs::create_dir_all(repo_dir.join("src")).unwrap();
+        std::fs::write(repo_dir.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(repo_dir.join("src/.gitignore"), "*.tmp\n").unwrap();
+        std::fs::write(repo_dir.join("src/lib.rs"), "mod vcs;").unwrap();
+        std::fs::write(repo_dir.join("src/scratch.tmp"), "").unwrap();
+        std::fs::write(repo_dir.join("scratch.tmp"), "").unwrap();
+        // A tracked file is compared even though it is ignored, while the rest of
+        // the ignored directory is never read, or it would be too deep.
+        let deep = vec!["d"; file_system::MAX_DEPTH].join("/");
+        std::fs::create_dir_all(repo_dir.join("target").join(deep)).unwrap();
+        std::fs::write(repo_dir.join("target/keep.rs"), "mod changed;").unwrap();
+
+        let browser = Browser::new(fixture.repository()).unwrap();
+
+        assert_eq!(
+            browser.status(repo_dir),
+            Ok(Status {
+                modified: vec![file_system::unsound::path::new("target/keep.rs")],
+                untracked: vec![file_system::unsound::path::new("scratch.tmp")],
+                deleted: vec![],
+            })
+        );
 
-        // We found a Commit object in the Tree, likely a submodule.
-        // We will skip this entry.
-        if let Some(git2::ObjectType::Commit) = entry.kind() {
-            return Err(TreeWalkError::Commit);
+        let missing = browser.status(&repo_dir.join("missing")).unwrap_err();
+        match missing.root_cause() {
+            Error::Io(err) => assert_eq!(err.kind(), std::io::ErrorKind::NotFound),
+            err => panic!("expected an I/O error, got {:?}", err),
         }
+        assert!(std::error::Error::source(missing.root_cause()).is_some());
+    }
 
-        let object = entry.to_object(repo)?;
-        let blob = object.as_blob().ok_or(TreeWalkError::NotBlob)?;
-        let name = str::from_utf8(entry.name_bytes())?;
+    #[test]
+    fn test_cherry() {
+        let fixture = Fixture::new();
+        let master = Some("refs/heads/master");
+        let topic = Some("refs/heads/topic");
 
-        let name = file_system::Label::try_from(name).map_err(Error::FileSystem)?;
+        let root = fixture.commit(master, &[("lib.rs", b"mod a;\n")], &[]);
+        // The same change as `picked` below, on top of a different version of `lib.rs`.
+        let upstream = fixture.commit(master, &[("lib.rs", b"mod a;\nmod b;\n")], &[root]);
+        let upstream = fixture.commit(
+            master,
+            &[("lib.rs", b"mod a;\nmod b;\nmod c;\n")],
+            &[upstream],
+        );
+        let picked = fixture.commit(topic, &[("lib.rs", b"mod a;\nmod c;\n")], &[root]);
+        let unpicked = fixture.commit(
+            topic,
+            &[("lib.rs", b"mod a;\nmod c;\n"), ("README.md", b"# Surf")],
+            &[picked],
+        );
 
-        Ok((
-            path,
-            name,
-            directory::File {
-                contents: blob.content().to_owned(),
-                size: blob.size(),
-            },
-        ))
+        let cherries = fixture.repository().cherry(upstream, unpicked).unwrap();
+
+        let cherries = cherries
+            .into_iter()
+            .map(|cherry| (cherry.commit.id, cherry.upstream))
+            .collect::<Vec<_>>();
+        assert_eq!(cherries, vec![(picked, Some(upstream)), (unpicked, None)]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_cherry_non_utf8_paths() {
+        let fixture = Fixture::new();
+        let git = &fixture.git;
+        // Two names in Latin-1 that are not valid UTF-8, and that are the same once
+        // decoded lossily.
+        let tree = |names: &[&[u8]]| {
+            let mut builder = git.treebuilder(None).unwrap();
+            for name in names {
+                builder
+                    .insert(*name, git.blob(b"mod a;\n").unwrap(), 0o100_644)
+                    .unwrap();
+            }
+            builder.write().unwrap()
+        };
+        let commit = |refname: &str, names: &[&[u8]], parents: &[Oid]| {
+            fixture.commit_tree(&fixture.signature, Some(refname), tree(names), parents)
+        };
+
+        let root = commit("refs/heads/master", &[], &[]);
+        let upstream = commit("refs/heads/master", &[b"caf\xe9"], &[root]);
+        let upstream = commit("refs/heads/master", &[b"caf\xe9", b"caf\xe7"], &[upstream]);
+        let picked = commit("refs/heads/topic", &[b"caf\xe9"], &[root]);
+        let unpicked = commit("refs/heads/topic", &[b"caf\xe9", b"caf\xe8"], &[picked]);
+
+        let cherries = fixture.repository().cherry(upstream, unpicked).unwrap();
+
+        let cherries = cherries
+            .into_iter()
+            .map(|cherry| (cherry.commit.id, cherry.upstream.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(cherries, vec![(picked, true), (unpicked, false)]);
+    }
+
+    #[test]
+    fn test_history_adapters() {
+        let fixture = Fixture::new();
+        let commit = |contents: &[u8], parents: &[Oid]| {
+            fixture.commit(None, &[("lib.rs", contents)], parents)
+        };
+
+        let root = commit(b"mod a;", &[]);
+        let empty = commit(b"mod a;", &[root]);
+        let revert = commit(b"mod b;", &[empty]);
+        let side = commit(b"mod c;", &[root]);
+        let merge = commit(b"mod a;", &[revert, side]);
+
+        let history = fixture.repository().to_history(merge).unwrap();
+
+        let ids = |history: Option<History>| {
+            let mut ids = history
+                .map(|history| history.iter().map(|commit| commit.id).collect::<Vec<_>>())
+                .unwrap_or_default();
+            ids.sort();
+            ids
+        };
+        let sorted = |mut ids: Vec<Oid>| {
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(
+            ids(history.skip_empty()),
+            sorted(vec![root, revert, side, merge])
+        );
+        assert_eq!(ids(history.merges_only()), vec![merge]);
+
+        let deduped = history.dedup_by_tree();
+        assert_eq!(deduped.first().id, merge);
+        assert_eq!(deduped.iter().count(), 3);
+        assert!(deduped
+            .iter()
+            .all(|commit| commit.id != empty && commit.id != root));
+    }
+
+    #[test]
+    fn test_peer_views() {
+        let fixture = Fixture::new();
+        let git = &fixture.git;
+        let commit = |refname: &str, parents: &[Oid]| fixture.commit(Some(refname), &[], parents);
+
+        let base = commit("refs/namespaces/alice/refs/heads/main", &[]);
+        git.reference("refs/heads/master", base, false, "").unwrap();
+        git.reference("refs/namespaces/bob/refs/heads/main", base, false, "")
+            .unwrap();
+        let alice = commit("refs/namespaces/alice/refs/heads/main", &[base]);
+        let carol = commit(
+            "refs/namespaces/carol/refs/namespaces/dev/refs/heads/main",
+            &[base],
+        );
+        commit("refs/namespaces/alice/refs/heads/other", &[base]);
+        let tree = fixture.tree(&[]);
+        git.reference("refs/namespaces/dave/refs/heads/main", tree, false, "")
+            .unwrap();
+
+        let mut browser = Browser::new(fixture.repository()).unwrap();
+        let views = browser.peer_views(&BranchName::new("main")).unwrap();
+        let divergences = browser.peer_divergence(&BranchName::new("main")).unwrap();
+        browser.freeze().unwrap();
+        let frozen_views = browser.peer_views(&BranchName::new("main")).unwrap();
+
+        assert_eq!(
+            views,
+            vec![
+                (Namespace::new("alice"), alice),
+                (Namespace::new("bob"), base),
+                (Namespace::new("carol/dev"), carol),
+            ]
+        );
+        assert_eq!(frozen_views, views);
+        assert_eq!(
+            divergences,
+            vec![
+                (
+                    Namespace::new("alice"),
+                    Namespace::new("bob"),
+                    Divergence {
+                        ahead: 1,
+                        behind: 0
+                    }
+                ),
+                (
+                    Namespace::new("alice"),
+                    Namespace::new("carol/dev"),
+                    Divergence {
+                        ahead: 1,
+                        behind: 1
+                    }
+                ),
+                (
+                    Namespace::new("bob"),
+                    Namespace::new("carol/dev"),
+                    Divergence {
+                        ahead: 0,
+                        behind: 1
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rev() {
+        let fixture = Fixture::new();
+        let first = fixture.commit(Some("HEAD"), &[], &[]);
+        let second = fixture.commit(Some("HEAD"), &[], &[first]);
+        let object = fixture.git.find_object(first, None).unwrap();
+        fixture
+            .git
+            .tag("v0.1.0", &object, &fixture.signature, "v0.1.0", false)
+            .unwrap();
+
+        let mut browser = Browser::new(fixture.repository()).unwrap();
+        let mut rev = |rev: &str| {
+            browser.rev(rev).map(|_| {
+                browser
+                    .get_history()
+                    .iter()
+                    .map(|c| c.id)
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(rev("master").unwrap(), vec![second, first]);
+        assert_eq!(rev("v0.1.0").unwrap(), vec![first]);
+        assert_eq!(rev(&first.to_string()[..7]).unwrap(), vec![first]);
+        assert_eq!(rev(&second.to_string()).unwrap(), vec![second, first]);
+        assert_eq!(
+            rev("nope").unwrap_err(),
+            Error::RevNotFound("nope".to_string())
+        );
+        assert!(rev(&first.to_string()[..3]).is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let fixture = Fixture::new();
+        let first = fixture.commit(
+            Some("HEAD"),
+            &[
+                ("README.md", b"# Surf"),
+                ("old.txt", b"old"),
+                ("src/lib.rs", b"mod a;"),
+                ("src/a.rs", b""),
+                ("src/c.rs", b"fn c() {}\nfn d() {}\n"),
+            ],
+            &[],
+        );
+        fixture.commit(
+            Some("HEAD"),
+            &[
+                ("README.md", b"# Surf"),
+                ("src/lib.rs", b"mod a; mod b;"),
+                ("src/a.rs", b""),
+                ("src/b.rs", b"fn b() {}"),
+                ("src/d.rs", b"fn c() {}\nfn d() {}\n"),
+            ],
+            &[first],
+        );
+
+        let browser = Browser::new(fixture.repository()).unwrap();
+        let diff = browser.diff("HEAD^", "HEAD").unwrap();
+        let reverse = browser.diff("HEAD", "HEAD~1").unwrap();
+        let same = browser.diff("HEAD", "master").unwrap();
+        let missing = browser.diff("HEAD", "nope");
+
+        let path = file_system::unsound::path::new;
+        assert_eq!(
+            diff.created,
+            vec![crate::diff::CreateFile(path("~/src/b.rs"))]
+        );
+        assert_eq!(
+            diff.deleted,
+            vec![crate::diff::DeleteFile(path("~/old.txt"))]
+        );
+        assert_eq!(
+            diff.moved,
+            vec![crate::diff::MoveFile {
+                old_path: path("~/src/c.rs"),
+                new_path: path("~/src/d.rs"),
+            }]
+        );
+        assert_eq!(
+            diff.modified
+                .iter()
+                .map(|modified| modified.path.clone())
+                .collect::<Vec<_>>(),
+            vec![path("~/src/lib.rs")]
+        );
+        assert_eq!(
+            reverse.created,
+            vec![crate::diff::CreateFile(path("~/old.txt"))]
+        );
+        assert_eq!(
+            reverse.moved,
+            vec![crate::diff::MoveFile {
+                old_path: path("~/src/d.rs"),
+                new_path: path("~/src/c.rs"),
+            }]
+        );
+        assert_eq!(
+            same.created.len() + same.deleted.len() + same.modified.len(),
+            0
+        );
+        assert_eq!(
+            missing.unwrap_err().root_cause(),
+            &Error::RevNotFound("nope".to_string())
+        );
+    }
+
+    #[test]
+    fn test_default_view() {
+        let fixture = Fixture::new();
+        let at = |seconds| {
+            git2::Signature::new("surf", "surf@example.com", &Time::new(seconds, 0)).unwrap()
+        };
+        let trunk = fixture.commit_by(&at(1), Some("refs/remotes/origin/trunk"), &[], &[]);
+        let git = &fixture.git;
+        git.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/trunk",
+            false,
+            "",
+        )
+        .unwrap();
+        git.set_head("refs/heads/unborn").unwrap();
+
+        let view = || {
+            let (browser, view) = Browser::new_with_default_view(fixture.repository()).unwrap();
+            (view, browser.get_history().first().id)
+        };
+        let branch = |name: &str| BranchName::new(name);
+
+        assert_eq!(
+            view(),
+            (
+                DefaultView::DefaultBranch(Branch::remote(branch("origin/trunk"))),
+                trunk
+            )
+        );
+
+        git.reference("refs/heads/trunk", trunk, false, "").unwrap();
+        assert_eq!(
+            view(),
+            (
+                DefaultView::DefaultBranch(Branch::local(branch("trunk"))),
+                trunk
+            )
+        );
+
+        // Without a default branch, the most recent local tip is viewed, and a
+        // remote's `HEAD` is never viewed as a branch of its own.
+        git.find_reference("refs/remotes/origin/HEAD")
+            .unwrap()
+            .delete()
+            .unwrap();
+        let topic = fixture.commit_by(&at(2), Some("refs/heads/topic"), &[], &[]);
+        assert_eq!(
+            view(),
+            (DefaultView::Branch(Branch::local(branch("topic"))), topic)
+        );
+        git.find_reference("refs/heads/topic")
+            .unwrap()
+            .delete()
+            .unwrap();
+        git.find_reference("refs/heads/trunk")
+            .unwrap()
+            .delete()
+            .unwrap();
+        git.reference_symbolic(
+            "refs/remotes/upstream/HEAD",
+            "refs/remotes/origin/trunk",
+            false,
+            "",
+        )
+        .unwrap();
+        assert_eq!(
+            view(),
+            (
+                DefaultView::Branch(Branch::remote(branch("origin/trunk"))),
+                trunk
+            )
+        );
+
+        git.find_reference("refs/remotes/upstream/HEAD")
+            .unwrap()
+            .delete()
+            .unwrap();
+        git.find_reference("refs/remotes/origin/trunk")
+            .unwrap()
+            .delete()
+            .unwrap();
+        git.reference("refs/tags/v0.1.0", trunk, false, "").unwrap();
+        assert_eq!(view(), (DefaultView::Tag(TagName::new("v0.1.0")), trunk));
+
+        git.find_reference("refs/tags/v0.1.0")
+            .unwrap()
+            .delete()
+            .unwrap();
+        let empty = Browser::new_with_default_view(fixture.repository()).map(|(_, view)| view);
+        assert_eq!(empty.unwrap_err().root_cause(), &Error::EmptyCommitHistory);
+    }
+
+    #[test]
+    fn test_branch_and_tag_errors() {
+        let fixture = Fixture::new();
+        let first = fixture.commit(Some("HEAD"), &[("README.md", b"# Surf")], &[]);
+        let object = fixture.git.find_object(first, None).unwrap();
+        fixture
+            .git
+            .tag_lightweight("v0.1.0", &object, false)
+            .unwrap();
+
+        let mut browser = Browser::new(fixture.repository()).unwrap();
+        let branch =
+            |browser: &mut Browser, name: &str| browser.branch(name.parse::<BranchName>().unwrap());
+        let tag = |browser: &mut Browser, name: &str| browser.tag(name.parse::<TagName>().unwrap());
+
+        // The crate's own errors are returned as they are, frozen or not.
+        for frozen in &[false, true] {
+            if *frozen {
+                browser.freeze().unwrap();
+            }
+            assert_eq!(branch(&mut browser, "v0.1.0"), Err(Error::NotBranch));
+            assert_eq!(tag(&mut browser, "master"), Err(Error::NotTag));
+            assert_eq!(
+                branch(&mut browser, "nope"),
+                Err(Error::RevNotFound("nope".to_string()))
+            );
+            assert_eq!(
+                tag(&mut browser, "v0.2.0"),
+                Err(Error::RevNotFound("v0.2.0".to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_commit_by_short_sha() {
+        let fixture = Fixture::new();
+        let first = fixture.commit(Some("HEAD"), &[("README.md", b"# Surf")], &[]);
+        let second = fixture.commit(Some("HEAD"), &[("README.md", b"# Surf!")], &[first]);
+
+        let mut browser = Browser::new(fixture.repository()).unwrap();
+        let short = first.to_string()[..7].parse::<Sha1>().unwrap();
+        browser.commit(short).unwrap();
+        assert_eq!(browser.get_history().first().id, first);
+
+        let full = Sha1::from(second);
+        browser.commit(full).unwrap();
+        assert_eq!(browser.get_history().first().id, second);
+
+        let missing = "0000000".to_string();
+        let err = browser
+            .commit(missing.parse::<Sha1>().unwrap())
+            .unwrap_err();
+        assert_eq!(err.root_cause(), &Error::RevNotFound(missing));
+    }
+
+    #[test]
+    fn test_rev_parse() {
+        let fixture = Fixture::new();
+        let git = &fixture.git;
+        let mut commits = vec![];
+        for _ in 0..3 {
+            let parents = commits.last().copied().into_iter().collect::<Vec<_>>();
+            commits.push(fixture.commit(Some("HEAD"), &[], &parents));
+        }
+        let object = git.find_object(commits[1], None).unwrap();
+        git.tag("v1.0", &object, &fixture.signature, "v1.0", false)
+            .unwrap();
+
+        // Find two blobs whose ids share their first four digits.
+        let mut prefixes = HashMap::new();
+        let ambiguous = (0..)
+            .find_map(|i: usize| {
+                let id = git.blob(i.to_string().as_bytes()).unwrap().to_string();
+                prefixes.insert(id[..4].to_string(), id.clone())
+            })
+            .unwrap();
+
+        let mut browser = Browser::new(fixture.repository()).unwrap();
+        let parse = |browser: &Browser, rev: &str| browser.rev_parse(rev);
+        let live = vec![
+            parse(&browser, "HEAD~2").unwrap(),
+            parse(&browser, "v1.0^{}").unwrap(),
+            parse(&browser, "master@{1}").unwrap(),
+            parse(&browser, &commits[0].to_string()[..8]).unwrap(),
+        ];
+        let missing = parse(&browser, "nope~1").unwrap_err();
+        let too_short = parse(&browser, &ambiguous[..4]).unwrap_err();
+
+        browser.freeze().unwrap();
+        let moved = fixture.commit(Some("HEAD"), &[], &[commits[2]]);
+        let frozen = vec![
+            parse(&browser, "HEAD").unwrap(),
+            parse(&browser, "master~1").unwrap(),
+        ];
+        browser.thaw();
+        let thawed = parse(&browser, "master").unwrap();
+
+        assert_eq!(live, vec![commits[0], commits[1], commits[1], commits[0]]);
+        assert_eq!(
+            missing.root_cause(),
+            &Error::RevNotFound("nope~1".to_string())
+        );
+        assert!(missing.breadcrumbs().is_empty());
+        assert_eq!(
+            too_short.root_cause(),
+            &Error::AmbiguousRev(ambiguous[..4].to_string())
+        );
+        assert_eq!(frozen, vec![commits[2], commits[1]]);
+        assert_eq!(thawed, moved);
+    }
+
+    #[test]
+    fn test_list_tag_details() {
+        let fixture = Fixture::new();
+        let git = &fixture.git;
+        let blob = git.blob(b"release notes").unwrap();
+        let commit = fixture.commit(Some("HEAD"), &[], &[]);
+        let object = git.find_object(commit, None).unwrap();
+        git.tag_lightweight("v0.1.0", &object, false).unwrap();
+        git.tag(
+            "v0.2.0",
+            &object,
+            &fixture.signature,
+            "Release v0.2.0\n",
+            false,
+        )
+        .unwrap();
+        git.tag(
+            "notes",
+            &git.find_object(blob, None).unwrap(),
+            &fixture.signature,
+            "notes",
+            false,
+        )
+        .unwrap();
+
+        let mut browser = Browser::new(fixture.repository()).unwrap();
+        let tags = browser.list_tag_details().unwrap();
+        browser.freeze().unwrap();
+        let frozen = browser.list_tag_details().unwrap();
+
+        for tags in &[tags, frozen] {
+            let summary = tags
+                .iter()
+                .map(|tag| {
+                    (
+                        tag.name.clone(),
+                        tag.target,
+                        tag.tagger.as_ref().map(|tagger| tagger.name.clone()),
+                        tag.message.clone(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                summary,
+                vec![
+                    (TagName::new("v0.1.0"), commit, None, None),
+                    (
+                        TagName::new("v0.2.0"),
+                        commit,
+                        Some("surf".to_string()),
+                        Some("Release v0.2.0\n".to_string())
+                    ),
+                ]
+            );
+            assert!(!tags[0].is_annotated());
+            assert!(tags[1].is_annotated());
+        }
+
+        // A tag in Latin-1 is decoded lossily, without failing the other tags.
+        let mut latin1 =
+            format!("object {}\ntype commit\ntag v0.3.0\ntagger Jos", commit).into_bytes();
+        latin1.extend_from_slice(b"\xe9 <jose@surf> 1 +0000\n\nVersi\xf3n\n");
+        let object = git
+            .odb()
+            .unwrap()
+            .write(git2::ObjectType::Tag, &latin1)
+            .unwrap();
+        git.reference("refs/tags/v0.3.0", object, false, "tag")
+            .unwrap();
+
+        let tags = Browser::new(fixture.repository())
+            .unwrap()
+            .list_tag_details()
+            .unwrap();
+        assert_eq!(tags.len(), 3);
+        let tagger = tags[2].tagger.as_ref().unwrap();
+        assert_eq!(tagger.name, "Jos\u{fffd}");
+        assert_eq!(tagger.email, "jose@surf");
+        assert_eq!(tags[2].message.as_deref(), Some("Versi\u{fffd}n\n"));
+    }
+
+    #[test]
+    fn test_list_branch_tips() {
+        let fixture = Fixture::new();
+        let git = &fixture.git;
+        let first = fixture.commit(Some("HEAD"), &[], &[]);
+        let second = fixture.commit(Some("refs/heads/dev"), &[], &[first]);
+        git.reference("refs/remotes/origin/dev", first, false, "fetch")
+            .unwrap();
+        git.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/gone",
+            false,
+            "",
+        )
+        .unwrap();
+
+        let mut browser = Browser::new(fixture.repository()).unwrap();
+        let mut tips = browser.list_branch_tips(None).unwrap();
+        tips.sort();
+        let mut remote = browser.list_branch_tips(Some(BranchType::Remote)).unwrap();
+        remote.sort();
+        browser.freeze().unwrap();
+        let mut frozen = browser.list_branch_tips(None).unwrap();
+        frozen.sort();
+
+        assert_eq!(
+            tips,
+            vec![
+                (Branch::local(BranchName::new("dev")), second),
+                (Branch::local(BranchName::new("master")), first),
+                (Branch::remote(BranchName::new("origin/dev")), first),
+            ]
+        );
+        assert_eq!(
+            remote,
+            vec![(Branch::remote(BranchName::new("origin/dev")), first)]
+        );
+        assert_eq!(frozen, tips);
+    }
+
+    #[test]
+    fn test_last_commits_for_non_utf8_names() {
+        let fixture = Fixture::new();
+        let git = &fixture.git;
+        // "café" encoded in Latin-1, which is not valid UTF-8.
+        let latin1 = b"caf\xe9".to_vec();
+        let commit = |readme: &[u8], parents: &[Oid]| {
+            let mut src = git.treebuilder(None).unwrap();
+            src.insert(latin1.clone(), git.blob(b"").unwrap(), 0o100_644)
+                .unwrap();
+            let mut root = git.treebuilder(None).unwrap();
+            root.insert("README.md", git.blob(readme).unwrap(), 0o100_644)
+                .unwrap();
+            root.insert(latin1.clone(), src.write().unwrap(), 0o040_000)
+                .unwrap();
+            let tree = git.find_tree(root.write().unwrap()).unwrap();
+            let parents = parents
+                .iter()
+                .map(|parent| git.find_commit(*parent).unwrap())
+                .collect::<Vec<_>>();
+            git.commit(
+                Some("HEAD"),
+                &fixture.signature,
+                &fixture.signature,
+                "commit",
+                &tree,
+                &parents.iter().collect::<Vec<_>>(),
+            )
+            .unwrap()
+        };
+        let first = commit(b"# Surf", &[]);
+        let second = commit(b"# Surf!", &[first]);
+
+        let browser = Browser::new(fixture.repository()).unwrap();
+        let label =
+            file_system::Label::try_from_bytes(&latin1, &file_system::DefaultLabelRules).unwrap();
+        let last = |path: &file_system::Path| {
+            let mut last = browser
+                .last_commits_for(path)
+                .unwrap()
+                .into_iter()
+                .map(|(label, commit)| (label, commit.id))
+                .collect::<Vec<_>>();
+            last.sort();
+            last
+        };
+
+        assert_eq!(
+            last(&file_system::Path::root()),
+            vec![
+                (file_system::unsound::label::new("README.md"), second),
+                (label.clone(), first),
+            ]
+        );
+        assert_eq!(
+            last(&file_system::Path::with_root(std::slice::from_ref(&label))),
+            vec![(label, first)]
+        );
+    }
+
+    #[test]
+    fn test_get_directory_skips_invalid_labels() {
+        let fixture = Fixture::new();
+        fixture.commit(
+            Some("HEAD"),
+            &[
+                ("README.md", b"# Surf"),
+                ("src/notes.", b""),
+                ("src/lib.rs", b""),
+            ],
+            &[],
+        );
+        // The entry is only invalid under the rules of the repository once it is
+        // written, as happens when a repository is cloned onto NTFS.
+        fixture
+            .git
+            .config()
+            .unwrap()
+            .set_bool("core.protectNTFS", true)
+            .unwrap();
+
+        let browser = Browser::new(fixture.repository()).unwrap();
+        let directory = browser.get_directory().unwrap();
+        let lazy = browser.get_directory_lazy().unwrap();
+
+        for directory in &[directory, lazy] {
+            assert_eq!(
+                directory
+                    .files()
+                    .map(|(path, _)| path.to_string())
+                    .collect::<Vec<_>>(),
+                vec!["README.md", "src/lib.rs"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_lazy_directory_matches_eager() {
+        let fixture = Fixture::new();
+        fixture.commit(
+            Some("HEAD"),
+            &[
+                ("README.md", b"# Surf\n\nLet's surf!\n"),
+                ("logo.png", b"\x89PNG\r\n\x1a\n\0"),
+                ("src/lib.rs", b"pub mod vcs;\n"),
+            ],
+            &[],
+        );
+
+        let browser = Browser::new(fixture.repository()).unwrap();
+        let eager = browser.get_directory().unwrap();
+        let lazy = browser.get_directory_lazy().unwrap();
+        assert!(lazy.files().all(|(_, file)| !file.is_loaded()));
+
+        for ((_, lazy), (_, eager)) in lazy.files().zip(eager.files()) {
+            assert_eq!(lazy, eager);
+            assert_eq!(lazy.checksum(), eager.checksum());
+            assert_eq!(lazy.is_binary(), eager.is_binary());
+            assert_eq!(lazy.line_count(), eager.line_count());
+            assert_eq!(
+                lazy.metadata().get::<Sha1>(),
+                eager.metadata().get::<Sha1>()
+            );
+        }
+        let readme = eager
+            .find_file(&file_system::unsound::path::new("README.md"))
+            .unwrap();
+        let blob = fixture.git.blob(b"# Surf\n\nLet's surf!\n").unwrap();
+        assert_eq!(readme.metadata().get::<Sha1>(), Some(&Sha1::from(blob)));
+        assert_eq!(lazy, eager);
+        assert_eq!(
+            lazy.tree_hash_with::<PatchHash>(),
+            eager.tree_hash_with::<PatchHash>()
+        );
+        assert_eq!(lazy.loc(), Ok(4));
+        // The contents that were read are shared with the `Directory`.
+        assert!(lazy.files().all(|(_, file)| file.is_loaded()));
+    }
 
     #[test]
     // An issue with submodules, see: https://github.com/radicle-dev/radicle-surf/issues/54
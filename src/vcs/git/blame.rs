@@ -0,0 +1,197 @@
+//! Annotating each line of a file with the commit that last changed it, like
+//! `git blame`.
+
+use crate::file_system;
+use crate::vcs::git::error::Error;
+use crate::vcs::git::{Repository, Signature};
+use git2::Oid;
+
+/// A line of a file, annotated with the commit it originates from, i.e. the commit
+/// that last changed it.
+///
+/// See [`Browser::blame`](struct.Browser.html#method.blame).
+#[derive(Debug, Clone)]
+pub struct BlameLine {
+    /// The commit that last changed the line.
+    pub commit: Oid,
+    /// The author of `commit`, whose name and email are decoded lossily if they are
+    /// not valid UTF-8.
+    pub author: Signature,
+    /// The number of the line in the file as of `commit`, counting from `1`.
+    pub original_line: usize,
+}
+
+impl Repository {
+    /// Annotate each line of the file at `path`, as of the commit `tip`, with the
+    /// commit it originates from.
+    pub(crate) fn blame(
+        &self,
+        tip: Oid,
+        path: &file_system::Path,
+    ) -> Result<Vec<BlameLine>, Error> {
+        let mut options = git2::BlameOptions::new();
+        options.newest_commit(tip);
+        let blame = self
+            .repo
+            .blame_file(&relative_path(path), Some(&mut options))?;
+
+        let mut lines = vec![];
+        for hunk in blame.iter() {
+            // An author that is not valid UTF-8 is decoded lossily, rather than
+            // failing the blame of the whole file.
+            let author = Signature::lossy(&hunk.final_signature());
+            for offset in 0..hunk.lines_in_hunk() {
+                lines.push(BlameLine {
+                    commit: hunk.final_commit_id(),
+                    author: author.clone(),
+                    original_line: hunk.orig_start_line() + offset,
+                });
+            }
+        }
+
+        Ok(lines)
+    }
+}
+
+/// The path of the file at `path` relative to the root of the repository, built from
+/// the bytes of its labels, so that a name that is not valid UTF-8 is kept as it is.
+#[cfg(unix)]
+fn relative_path(path: &file_system::Path) -> std::path::PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut relative = vec![];
+    for label in path.iter().skip_while(|label| label.is_root()) {
+        if !relative.is_empty() {
+            relative.push(b'/');
+        }
+        relative.extend_from_slice(label.as_bytes());
+    }
+    std::ffi::OsStr::from_bytes(&relative).into()
+}
+
+#[cfg(not(unix))]
+fn relative_path(path: &file_system::Path) -> std::path::PathBuf {
+    path.iter()
+        .skip_while(|label| label.is_root())
+        .map(|label| label.to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::unsound;
+    use crate::vcs::git::fixture::Fixture;
+
+    #[test]
+    fn test_blame_lines() {
+        let fixture = Fixture::new();
+        let commit = |name: &str, readme: &[u8], parents: &[Oid]| {
+            let signature = git2::Signature::now(name, "surf@example.com").unwrap();
+            fixture.commit_by(&signature, Some("HEAD"), &[("README.md", readme)], parents)
+        };
+
+        let first = commit("alice", b"# Surf\nsurfing\nthe end\n", &[]);
+        let second = commit("bob", b"# Surf\nwaves\nmore waves\nthe end\n", &[first]);
+
+        let repo = fixture.repository();
+        let readme = unsound::path::new("~/README.md");
+        let summary = |lines: Vec<BlameLine>| {
+            lines
+                .into_iter()
+                .map(|line| (line.commit, line.author.name, line.original_line))
+                .collect::<Vec<_>>()
+        };
+        let at_second = summary(repo.blame(second, &readme).unwrap());
+        let at_first = summary(repo.blame(first, &readme).unwrap());
+        let missing = repo.blame(second, &unsound::path::new("~/NOPE.md"));
+
+        assert_eq!(
+            at_second,
+            vec![
+                (first, "alice".to_string(), 1),
+                (second, "bob".to_string(), 2),
+                (second, "bob".to_string(), 3),
+                (first, "alice".to_string(), 3),
+            ]
+        );
+        assert_eq!(
+            at_first,
+            vec![
+                (first, "alice".to_string(), 1),
+                (first, "alice".to_string(), 2),
+                (first, "alice".to_string(), 3),
+            ]
+        );
+        assert!(missing.is_err());
+
+        // An author in Latin-1 is decoded lossily, for their lines only.
+        let tree = fixture.tree(&[("README.md", b"# Surf\nwaves\nmore waves\nthe end!\n")]);
+        let mut latin1 = format!("tree {}\nparent {}\nauthor Jos", tree, second).into_bytes();
+        latin1.extend_from_slice(b"\xe9 <jose@surf> 1 +0000\n");
+        latin1.extend_from_slice(b"committer Jos\xe9 <jose@surf> 1 +0000\n\nThe end!\n");
+        let third = fixture
+            .git
+            .odb()
+            .unwrap()
+            .write(git2::ObjectType::Commit, &latin1)
+            .unwrap();
+
+        let at_third = summary(repo.blame(third, &readme).unwrap());
+        assert_eq!(
+            at_third,
+            vec![
+                (first, "alice".to_string(), 1),
+                (second, "bob".to_string(), 2),
+                (second, "bob".to_string(), 3),
+                (third, "Jos\u{fffd}".to_string(), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blame_latin1_path() {
+        let fixture = Fixture::new();
+        // The lossy conversion of the Latin-1 name "caf\xe9", which is blamed
+        // instead if the path is built from strings.
+        let first = fixture.commit(Some("HEAD"), &[("caf\u{fffd}", b"lossy\nlossy\n")], &[]);
+
+        let git = &fixture.git;
+        let mut builder = git
+            .treebuilder(Some(&git.find_commit(first).unwrap().tree().unwrap()))
+            .unwrap();
+        builder
+            .insert(
+                b"caf\xe9".to_vec(),
+                git.blob(b"latin1\n").unwrap(),
+                0o100_644,
+            )
+            .unwrap();
+        let tree = git.find_tree(builder.write().unwrap()).unwrap();
+        let second = git
+            .commit(
+                None,
+                &fixture.signature,
+                &fixture.signature,
+                "Add a Latin-1 file",
+                &tree,
+                &[&git.find_commit(first).unwrap()],
+            )
+            .unwrap();
+
+        let label = file_system::Label::try_from_bytes(b"caf\xe9", &file_system::DefaultLabelRules)
+            .unwrap();
+        let path = file_system::Path::with_root(&[label]);
+        let lines = fixture.repository().blame(second, &path).unwrap();
+
+        assert_eq!(
+            lines
+                .into_iter()
+                .map(|line| (line.commit, line.original_line))
+                .collect::<Vec<_>>(),
+            vec![(second, 1)]
+        );
+    }
+}
@@ -0,0 +1,31 @@
+//! Reading the contents of lazy `File`s from the git blobs they were made from.
+
+use crate::file_system::error::Error;
+use crate::file_system::Blob;
+use git2::Oid;
+use std::sync::{Arc, Mutex};
+
+/// A git blob, read from a repository that is shared by all the lazy `File`s of a
+/// `Directory`, see [`Browser::get_directory_lazy`](struct.Browser.html#method.get_directory_lazy).
+pub(crate) struct GitBlob {
+    pub(crate) repo: Arc<Mutex<git2::Repository>>,
+    pub(crate) oid: Oid,
+}
+
+impl Blob for GitBlob {
+    fn id(&self) -> String {
+        self.oid.to_string()
+    }
+
+    fn read(&self) -> Result<Vec<u8>, Error> {
+        // A panic while reading one blob does not affect reading the others.
+        let repo = self
+            .repo
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let blob = repo
+            .find_blob(self.oid)
+            .map_err(|err| Error::Blob(err.to_string()))?;
+        Ok(blob.content().to_owned())
+    }
+}
@@ -1,15 +1,332 @@
+use crate::file_system;
 use crate::file_system::error as file_error;
+use std::fmt;
 use std::str;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub enum Error {
     EmptyCommitHistory,
     NotBranch,
     NotTag,
+    /// The revision given to [`Browser::rev_parse`](../struct.Browser.html#method.rev_parse),
+    /// or the branch or tag given to [`Browser::branch`](../struct.Browser.html#method.branch)
+    /// or [`Browser::tag`](../struct.Browser.html#method.tag), does not name any object,
+    /// e.g. a misspelled branch or a commit that is not in the repository.
+    RevNotFound(String),
+    /// The revision given to [`Browser::rev_parse`](../struct.Browser.html#method.rev_parse)
+    /// names more than one object, e.g. an abbreviated commit id that is too short.
+    AmbiguousRev(String),
     Utf8Error(str::Utf8Error),
     FileSystem(file_error::Error),
     FileDiffException,
+    /// Computing the [`Diff`](../../../diff/struct.Diff.html) of two commits failed.
+    Diff(crate::diff::DiffError),
+    /// An error that is likely caused by the repository being maintained concurrently,
+    /// e.g. a reference being locked by a fetch, or a pack being removed by `git gc`.
+    /// These errors are transient, see [`RetryPolicy`](../struct.RetryPolicy.html).
+    ConcurrentMaintenance(git2::Error),
     Internal(git2::Error),
+    /// An error reading from disk, e.g. a working directory.
+    Io(std::io::Error),
+    /// An error of git or of reading from disk that happened in the given `Context`,
+    /// e.g. while rendering the snapshot of a particular revision.
+    ///
+    /// The errors of this crate, e.g. [`Error::NotBranch`](#variant.NotBranch), are
+    /// returned as they are, so that they can be matched on.
+    WithContext(Box<Context>, Box<Error>),
+}
+
+impl Error {
+    /// The `Context`s the error happened in, from the outermost operation
+    /// to the innermost.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::{Browser, BranchName, Repository};
+    /// use radicle_surf::vcs::git::error::Error;
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let mut browser = Browser::new(repo).unwrap();
+    ///
+    /// let err = browser.status(std::path::Path::new("./data/missing")).unwrap_err();
+    ///
+    /// assert!(matches!(err.root_cause(), Error::Io(_)));
+    /// assert_eq!(err.breadcrumbs()[0].operation, "status");
+    ///
+    /// // The errors of this crate are not given a `Context`.
+    /// let err = browser.branch("v0.1.0".parse::<BranchName>().unwrap()).unwrap_err();
+    ///
+    /// assert_eq!(err, Error::NotBranch);
+    /// assert!(err.breadcrumbs().is_empty());
+    /// ```
+    pub fn breadcrumbs(&self) -> Vec<&Context> {
+        let mut breadcrumbs = vec![];
+        let mut err = self;
+        while let Error::WithContext(context, source) = err {
+            breadcrumbs.push(context.as_ref());
+            err = source;
+        }
+        breadcrumbs
+    }
+
+    /// Check whether the underlying error is [`Error::ConcurrentMaintenance`](enum.Error.html#variant.ConcurrentMaintenance).
+    pub fn is_concurrent_maintenance(&self) -> bool {
+        matches!(self.root_cause(), Error::ConcurrentMaintenance(_))
+    }
+
+    /// The underlying error, without any `Context`.
+    pub fn root_cause(&self) -> &Self {
+        match self {
+            Error::WithContext(_, source) => source.root_cause(),
+            err => err,
+        }
+    }
+
+    /// Check whether the error means that what was looked up doesn't exist, e.g. a
+    /// branch that is not in the repository, or a `HEAD` that points to an unborn
+    /// branch, rather than that looking it up failed.
+    pub(crate) fn is_not_found(&self) -> bool {
+        match self.root_cause() {
+            Error::EmptyCommitHistory | Error::RevNotFound(_) => true,
+            Error::Internal(err) => matches!(
+                err.code(),
+                git2::ErrorCode::NotFound | git2::ErrorCode::UnbornBranch
+            ),
+            _ => false,
+        }
+    }
+
+    /// Check whether the error comes from git or from reading from disk, and so
+    /// can only be attributed by the `Context` it happened in.
+    fn is_opaque(&self) -> bool {
+        matches!(
+            self,
+            Error::Utf8Error(_)
+                | Error::ConcurrentMaintenance(_)
+                | Error::Internal(_)
+                | Error::Io(_)
+                | Error::WithContext(..)
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::EmptyCommitHistory => write!(f, "the commit history is empty"),
+            Error::NotBranch => write!(f, "the reference is not a branch"),
+            Error::NotTag => write!(f, "the reference is not a tag"),
+            Error::RevNotFound(rev) => write!(f, "the revision '{}' was not found", rev),
+            Error::AmbiguousRev(rev) => write!(f, "the revision '{}' is ambiguous", rev),
+            Error::Utf8Error(err) => write!(f, "{}", err),
+            Error::FileSystem(err) => write!(f, "{}", err),
+            Error::FileDiffException => write!(f, "a diff delta is missing its file path"),
+            Error::Diff(err) => write!(f, "{}", err),
+            Error::ConcurrentMaintenance(err) => {
+                write!(
+                    f,
+                    "the repository is being maintained concurrently: {}",
+                    err
+                )
+            }
+            Error::Internal(err) => write!(f, "{}", err),
+            Error::Io(err) => write!(f, "{}", err),
+            Error::WithContext(context, source) => write!(f, "{}: {}", context, source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Utf8Error(err) => Some(err),
+            Error::FileSystem(err) => Some(err),
+            Error::Diff(err) => Some(err),
+            Error::ConcurrentMaintenance(err) | Error::Internal(err) => Some(err),
+            Error::Io(err) => Some(err),
+            Error::WithContext(_, source) => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+// An `io::Error` can't be compared, so `Error::Io`s are compared by their kind and
+// message instead.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Error::EmptyCommitHistory, Error::EmptyCommitHistory)
+            | (Error::NotBranch, Error::NotBranch)
+            | (Error::NotTag, Error::NotTag)
+            | (Error::FileDiffException, Error::FileDiffException) => true,
+            (Error::RevNotFound(left), Error::RevNotFound(right))
+            | (Error::AmbiguousRev(left), Error::AmbiguousRev(right)) => left == right,
+            (Error::Utf8Error(left), Error::Utf8Error(right)) => left == right,
+            (Error::FileSystem(left), Error::FileSystem(right)) => left == right,
+            (Error::Diff(left), Error::Diff(right)) => left == right,
+            (Error::ConcurrentMaintenance(left), Error::ConcurrentMaintenance(right))
+            | (Error::Internal(left), Error::Internal(right)) => left == right,
+            (Error::Io(left), Error::Io(right)) => {
+                left.kind() == right.kind() && left.to_string() == right.to_string()
+            }
+            (Error::WithContext(left, left_source), Error::WithContext(right, right_source)) => {
+                left == right && left_source == right_source
+            }
+            _ => false,
+        }
+    }
+}
+
+/// An error found while parsing a [`Mailmap`](../struct.Mailmap.html), along with
+/// the number of the line it was found on, counting from `1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MailmapError {
+    /// The line has no email between a `<` and a `>`.
+    MissingEmail(usize),
+    /// The line has a `<` that is not closed by a `>`.
+    UnclosedEmail(usize),
+    /// The line has more than two emails.
+    TooManyEmails(usize),
+}
+
+impl fmt::Display for MailmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailmapError::MissingEmail(line) => write!(f, "line {} has no email", line),
+            MailmapError::UnclosedEmail(line) => {
+                write!(f, "line {} has an email that is not closed by '>'", line)
+            }
+            MailmapError::TooManyEmails(line) => {
+                write!(f, "line {} has more than two emails", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MailmapError {}
+
+/// An error found while parsing a [`BranchName`](../struct.BranchName.html),
+/// [`TagName`](../struct.TagName.html), [`RefName`](../struct.RefName.html), or
+/// [`Sha1`](../struct.Sha1.html), along with the name that was parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    /// The name is not a valid name for a branch.
+    InvalidBranchName(String),
+    /// The name is not a valid name for a tag.
+    InvalidTagName(String),
+    /// The name is not a valid full name of a reference.
+    InvalidRefName(String),
+    /// The name is not a commit id, or a prefix of one of at least 4 hex digits.
+    InvalidSha1(String),
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameError::InvalidBranchName(name) => {
+                write!(f, "'{}' is not a valid branch name", name)
+            }
+            NameError::InvalidTagName(name) => write!(f, "'{}' is not a valid tag name", name),
+            NameError::InvalidRefName(name) => {
+                write!(f, "'{}' is not a valid reference name", name)
+            }
+            NameError::InvalidSha1(name) => write!(f, "'{}' is not a valid commit id", name),
+        }
+    }
+}
+
+impl std::error::Error for NameError {}
+
+/// The context an [`Error`](enum.Error.html) happened in: the operation being
+/// performed, and the revision and path it was performed on, if any.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::file_system::unsound;
+/// use radicle_surf::vcs::git::error::Context;
+///
+/// let context = Context::new("last_commit")
+///     .rev("3873745c8f6ffb45c990eb23b491d4b4b6182f95")
+///     .path(unsound::path::new("src/memory.rs"));
+///
+/// assert_eq!(
+///     context.to_string(),
+///     "last_commit at 3873745c8f6ffb45c990eb23b491d4b4b6182f95 on src/memory.rs"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Context {
+    /// The operation being performed, e.g. `"snapshot"`.
+    pub operation: &'static str,
+    /// The revision the operation was performed on, e.g. a commit id or branch name.
+    pub rev: Option<String>,
+    /// The path the operation was performed on.
+    pub path: Option<file_system::Path>,
+}
+
+impl Context {
+    /// Create a `Context` for the given `operation`.
+    pub fn new(operation: &'static str) -> Self {
+        Context {
+            operation,
+            rev: None,
+            path: None,
+        }
+    }
+
+    /// Set the revision the operation was performed on.
+    pub fn rev<R: fmt::Display>(mut self, rev: R) -> Self {
+        self.rev = Some(rev.to_string());
+        self
+    }
+
+    /// Set the path the operation was performed on.
+    pub fn path(mut self, path: file_system::Path) -> Self {
+        self.path = Some(path);
+        self
+    }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.operation)?;
+        if let Some(rev) = &self.rev {
+            write!(f, " at {}", rev)?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, " on {}", path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Wrap the error of a `Result` in a [`Context`](struct.Context.html), unless it is
+/// one of the crate's own errors.
+pub(crate) trait WithContext<T> {
+    fn context<F>(self, context: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Context;
+}
+
+impl<T, E> WithContext<T> for Result<T, E>
+where
+    E: Into<Error>,
+{
+    fn context<F>(self, context: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Context,
+    {
+        self.map_err(|err| {
+            let err = err.into();
+            if err.is_opaque() {
+                Error::WithContext(Box::new(context()), Box::new(err))
+            } else {
+                err
+            }
+        })
+    }
 }
 
 impl From<str::Utf8Error> for Error {
@@ -24,46 +341,83 @@ impl From<file_error::Error> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
 impl From<git2::Error> for Error {
     fn from(err: git2::Error) -> Self {
-        Error::Internal(err)
+        if is_concurrent_maintenance(&err) {
+            Error::ConcurrentMaintenance(err)
+        } else {
+            Error::Internal(err)
+        }
     }
 }
 
-/// A private enum that captures a recoverable and
-/// non-recoverable error when walking the git tree.
-///
-/// In the case of `NotBlob` we abort the the computation but do
-/// a check for it and recover.
+/// Whether `err` is likely caused by the repository being maintained concurrently:
+/// a locked reference or index, or a pack file that could not be opened, e.g.
+/// because `git gc` removed it after repacking.
 ///
-/// In the of `Git` we abort both computations.
-#[derive(Debug)]
-pub(crate) enum TreeWalkError {
-    NotBlob,
-    Commit,
-    Git(Error),
+/// Other object database errors, and zlib errors, are left out, since they point
+/// to a corrupt or missing object that retrying will not fix.
+fn is_concurrent_maintenance(err: &git2::Error) -> bool {
+    is_transient(err.class(), err.code(), err.message())
 }
 
-impl From<git2::Error> for TreeWalkError {
-    fn from(err: git2::Error) -> Self {
-        TreeWalkError::Git(err.into())
+fn is_transient(class: git2::ErrorClass, code: git2::ErrorCode, message: &str) -> bool {
+    match (class, code) {
+        (_, git2::ErrorCode::Locked) => true,
+        (git2::ErrorClass::Odb, _) | (git2::ErrorClass::Os, _) => {
+            let message = message.to_lowercase();
+            message.contains("pack")
+                && (message.contains("failed to open")
+                    || message.contains("cannot be accessed")
+                    || message.contains("no such file"))
+        }
+        _ => false,
     }
 }
 
-impl From<file_error::Error> for TreeWalkError {
-    fn from(err: file_error::Error) -> Self {
-        err.into()
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{ErrorClass, ErrorCode};
 
-impl From<str::Utf8Error> for TreeWalkError {
-    fn from(err: str::Utf8Error) -> Self {
-        err.into()
-    }
-}
+    #[test]
+    fn test_transient_errors() {
+        assert!(is_transient(
+            ErrorClass::Reference,
+            ErrorCode::Locked,
+            "failed to lock file 'refs/heads/master.lock' for writing"
+        ));
+        assert!(is_transient(
+            ErrorClass::Os,
+            ErrorCode::NotFound,
+            "failed to open '.git/objects/pack/pack-3873745c.pack': No such file or directory"
+        ));
+        assert!(is_transient(
+            ErrorClass::Odb,
+            ErrorCode::GenericError,
+            "packfile '.git/objects/pack/pack-3873745c.pack' cannot be accessed"
+        ));
 
-impl From<Error> for TreeWalkError {
-    fn from(err: Error) -> Self {
-        err.into()
+        assert!(!is_transient(
+            ErrorClass::Odb,
+            ErrorCode::NotFound,
+            "object not found - no match for id (3873745c8f6ffb45c990eb23b491d4b4b6182f95)"
+        ));
+        assert!(!is_transient(
+            ErrorClass::Odb,
+            ErrorCode::GenericError,
+            "invalid pack file - bad header"
+        ));
+        assert!(!is_transient(
+            ErrorClass::Zlib,
+            ErrorCode::GenericError,
+            "failed to inflate data"
+        ));
     }
 }
@@ -0,0 +1,113 @@
+//! Throwaway repositories for tests, created in a temporary directory that is
+//! removed when the `Fixture` is dropped, even if the test panics.
+
+use crate::vcs::git::Repository;
+use git2::Oid;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use tempfile::TempDir;
+
+/// A repository in a temporary directory, with helpers to write trees and commits.
+pub(crate) struct Fixture {
+    pub git: git2::Repository,
+    pub signature: git2::Signature<'static>,
+    commits: Cell<usize>,
+    // Dropped last, after the repository is closed.
+    dir: TempDir,
+}
+
+impl Fixture {
+    /// Initialise an empty repository.
+    pub fn new() -> Self {
+        let dir = tempfile::Builder::new()
+            .prefix("radicle-surf-")
+            .tempdir()
+            .unwrap();
+        let git = git2::Repository::init(dir.path()).unwrap();
+        Fixture {
+            git,
+            signature: git2::Signature::now("surf", "surf@example.com").unwrap(),
+            commits: Cell::new(0),
+            dir,
+        }
+    }
+
+    /// The working directory of the repository.
+    pub fn path(&self) -> &std::path::Path {
+        self.dir.path()
+    }
+
+    /// Open the repository as a [`Repository`](../struct.Repository.html).
+    pub fn repository(&self) -> Repository {
+        Repository::from(git2::Repository::open(self.path()).unwrap())
+    }
+
+    /// Write a tree holding `files`, where the names may contain `/`s to put files
+    /// in sub-trees, e.g. `src/lib.rs`.
+    pub fn tree(&self, files: &[(&str, &[u8])]) -> Oid {
+        let mut builder = self.git.treebuilder(None).unwrap();
+        let mut directories = BTreeMap::<&str, Vec<(&str, &[u8])>>::new();
+        for (name, contents) in files {
+            match name.find('/') {
+                Some(slash) => directories
+                    .entry(&name[..slash])
+                    .or_default()
+                    .push((&name[slash + 1..], contents)),
+                None => {
+                    let blob = self.git.blob(contents).unwrap();
+                    builder.insert(name, blob, 0o100_644).unwrap();
+                }
+            }
+        }
+        for (name, files) in directories {
+            builder.insert(name, self.tree(&files), 0o040_000).unwrap();
+        }
+        builder.write().unwrap()
+    }
+
+    /// Commit `files` on top of `parents`, updating `refname` if it is given.
+    pub fn commit(&self, refname: Option<&str>, files: &[(&str, &[u8])], parents: &[Oid]) -> Oid {
+        self.commit_by(&self.signature, refname, files, parents)
+    }
+
+    /// Commit `files` on top of `parents` as `signature`, updating `refname` if it
+    /// is given.
+    pub fn commit_by(
+        &self,
+        signature: &git2::Signature,
+        refname: Option<&str>,
+        files: &[(&str, &[u8])],
+        parents: &[Oid],
+    ) -> Oid {
+        self.commit_tree(signature, refname, self.tree(files), parents)
+    }
+
+    /// Commit `tree` on top of `parents` as `signature`, updating `refname` if it
+    /// is given, e.g. for a tree with names that are not valid UTF-8.
+    pub fn commit_tree(
+        &self,
+        signature: &git2::Signature,
+        refname: Option<&str>,
+        tree: Oid,
+        parents: &[Oid],
+    ) -> Oid {
+        let tree = self.git.find_tree(tree).unwrap();
+        let parents = parents
+            .iter()
+            .map(|parent| self.git.find_commit(*parent).unwrap())
+            .collect::<Vec<_>>();
+        // Every commit gets its own message, so that no two commits share an id.
+        let count = self.commits.get();
+        self.commits.set(count + 1);
+        self.git
+            .commit(
+                refname,
+                signature,
+                signature,
+                &format!("commit {}", count),
+                &tree,
+                &parents.iter().collect::<Vec<_>>(),
+            )
+            .unwrap()
+    }
+}
@@ -0,0 +1,152 @@
+//! Caching the history of individual files, keyed by their `Path` and the commit the
+//! history starts from, so that repeated queries for popular files, e.g. a `README.md`,
+//! don't walk the whole history every time.
+
+use crate::cache::{Budget, Cache, Cacheable, Metrics};
+use crate::file_system::Path;
+use crate::vcs::git::error::Error;
+use crate::vcs::git::{Commit, Repository};
+use git2::Oid;
+use std::collections::HashMap;
+
+/// The commits that touched a file, most recent first.
+#[derive(Debug, Clone)]
+struct FileHistory(Vec<Commit>);
+
+/// A `FileHistory` weighs the size of its commits' ids, messages and signatures, and
+/// is not spilled.
+impl Cacheable for FileHistory {
+    fn weight(&self) -> usize {
+        self.0
+            .iter()
+            .map(|commit| {
+                commit.id.as_bytes().len()
+                    + commit.message.len()
+                    + commit.summary.len()
+                    + commit.author.name.len()
+                    + commit.author.email.len()
+                    + commit.committer.name.len()
+                    + commit.committer.email.len()
+                    + commit.tree.as_bytes().len()
+                    + commit.parents.len() * commit.id.as_bytes().len()
+            })
+            .sum()
+    }
+}
+
+/// A cache of the commits that touched a file, keyed by the file's `Path` and the
+/// tip the history was walked from.
+///
+/// When the history of a `Path` is asked for at a new tip that descends from a tip
+/// that is already cached, only the commits between the two tips are walked, and
+/// the cached history is extended with them.
+///
+/// See [`Browser::file_history_cached`](struct.Browser.html#method.file_history_cached).
+pub struct FileHistoryCache {
+    cache: Cache<(Path, Oid), FileHistory>,
+    /// The tip that the history of each `Path` was last walked from.
+    tips: HashMap<Path, Oid>,
+    extensions: u64,
+}
+
+impl FileHistoryCache {
+    /// Create an empty `FileHistoryCache` with the given `Budget`.
+    pub fn new(budget: Budget) -> Self {
+        FileHistoryCache {
+            cache: Cache::new(budget),
+            tips: HashMap::new(),
+            extensions: 0,
+        }
+    }
+
+    /// Get the `Metrics` of the underlying [`Cache`](../../cache/struct.Cache.html).
+    pub fn metrics(&self) -> Metrics {
+        self.cache.metrics()
+    }
+
+    /// The number of times a cached history was extended to a new tip, rather
+    /// than walked from scratch.
+    pub fn extensions(&self) -> u64 {
+        self.extensions
+    }
+
+    /// Get the history of `path` from `tip`, walking as little of the history of
+    /// `repo` as the cache allows.
+    pub(crate) fn get_or_walk(
+        &mut self,
+        repo: &Repository,
+        path: &Path,
+        tip: Oid,
+    ) -> Result<Vec<Commit>, Error> {
+        let key = (path.clone(), tip);
+        if let Some(FileHistory(commits)) = self.cache.get(&key) {
+            return Ok(commits);
+        }
+
+        let previous = self
+            .tips
+            .get(path)
+            .copied()
+            .filter(|previous| {
+                repo.repo
+                    .graph_descendant_of(tip, *previous)
+                    .unwrap_or(false)
+            })
+            .and_then(|previous| {
+                self.cache
+                    .get(&(path.clone(), previous))
+                    .map(|history| (previous, history))
+            });
+
+        let commits = match previous {
+            Some((previous, FileHistory(older))) => {
+                let mut commits = repo.path_history(tip, Some(previous), path)?;
+                commits.extend(older);
+                self.extensions += 1;
+                commits
+            }
+            None => repo.path_history(tip, None, path)?,
+        };
+
+        self.cache.insert(key, FileHistory(commits.clone()));
+        self.tips.insert(path.clone(), tip);
+        Ok(commits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::unsound;
+    use crate::vcs::git::fixture::Fixture;
+
+    #[test]
+    fn test_extend_to_new_tip() {
+        let fixture = Fixture::new();
+        let commit =
+            |files: &[(&str, &[u8])], parents: &[Oid]| fixture.commit(Some("HEAD"), files, parents);
+
+        let first = commit(&[("README.md", b"# Surf"), ("lib.rs", b"")], &[]);
+        let second = commit(&[("README.md", b"# Surf"), ("lib.rs", b"mod a;")], &[first]);
+        let third = commit(
+            &[("README.md", b"# Surf!"), ("lib.rs", b"mod a;")],
+            &[second],
+        );
+
+        let repo = fixture.repository();
+        let readme = unsound::path::new("~/README.md");
+        let ids = |commits: Vec<Commit>| commits.into_iter().map(|c| c.id).collect::<Vec<_>>();
+
+        let mut cache = FileHistoryCache::new(Budget::new(1024 * 1024));
+        let at_second = cache.get_or_walk(&repo, &readme, second).unwrap();
+        let at_third = cache.get_or_walk(&repo, &readme, third).unwrap();
+        let again = cache.get_or_walk(&repo, &readme, third).unwrap();
+        let walked = repo.path_history(third, None, &readme).unwrap();
+
+        assert_eq!(ids(at_second), vec![first]);
+        assert_eq!(ids(at_third), vec![third, first]);
+        assert_eq!(ids(again), ids(walked));
+        assert_eq!(cache.extensions(), 1);
+        assert_eq!(cache.metrics().hits, 2);
+    }
+}
@@ -0,0 +1,417 @@
+//! Walking the history of a repository on demand, one commit or one page at a time,
+//! instead of collecting it into a `History` up front, which is too slow for
+//! repositories with many commits.
+
+use crate::file_system::Path;
+use crate::vcs::git::error::Error;
+use crate::vcs::git::slow_op::SlowOp;
+use crate::vcs::git::{Commit, Repository};
+use git2::Oid;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Walks the commits reachable from some pending commits, most recent commit time
+/// first, and in the order they were found for the same time, as git does.
+///
+/// The walk can be stopped and picked up again from its
+/// [`pending`](#method.pending) commits, without walking the commits before them.
+pub(crate) struct Walk<'repo> {
+    repo: &'repo git2::Repository,
+    /// The commits found but not walked yet, by their commit time and the order
+    /// they were found in.
+    queue: BinaryHeap<(i64, Reverse<usize>, Oid)>,
+    seen: HashSet<Oid>,
+}
+
+impl<'repo> Walk<'repo> {
+    pub(crate) fn new(repo: &'repo git2::Repository, pending: &[Oid]) -> Result<Self, Error> {
+        let mut walk = Walk {
+            repo,
+            queue: BinaryHeap::new(),
+            seen: HashSet::new(),
+        };
+        for id in pending {
+            walk.push(*id)?;
+        }
+        Ok(walk)
+    }
+
+    /// Add the commit `id` to the walk, unless it was found already.
+    pub(crate) fn push(&mut self, id: Oid) -> Result<(), Error> {
+        if self.seen.insert(id) {
+            let time = self.repo.find_commit(id)?.time().seconds();
+            self.queue.push((time, Reverse(self.seen.len()), id));
+        }
+        Ok(())
+    }
+
+    /// Take the next commit of the walk, without adding its parents.
+    pub(crate) fn pop(&mut self) -> Result<Option<git2::Commit<'repo>>, Error> {
+        match self.queue.pop() {
+            Some((_, _, id)) => Ok(Some(self.repo.find_commit(id)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The commits found but not walked yet, in the order they are walked in.
+    fn pending(&self) -> Vec<Oid> {
+        let mut pending = self.queue.clone().into_sorted_vec();
+        pending.reverse();
+        pending.into_iter().map(|(_, _, id)| id).collect()
+    }
+}
+
+/// The commits reachable from a tip, most recent commit time first, in the same
+/// order as a [`HistoryPage`](struct.HistoryPage.html), read as they are iterated
+/// over.
+///
+/// See [`Browser::commits`](struct.Browser.html#method.commits).
+pub struct Commits<'repo> {
+    walk: Walk<'repo>,
+}
+
+impl<'repo> Commits<'repo> {
+    pub(crate) fn new(repo: &'repo git2::Repository, tip: Oid) -> Result<Self, Error> {
+        Ok(Commits {
+            walk: Walk::new(repo, &[tip])?,
+        })
+    }
+}
+
+impl<'repo> fmt::Debug for Commits<'repo> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Commits")
+            .field("pending", &self.walk.pending())
+            .finish()
+    }
+}
+
+impl<'repo> Iterator for Commits<'repo> {
+    type Item = Result<Commit, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let commit = match self.walk.pop() {
+            Ok(commit) => commit?,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(
+            commit
+                .parent_ids()
+                .try_for_each(|parent| self.walk.push(parent))
+                .and_then(|()| Commit::try_from(commit)),
+        )
+    }
+}
+
+/// Where a [`HistoryPage`](struct.HistoryPage.html) starts: the tip the history is
+/// walked from, and the commits the walk goes on from.
+///
+/// The tip is the commit the revision resolved to for the first page, so that the
+/// following pages are not shifted by commits pushed in the meantime. Each page
+/// picks up the walk where the one before it stopped, so getting a page does not
+/// depend on how many pages come before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryCursor {
+    /// The commit the history is walked from.
+    pub tip: Oid,
+    /// The commits found, but not walked yet, when the page before stopped, in the
+    /// order they are walked in. The first of them is the first commit of the page,
+    /// unless it is left out of a file's history.
+    pub pending: Vec<Oid>,
+}
+
+impl HistoryCursor {
+    /// The `HistoryCursor` of the first page of the history from `tip`.
+    pub fn new(tip: Oid) -> Self {
+        HistoryCursor {
+            tip,
+            pending: vec![tip],
+        }
+    }
+}
+
+/// A page of the commits reachable from a tip, or of the ones that touched a file,
+/// along with the [`HistoryCursor`](struct.HistoryCursor.html) of the next page, if
+/// there is one.
+///
+/// See [`Browser::history_page`](struct.Browser.html#method.history_page) and
+/// [`Browser::file_history`](struct.Browser.html#method.file_history).
+#[derive(Debug, Clone)]
+pub struct HistoryPage {
+    /// The commits of the page, most recent commit time first.
+    pub commits: Vec<Commit>,
+    /// The `HistoryCursor` to get the next page with, or `None` if this is the last
+    /// page, or the page was asked for with a `limit` of `0`.
+    pub next: Option<HistoryCursor>,
+}
+
+impl Repository {
+    /// Read the `limit` commits that come at `cursor`, keeping only the ones that
+    /// touched `path`, or anything below it, if it is given.
+    ///
+    /// The walk goes on until the first commit of the next page is found, so that
+    /// there is no `next` cursor on the last page. A `limit` of `0` gives an empty
+    /// page without a `next` cursor, so that paging through it ends.
+    pub(crate) fn history_page(
+        &self,
+        cursor: &HistoryCursor,
+        limit: usize,
+        path: Option<&Path>,
+    ) -> Result<HistoryPage, Error> {
+        if limit == 0 {
+            return Ok(HistoryPage {
+                commits: vec![],
+                next: None,
+            });
+        }
+
+        self.retry_policy.retry(|| {
+            let mut slow_op = SlowOp::start("history_page", self, &cursor.tip);
+            let mut walk = Walk::new(&self.repo, &cursor.pending)?;
+
+            let mut commits = vec![];
+            let mut next = None;
+            while let Some(commit) = walk.pop()? {
+                slow_op.read_object();
+                let (touched, parents) = match path {
+                    Some(path) => self.touches(&commit, path)?,
+                    None => (true, commit.parent_ids().collect()),
+                };
+                if touched && commits.len() == limit {
+                    // The commit is put back in front, to start the next page with.
+                    let mut pending = vec![commit.id()];
+                    pending.extend(walk.pending());
+                    next = Some(HistoryCursor {
+                        tip: cursor.tip,
+                        pending,
+                    });
+                    break;
+                }
+
+                for parent in parents {
+                    walk.push(parent)?;
+                }
+                if touched {
+                    commits.push(Commit::try_from(commit)?);
+                }
+            }
+
+            Ok(HistoryPage { commits, next })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::unsound;
+    use crate::vcs::git::fixture::Fixture;
+
+    #[test]
+    fn test_pages_cover_history() {
+        let fixture = Fixture::new();
+        let mut tip = fixture.commit(Some("HEAD"), &[], &[]);
+        for _ in 1..7 {
+            tip = fixture.commit(Some("HEAD"), &[], &[tip]);
+        }
+
+        let repo = fixture.repository();
+        let history = repo.to_history(tip).unwrap();
+        let commits = Commits::new(&repo.repo, tip)
+            .unwrap()
+            .map(|commit| commit.map(|commit| commit.id))
+            .collect::<Result<Vec<_>, Error>>()
+            .unwrap();
+        let mut pages = vec![];
+        let mut cursor = Some(HistoryCursor::new(tip));
+        while let Some(at) = cursor {
+            let page = repo.history_page(&at, 3, None).unwrap();
+            cursor = page.next;
+            pages.push(page.commits.iter().map(|c| c.id).collect::<Vec<_>>());
+        }
+        let exact = repo
+            .history_page(&HistoryCursor::new(tip), 7, None)
+            .unwrap();
+
+        let ids = history.iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(commits, ids);
+        assert_eq!(
+            pages.iter().map(|page| page.len()).collect::<Vec<_>>(),
+            vec![3, 3, 1]
+        );
+        assert_eq!(pages.concat(), ids);
+        assert_eq!(exact.commits.len(), 7);
+        assert_eq!(exact.next, None);
+    }
+
+    #[test]
+    fn test_pages_resume_from_pending() {
+        let fixture = Fixture::new();
+        let at = |seconds| {
+            git2::Signature::new("surf", "surf@example.com", &git2::Time::new(seconds, 0)).unwrap()
+        };
+        let commit = |seconds, parents: &[Oid]| fixture.commit_by(&at(seconds), None, &[], parents);
+        let base = commit(1, &[]);
+        let left = commit(2, &[base]);
+        let right = commit(4, &[base]);
+        let left_again = commit(3, &[left]);
+        let merge = commit(5, &[left_again, right]);
+        let tip = commit(6, &[merge]);
+
+        let repo = fixture.repository();
+        let first = repo
+            .history_page(&HistoryCursor::new(tip), 2, None)
+            .unwrap();
+        let cursor = first.next.clone().unwrap();
+        assert_eq!(cursor.tip, tip);
+        assert_eq!(cursor.pending, vec![right, left_again]);
+
+        let second = repo.history_page(&cursor, 3, None).unwrap();
+        let cursor = second.next.clone().unwrap();
+        assert_eq!(cursor.pending, vec![base]);
+        let third = repo.history_page(&cursor, 3, None).unwrap();
+        assert_eq!(third.next, None);
+
+        let ids = vec![tip, merge, right, left_again, left, base];
+        let pages = vec![first, second, third]
+            .into_iter()
+            .flat_map(|page| page.commits)
+            .map(|commit| commit.id)
+            .collect::<Vec<_>>();
+        assert_eq!(pages, ids);
+        let commits = Commits::new(&repo.repo, tip)
+            .unwrap()
+            .map(|commit| commit.map(|commit| commit.id))
+            .collect::<Result<Vec<_>, Error>>()
+            .unwrap();
+        assert_eq!(commits, ids);
+        let history = repo.to_history(tip).unwrap();
+        assert_eq!(history.iter().map(|c| c.id).collect::<Vec<_>>(), ids);
+
+        let empty = repo
+            .history_page(&HistoryCursor::new(tip), 0, None)
+            .unwrap();
+        assert!(empty.commits.is_empty());
+        assert_eq!(empty.next, None);
+    }
+
+    #[test]
+    fn test_pages_latin1_commit() {
+        let fixture = Fixture::new();
+        let base = fixture.commit(None, &[("README.md", b"# Surf")], &[]);
+        let tree = fixture.tree(&[("README.md", b"# Caf\xe9")]);
+        let mut latin1 = format!("tree {}\nparent {}\nauthor Jos", tree, base).into_bytes();
+        latin1.extend_from_slice(b"\xe9 <jose@surf> 1 +0000\n");
+        latin1.extend_from_slice(b"committer Jos\xe9 <jose@surf> 1 +0000\n\nCaf\xe9\n");
+        let middle = fixture
+            .git
+            .odb()
+            .unwrap()
+            .write(git2::ObjectType::Commit, &latin1)
+            .unwrap();
+        let tip = fixture.commit(None, &[("README.md", b"# Surf!")], &[middle]);
+
+        let repo = fixture.repository();
+        let first = repo
+            .history_page(&HistoryCursor::new(tip), 2, None)
+            .unwrap();
+        let second = repo
+            .history_page(first.next.as_ref().unwrap(), 2, None)
+            .unwrap();
+        assert_eq!(second.next, None);
+
+        let latin1 = &first.commits[1];
+        assert_eq!(latin1.id, middle);
+        assert_eq!(latin1.author.name, "Jos\u{fffd}");
+        assert_eq!(latin1.committer.name, "Jos\u{fffd}");
+        assert_eq!(latin1.message, "Caf\u{fffd}\n");
+        assert_eq!(latin1.summary, "Caf\u{fffd}");
+        assert_eq!(
+            first
+                .commits
+                .iter()
+                .chain(&second.commits)
+                .map(|commit| commit.id)
+                .collect::<Vec<_>>(),
+            vec![tip, middle, base]
+        );
+    }
+
+    #[test]
+    fn test_file_history_pages() {
+        let fixture = Fixture::new();
+        let commit = |readme: &[u8], lib: &[u8], parents: &[Oid]| {
+            fixture.commit(None, &[("README.md", readme), ("src/lib.rs", lib)], parents)
+        };
+
+        let first = commit(b"# Surf", b"", &[]);
+        let second = commit(b"# Surf", b"mod a;", &[first]);
+        let third = commit(b"# Surf!", b"mod a;", &[second]);
+        let fourth = commit(b"# Surf!", b"mod a; mod b;", &[third]);
+        let fifth = commit(b"# Surf!!", b"mod a; mod b;", &[fourth]);
+        // A side branch that changes both files, merged taking the README from
+        // `fifth`, and then merged again changing the README on both sides.
+        let side = commit(b"# Side", b"mod a; mod b; mod c;", &[fourth]);
+        let merge = commit(b"# Surf!!", b"mod a; mod b; mod c;", &[fifth, side]);
+        let evil = commit(b"# Merged", b"mod a; mod b;", &[fifth, side]);
+
+        let repo = fixture.repository();
+        let pages_from = |tip: Oid, path: &Path, limit: usize| {
+            let mut pages = vec![];
+            let mut cursor = Some(HistoryCursor::new(tip));
+            while let Some(at) = cursor {
+                let page = repo.history_page(&at, limit, Some(path)).unwrap();
+                cursor = page.next;
+                pages.push(page.commits.iter().map(|c| c.id).collect::<Vec<_>>());
+            }
+            pages
+        };
+        let pages = |path: &Path, limit: usize| pages_from(fifth, path, limit);
+        let readme = pages(&unsound::path::new("~/README.md"), 2);
+        let src = pages(&unsound::path::new("~/src"), 2);
+        let walked = repo
+            .path_history(fifth, None, &unsound::path::new("~/src"))
+            .unwrap();
+
+        assert_eq!(readme, vec![vec![fifth, third], vec![first]]);
+        assert_eq!(src, vec![vec![fourth, second], vec![first]]);
+        assert_eq!(
+            src.concat(),
+            walked.iter().map(|c| c.id).collect::<Vec<_>>()
+        );
+
+        // The README of `merge` is the one of `fifth`, so only `fifth` is walked, and
+        // the README of `side` is left out. Its `src` is the one of `side`, so only
+        // `side` is walked for it.
+        let readme = unsound::path::new("~/README.md");
+        let src = unsound::path::new("~/src");
+        assert_eq!(
+            pages_from(merge, &readme, 2),
+            vec![vec![fifth, third], vec![first]]
+        );
+        assert_eq!(
+            pages_from(merge, &src, 2),
+            vec![vec![side, fourth], vec![second, first]]
+        );
+        // The README of `evil` differs from both parents, so both sides are walked.
+        assert_eq!(
+            pages_from(evil, &readme, 3),
+            vec![vec![evil, fifth, side], vec![third, first]]
+        );
+        let walked = repo.path_history(evil, None, &readme).unwrap();
+        assert_eq!(
+            walked.iter().map(|c| c.id).collect::<Vec<_>>(),
+            vec![evil, fifth, side, third, first]
+        );
+        let walked = repo.path_history(merge, Some(fifth), &src).unwrap();
+        assert_eq!(walked.iter().map(|c| c.id).collect::<Vec<_>>(), vec![side]);
+
+        let empty = repo
+            .history_page(&HistoryCursor::new(fifth), 0, Some(&readme))
+            .unwrap();
+        assert!(empty.commits.is_empty());
+        assert_eq!(empty.next, None);
+    }
+}
@@ -0,0 +1,348 @@
+//! Mapping the names and emails that commits were authored with to the ones their
+//! authors prefer, as described by a `.mailmap` file.
+//!
+//! See [gitmailmap](https://git-scm.com/docs/gitmailmap) for the file format.
+
+use crate::vcs::git::error::MailmapError;
+use crate::vcs::git::Signature;
+use std::fmt;
+use std::str::FromStr;
+
+/// A single entry of a [`Mailmap`](struct.Mailmap.html), mapping the name and email
+/// a commit was made with to the proper name and email.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailmapEntry {
+    /// The name to use instead of the commit's, if any.
+    pub proper_name: Option<String>,
+    /// The email to use instead of the commit's, if any.
+    pub proper_email: Option<String>,
+    /// The name the commit must have been made with for the entry to apply, if any.
+    pub commit_name: Option<String>,
+    /// The email the commit must have been made with for the entry to apply.
+    pub commit_email: String,
+}
+
+impl MailmapEntry {
+    /// Parse an entry, along with the text that follows its last email, e.g. a
+    /// trailing comment, which git ignores.
+    fn parse(line: &str, number: usize) -> Result<(Self, String), MailmapError> {
+        let mut rest = line;
+        let mut names_and_emails = vec![];
+        while let Some(open) = rest.find('<') {
+            if rest[..open].trim_start().starts_with('#') {
+                break;
+            }
+            let close = rest[open..]
+                .find('>')
+                .ok_or(MailmapError::UnclosedEmail(number))?;
+            let name = rest[..open].trim();
+            let name = if name.is_empty() {
+                None
+            } else {
+                Some(name.to_string())
+            };
+            names_and_emails.push((name, rest[open + 1..open + close].to_string()));
+            rest = &rest[open + close + 1..];
+        }
+
+        let trailing = rest.trim().to_string();
+        let mut names_and_emails = names_and_emails.into_iter();
+        let entry = match (
+            names_and_emails.next(),
+            names_and_emails.next(),
+            names_and_emails.next(),
+        ) {
+            (None, _, _) => Err(MailmapError::MissingEmail(number)),
+            (Some((proper_name, commit_email)), None, None) => Ok(MailmapEntry {
+                proper_name,
+                proper_email: None,
+                commit_name: None,
+                commit_email,
+            }),
+            (Some((proper_name, proper_email)), Some((commit_name, commit_email)), None) => {
+                Ok(MailmapEntry {
+                    proper_name,
+                    proper_email: Some(proper_email),
+                    commit_name,
+                    commit_email,
+                })
+            }
+            (Some(_), Some(_), Some(_)) => Err(MailmapError::TooManyEmails(number)),
+            (Some(_), None, Some(_)) => unreachable!("names_and_emails is a Vec iterator"),
+        }?;
+        Ok((entry, trailing))
+    }
+
+    fn matches(&self, name: &str, email: &str) -> bool {
+        self.commit_email.eq_ignore_ascii_case(email)
+            && self
+                .commit_name
+                .as_ref()
+                .is_none_or(|commit_name| commit_name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl fmt::Display for MailmapEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = &self.proper_name {
+            write!(f, "{} ", name)?;
+        }
+        if let Some(email) = &self.proper_email {
+            write!(f, "<{}> ", email)?;
+        }
+        if let Some(name) = &self.commit_name {
+            write!(f, "{} ", name)?;
+        }
+        write!(f, "<{}>", self.commit_email)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+    Blank(String),
+    Comment(String),
+    /// An entry, along with the text that follows it, if any.
+    Entry(MailmapEntry, String),
+}
+
+/// The entries of a `.mailmap` file, or of a similar file provided by a host,
+/// e.g. git's `mailmap.file`.
+///
+/// Blank lines and comments are kept, so that parsing a file and writing it
+/// back out gives the same file, up to the whitespace within entries and a
+/// trailing newline.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::vcs::git::Mailmap;
+///
+/// let file = "\
+/// ## Old addresses
+/// Fintan Halpenny <fintan@monadic.xyz>
+/// Fintan Halpenny <fintan@monadic.xyz> <fintan@old.example>
+/// ";
+/// let mailmap = file.parse::<Mailmap>().unwrap();
+///
+/// assert_eq!(mailmap.entries().count(), 2);
+/// assert_eq!(mailmap.to_string(), file);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mailmap {
+    lines: Vec<Line>,
+}
+
+impl Mailmap {
+    /// Parse the contents of a mailmap file.
+    pub fn parse(contents: &str) -> Result<Self, MailmapError> {
+        let lines = contents
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                if line.trim().is_empty() {
+                    Ok(Line::Blank(line.to_string()))
+                } else if line.trim_start().starts_with('#') {
+                    Ok(Line::Comment(line.to_string()))
+                } else {
+                    MailmapEntry::parse(line, index + 1)
+                        .map(|(entry, trailing)| Line::Entry(entry, trailing))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Mailmap { lines })
+    }
+
+    /// Check whether there are no entries, though there may still be blank
+    /// lines and comments.
+    pub fn is_empty(&self) -> bool {
+        self.entries().next().is_none()
+    }
+
+    /// The entries in the order they appear in the file.
+    pub fn entries(&self) -> impl Iterator<Item = &MailmapEntry> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry(entry, _) => Some(entry),
+            Line::Blank(_) | Line::Comment(_) => None,
+        })
+    }
+
+    /// Merge the entries of `overrides` after the entries of `self`, so that
+    /// they take precedence when both apply to the same commit.
+    ///
+    /// git gives the host's `mailmap.file` precedence over a repository's
+    /// `.mailmap`, so the host's `Mailmap` should be merged into the repository's.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::Mailmap;
+    ///
+    /// let repo = "Alice <alice@old.example>".parse::<Mailmap>().unwrap();
+    /// let host = "Alice Liddell <alice@old.example>".parse::<Mailmap>().unwrap();
+    ///
+    /// let merged = repo.merge(&host);
+    ///
+    /// assert_eq!(
+    ///     merged.resolve("alice", "alice@old.example"),
+    ///     ("Alice Liddell".to_string(), "alice@old.example".to_string())
+    /// );
+    /// ```
+    pub fn merge(&self, overrides: &Self) -> Self {
+        let mut lines = self.lines.clone();
+        lines.extend(overrides.lines.iter().cloned());
+        Mailmap { lines }
+    }
+
+    /// Get the proper name and email for a commit made with `name` and `email`.
+    ///
+    /// Emails, and names, are compared ignoring their case. Entries that mention
+    /// the commit's name take precedence over those that only mention its email,
+    /// and among those, as in git, a later entry overrides the proper name, or
+    /// email, given by an earlier one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::Mailmap;
+    ///
+    /// let mailmap = Mailmap::parse("\
+    /// Alice <alice@example.com>
+    /// <bob@example.com> Bob <BOB@old.example>
+    /// ").unwrap();
+    ///
+    /// assert_eq!(
+    ///     mailmap.resolve("alice", "alice@example.com"),
+    ///     ("Alice".to_string(), "alice@example.com".to_string())
+    /// );
+    /// assert_eq!(
+    ///     mailmap.resolve("Bob", "bob@old.example"),
+    ///     ("Bob".to_string(), "bob@example.com".to_string())
+    /// );
+    /// assert_eq!(
+    ///     mailmap.resolve("Robert", "bob@old.example"),
+    ///     ("Robert".to_string(), "bob@old.example".to_string())
+    /// );
+    /// ```
+    pub fn resolve(&self, name: &str, email: &str) -> (String, String) {
+        let entries = self
+            .entries()
+            .filter(|entry| entry.matches(name, email))
+            .collect::<Vec<_>>();
+        let named = entries.iter().any(|entry| entry.commit_name.is_some());
+
+        let mut proper = (name, email);
+        for entry in entries {
+            if entry.commit_name.is_some() == named {
+                if let Some(name) = &entry.proper_name {
+                    proper.0 = name;
+                }
+                if let Some(email) = &entry.proper_email {
+                    proper.1 = email;
+                }
+            }
+        }
+        (proper.0.to_string(), proper.1.to_string())
+    }
+
+    /// Get the `Signature` with the proper name and email for `signature`,
+    /// see [`resolve`](#method.resolve).
+    pub fn resolve_signature(&self, signature: &Signature) -> Signature {
+        let (name, email) = self.resolve(&signature.name, &signature.email);
+        Signature {
+            name,
+            email,
+            time: signature.time,
+        }
+    }
+}
+
+impl FromStr for Mailmap {
+    type Err = MailmapError;
+
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        Self::parse(contents)
+    }
+}
+
+impl fmt::Display for Mailmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            match line {
+                Line::Blank(line) | Line::Comment(line) => writeln!(f, "{}", line)?,
+                Line::Entry(entry, trailing) if trailing.is_empty() => writeln!(f, "{}", entry)?,
+                Line::Entry(entry, trailing) => writeln!(f, "{} {}", entry, trailing)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entries() {
+        let mailmap = Mailmap::parse(
+            "\
+Proper Name <proper@example.com> Commit Name <commit@example.com>
+<proper@example.com> <commit@example.com> # moved
+Proper Name <commit@example.com> Commit Name
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            mailmap.entries().cloned().collect::<Vec<_>>(),
+            vec![
+                MailmapEntry {
+                    proper_name: Some("Proper Name".to_string()),
+                    proper_email: Some("proper@example.com".to_string()),
+                    commit_name: Some("Commit Name".to_string()),
+                    commit_email: "commit@example.com".to_string(),
+                },
+                MailmapEntry {
+                    proper_name: None,
+                    proper_email: Some("proper@example.com".to_string()),
+                    commit_name: None,
+                    commit_email: "commit@example.com".to_string(),
+                },
+                MailmapEntry {
+                    proper_name: Some("Proper Name".to_string()),
+                    proper_email: None,
+                    commit_name: None,
+                    commit_email: "commit@example.com".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            mailmap.resolve("commit name", "COMMIT@example.com"),
+            ("Proper Name".to_string(), "proper@example.com".to_string())
+        );
+        assert_eq!(
+            mailmap.resolve("Other Name", "commit@example.com"),
+            ("Proper Name".to_string(), "proper@example.com".to_string())
+        );
+        assert_eq!(
+            mailmap.to_string().lines().nth(1),
+            Some("<proper@example.com> <commit@example.com> # moved")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Mailmap::parse("# comment\nAlice\n"),
+            Err(MailmapError::MissingEmail(2))
+        );
+        assert_eq!(
+            Mailmap::parse("Alice <alice@example.com"),
+            Err(MailmapError::UnclosedEmail(1))
+        );
+        assert_eq!(
+            Mailmap::parse("<a@example.com> <b@example.com> <c@example.com>"),
+            Err(MailmapError::TooManyEmails(1))
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! The names of branches, tags, references, and commits, which are validated when
+//! they are parsed, so that one can't be passed where another is expected.
+
+use crate::vcs::git::error::NameError;
+use git2::Oid;
+use std::fmt;
+use std::str::FromStr;
+
+/// Check that `name` is a valid reference name, see
+/// [git-check-ref-format](https://git-scm.com/docs/git-check-ref-format).
+fn is_valid_ref_name(name: &str) -> bool {
+    // libgit2 expects a C string.
+    !name.contains('\0') && git2::Reference::is_valid_name(name)
+}
+
+/// A newtype wrapper over `String` to separate out
+/// the fact that a caller wants to fetch a branch.
+///
+/// Parsing a `BranchName` checks that it is a valid name for a branch, e.g. a
+/// local branch `main`, or a remote branch `origin/main`.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::vcs::git::BranchName;
+///
+/// let main = "main".parse::<BranchName>().unwrap();
+/// assert_eq!(main.name(), "main");
+/// assert_eq!(main.to_string(), "main");
+///
+/// assert!("origin/main".parse::<BranchName>().is_ok());
+/// assert!("main..dev".parse::<BranchName>().is_err());
+/// assert!("".parse::<BranchName>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BranchName(pub(crate) String);
+
+impl BranchName {
+    /// Create a `BranchName` without validating it, for the names read from git.
+    pub(crate) fn new(name: &str) -> Self {
+        BranchName(name.into())
+    }
+
+    pub fn name(&self) -> String {
+        self.0.clone()
+    }
+}
+
+impl FromStr for BranchName {
+    type Err = NameError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if is_valid_ref_name(&format!("refs/heads/{}", name)) {
+            Ok(BranchName(name.to_string()))
+        } else {
+            Err(NameError::InvalidBranchName(name.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for BranchName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A newtype wrapper over `String` to separate out
+/// the fact that a caller wants to fetch a tag.
+///
+/// Parsing a `TagName` checks that it is a valid name for a tag.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::vcs::git::TagName;
+///
+/// assert_eq!(
+///     "v0.1.0".parse::<TagName>().map(|tag| tag.name()),
+///     Ok("v0.1.0".to_string())
+/// );
+/// assert!("v0.1.0.lock".parse::<TagName>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TagName(pub(crate) String);
+
+impl TagName {
+    /// Create a `TagName` without validating it, for the names read from git.
+    pub(crate) fn new(name: &str) -> Self {
+        TagName(name.into())
+    }
+
+    pub fn name(&self) -> String {
+        self.0.clone()
+    }
+}
+
+impl FromStr for TagName {
+    type Err = NameError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if is_valid_ref_name(&format!("refs/tags/{}", name)) {
+            Ok(TagName(name.to_string()))
+        } else {
+            Err(NameError::InvalidTagName(name.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for TagName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The full name of a reference, e.g. `refs/heads/main` or `HEAD`, which is always
+/// valid.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::vcs::git::{BranchName, RefName, TagName};
+///
+/// let main = "refs/heads/main".parse::<RefName>().unwrap();
+/// assert_eq!(main, RefName::from("main".parse::<BranchName>().unwrap()));
+/// assert_eq!(
+///     RefName::from("v0.1.0".parse::<TagName>().unwrap()).to_string(),
+///     "refs/tags/v0.1.0"
+/// );
+///
+/// assert!("HEAD".parse::<RefName>().is_ok());
+/// assert!("main".parse::<RefName>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RefName(pub(crate) String);
+
+impl RefName {
+    pub fn name(&self) -> String {
+        self.0.clone()
+    }
+}
+
+impl FromStr for RefName {
+    type Err = NameError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if is_valid_ref_name(name) {
+            Ok(RefName(name.to_string()))
+        } else {
+            Err(NameError::InvalidRefName(name.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for RefName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<BranchName> for RefName {
+    fn from(branch: BranchName) -> Self {
+        RefName(format!("refs/heads/{}", branch.0))
+    }
+}
+
+impl From<TagName> for RefName {
+    fn from(tag: TagName) -> Self {
+        RefName(format!("refs/tags/{}", tag.0))
+    }
+}
+
+/// A newtype wrapper over `String` to separate out
+/// the fact that a caller wants to fetch a commit.
+///
+/// Parsing a `Sha1` checks that it is a full commit id, or a prefix of at least 4
+/// hex digits.
+///
+/// The full `Sha1` of each blob, and of each submodule's commit, is attached to the
+/// [`Metadata`](../../file_system/struct.Metadata.html) of its `File`.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::vcs::git::{Oid, Sha1};
+///
+/// let id = "80ded66281a4de2889cc07293a8f10947c6d57fe";
+/// assert_eq!(id.parse::<Sha1>(), Ok(Sha1::from(Oid::from_str(id).unwrap())));
+///
+/// assert!("80de".parse::<Sha1>().is_ok());
+/// assert!("80d".parse::<Sha1>().is_err());
+/// assert!("main".parse::<Sha1>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sha1(pub(crate) String);
+
+impl Sha1 {
+    pub fn name(&self) -> String {
+        self.0.clone()
+    }
+}
+
+impl FromStr for Sha1 {
+    type Err = NameError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        if (4..=40).contains(&id.len()) && id.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            Ok(Sha1(id.to_string()))
+        } else {
+            Err(NameError::InvalidSha1(id.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for Sha1 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<Oid> for Sha1 {
+    fn from(oid: Oid) -> Self {
+        Sha1(oid.to_string())
+    }
+}
+
+/// The names are serialized as strings, and are validated when deserialized.
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::vcs::git::BranchName;
+/// use serde::de::value::{Error, StrDeserializer};
+/// use serde::de::IntoDeserializer;
+/// use serde::Deserialize;
+///
+/// let main: StrDeserializer<Error> = "main".into_deserializer();
+/// assert_eq!(BranchName::deserialize(main).map(|name| name.name()), Ok("main".to_string()));
+///
+/// let invalid: StrDeserializer<Error> = "main..dev".into_deserializer();
+/// assert!(BranchName::deserialize(invalid).is_err());
+/// ```
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{BranchName, RefName, Sha1, TagName};
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::{Serialize, Serializer};
+
+    macro_rules! serde_via_str {
+        ($name:ident) => {
+            impl Serialize for $name {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    serializer.serialize_str(&self.0)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $name {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: Deserializer<'de>,
+                {
+                    let name = String::deserialize(deserializer)?;
+                    name.parse().map_err(D::Error::custom)
+                }
+            }
+        };
+    }
+
+    serde_via_str!(BranchName);
+    serde_via_str!(TagName);
+    serde_via_str!(RefName);
+    serde_via_str!(Sha1);
+}
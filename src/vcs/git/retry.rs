@@ -0,0 +1,157 @@
+//! Retrying operations that fail because the repository is being maintained
+//! concurrently, e.g. `git gc` repacking objects or a fetch updating references.
+
+use crate::vcs::git::error::Error;
+use std::thread;
+use std::time::Duration;
+
+/// How to retry operations that fail with [`Error::ConcurrentMaintenance`](error/enum.Error.html).
+///
+/// A [`Repository`](struct.Repository.html) does not retry by default, see
+/// [`Repository::with_retry_policy`](struct.Repository.html#method.with_retry_policy).
+///
+/// # Examples
+///
+/// ```
+/// use radicle_surf::vcs::git::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy {
+///     max_retries: 5,
+///     ..RetryPolicy::default()
+/// };
+///
+/// assert_eq!(policy.initial_backoff, Duration::from_millis(50));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of times an operation is retried.
+    pub max_retries: u32,
+    /// How long to wait before the first retry. The wait doubles after each retry.
+    pub initial_backoff: Duration,
+    /// The longest to wait between two retries.
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// A `RetryPolicy` that never retries.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(0),
+            max_backoff: Duration::from_millis(0),
+        }
+    }
+
+    /// Run `operation`, retrying it with backoff while it fails with
+    /// [`Error::ConcurrentMaintenance`](error/enum.Error.html) and there are
+    /// retries left.
+    pub fn retry<T, F>(&self, mut operation: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Result<T, Error>,
+    {
+        let mut backoff = self.initial_backoff;
+        let mut retries = 0;
+        loop {
+            match operation() {
+                Err(err) if retries < self.max_retries && err.is_concurrent_maintenance() => {
+                    thread::sleep(backoff.min(self.max_backoff));
+                    backoff = backoff
+                        .checked_mul(2)
+                        .map_or(self.max_backoff, |doubled| doubled.min(self.max_backoff));
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Retry 3 times, waiting 50ms, 100ms and 200ms.
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::git::error::Context;
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            ..RetryPolicy::none()
+        }
+    }
+
+    fn pack_disappeared() -> Error {
+        Error::WithContext(
+            Box::new(Context::new("snapshot")),
+            Box::new(Error::ConcurrentMaintenance(git2::Error::from_str(
+                "failed to open packfile",
+            ))),
+        )
+    }
+
+    #[test]
+    fn test_retries_concurrent_maintenance() {
+        let mut attempts = 0;
+        let result = policy(3).retry(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(pack_disappeared())
+            } else {
+                Ok(attempts)
+            }
+        });
+
+        assert_eq!(result, Ok(3));
+    }
+
+    #[test]
+    fn test_gives_up_after_max_retries() {
+        let mut attempts = 0;
+        let result: Result<(), Error> = policy(2).retry(|| {
+            attempts += 1;
+            Err(pack_disappeared())
+        });
+
+        assert_eq!(result, Err(pack_disappeared()));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_backoff_does_not_overflow() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_secs(u64::MAX),
+            max_backoff: Duration::from_millis(0),
+        };
+        let mut attempts = 0;
+        let result: Result<(), Error> = policy.retry(|| {
+            attempts += 1;
+            Err(pack_disappeared())
+        });
+
+        assert_eq!(result, Err(pack_disappeared()));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_does_not_retry_other_errors() {
+        let mut attempts = 0;
+        let result: Result<(), Error> = policy(3).retry(|| {
+            attempts += 1;
+            Err(Error::NotBranch)
+        });
+
+        assert_eq!(result, Err(Error::NotBranch));
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,137 @@
+//! Reporting of slow git operations, e.g. rendering a snapshot of a very large
+//! tree or walking a very long history, so that operators can find pathological
+//! repositories.
+
+use crate::vcs::git::Repository;
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A timer for an operation on a repository, which reports the operation
+/// when it is dropped if it took longer than the repository's threshold, see
+/// [`Repository::with_slow_op_threshold`](../struct.Repository.html#method.with_slow_op_threshold).
+pub(crate) struct SlowOp<'a, Rev: fmt::Display> {
+    operation: &'static str,
+    repo: &'a Path,
+    threshold: Option<Duration>,
+    rev: Rev,
+    objects_read: usize,
+    start: Instant,
+}
+
+impl<'a, Rev: fmt::Display> SlowOp<'a, Rev> {
+    pub(crate) fn start(operation: &'static str, repository: &'a Repository, rev: Rev) -> Self {
+        SlowOp {
+            operation,
+            repo: repository.repo.path(),
+            threshold: repository.slow_op_threshold,
+            rev,
+            objects_read: 0,
+            start: Instant::now(),
+        }
+    }
+
+    /// Record that an object was read from the repository.
+    pub(crate) fn read_object(&mut self) {
+        self.objects_read += 1;
+    }
+}
+
+impl<'a, Rev: fmt::Display> Drop for SlowOp<'a, Rev> {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        match self.threshold {
+            Some(threshold) if elapsed >= threshold => tracing::warn!(
+                operation = self.operation,
+                repo = %self.repo.display(),
+                rev = %self.rev,
+                elapsed_ms = elapsed.as_millis() as u64,
+                objects_read = self.objects_read as u64,
+                "slow git operation"
+            ),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::git::fixture::Fixture;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span;
+
+    /// The names and values of the fields of an event.
+    type Fields = Vec<(String, String)>;
+
+    /// A subscriber that captures the fields of each event.
+    #[derive(Clone, Default)]
+    struct Events(Arc<Mutex<Vec<Fields>>>);
+
+    struct Visitor(Fields);
+
+    impl Visit for Visitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+
+    impl tracing::Subscriber for Events {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            let mut fields = Visitor(vec![]);
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0);
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn test_slow_ops_are_reported_per_repository() {
+        let fixture = Fixture::new();
+        let tip = fixture.commit(Some("HEAD"), &[("README.md", b"# Surf")], &[]);
+
+        let events = Events::default();
+        tracing::subscriber::with_default(events.clone(), || {
+            let quiet = fixture.repository();
+            let slow = fixture
+                .repository()
+                .with_slow_op_threshold(Some(Duration::from_secs(0)));
+            quiet.to_history(tip).unwrap();
+            slow.to_history(tip).unwrap();
+            quiet.to_history(tip).unwrap();
+        });
+
+        // Only the repository with a threshold reports its operation.
+        let events = events.0.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        let field = |name: &str| {
+            events[0]
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(field("message"), Some("slow git operation".to_string()));
+        assert_eq!(field("operation"), Some("\"history\"".to_string()));
+        assert_eq!(field("rev"), Some(tip.to_string()));
+        assert_eq!(field("objects_read"), Some("1".to_string()));
+        assert!(field("repo").is_some());
+        assert!(field("elapsed_ms").is_some());
+    }
+}
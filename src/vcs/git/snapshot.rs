@@ -0,0 +1,177 @@
+//! Pinning the references of a repository at a point in time, so that a series of
+//! operations, e.g. rendering a tree, its history and a diff for a single page,
+//! see the same state of the repository even if someone pushes in the meantime.
+
+use crate::vcs::git::error::Error;
+use crate::vcs::git::Repository;
+use git2::Oid;
+use std::collections::BTreeMap;
+
+/// The number of times the references are read while waiting for them
+/// to settle, see [`Snapshot::capture`](struct.Snapshot.html#method.capture).
+const MAX_READS: usize = 5;
+
+/// The targets of all the references of a repository at a point in time.
+///
+/// See [`Browser::freeze`](struct.Browser.html#method.freeze).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    head: Option<Oid>,
+    refs: BTreeMap<String, Oid>,
+}
+
+impl Snapshot {
+    /// Read the targets of `HEAD` and all references of `repo`.
+    ///
+    /// git updates references one file at a time, so the references are read
+    /// until two consecutive reads agree, or the references have been read
+    /// `MAX_READS` times, in which case the last read is used.
+    pub(crate) fn capture(repo: &git2::Repository) -> Result<Self, Error> {
+        let mut snapshot = Self::read(repo)?;
+        for _ in 1..MAX_READS {
+            let next = Self::read(repo)?;
+            if next == snapshot {
+                break;
+            }
+            snapshot = next;
+        }
+        Ok(snapshot)
+    }
+
+    fn read(repo: &git2::Repository) -> Result<Self, Error> {
+        let head = match repo.head() {
+            Ok(head) => head.target(),
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut refs = BTreeMap::new();
+        for reference in repo.references()? {
+            let reference = reference?;
+            let name = String::from_utf8_lossy(reference.name_bytes()).into_owned();
+            // Symbolic references that do not resolve, e.g. a dangling
+            // `refs/remotes/origin/HEAD`, are left out.
+            if let Some(target) = reference.resolve().ok().and_then(|r| r.target()) {
+                refs.insert(name, target);
+            }
+        }
+
+        Ok(Snapshot { head, refs })
+    }
+
+    /// Check whether `HEAD` or any of the references in this `Snapshot` moved, or was
+    /// deleted, in `repo` since the `Snapshot` was captured.
+    ///
+    /// Only the references in this `Snapshot` are looked up, so references
+    /// created since then are not taken into account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radicle_surf::vcs::git::Repository;
+    ///
+    /// let repo = Repository::new("./data/git-platinum").unwrap();
+    /// let snapshot = repo.snapshot().unwrap();
+    ///
+    /// assert_eq!(snapshot.has_drifted(&repo).unwrap(), false);
+    /// ```
+    pub fn has_drifted(&self, repo: &Repository) -> Result<bool, Error> {
+        let head = match repo.repo.head() {
+            Ok(head) => head.target(),
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => None,
+            Err(err) => return Err(err.into()),
+        };
+        if head != self.head {
+            return Ok(true);
+        }
+
+        for (name, target) in &self.refs {
+            match repo.repo.refname_to_id(name) {
+                Ok(current) if current == *target => {}
+                Ok(_) => return Ok(true),
+                Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(true),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// The target of `HEAD`, or `None` if `HEAD` points to a branch without any commits.
+    pub fn head(&self) -> Option<Oid> {
+        self.head
+    }
+
+    /// The full names of the references, e.g. `refs/heads/master`, along with their
+    /// targets, in the order of their names.
+    pub fn refs(&self) -> impl Iterator<Item = (&str, Oid)> {
+        self.refs
+            .iter()
+            .map(|(name, target)| (name.as_str(), *target))
+    }
+
+    /// The target of the reference with the full `name`, e.g. `refs/heads/master`.
+    pub fn target(&self, name: &str) -> Option<Oid> {
+        self.refs.get(name).copied()
+    }
+
+    /// Look up a reference by its short `name`, e.g. `master` or `origin/dev`,
+    /// in the same way as `git2::Repository::resolve_reference_from_short_name`,
+    /// returning its full name and target.
+    pub fn resolve(&self, name: &str) -> Option<(&str, Oid)> {
+        [
+            name.to_string(),
+            format!("refs/{}", name),
+            format!("refs/tags/{}", name),
+            format!("refs/heads/{}", name),
+            format!("refs/remotes/{}", name),
+            format!("refs/remotes/{}/HEAD", name),
+        ]
+        .iter()
+        .find_map(|candidate| self.refs.get_key_value(candidate))
+        .map(|(name, target)| (name.as_str(), *target))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oid(byte: u8) -> Oid {
+        Oid::from_bytes(&[byte; 20]).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_short_names() {
+        let snapshot = Snapshot {
+            head: Some(oid(1)),
+            refs: vec![
+                ("refs/heads/master".to_string(), oid(1)),
+                ("refs/remotes/origin/HEAD".to_string(), oid(2)),
+                ("refs/remotes/origin/dev".to_string(), oid(3)),
+                ("refs/tags/master".to_string(), oid(4)),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        // Tags take precedence over branches, as they do in git.
+        assert_eq!(
+            snapshot.resolve("master"),
+            Some(("refs/tags/master", oid(4)))
+        );
+        assert_eq!(
+            snapshot.resolve("heads/master"),
+            Some(("refs/heads/master", oid(1)))
+        );
+        assert_eq!(
+            snapshot.resolve("origin/dev"),
+            Some(("refs/remotes/origin/dev", oid(3)))
+        );
+        assert_eq!(
+            snapshot.resolve("origin"),
+            Some(("refs/remotes/origin/HEAD", oid(2)))
+        );
+        assert_eq!(snapshot.resolve("dev"), None);
+    }
+}
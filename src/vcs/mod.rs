@@ -48,6 +48,131 @@ impl<A> History<A> {
         new_history.map(History)
     }
 
+    /// Sample `n` artifacts from the `History`, evenly spaced by their position.
+    /// The first and last artifacts are always part of the sample, as long as `n > 1`.
+    /// No randomness is involved, so the same `History` and `n` always give the same sample.
+    ///
+    /// If `n` is larger than the `History` then the whole `History` is returned,
+    /// and if `n` is `0` then `None` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nonempty::NonEmpty;
+    /// use radicle_surf::vcs::History;
+    ///
+    /// let history = History(NonEmpty::from((0, (1..10).collect())));
+    ///
+    /// assert_eq!(history.sample(4), Some(History(NonEmpty::from((0, vec![3, 6, 9])))));
+    /// assert_eq!(history.sample(1), Some(History::new(0)));
+    /// assert_eq!(history.sample(20), Some(history.clone()));
+    /// assert_eq!(history.sample(0), None);
+    /// ```
+    pub fn sample(&self, n: usize) -> Option<Self>
+    where
+        A: Clone,
+    {
+        let len = self.0.len();
+        if n >= len {
+            return Some(self.clone());
+        }
+
+        let artifacts = self.iter().collect::<Vec<_>>();
+        let sample = match n {
+            0 => vec![],
+            1 => vec![artifacts[0].clone()],
+            _ => (0..n)
+                .map(|i| artifacts[i * (len - 1) / (n - 1)].clone())
+                .collect(),
+        };
+
+        NonEmpty::from_slice(&sample).map(History)
+    }
+
+    /// Sample `n` artifacts from the `History`, evenly spaced by the time
+    /// given by `time_of`, e.g. the commit time in seconds. For each point in
+    /// time the closest artifact is picked, and an artifact is never picked twice,
+    /// so the sample may be smaller than `n`.
+    ///
+    /// If `n` is `0` then `None` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nonempty::NonEmpty;
+    /// use radicle_surf::vcs::History;
+    ///
+    /// // Artifacts are their own timestamps, with a burst of activity at the start.
+    /// let history = History(NonEmpty::from((100, vec![99, 98, 97, 96, 50, 0])));
+    ///
+    /// assert_eq!(
+    ///     history.sample_by_time(3, |time| *time),
+    ///     Some(History(NonEmpty::from((100, vec![50, 0]))))
+    /// );
+    ///
+    /// // Times may span the whole range of `i64`.
+    /// let wide = History(NonEmpty::from((i64::MAX, vec![0, i64::MIN])));
+    ///
+    /// assert_eq!(wide.sample_by_time(3, |time| *time), Some(wide.clone()));
+    /// ```
+    pub fn sample_by_time<F>(&self, n: usize, time_of: F) -> Option<Self>
+    where
+        A: Clone,
+        F: Fn(&A) -> i64,
+    {
+        let times = self.iter().map(time_of).collect::<Vec<_>>();
+        // Computed in `i128`, so that times far apart don't overflow.
+        let start = i128::from(times[0]);
+        let end = i128::from(times[times.len() - 1]);
+        let mut targets = (0..n)
+            .map(|i| {
+                if n == 1 {
+                    start
+                } else {
+                    start + (end - start) * i as i128 / (n - 1) as i128
+                }
+            })
+            .collect::<Vec<_>>();
+        if end < start {
+            targets.reverse();
+        }
+
+        // The artifacts from the earliest time to the latest, keeping only the first
+        // of those with the same time, which is the one picked for it.
+        let mut by_time = (0..times.len()).collect::<Vec<_>>();
+        by_time.sort_by_key(|ix| times[*ix]);
+        by_time.dedup_by_key(|ix| times[*ix]);
+
+        // The targets are in order of time too, so the closest artifact to each is
+        // found by moving a single cursor forward, preferring the earlier artifact
+        // of the history when two are as close.
+        let distance = |ix: usize, target: i128| (i128::from(times[ix]) - target).abs();
+        let mut cursor = 0;
+        let mut indices = Vec::with_capacity(n);
+        for target in targets {
+            while let Some(&next) = by_time.get(cursor + 1) {
+                let here = distance(by_time[cursor], target);
+                let there = distance(next, target);
+                if there < here || (there == here && next < by_time[cursor]) {
+                    cursor += 1;
+                } else {
+                    break;
+                }
+            }
+            indices.push(by_time[cursor]);
+        }
+        indices.sort_unstable();
+        indices.dedup();
+
+        let artifacts = self.iter().collect::<Vec<_>>();
+        let sample = indices
+            .into_iter()
+            .map(|ix| artifacts[ix].clone())
+            .collect::<Vec<_>>();
+
+        NonEmpty::from_slice(&sample).map(History)
+    }
+
     pub fn map<F, B>(&self, f: F) -> History<B>
     where
         F: Fn(&A) -> B,